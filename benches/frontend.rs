@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use ezlang::{
+    core::{lexer, parser},
+    utils::diag::FileName,
+};
+
+/// Generates a program with the given number of lines, made of small blocks
+/// so that the scopes stay shallow and the time is spent in the lexer and parser
+fn generate_source(lines: usize) -> String {
+    let mut source = String::new();
+    for i in 0..lines / 5 {
+        source.push_str("{\n");
+        source.push_str(&format!("    let a = {} + 3, b = a * 2\n", i % 200));
+        source.push_str("    if (a < b) { ezout a } else { ezout b }\n");
+        source.push_str("    b = b - a\n");
+        source.push_str("}\n");
+    }
+    source
+}
+
+fn lex_and_parse(c: &mut Criterion) {
+    let source = generate_source(50_000);
+    let file = FileName::new("bench.ez");
+    let tokens = lexer::lex(&source, file.clone()).unwrap().len();
+
+    let mut group = c.benchmark_group("frontend");
+    group.throughput(Throughput::Elements(tokens as u64));
+    group.sample_size(10);
+    group.bench_function("lex", |b| {
+        b.iter(|| lexer::lex(black_box(&source), file.clone()).unwrap())
+    });
+    group.bench_function("lex+parse", |b| {
+        b.iter(|| {
+            let tokens = lexer::lex(black_box(&source), file.clone()).unwrap();
+            parser::parse(tokens).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lex_and_parse);
+criterion_main!(benches);
@@ -0,0 +1,105 @@
+//! Counts how many times every function of a program is called, with a hook of the compiler
+//! pipeline that adds a counter to the start of the body of every top level function.
+//! The counters are printed when the program ends.
+//! ```text
+//! cargo run --example instrument -- examples/functions.ez
+//! ```
+//! Without a file, a small program is compiled instead
+
+use std::io;
+
+use ezlang::{
+    core::{interpreter, pipeline::Pipeline},
+    utils::{
+        ast::{Node, Type},
+        diag::Error,
+        token::{Token, TokenType},
+        Options, Stats,
+    },
+};
+
+const PROGRAM: &str = "
+ez square(x: int) -> int { return x * x }
+ez sum_of_squares(a: int, b: int) -> int { return square(a) + square(b) }
+ez unused() {}
+ezoutln sum_of_squares(3, 4)
+";
+
+fn main() {
+    let (contents, filename) = match std::env::args().nth(1) {
+        Some(path) => match ezlang::read_source(&path) {
+            Ok(contents) => (contents, path),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => (PROGRAM.to_string(), "main.ez".to_string()),
+    };
+    let code = match Pipeline::new(Options::default())
+        .after_parse(instrument)
+        .run(&contents, filename, &mut Stats::default())
+    {
+        Ok(code) => code,
+        Err(e) => {
+            eprint!("{}\n\n{}", e, e.snippet(&contents));
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = interpreter::interpret(&code, &mut io::stdin().lock(), &mut io::stdout()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Declares a `calls_<name>` counter at the start of the program for every top level function,
+/// increments it at the start of the function's body, and prints it at the end of the program.
+/// The overloads of a function share its counter
+fn instrument(ast: &mut Node) -> Result<(), Error> {
+    let Node::Statements(nodes, _, _, position) = ast else {
+        return Ok(());
+    };
+    let token = |token_type| Token {
+        token_type,
+        position: position.clone(),
+    };
+    let mut counters = vec![];
+    for node in nodes.iter_mut() {
+        let Node::FuncDef(name, _, body, ..) = node else {
+            continue;
+        };
+        let Node::Statements(statements, ..) = body.as_mut() else {
+            continue;
+        };
+        let counter = token(TokenType::Identifier(format!("calls_{}", name.token_type)));
+        let value = Node::VarAccess(counter.clone(), Type::Number);
+        let one = Node::Number(token(TokenType::Number(1)));
+        statements.insert(
+            0,
+            Node::VarReassign(
+                counter.clone(),
+                Box::new(Node::BinaryOp(
+                    token(TokenType::Add),
+                    Box::new(value),
+                    Box::new(one),
+                    Type::Number,
+                )),
+            ),
+        );
+        let name = name.token_type.to_string();
+        if !counters.iter().any(|(n, _)| *n == name) {
+            counters.push((name, counter));
+        }
+    }
+    for (name, counter) in counters {
+        let zero = Node::Number(token(TokenType::Number(0)));
+        nodes.insert(
+            0,
+            Node::VarAssign(counter.clone(), Box::new(zero), Type::Number),
+        );
+        let label = Node::String(token(TokenType::String(format!("{} was called", name))));
+        let calls = Node::VarAccess(counter, Type::Number);
+        nodes.push(Node::Print(vec![label, calls], true, position.clone()));
+    }
+    Ok(())
+}
@@ -1,16 +1,120 @@
 use std::env;
 
+use ezlang::utils::diag::{parse_warning_setting, Severity, WarningKind};
+
+pub enum Command {
+    /// Compile the input file into brainfuck
+    Build,
+    /// Compile a single function call and run it
+    Test { function: String, args: Vec<i64> },
+    /// Show the definition and the references of the symbol at a line and column
+    Refs { line: usize, column: usize },
+    /// Create a new project in the directory `name`
+    New { name: String },
+    /// Run the program, counting the instructions it runs, at the optimization level or at the
+    /// two levels of `compare`
+    Bench {
+        stdin: Option<String>,
+        compare: Option<(u8, u8)>,
+    },
+    /// Run the program, or the intermediate code written by `--emit=ir-text` if `ir` is set,
+    /// reading its input from `stdin` or from the terminal
+    Run { ir: bool, stdin: Option<String> },
+}
+
 pub struct Args {
+    pub command: Command,
     pub output_file: String,
     pub input_file: String,
+    pub stats: bool,
+    /// File included before the input file
+    pub prelude: Option<String>,
+    /// Directories searched by `use`
+    pub include_paths: Vec<String>,
+    /// Flags declared before preprocessing
+    pub defines: Vec<String>,
+    pub opt_level: Option<u8>,
+    pub cell_size: Option<u8>,
+    /// Threads the output is optimized on
+    pub jobs: usize,
+    /// Ignore the project config file
+    pub no_config: bool,
+    /// Severities of warning kinds, given with `--warn kind=level`
+    pub warnings: Vec<(WarningKind, Severity)>,
+    /// Lower multiplication, division, modulo and powers into loops
+    pub lower_arith: bool,
+    /// Generate the globals with constant values like the other variables
+    pub no_fold_globals: bool,
+    /// Printed between the values of an `ezout`
+    pub ezout_separator: Option<String>,
+    /// File the files the program was made of are written to, for build tools
+    pub deps_file: Option<String>,
+    /// Print the intermediate code
+    pub emit_ir: bool,
+    /// Print the AST once it is parsed
+    pub emit_ast: bool,
+    /// File the intermediate code is written to, in the form `run --ir` reads
+    pub ir_file: Option<String>,
+    /// Only print the intermediate code of this function
+    pub only_fn: Option<String>,
+    /// Print the number of instructions of every function with the stats
+    pub per_fn: bool,
+    /// Print the cells every variable and temporary value uses
+    pub explain_memory: bool,
+    /// The most nodes expanded function calls can add
+    pub max_inline_size: Option<usize>,
+    /// Most tokens the `replace` directives can add
+    pub max_replaced_tokens: Option<usize>,
+    /// Log the preprocessor directives to stderr
+    pub trace_preprocessor: bool,
+    /// Levels of nodes shown of the printed AST
+    pub ast_depth: Option<usize>,
+    /// Check that the optimized program prints the same as the one at `-O0`
+    pub verify: bool,
+    /// Input of the program checked by `--verify`
+    pub verify_stdin: Option<String>,
+    /// File the annotated HTML report of the program is written to, instead of the brainfuck
+    pub html_report: Option<String>,
 }
 
 impl Args {
     pub fn get() -> Result<Args, String> {
-        let args = env::args().skip(1);
+        let mut args = env::args().skip(1).peekable();
         let mut output_file = None;
         let mut input_file = None;
-        for arg in args {
+        let mut stats = false;
+        let mut prelude = None;
+        let mut include_paths = vec![];
+        let mut defines = vec![];
+        let mut opt_level = None;
+        let mut cell_size = None;
+        let mut jobs = 1;
+        let mut no_config = false;
+        let mut warnings = vec![];
+        let mut lower_arith = false;
+        let mut no_fold_globals = false;
+        let mut ezout_separator = None;
+        let mut emits = vec![];
+        let mut only_fn = None;
+        let mut per_fn = false;
+        let mut explain_memory = false;
+        let mut max_inline_size = None;
+        let mut max_replaced_tokens = None;
+        let mut trace_preprocessor = false;
+        let mut ast_depth = None;
+        let mut verify = false;
+        let mut function = None;
+        let mut call_args = None;
+        let test = args.next_if(|arg| arg == "test").is_some();
+        let refs = !test && args.next_if(|arg| arg == "refs").is_some();
+        let new = !test && !refs && args.next_if(|arg| arg == "new").is_some();
+        let bench = !test && !refs && !new && args.next_if(|arg| arg == "bench").is_some();
+        let run = !test && !refs && !new && !bench && args.next_if(|arg| arg == "run").is_some();
+        let mut ir = false;
+        let mut stdin = None;
+        let mut compare = None;
+        let mut location = None;
+        while let Some(arg) = args.next() {
             match *arg.split('=').collect::<Vec<_>>() {
                 ["-o", file] => {
                     if output_file.is_some() {
@@ -19,6 +123,107 @@ impl Args {
                     output_file = Some(file.to_string());
                 }
                 ["-o"] => return Err(String::from("No output file specified after -o")),
+                ["--stats"] => stats = true,
+                ["--prelude", file] => prelude = Some(file.to_string()),
+                ["--prelude"] => match args.next() {
+                    Some(file) => prelude = Some(file),
+                    None => return Err(String::from("No file specified after --prelude")),
+                },
+                ["-I", dir] => include_paths.push(dir.to_string()),
+                ["-I"] => match args.next() {
+                    Some(dir) => include_paths.push(dir),
+                    None => return Err(String::from("No directory specified after -I")),
+                },
+                ["-D" | "--define", flag] => defines.push(flag.to_string()),
+                ["-D" | "--define"] => match args.next() {
+                    Some(flag) => defines.push(flag),
+                    None => return Err(String::from("No flag specified after --define")),
+                },
+                [level] if level.starts_with("-O") => opt_level = Some(parse_opt_level(level)?),
+                ["--cell-size", size] => cell_size = Some(parse_cell_size(size)?),
+                ["--cell-size"] => match args.next() {
+                    Some(size) => cell_size = Some(parse_cell_size(&size)?),
+                    None => return Err(String::from("No size specified after --cell-size")),
+                },
+                ["--jobs", n] => jobs = parse_jobs(n)?,
+                ["--jobs"] => match args.next() {
+                    Some(n) => jobs = parse_jobs(&n)?,
+                    None => return Err(String::from("No number specified after --jobs")),
+                },
+                ["--no-config"] => no_config = true,
+                ["--lower-arith"] => lower_arith = true,
+                ["--no-fold-globals"] => no_fold_globals = true,
+                ["--explain-memory"] => explain_memory = true,
+                ["--max-inline-size", n] => max_inline_size = Some(parse_max_inline_size(n)?),
+                ["--max-inline-size"] => match args.next() {
+                    Some(n) => max_inline_size = Some(parse_max_inline_size(&n)?),
+                    None => return Err(String::from("No size specified after --max-inline-size")),
+                },
+                ["--ezout-sep", sep] => ezout_separator = Some(sep.to_string()),
+                ["--ezout-sep"] => match args.next() {
+                    Some(sep) => ezout_separator = Some(sep),
+                    None => return Err(String::from("No separator specified after --ezout-sep")),
+                },
+                ["--emit", kind] => emits.push(parse_emit(kind, &mut args)?),
+                ["--emit"] => match args.next() {
+                    Some(kind) => emits.push(parse_emit(&kind, &mut args)?),
+                    None => return Err(String::from("No output specified after --emit")),
+                },
+                ["--only-fn", name] => only_fn = Some(name.to_string()),
+                ["--only-fn"] => match args.next() {
+                    Some(name) => only_fn = Some(name),
+                    None => return Err(String::from("No function specified after --only-fn")),
+                },
+                ["--per-fn"] => per_fn = true,
+                ["--max-replace-tokens", n] => max_replaced_tokens = Some(parse_max_tokens(n)?),
+                ["--max-replace-tokens"] => match args.next() {
+                    Some(n) => max_replaced_tokens = Some(parse_max_tokens(&n)?),
+                    None => {
+                        return Err(String::from(
+                            "No number specified after --max-replace-tokens",
+                        ))
+                    }
+                },
+                ["--trace-preprocessor"] => trace_preprocessor = true,
+                ["--verify"] => verify = true,
+                ["--emit-ast-depth", n] => ast_depth = Some(parse_ast_depth(n)?),
+                ["--emit-ast-depth"] => match args.next() {
+                    Some(n) => ast_depth = Some(parse_ast_depth(&n)?),
+                    None => return Err(String::from("No depth specified after --emit-ast-depth")),
+                },
+                ["--warn", kind, level] => {
+                    warnings.push(parse_warning_setting(&format!("{}={}", kind, level))?)
+                }
+                ["--warn"] => match args.next() {
+                    Some(setting) => warnings.push(parse_warning_setting(&setting)?),
+                    None => return Err(String::from("No warning specified after --warn")),
+                },
+                ["--fn"] if test => match args.next() {
+                    Some(name) => function = Some(name),
+                    None => return Err(String::from("No function specified after --fn")),
+                },
+                ["--args"] if test => match args.next() {
+                    Some(list) => call_args = Some(parse_call_args(&list)?),
+                    None => return Err(String::from("No arguments specified after --args")),
+                },
+                ["--stdin", file] => stdin = Some(file.to_string()),
+                ["--stdin"] => match args.next() {
+                    Some(file) => stdin = Some(file),
+                    None => return Err(String::from("No file specified after --stdin")),
+                },
+                ["--compare"] if bench => {
+                    let mut level = || match args.next() {
+                        Some(level) => parse_opt_level(&level),
+                        None => Err(String::from(
+                            "Expected two levels after --compare, like -O0 -O1",
+                        )),
+                    };
+                    compare = Some((level()?, level()?));
+                }
+                ["--ir"] if run => ir = true,
+                [loc] if refs && input_file.is_some() && location.is_none() => {
+                    location = Some(parse_location(loc)?)
+                }
                 [file] => {
                     if input_file.is_some() {
                         return Err(String::from("Multiple input files specified"));
@@ -29,12 +234,196 @@ impl Args {
             }
         }
 
+        let mut deps_file = None;
+        let mut emit_ir = false;
+        let mut emit_ast = false;
+        let mut ir_file = None;
+        let mut html_report = None;
+        for emit in emits {
+            match emit {
+                Emit::Deps(file) => deps_file = Some(file),
+                Emit::Ir => emit_ir = true,
+                Emit::Ast => emit_ast = true,
+                Emit::IrText(file) => ir_file = Some(file),
+                Emit::Html(file) => html_report = Some(file),
+            }
+        }
+        if only_fn.is_some() && !emit_ir {
+            return Err(String::from("--only-fn can only be used with --emit=ir"));
+        }
+        if per_fn && !stats {
+            return Err(String::from("--per-fn can only be used with --stats"));
+        }
+        let building = !test && !refs && !new && !bench && !run;
+        if html_report.is_some() && verify {
+            return Err(String::from(
+                "--verify can't be used with --emit=html, which doesn't write the brainfuck",
+            ));
+        }
+        if verify && !building {
+            return Err(String::from("--verify can only be used when building"));
+        }
+        if stdin.is_some() && !bench && !run && !verify {
+            return Err(String::from(
+                "--stdin can only be used with bench, run or --verify",
+            ));
+        }
+        let verify_stdin = if verify { stdin.take() } else { None };
+
+        let command = if test {
+            Command::Test {
+                function: function
+                    .ok_or_else(|| String::from("No function specified with --fn"))?,
+                args: call_args.unwrap_or_default(),
+            }
+        } else if refs {
+            let (line, column) =
+                location.ok_or_else(|| String::from("No location specified, expected line:col"))?;
+            Command::Refs { line, column }
+        } else if new {
+            Command::New {
+                name: input_file
+                    .clone()
+                    .ok_or_else(|| String::from("No project name specified"))?,
+            }
+        } else if bench {
+            Command::Bench { stdin, compare }
+        } else if run {
+            Command::Run { ir, stdin }
+        } else {
+            Command::Build
+        };
+
         Ok(Args {
+            command,
             output_file: output_file.unwrap_or_else(|| String::from("output.bf")),
             input_file: match input_file {
                 Some(file) => file,
                 None => return Err(String::from("No input file specified")),
             },
+            stats,
+            prelude,
+            include_paths,
+            defines,
+            opt_level,
+            cell_size,
+            jobs,
+            no_config,
+            warnings,
+            lower_arith,
+            no_fold_globals,
+            ezout_separator,
+            deps_file,
+            emit_ir,
+            emit_ast,
+            ir_file,
+            only_fn,
+            per_fn,
+            explain_memory,
+            max_inline_size,
+            max_replaced_tokens,
+            trace_preprocessor,
+            ast_depth,
+            verify,
+            verify_stdin,
+            html_report,
         })
     }
 }
+
+/// Parses a comma separated list of numbers, like `2,3`
+fn parse_call_args(list: &str) -> Result<Vec<i64>, String> {
+    list.split(',')
+        .filter(|arg| !arg.trim().is_empty())
+        .map(|arg| {
+            arg.trim()
+                .parse()
+                .map_err(|_| format!("Invalid argument: {}", arg))
+        })
+        .collect()
+}
+
+/// Parses an optimization level, like `-O1`
+fn parse_opt_level(level: &str) -> Result<u8, String> {
+    level
+        .strip_prefix("-O")
+        .and_then(|level| level.parse().ok())
+        .ok_or_else(|| format!("Invalid optimization level: {}", level))
+}
+
+fn parse_cell_size(size: &str) -> Result<u8, String> {
+    size.parse()
+        .map_err(|_| format!("Invalid cell size: {}", size))
+}
+
+/// What `--emit` writes
+enum Emit {
+    /// The files the program is made of, to a file
+    Deps(String),
+    /// The intermediate code, printed
+    Ir,
+    /// The AST, printed
+    Ast,
+    /// The intermediate code in the form `run --ir` reads, to a file
+    IrText(String),
+    /// The annotated page of HTML of the program, to a file instead of the brainfuck
+    Html(String),
+}
+
+/// Parses what `--emit` writes, taking the file `deps`, `ir-text` and `html` are written to
+/// from `args`
+fn parse_emit(kind: &str, args: &mut impl Iterator<Item = String>) -> Result<Emit, String> {
+    match kind {
+        "deps" => args
+            .next()
+            .map(Emit::Deps)
+            .ok_or_else(|| String::from("No file specified after --emit=deps")),
+        "ir" => Ok(Emit::Ir),
+        "ast" => Ok(Emit::Ast),
+        "ir-text" => args
+            .next()
+            .map(Emit::IrText)
+            .ok_or_else(|| String::from("No file specified after --emit=ir-text")),
+        "html" => args
+            .next()
+            .map(Emit::Html)
+            .ok_or_else(|| String::from("No file specified after --emit=html")),
+        _ => Err(format!(
+            "Unknown output: {}, expected deps, ast, ir, ir-text or html",
+            kind
+        )),
+    }
+}
+
+fn parse_max_tokens(n: &str) -> Result<usize, String> {
+    n.parse()
+        .map_err(|_| format!("Invalid number of tokens: {}", n))
+}
+
+fn parse_ast_depth(depth: &str) -> Result<usize, String> {
+    depth
+        .parse()
+        .map_err(|_| format!("Invalid AST depth: {}", depth))
+}
+
+fn parse_jobs(jobs: &str) -> Result<usize, String> {
+    match jobs.parse() {
+        Ok(0) | Err(_) => Err(format!("Invalid number of jobs: {}", jobs)),
+        Ok(n) => Ok(n),
+    }
+}
+
+fn parse_max_inline_size(size: &str) -> Result<usize, String> {
+    size.parse()
+        .map_err(|_| format!("Invalid maximum inline size: {}", size))
+}
+
+/// Parses a location in a file, like `3:5`
+fn parse_location(location: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("Invalid location: {}, expected line:col", location);
+    let (line, column) = location.split_once(':').ok_or_else(invalid)?;
+    Ok((
+        line.trim().parse().map_err(|_| invalid())?,
+        column.trim().parse().map_err(|_| invalid())?,
+    ))
+}
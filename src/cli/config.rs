@@ -0,0 +1,163 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use ezlang::utils::diag::{parse_warning_setting, Severity, WarningKind};
+
+/// Names of the project config file, looked for in the directory of the input file and
+/// then in every directory above it
+pub const CONFIG_FILES: [&str; 2] = ["ez.toml", "ezlang.toml"];
+
+/// Options read from the project config file. Relative paths in it are relative to the
+/// directory of the config file
+#[derive(Debug, Default)]
+pub struct Config {
+    pub prelude: Option<String>,
+    pub include_paths: Vec<String>,
+    pub defines: Vec<String>,
+    pub opt_level: Option<u8>,
+    pub cell_size: Option<u8>,
+    /// Printed between the values of an `ezout`
+    pub ezout_separator: Option<String>,
+    /// Severities of warning kinds, from the `[warn]` section
+    pub warnings: Vec<(WarningKind, Severity)>,
+}
+
+/// A value in the config file
+enum Value {
+    String(String),
+    Number(u8),
+    List(Vec<String>),
+}
+
+impl Config {
+    /// Finds the config file of the project `input_file` is in and reads it, or returns the
+    /// default config if there is none
+    pub fn find(input_file: &str) -> Result<Config, String> {
+        let dir = match Path::new(input_file).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let dir = match fs::canonicalize(dir) {
+            Ok(dir) => dir,
+            Err(_) => return Ok(Config::default()),
+        };
+        for dir in dir.ancestors() {
+            for name in CONFIG_FILES {
+                let path = dir.join(name);
+                if path.is_file() {
+                    return Config::load(&path);
+                }
+            }
+        }
+        Ok(Config::default())
+    }
+
+    /// Reads the config file at `path`
+    fn load(path: &Path) -> Result<Config, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read config file '{}': {}", path.display(), e))?;
+        let dir = relative_to_cwd(path.parent().unwrap());
+        Config::parse(&contents, &dir)
+            .map_err(|e| format!("Invalid config file '{}': {}", path.display(), e))
+    }
+
+    /// Parses a config file, which is a small subset of TOML: `[section]` headers, and
+    /// `key = value` lines where the value is a string, a number or a list of strings.
+    /// Blank lines and lines starting with `#` are ignored
+    fn parse(contents: &str, dir: &Path) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        let path = |p: String| dir.join(p).to_string_lossy().into_owned();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |msg: String| format!("line {}: {}", i + 1, msg);
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                if !["", "include", "define", "build", "warn"].contains(&section.as_str()) {
+                    return Err(error(format!("unknown section `[{}]`", section)));
+                }
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(String::from("expected `key = value`")))?;
+            let key = key.trim();
+            let value = parse_value(value.trim()).map_err(error)?;
+            match (section.as_str(), key, value) {
+                ("", "prelude", Value::String(file)) => config.prelude = Some(path(file)),
+                ("include", "paths", Value::List(dirs)) => {
+                    config.include_paths = dirs.into_iter().map(path).collect()
+                }
+                ("define", "flags", Value::List(flags)) => config.defines = flags,
+                ("build", "opt-level", Value::Number(n)) => config.opt_level = Some(n),
+                ("build", "cell-size", Value::Number(n)) => config.cell_size = Some(n),
+                ("build", "ezout-separator", Value::String(sep)) => {
+                    config.ezout_separator = Some(sep)
+                }
+                ("warn", kind, Value::String(level)) => config
+                    .warnings
+                    .push(parse_warning_setting(&format!("{}={}", kind, level)).map_err(error)?),
+                ("", "prelude", _) => {
+                    return Err(error(String::from("`prelude` must be a string")))
+                }
+                ("include", "paths", _) | ("define", "flags", _) => {
+                    return Err(error(format!("`{}` must be a list of strings", key)))
+                }
+                ("build", "opt-level" | "cell-size", _) => {
+                    return Err(error(format!("`{}` must be a number", key)))
+                }
+                ("build", "ezout-separator", _) => {
+                    return Err(error(format!("`{}` must be a string", key)))
+                }
+                ("warn", _, _) => return Err(error(format!("`{}` must be a string", key))),
+                ("", key, _) => return Err(error(format!("unknown key `{}`", key))),
+                (section, key, _) => {
+                    return Err(error(format!("unknown key `{}` in `[{}]`", key, section)))
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Parses the value of a `key = value` line
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        list.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(parse_string)
+            .collect::<Result<_, _>>()
+            .map(Value::List)
+    } else if value.starts_with('"') {
+        parse_string(value).map(Value::String)
+    } else {
+        value
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| format!("invalid value {}", value))
+    }
+}
+
+fn parse_string(value: &str) -> Result<String, String> {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .map(str::to_string)
+        .ok_or_else(|| format!("expected a quoted string, found {}", value))
+}
+
+/// Makes `dir` relative to the current directory if it is inside of it, so that paths from
+/// the config file are shown the way the user would write them
+fn relative_to_cwd(dir: &Path) -> PathBuf {
+    env::current_dir()
+        .and_then(fs::canonicalize)
+        .ok()
+        .and_then(|cwd| dir.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| dir.to_path_buf())
+}
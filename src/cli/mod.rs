@@ -1,7 +1,28 @@
 mod cmd_args;
-use std::{fs, io::ErrorKind, process};
+mod config;
+mod project;
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    process,
+};
 
-use cmd_args::Args;
+use cmd_args::{Args, Command};
+use config::Config;
+use ezlang::{
+    core::{
+        bench, interpreter,
+        pipeline::Pipeline,
+        report,
+        verify::{self, Verification},
+    },
+    utils::{
+        diag::{Error, ErrorType, FileName, Position, Warning},
+        ir::Instructions,
+        Options, Stats,
+    },
+};
 
 pub fn main() {
     let args = match Args::get() {
@@ -12,24 +33,140 @@ pub fn main() {
         }
     };
 
-    let output = ezlang::run(
-        &fs::read_to_string(&args.input_file).unwrap_or_else(|e| {
-            match e.kind() {
-                ErrorKind::NotFound => println!("File not found: {}", args.input_file),
-                ErrorKind::PermissionDenied => {
-                    println!("Cannot open file '{}': Permission denied", args.input_file)
-                }
-                _ => println!("An error occured: {}", e),
-            }
+    if let Command::New { ref name } = args.command {
+        project::create(name).unwrap_or_else(|e| {
+            println!("{}", e);
             process::exit(1);
-        }),
-        args.input_file,
-    )
-    .unwrap_or_else(|e| {
-        println!("{}", e);
+        });
+        println!(
+            "Created project '{}', compile it with `ezlang {}`",
+            name,
+            Path::new(name).join("main.ez").display()
+        );
+        return;
+    }
+
+    let config = if args.no_config {
+        Config::default()
+    } else {
+        Config::find(&args.input_file).unwrap_or_else(|e| {
+            println!("{}", e);
+            process::exit(1);
+        })
+    };
+    // Options passed on the command line take precedence over the config file
+    let cell_size = args.cell_size.or(config.cell_size).unwrap_or(8);
+    if cell_size != 8 {
+        println!(
+            "Unsupported cell size: {}, only 8 bit cells are supported",
+            cell_size
+        );
+        process::exit(1);
+    }
+    let options = Options {
+        prelude: args.prelude.clone().or(config.prelude),
+        include_paths: [args.include_paths.clone(), config.include_paths].concat(),
+        defines: [args.defines.clone(), config.defines].concat(),
+        opt_level: args.opt_level.or(config.opt_level).unwrap_or(1),
+        jobs: args.jobs,
+        // Later settings win, so the command line overrides the config file
+        warnings: [config.warnings, args.warnings.clone()].concat(),
+        fold_globals: !args.no_fold_globals,
+        lower_arith: args.lower_arith,
+        ezout_separator: args
+            .ezout_separator
+            .clone()
+            .or(config.ezout_separator)
+            .unwrap_or_else(|| Options::default().ezout_separator),
+        max_inline_size: args
+            .max_inline_size
+            .unwrap_or(Options::default().max_inline_size),
+        max_replaced_tokens: args
+            .max_replaced_tokens
+            .unwrap_or_else(|| Options::default().max_replaced_tokens),
+        trace_preprocessor: args.trace_preprocessor,
+        ast_depth: args
+            .ast_depth
+            .unwrap_or_else(|| Options::default().ast_depth),
+    };
+
+    let contents = ezlang::read_source(&args.input_file).unwrap_or_else(|e| {
+        match e.kind() {
+            ErrorKind::NotFound => println!("File not found: {}", args.input_file),
+            ErrorKind::PermissionDenied => {
+                println!("Cannot open file '{}': Permission denied", args.input_file)
+            }
+            ErrorKind::InvalidData => println!("{}", e),
+            _ => println!("An error occured: {}", e),
+        }
         process::exit(1);
     });
 
+    match args.command {
+        Command::Build => build(&contents, &args, &options),
+        Command::Test {
+            ref function,
+            args: ref call_args,
+        } => test(&contents, &args, &options, function, call_args),
+        Command::Refs { line, column } => refs(&contents, &args, &options, line, column),
+        Command::Bench { ref stdin, compare } => {
+            bench(&contents, &args, &options, stdin.as_deref(), compare)
+        }
+        Command::Run { ir, ref stdin } => run(&contents, &args, &options, ir, stdin.as_deref()),
+        Command::New { .. } => unreachable!(),
+    }
+}
+
+/// Prints a compilation error, along with the code it is about if it is in the main file
+fn report(e: &Error, contents: &str, args: &Args) {
+    println!("{}", e);
+    if e.position.included_from.is_none() && *e.position.file == args.input_file {
+        print!("{}", e.snippet(contents));
+    }
+}
+
+/// Prints a warning, along with the code it is about
+fn report_warning(warning: &Warning, contents: &str, args: &Args) {
+    println!("{}", warning);
+    if *warning.position.file == args.input_file {
+        let mut error = Error::new(
+            ErrorType::Warning,
+            warning.position.clone(),
+            warning.details.clone(),
+        );
+        error.labels = warning.labels.clone();
+        print!("{}", error.snippet(contents));
+    }
+}
+
+fn build(contents: &str, args: &Args, options: &Options) {
+    if let Some(ref html_file) = args.html_report {
+        return html(contents, args, options, html_file);
+    }
+    let mut stats = Stats::default();
+    let mut pipeline = Pipeline::new(options.clone());
+    if args.emit_ast {
+        pipeline = pipeline.after_parse(|ast| {
+            println!("{}", ast.display(options.ast_depth));
+            Ok(())
+        });
+    }
+    let code = pipeline.compile_ir(contents, args.input_file.clone(), &mut stats);
+    for line in &stats.preprocessor_trace {
+        eprintln!("{}", line);
+    }
+    let code = code.unwrap_or_else(|e| {
+        report(&e, contents, args);
+        process::exit(1);
+    });
+    let output = ezlang::to_brainfuck_with_options(&code, options);
+    for warning in &stats.warnings {
+        report_warning(warning, contents, args);
+    }
+    if args.verify {
+        check_optimized(&code, args, options);
+    }
+
     fs::write(&args.output_file, output).unwrap_or_else(|e| {
         match e.kind() {
             ErrorKind::PermissionDenied => {
@@ -39,4 +176,239 @@ pub fn main() {
         }
         process::exit(1);
     });
+
+    if let Some(ref deps_file) = args.deps_file {
+        let deps = dependencies(deps_file, args, &stats.included_files);
+        fs::write(deps_file, deps).unwrap_or_else(|e| {
+            println!("Cannot write '{}': {}", deps_file, e);
+            process::exit(1);
+        });
+    }
+
+    if let Some(ref ir_file) = args.ir_file {
+        fs::write(ir_file, code.to_text()).unwrap_or_else(|e| {
+            println!("Cannot write '{}': {}", ir_file, e);
+            process::exit(1);
+        });
+    }
+
+    if args.emit_ir {
+        match args.only_fn {
+            Some(ref function) => match code.function(function) {
+                Some(code) => print!("{}", code),
+                None => {
+                    println!("No code was generated for the function {}", function);
+                    process::exit(1);
+                }
+            },
+            None => print!("{}", code),
+        }
+    }
+
+    if args.stats {
+        println!("{}", stats);
+        if args.per_fn {
+            println!("Instructions per function:");
+            for (function, count) in code.counts_per_function() {
+                println!("{}: {}", function, count);
+            }
+        }
+    }
+
+    if args.explain_memory {
+        println!("Memory used, the biggest first:");
+        for cells in &stats.memory {
+            println!("{}", cells);
+        }
+    }
+}
+
+/// Writes the annotated page of HTML of the program to `file`, instead of the brainfuck
+fn html(contents: &str, args: &Args, options: &Options, file: &str) {
+    let mut stats = Stats::default();
+    let page = report::html(contents, args.input_file.clone(), options, &mut stats);
+    for line in &stats.preprocessor_trace {
+        eprintln!("{}", line);
+    }
+    let page = page.unwrap_or_else(|e| {
+        report(&e, contents, args);
+        process::exit(1);
+    });
+    for warning in &stats.warnings {
+        report_warning(warning, contents, args);
+    }
+    fs::write(file, page).unwrap_or_else(|e| {
+        println!("Cannot write '{}': {}", file, e);
+        process::exit(1);
+    });
+}
+
+/// Runs the program at `-O0` and at its optimization level, and stops the build if what they
+/// print is different
+fn check_optimized(code: &Instructions, args: &Args, options: &Options) {
+    let input = match args.verify_stdin {
+        Some(ref file) => fs::read(file).unwrap_or_else(|e| {
+            println!("Cannot read '{}': {}", file, e);
+            process::exit(1);
+        }),
+        None => vec![],
+    };
+    match verify::verify(code, &input, options) {
+        Ok(Verification::Same) => (),
+        Ok(Verification::Skipped) => println!(
+            "Skipping --verify: the program has ezraw code, which the interpreter can't check"
+        ),
+        Ok(Verification::Differs(divergence)) => {
+            println!("{}", divergence);
+            process::exit(1);
+        }
+        Err(e) => {
+            println!("An error occured while verifying: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Lists the files the output was made from, the main file first. A `.json` file gets a JSON
+/// list of them, any other file gets a rule like `output.bf: main.ez lib.ez` for `make`
+fn dependencies(deps_file: &str, args: &Args, included: &[PathBuf]) -> String {
+    let mut files = vec![args.input_file.clone()];
+    for file in included {
+        let file = file.display().to_string();
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    if deps_file.ends_with(".json") {
+        let files: Vec<_> = files
+            .iter()
+            .map(|f| format!("\"{}\"", f.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect();
+        format!("[{}]\n", files.join(", "))
+    } else {
+        let escape = |f: &String| f.replace(' ', "\\ ");
+        let files: Vec<_> = files.iter().map(escape).collect();
+        format!("{}: {}\n", escape(&args.output_file), files.join(" "))
+    }
+}
+
+fn test(contents: &str, args: &Args, options: &Options, function: &str, call_args: &[i64]) {
+    let code = ezlang::parse_with_options(contents, args.input_file.clone(), options)
+        .and_then(|ast| ezlang::compile_function_call(ast, function, call_args))
+        .unwrap_or_else(|e| {
+            report(&e, contents, args);
+            process::exit(1);
+        });
+
+    let bf_code = ezlang::to_brainfuck(&code);
+    if let Err(e) = interpreter::interpret(&bf_code, &mut io::stdin(), &mut io::stdout()) {
+        println!("An error occured while running: {}", e);
+        process::exit(1);
+    }
+}
+
+fn refs(contents: &str, args: &Args, options: &Options, line: usize, column: usize) {
+    let analysis = ezlang::analyze_with_options(contents, args.input_file.clone(), options);
+    let analysis = analysis.unwrap_or_else(|e| {
+        report(&e, contents, args);
+        process::exit(1);
+    });
+
+    let pos = Position::new(line, column, column, FileName::new(&args.input_file));
+    let (definition, usages) = analysis.references_at(&pos).unwrap_or_else(|| {
+        println!("No symbol found at {}:{}", line, column);
+        process::exit(1);
+    });
+    println!("definition: {}", location(&definition));
+    for usage in usages {
+        println!("reference: {}", location(&usage));
+    }
+}
+
+fn location(pos: &Position) -> String {
+    format!("{}:{}:{}", pos.file, pos.line_start, pos.start)
+}
+
+fn bench(
+    contents: &str,
+    args: &Args,
+    options: &Options,
+    stdin: Option<&str>,
+    compare: Option<(u8, u8)>,
+) {
+    let mut stats = Stats::default();
+    let code = ezlang::compile_ir(contents, args.input_file.clone(), options, &mut stats)
+        .unwrap_or_else(|e| {
+            report(&e, contents, args);
+            process::exit(1);
+        });
+    let input = match stdin {
+        Some(file) => fs::read(file).unwrap_or_else(|e| {
+            println!("Cannot read '{}': {}", file, e);
+            process::exit(1);
+        }),
+        None => vec![],
+    };
+    let run = |opt_level| {
+        bench::run(&code, &stats.source_map, &input, opt_level, options.jobs).unwrap_or_else(|e| {
+            println!("An error occured while running: {}", e);
+            process::exit(1);
+        })
+    };
+
+    match compare {
+        None => println!("{}", run(options.opt_level)),
+        Some((first, second)) => {
+            let (before, after) = (run(first), run(second));
+            println!("{}", before);
+            println!(
+                "Brainfuck steps: {} at -O{}, {} at -O{} ({:+})",
+                before.steps,
+                first,
+                after.steps,
+                second,
+                after.steps as i64 - before.steps as i64
+            );
+        }
+    }
+}
+
+/// Runs the program, or the intermediate code in `contents` if `ir` is set, which skips
+/// compiling it again
+fn run(contents: &str, args: &Args, options: &Options, ir: bool, stdin: Option<&str>) {
+    let code = if ir {
+        Instructions::from_text(contents).unwrap_or_else(|e| {
+            println!(
+                "Cannot read the intermediate code in '{}': {}",
+                args.input_file, e
+            );
+            process::exit(1);
+        })
+    } else {
+        let mut stats = Stats::default();
+        let code = ezlang::compile_ir(contents, args.input_file.clone(), options, &mut stats)
+            .unwrap_or_else(|e| {
+                report(&e, contents, args);
+                process::exit(1);
+            });
+        for warning in &stats.warnings {
+            report_warning(warning, contents, args);
+        }
+        code
+    };
+    let bf_code = ezlang::to_brainfuck_with_options(&code, options);
+    let result = match stdin {
+        Some(file) => {
+            let input = fs::read(file).unwrap_or_else(|e| {
+                println!("Cannot read '{}': {}", file, e);
+                process::exit(1);
+            });
+            interpreter::interpret(&bf_code, &mut input.as_slice(), &mut io::stdout())
+        }
+        None => interpreter::interpret(&bf_code, &mut io::stdin(), &mut io::stdout()),
+    };
+    if let Err(e) = result {
+        println!("An error occured while running: {}", e);
+        process::exit(1);
+    }
 }
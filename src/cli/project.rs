@@ -0,0 +1,37 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Component, Path},
+};
+
+/// The files of a new project, with their names and contents
+const TEMPLATES: [(&str, &str); 3] = [
+    ("main.ez", include_str!("templates/main.ez")),
+    ("lib.ez", include_str!("templates/lib.ez")),
+    ("ez.toml", include_str!("templates/ez.toml")),
+];
+
+/// Creates a project in the new directory `name`, with a `main.ez` using a `lib.ez` and an
+/// `ez.toml`. Nothing is created if the directory already exists
+pub fn create(name: &str) -> Result<(), String> {
+    let dir = Path::new(name);
+    if !matches!(dir.components().next_back(), Some(Component::Normal(_))) {
+        return Err(format!("Invalid project name: '{}'", name));
+    }
+    // `create_dir` fails if the directory exists, so that nothing in it gets overwritten
+    fs::create_dir(dir).map_err(|e| match e.kind() {
+        ErrorKind::AlreadyExists => format!("Cannot create project '{}': it already exists", name),
+        ErrorKind::NotFound => format!(
+            "Cannot create project '{}': the directory it would be in doesn't exist",
+            name
+        ),
+        ErrorKind::PermissionDenied => {
+            format!("Cannot create project '{}': Permission denied", name)
+        }
+        _ => format!("An error occured: {}", e),
+    })?;
+    for (file, contents) in TEMPLATES {
+        fs::write(dir.join(file), contents).map_err(|e| format!("An error occured: {}", e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+
+use crate::utils::{
+    diag::Position,
+    ir::{Instruction, Instructions},
+};
+
+use super::{compiler, interpreter};
+
+/// How much work a program did while running
+#[derive(Debug, Clone, Default)]
+pub struct Bench {
+    /// How many instructions of the intermediate code of every kind ran, the most first
+    pub instructions: Vec<(&'static str, u64)>,
+    /// How many times the body of every `while` loop ran, with the code of the loop,
+    /// in the order of the loops in the code
+    pub loops: Vec<(Option<Position>, u64)>,
+    /// How many brainfuck instructions ran
+    pub steps: u64,
+}
+
+impl Bench {
+    /// The total number of instructions of the intermediate code that ran
+    pub fn total(&self) -> u64 {
+        self.instructions.iter().map(|(_, count)| count).sum()
+    }
+}
+
+impl fmt::Display for Bench {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, count) in &self.instructions {
+            writeln!(f, "{:<12} {:>12}", name, count)?;
+        }
+        writeln!(f, "{:<12} {:>12}", "Total", self.total())?;
+        for (position, count) in &self.loops {
+            match position {
+                Some(pos) => write!(f, "while at {}:{}:{}", pos.file, pos.line_start, pos.start)?,
+                None => write!(f, "while")?,
+            }
+            writeln!(f, " ran {} times", count)?;
+        }
+        write!(f, "Brainfuck steps: {}", self.steps)
+    }
+}
+
+/// Runs the intermediate code with the passed input, counting the instructions that ran.
+/// `source_map` has the code every instruction was generated from, like
+/// [`crate::utils::Stats::source_map`]. The brainfuck steps are counted on the code optimized
+/// at `opt_level`, the other counts don't depend on it.
+/// The output of the program is thrown away
+/// # Errors
+/// Returns the errors of [`interpreter::interpret`]
+/// # Examples
+/// ```
+/// use ezlang::core::bench;
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "let i = 0\nwhile (i < 3) {\n    i += 1\n}\nezout i";
+/// let mut stats = Stats::default();
+/// let ir = ezlang::compile_ir(code, "main.ez".to_string(), &Options::default(), &mut stats).unwrap();
+/// let result = bench::run(&ir, &stats.source_map, b"", 0, 1).unwrap();
+///
+/// let count = |name| result.instructions.iter().find(|(n, _)| *n == name).unwrap().1;
+/// assert_eq!(count("While"), 1);
+/// assert_eq!(count("EndWhile"), 3);
+/// assert_eq!(count("Print"), 1);
+/// assert_eq!(result.loops.len(), 1);
+/// assert_eq!(result.loops[0].1, 3);
+/// assert_eq!(result.loops[0].0.as_ref().unwrap().line_start, 2);
+///
+/// // Optimizing the brainfuck code runs fewer steps, but the same instructions
+/// let optimized = bench::run(&ir, &stats.source_map, b"", 1, 1).unwrap();
+/// assert!(optimized.steps < result.steps);
+/// assert_eq!(optimized.total(), result.total());
+/// ```
+pub fn run(
+    code: &Instructions,
+    source_map: &[Option<Position>],
+    input: &[u8],
+    opt_level: u8,
+    jobs: usize,
+) -> Result<Bench, String> {
+    let (mut bf_code, starts) = compiler::transpile_mapped(code);
+    let counts = interpreter::profile(&bf_code, &mut &input[..], &mut std::io::sink())?;
+
+    // The first brainfuck instruction of an instruction runs once every time it runs,
+    // instructions without any code are counted as never running
+    let ran = |i: usize| {
+        let end = starts.get(i + 1).copied().unwrap_or(counts.len());
+        if starts[i] < end {
+            counts[starts[i]]
+        } else {
+            0
+        }
+    };
+
+    let mut instructions: Vec<(&'static str, u64)> = vec![];
+    let mut loops = vec![];
+    let mut open = vec![];
+    for (i, (_, instruction)) in code.0.iter().enumerate() {
+        match instructions
+            .iter_mut()
+            .find(|(name, _)| *name == instruction.name())
+        {
+            Some((_, count)) => *count += ran(i),
+            None => instructions.push((instruction.name(), ran(i))),
+        }
+        match instruction {
+            Instruction::While(_) => {
+                open.push(loops.len());
+                loops.push((source_map.get(i).cloned().flatten(), 0));
+            }
+            Instruction::EndWhile(_) => {
+                if let Some(start) = open.pop() {
+                    loops[start].1 = ran(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    instructions.retain(|(_, count)| *count > 0);
+    instructions.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    let steps = if opt_level > 0 {
+        crate::optimize(&mut bf_code, jobs);
+        interpreter::profile(&bf_code, &mut &input[..], &mut std::io::sink())?
+            .iter()
+            .sum()
+    } else {
+        counts.iter().sum()
+    };
+    Ok(Bench {
+        instructions,
+        loops,
+        steps,
+    })
+}
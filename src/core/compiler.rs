@@ -1,11 +1,76 @@
-use crate::utils::{Instruction, Instructions, Val, POINTER_SIZE};
+use crate::utils::ir::{Instruction, Instructions, Val, POINTER_SIZE};
 
 /// Compiles the 3-address code into brainfuck code.
+/// # Examples
+/// Reading and writing through pointers, also from functions and past the 255th cell
+/// ```
+/// let code = "
+/// let a = 5
+/// let c: point int = *point a
+/// ezout *c, c[0]
+/// ez set(p: point int, v: int) { *p = v }
+/// let v = ezin
+/// set(c, v + 1)
+/// ezout a
+/// let arr = [1, 2, 3, 4]
+/// let q: point int = arr
+/// q[1] = v
+/// ezout arr[1], *(q + 2), q[3]
+/// ez bump(p: point int, n: int) { for (let i = 0 : i < n : i += 1) { p[i] += 10 } }
+/// bump(arr, 4)
+/// ezout arr[0], arr[1], arr[2], arr[3]
+/// let big = [0; 100 * 3]
+/// let r: point int = big
+/// r[255] = v
+/// ezout big[255], big[254]
+/// let s: point char = \"hi\"
+/// s[0] = 'H'
+/// ezout s
+/// ";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut "7".as_bytes(), &mut output).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), "5 587 3 411 17 13 147 0Hi");
+///
+/// let error = |code: &str| ezlang::run(code, "main.ez".to_string()).unwrap_err().details;
+/// assert_eq!(error("let x = 1\nezout *x"), "Cannot dereference `x` of type int, it is not a reference or a pointer");
+/// assert_eq!(error("let x = 3\nezout x[0]"), "Cannot index type int, it is not an array");
+/// assert_eq!(error("let a = 1\nlet p: point char = *point a"), "Cannot assign *point int to *point char");
+/// assert_eq!(error("let a = 1\nlet p = *point a;\n*p = true"), "Cannot assign bool to int");
+/// assert_eq!(error("let p = *point 3"), "Cannot take the address of integer");
+/// ```
 pub fn transpile(code: &Instructions) -> String {
+    transpile_mapped(code).0
+}
+
+/// Compiles the 3-address code into brainfuck code like [`transpile`], and also returns where
+/// the code of every instruction starts, as the number of brainfuck instructions before it.
+/// It is the index [`crate::core::interpreter::profile`] gives the first of them
+/// # Examples
+/// ```
+/// use ezlang::core::compiler;
+/// use ezlang::utils::ir::{Instruction, Instructions, Val, ValType};
+///
+/// let mut code = Instructions::new();
+/// code.push(Instruction::Copy(Val::Num(2)), (Some((32768, 1)), 32769));
+/// code.push(Instruction::Ascii(Val::Index(32768, ValType::Number)), (None, 32769));
+/// let (bf, starts) = compiler::transpile_mapped(&code);
+/// assert_eq!(starts, [0, 5]);
+/// assert!(bf.starts_with("[-]++"));
+/// ```
+pub fn transpile_mapped(code: &Instructions) -> (String, Vec<usize>) {
     use crate::goto_add;
     let mut location = 2usize.pow(15);
     let mut bf_code = String::new();
+    let mut starts = vec![];
+    let (mut counted, mut commands) = (0, 0);
     for (assign, instruction) in &code.0 {
+        commands += bf_code[counted..]
+            .bytes()
+            .filter(|c| b"+-<>[].,!$".contains(c))
+            .count();
+        counted = bf_code.len();
+        starts.push(commands);
         let free_idx = assign.1;
         let size = if let Some((val, size)) = assign.0 {
             goto(&mut bf_code, &mut location, val);
@@ -18,6 +83,11 @@ pub fn transpile(code: &Instructions) -> String {
             Instruction::Input => {
                 bf_code.push(',');
             }
+            // The raw code starts at the first free cell, and has to end there
+            Instruction::Raw(code) => {
+                goto(&mut bf_code, &mut location, free_idx);
+                bf_code.push_str(code);
+            }
             Instruction::Print(val) => {
                 goto(&mut bf_code, &mut location, free_idx);
                 goto_add!(val, &mut bf_code, &mut location, {
@@ -66,7 +136,7 @@ pub fn transpile(code: &Instructions) -> String {
                 match val.get_size() {
                     1 => bf_code.push('-'),
                     2 => {
-                        "[>>+>+<<<-]>>>[-<<<+>>>][-]<[->-<]+>[<->[-]]>>[-]<<[-]<[>+>>+<<<-]>>>[-<<<+>>>]<<[<<->>[-]]<<<-";
+                        bf_code.push_str("[>>+>+<<<-]>>>[-<<<+>>>][-]<[->-<]+>[<->[-]]>>[-]<<[-]<[>+>>+<<<-]>>>[-<<<+>>>]<<[<<->>[-]]<<<-");
                     }
                     _ => todo!(),
                 };
@@ -85,6 +155,17 @@ pub fn transpile(code: &Instructions) -> String {
                 });
                 bf_code.push_str(">[-]<[>-<-]>[<->+]<-");
             }
+            Instruction::Add(left, right) if size == POINTER_SIZE => {
+                goto_add!(left, &mut bf_code, &mut location, {
+                    copy(&mut bf_code, location, start, location, free_idx, size);
+                });
+                goto(&mut bf_code, &mut location, free_idx);
+                goto_add!(right, &mut bf_code, &mut location, {
+                    copy(&mut bf_code, location, free_idx, location, free_idx + 1, 1);
+                    goto(&mut bf_code, &mut location, free_idx);
+                });
+                add_to_pointer(&mut bf_code, &mut location, start, free_idx);
+            }
             Instruction::Add(left, right) => {
                 goto_add!(left, &mut bf_code, &mut location, {
                     copy(&mut bf_code, location, start, location, free_idx, size);
@@ -172,7 +253,7 @@ pub fn transpile(code: &Instructions) -> String {
                     );
                 });
                 goto(&mut bf_code, &mut location, start);
-                bf_code.push_str("[-]>[>[<<+>>-]<-]>[-]<<");
+                bf_code.push_str("[-]>[[-]>[<<+>>[-]]<]>[-]<<");
             }
             Instruction::LNot(val) => {
                 goto(&mut bf_code, &mut location, start + size);
@@ -187,7 +268,7 @@ pub fn transpile(code: &Instructions) -> String {
                     );
                 });
                 goto(&mut bf_code, &mut location, start);
-                bf_code.push_str("+>[-<->]<");
+                bf_code.push_str("[-]+>[<->[-]]<");
             }
             Instruction::Mul(left, right) => {
                 goto_add!(left, &mut bf_code, &mut location, {
@@ -249,7 +330,8 @@ pub fn transpile(code: &Instructions) -> String {
                     );
                 });
                 goto(&mut bf_code, &mut location, start + size);
-                bf_code.push_str("[>->+<[>]>[<+>-]<<[<]>-]>[-]>[-<<<+>>>]<<");
+                // The loop needs the cell before the dividend and the two after the divisor to be empty
+                bf_code.push_str("<[-]>>>[-]>[-]<<<[>->+<[>]>[<+>-]<<[<]>-]>[-]>[-<<<+>>>]<<");
                 goto(&mut bf_code, &mut location, start);
             }
             Instruction::Eq(left, right) => {
@@ -294,7 +376,7 @@ pub fn transpile(code: &Instructions) -> String {
                     );
                 });
                 goto(&mut bf_code, &mut location, start + size);
-                bf_code.push_str("[->-<]+>[<->[-]]< <+>[-<->]");
+                bf_code.push_str("[->-<]+>[<->[-]]<<[-]+>[-<->]");
                 goto(&mut bf_code, &mut location, start);
             }
             Instruction::Lt(left, right) => {
@@ -349,7 +431,7 @@ pub fn transpile(code: &Instructions) -> String {
                     );
                 });
                 goto(&mut bf_code, &mut location, start);
-                bf_code.push_str("[>+<-]>[<+>[-]]<");
+                bf_code.push_str("[[-]>[-]+<]>[<+>[-]]<");
             }
             Instruction::Shl(left, right) => {
                 goto_add!(left, &mut bf_code, &mut location, {
@@ -422,7 +504,7 @@ pub fn transpile(code: &Instructions) -> String {
                     );
                 });
                 goto(&mut bf_code, &mut location, start);
-                bf_code.push_str("[-]>[>-<-]>[<<+>>[-]]<<");
+                bf_code.push_str("[-]>[[-]<+>]<[->+<]>>[[-]<<+>>]<<[->>+<<]>[>-<-]>[<<+>>[-]]<<");
             }
             Instruction::While(cond) => {
                 goto_add!(cond, &mut bf_code, &mut location, {});
@@ -489,36 +571,38 @@ pub fn transpile(code: &Instructions) -> String {
                     free_idx + POINTER_SIZE,
                     POINTER_SIZE,
                 );
-                goto(&mut bf_code, &mut location, free_idx);
-                bf_code.push('!');
-                goto_add!(assign, &mut bf_code, &mut location, {
-                    let start = location;
+                if let Val::Index(from, _) | Val::Ref(from, _) = assign {
                     for i in 0..size {
-                        bf_code.push_str(&format!("{}[-]$", ">".repeat(i)));
-                        location = 2usize.pow(15);
-                        goto(&mut bf_code, &mut location, free_idx + POINTER_SIZE + i);
+                        let temp = free_idx + POINTER_SIZE + i;
+                        goto(&mut bf_code, &mut location, temp);
                         bf_code.push_str("[-]");
                         goto(&mut bf_code, &mut location, free_idx);
-                        bf_code.push_str(&format!("!{}[$", ">".repeat(i)));
-                        location = 2usize.pow(15);
-                        goto(&mut bf_code, &mut location, start + i);
-                        bf_code.push('+');
-                        goto(&mut bf_code, &mut location, free_idx + POINTER_SIZE + i);
-                        bf_code.push('+');
-                        goto(&mut bf_code, &mut location, free_idx);
-                        bf_code.push_str(&format!("!{}-]$", ">".repeat(i)));
+                        bf_code.push_str(&format!("!{}[-]$", ">".repeat(i)));
                         location = 2usize.pow(15);
-                        goto(&mut bf_code, &mut location, free_idx + POINTER_SIZE + i);
+                        goto(&mut bf_code, &mut location, from + i);
                         bf_code.push_str("[-");
+                        goto(&mut bf_code, &mut location, temp);
+                        bf_code.push('+');
                         goto(&mut bf_code, &mut location, free_idx);
                         bf_code.push_str(&format!("!{}+$", ">".repeat(i)));
                         location = 2usize.pow(15);
-                        goto(&mut bf_code, &mut location, free_idx + POINTER_SIZE + i);
+                        goto(&mut bf_code, &mut location, from + i);
+                        bf_code.push(']');
+                        goto(&mut bf_code, &mut location, temp);
+                        bf_code.push_str("[-");
+                        goto(&mut bf_code, &mut location, from + i);
+                        bf_code.push('+');
+                        goto(&mut bf_code, &mut location, temp);
                         bf_code.push(']');
                     }
-                });
-                bf_code.push('$');
-                location = 2usize.pow(15);
+                } else {
+                    goto(&mut bf_code, &mut location, free_idx);
+                    bf_code.push('!');
+                    goto_add!(assign, &mut bf_code, &mut location, {});
+                    bf_code.push('$');
+                    location = 2usize.pow(15);
+                }
+                clear_pointer_copy(&mut bf_code, &mut location, free_idx);
                 goto(&mut bf_code, &mut location, start);
             }
             Instruction::Deref(val) => {
@@ -554,17 +638,122 @@ pub fn transpile(code: &Instructions) -> String {
                     goto(&mut bf_code, &mut location, free_idx + POINTER_SIZE + i);
                     bf_code.push(']');
                 }
+                clear_pointer_copy(&mut bf_code, &mut location, free_idx);
+                goto(&mut bf_code, &mut location, start);
+            }
+            Instruction::Shr(left, right) => {
+                goto_add!(left, &mut bf_code, &mut location, {
+                    copy(&mut bf_code, location, start, location, free_idx, size);
+                });
+                goto(&mut bf_code, &mut location, start + size);
+                goto_add!(right, &mut bf_code, &mut location, {
+                    copy(
+                        &mut bf_code,
+                        location,
+                        start + size,
+                        location,
+                        free_idx + size,
+                        size,
+                    );
+                });
+                let (count, rem) = (start + 1, start + 2);
+                goto(&mut bf_code, &mut location, count);
+                bf_code.push_str("[-");
+                halve(&mut bf_code, &mut location, start, rem, start + 3);
+                goto(&mut bf_code, &mut location, rem);
+                bf_code.push_str("[-]");
+                goto(&mut bf_code, &mut location, count);
+                bf_code.push(']');
                 goto(&mut bf_code, &mut location, start);
             }
-            Instruction::Shr(_, _) => todo!(),
-            Instruction::BAnd(_, _) => todo!(),
-            Instruction::BOr(_, _) => todo!(),
-            Instruction::BXor(_, _) => todo!(),
+            Instruction::BAnd(left, right) => {
+                bitwise(
+                    &mut bf_code,
+                    &mut location,
+                    start,
+                    left,
+                    right,
+                    |bf, current, bits, weight| {
+                        let (result, a, b) = (start, bits, bits + 1);
+                        goto(bf, current, a);
+                        bf.push_str("[-");
+                        goto(bf, current, b);
+                        bf.push_str("[-");
+                        goto(bf, current, result);
+                        bf.push_str(&"+".repeat(weight));
+                        goto(bf, current, b);
+                        bf.push(']');
+                        goto(bf, current, a);
+                        bf.push(']');
+                        goto(bf, current, b);
+                        bf.push_str("[-]");
+                    },
+                );
+            }
+            Instruction::BOr(left, right) => {
+                bitwise(
+                    &mut bf_code,
+                    &mut location,
+                    start,
+                    left,
+                    right,
+                    |bf, current, bits, weight| {
+                        let (result, a, b) = (start, bits, bits + 1);
+                        goto(bf, current, b);
+                        bf.push_str("[-");
+                        goto(bf, current, a);
+                        bf.push('+');
+                        goto(bf, current, b);
+                        bf.push(']');
+                        goto(bf, current, a);
+                        bf.push_str("[[-]");
+                        goto(bf, current, result);
+                        bf.push_str(&"+".repeat(weight));
+                        goto(bf, current, a);
+                        bf.push(']');
+                    },
+                );
+            }
+            Instruction::BXor(left, right) => {
+                bitwise(
+                    &mut bf_code,
+                    &mut location,
+                    start,
+                    left,
+                    right,
+                    |bf, current, bits, weight| {
+                        let (result, a, b) = (start, bits, bits + 1);
+                        goto(bf, current, b);
+                        bf.push_str("[-");
+                        goto(bf, current, a);
+                        bf.push('+');
+                        goto(bf, current, b);
+                        bf.push(']');
+                        // `a` holds the number of set bits, `b` is set if it is exactly 1
+                        goto(bf, current, a);
+                        bf.push_str("[-");
+                        goto(bf, current, b);
+                        bf.push('+');
+                        goto(bf, current, a);
+                        bf.push_str("[-");
+                        goto(bf, current, b);
+                        bf.push('-');
+                        goto(bf, current, a);
+                        bf.push_str("]]");
+                        goto(bf, current, b);
+                        bf.push_str("[-");
+                        goto(bf, current, result);
+                        bf.push_str(&"+".repeat(weight));
+                        goto(bf, current, b);
+                        bf.push(']');
+                    },
+                );
+            }
             _ => unreachable!(),
         }
         bf_code.push_str("\n|");
     }
-    bf_code
+    (bf_code, starts)
 }
 
 /// Goes from the `from` location to the `to` location
@@ -589,7 +778,7 @@ macro_rules! goto_add {
             Val::Num(val) => {
                 $bf_code.push_str("[-]");
                 if *val < 0 {
-                    $bf_code.push_str(&("-".repeat(-*val as u32 as usize)));
+                    $bf_code.push_str(&("-".repeat(val.unsigned_abs() as usize)));
                 } else {
                     $bf_code.push_str(&("+".repeat(*val as u32 as usize)));
                 }
@@ -626,7 +815,7 @@ macro_rules! goto_add {
             Val::Num(val) => {
                 $bf_code.push_str("[-]");
                 if *val < 0 {
-                    $bf_code.push_str(&("-".repeat(-*val as u32 as usize)));
+                    $bf_code.push_str(&("-".repeat(val.unsigned_abs() as usize)));
                 } else {
                     $bf_code.push_str(&("+".repeat(*val as u32 as usize)));
                 }
@@ -664,6 +853,125 @@ macro_rules! goto_add {
     };
 }
 
+/// Divides the value at `cell` by 2, and puts the remainder in the empty cell `rem`.
+/// Uses the 6 cells from `scratch`, which are left empty
+fn halve(bf_code: &mut String, current: &mut usize, cell: usize, rem: usize, scratch: usize) {
+    for i in 0..6 {
+        goto(bf_code, current, scratch + i);
+        bf_code.push_str("[-]");
+    }
+    goto(bf_code, current, cell);
+    bf_code.push_str("[-");
+    goto(bf_code, current, scratch);
+    bf_code.push('+');
+    goto(bf_code, current, cell);
+    bf_code.push(']');
+    goto(bf_code, current, scratch + 2);
+    bf_code.push_str("++");
+    // n 0 d -> 0 n d-n%d n%d n/d, the same divmod `Print` uses
+    goto(bf_code, current, scratch);
+    bf_code.push_str("[->+>-[>+>>]>[+[-<+>]>+>>]<<<<<<]");
+    for i in 1..3 {
+        goto(bf_code, current, scratch + i);
+        bf_code.push_str("[-]");
+    }
+    for (from, to) in [(scratch + 3, rem), (scratch + 4, cell)] {
+        goto(bf_code, current, from);
+        bf_code.push_str("[-");
+        goto(bf_code, current, to);
+        bf_code.push('+');
+        goto(bf_code, current, from);
+        bf_code.push(']');
+    }
+}
+
+/// Computes a bitwise operation of `left` and `right` into `start`, one bit at a time.
+/// The bits of both operands are put in the two cells at the index given to `combine`,
+/// which has to add the weight of the bit to `start` if needed, and leave both cells empty
+fn bitwise(
+    bf_code: &mut String,
+    current: &mut usize,
+    start: usize,
+    left: &Val,
+    right: &Val,
+    combine: impl Fn(&mut String, &mut usize, usize, usize),
+) {
+    let (a, b, bits, scratch) = (start + 1, start + 2, start + 3, start + 5);
+    goto(bf_code, current, start);
+    bf_code.push_str("[-]");
+    for (val, cell) in [(left, a), (right, b)] {
+        goto(bf_code, current, cell);
+        goto_add!(val, bf_code, current, {
+            copy(bf_code, *current, cell, *current, scratch, 1);
+        });
+    }
+    for bit in 0..8 {
+        halve(bf_code, current, a, bits, scratch);
+        halve(bf_code, current, b, bits + 1, scratch);
+        combine(bf_code, current, bits, 1 << bit);
+    }
+    for cell in [a, b] {
+        goto(bf_code, current, cell);
+        bf_code.push_str("[-]");
+    }
+    goto(bf_code, current, start);
+}
+
+/// Clears the copy of a pointer made at `free_idx` to dereference it, as the cells after
+/// `free_idx` are expected to be empty by the following instructions
+fn clear_pointer_copy(bf_code: &mut String, current: &mut usize, free_idx: usize) {
+    for i in 0..POINTER_SIZE {
+        goto(bf_code, current, free_idx + i);
+        bf_code.push_str("[-]");
+    }
+}
+
+/// Adds the byte at `amount` to the pointer at `ptr`, carrying into its high byte.
+/// Empties `amount` and uses the cells after it as scratch
+fn add_to_pointer(bf_code: &mut String, current: &mut usize, ptr: usize, amount: usize) {
+    let (flag, test, restore) = (amount + 1, amount + 2, amount + 3);
+    goto(bf_code, current, amount);
+    bf_code.push('[');
+    bf_code.push('-');
+    goto(bf_code, current, ptr);
+    bf_code.push('+');
+    // Set the flag, and clear it if the low byte didn't wrap around to 0
+    goto(bf_code, current, flag);
+    bf_code.push_str("[-]+");
+    goto(bf_code, current, test);
+    bf_code.push_str("[-]");
+    goto(bf_code, current, restore);
+    bf_code.push_str("[-]");
+    goto(bf_code, current, ptr);
+    bf_code.push('[');
+    goto(bf_code, current, test);
+    bf_code.push('+');
+    goto(bf_code, current, restore);
+    bf_code.push('+');
+    goto(bf_code, current, ptr);
+    bf_code.push_str("-]");
+    goto(bf_code, current, restore);
+    bf_code.push('[');
+    goto(bf_code, current, ptr);
+    bf_code.push('+');
+    goto(bf_code, current, restore);
+    bf_code.push_str("-]");
+    goto(bf_code, current, test);
+    bf_code.push('[');
+    goto(bf_code, current, flag);
+    bf_code.push_str("[-]");
+    goto(bf_code, current, test);
+    bf_code.push_str("[-]]");
+    goto(bf_code, current, flag);
+    bf_code.push('[');
+    goto(bf_code, current, ptr + 1);
+    bf_code.push('+');
+    goto(bf_code, current, flag);
+    bf_code.push_str("-]");
+    goto(bf_code, current, amount);
+    bf_code.push(']');
+}
+
 /// Copies the value from the `from` location to the `to` location. Uses the current location as a reference
 fn copy(
     bf_code: &mut String,
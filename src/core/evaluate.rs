@@ -1,13 +1,18 @@
 use std::collections::HashMap;
 
-use crate::utils::{Instruction, Instructions, Val, ValNumber};
+use crate::utils::{
+    ir::{Instruction, Instructions, Val, ValNumber},
+    token::TokenType,
+};
 
 /// Evaluates constant time operations during compile time
 pub fn evaluate(code: &Instructions) -> Instructions {
     use crate::check;
     let mut vars = HashMap::new();
     let mut new = Instructions::new();
+    let mut starts = vec![];
     for (assign, instruction) in &code.0 {
+        starts.push(new.0.len());
         let evaluated = match instruction {
             Instruction::Add(left, right) => {
                 check!(BINARY left, right, new, vars, assign, instruction);
@@ -50,6 +55,12 @@ pub fn evaluate(code: &Instructions) -> Instructions {
                 new.push(instruction.clone(), *assign);
                 continue;
             }
+            // Raw code can change any cell, so no value is known after it
+            Instruction::Raw(_) => {
+                vars.clear();
+                new.push(instruction.clone(), *assign);
+                continue;
+            }
             Instruction::Print(val) => {
                 check!(VAL val, new, vars, assign, instruction);
                 let left_str = val.to_string();
@@ -210,9 +221,65 @@ pub fn evaluate(code: &Instructions) -> Instructions {
         };
         vars.insert(assign.0.unwrap().0, evaluated);
     }
+    new.keep_regions(code, &starts);
     new
 }
 
+/// Folds a binary operation on two constant values.
+/// Numbers and chars are treated as the unsigned bytes they are stored as on the tape,
+/// so the folded value is the same as the one the generated code would compute.
+/// Returns `None` if the operation cannot be folded, like a division by zero.
+pub fn fold_binary(op: &TokenType, left: &Val, right: &Val) -> Option<Val> {
+    match (left, right) {
+        (Val::Num(left), Val::Num(right)) => {
+            fold_bytes(op, *left as u8, *right as u8, |n| Val::Num(n as ValNumber))
+        }
+        (Val::Char(left), Val::Char(right)) => fold_bytes(op, *left, *right, Val::Char),
+        (Val::Bool(left), Val::Bool(right)) => Some(Val::Bool(match op {
+            TokenType::LAnd => *left && *right,
+            TokenType::LOr => *left || *right,
+            TokenType::LXor | TokenType::Neq => left != right,
+            TokenType::Eq => left == right,
+            _ => return None,
+        })),
+        _ => None,
+    }
+}
+
+/// Folds a unary operation on a constant value.
+/// `++` and `--` are never folded, since they modify their operand.
+pub fn fold_unary(op: &TokenType, val: &Val) -> Option<Val> {
+    match (op, val) {
+        (TokenType::Sub, Val::Num(n)) => Some(Val::Num((*n as u8).wrapping_neg() as ValNumber)),
+        (TokenType::BNot, Val::Num(n)) => Some(Val::Num(!n)),
+        (TokenType::LNot, Val::Bool(b)) => Some(Val::Bool(!b)),
+        _ => None,
+    }
+}
+
+fn fold_bytes(op: &TokenType, left: u8, right: u8, make: fn(u8) -> Val) -> Option<Val> {
+    Some(match op {
+        TokenType::Add => make(left.wrapping_add(right)),
+        TokenType::Sub => make(left.wrapping_sub(right)),
+        TokenType::Mul => make(left.wrapping_mul(right)),
+        TokenType::Div => make(left.checked_div(right)?),
+        TokenType::Mod => make(left.checked_rem(right)?),
+        TokenType::Pow => make(left.wrapping_pow(right as u32)),
+        TokenType::Shl => make(left.checked_shl(right as u32)?),
+        TokenType::Shr => make(left.checked_shr(right as u32)?),
+        TokenType::BAnd => make(left & right),
+        TokenType::BOr => make(left | right),
+        TokenType::BXor => make(left ^ right),
+        TokenType::Eq => Val::Bool(left == right),
+        TokenType::Neq => Val::Bool(left != right),
+        TokenType::Lt => Val::Bool(left < right),
+        TokenType::Le => Val::Bool(left <= right),
+        TokenType::Gt => Val::Bool(left > right),
+        TokenType::Ge => Val::Bool(left >= right),
+        _ => return None,
+    })
+}
+
 #[macro_export]
 macro_rules! check {
     ($val:ident, $new: ident, $vars: ident, $assign: ident, $instruction: ident) => {
@@ -0,0 +1,173 @@
+use std::io::{self, Read, Write};
+
+/// The cell the tape pointer starts at, and where `$` jumps back to
+pub const ORIGIN: usize = 1 << 15;
+
+/// Number of cells on the tape, enough for every address `!` can jump to
+const TAPE_SIZE: usize = 1 << 16;
+
+/// Runs the given brainfuck++ code, reading `,` from `input` and writing `.` to `output`.
+/// Any character that isn't an instruction is ignored.
+/// # Errors
+/// Returns an error if the brackets don't match, the pointer leaves the tape,
+/// or reading or writing fails
+pub fn interpret(code: &str, input: &mut impl Read, output: &mut impl Write) -> Result<(), String> {
+    execute(code, input, output, |_| (), |_| ())
+}
+
+/// Runs the given brainfuck++ code like [`interpret`], and returns a copy of the whole tape
+/// from every time a `.` ran, right after it wrote its cell. The cells of the variables are
+/// at the same indices as in the intermediate code, starting from [`ORIGIN`]
+/// # Errors
+/// Returns the same errors as [`interpret`]
+/// # Examples
+/// ```
+/// use ezlang::core::interpreter::{snapshots, ORIGIN};
+///
+/// let code = "
+/// let a = ezin char as int;
+/// let b = ezin char as int;
+/// let raw = ezin char as bool;
+/// let lt = a < b;
+/// let chain = a < b && b <= 200 || a == b;
+/// let mixed = !(a >= b) !&| (raw && b > a);
+/// let flipped = !raw || !!raw == raw;
+/// ezascii lt, chain, mixed, raw, flipped;
+/// ";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// for a in [0, 1, 7, 200, 255] {
+///     for b in [0, 7, 201] {
+///         for raw in [0, 1, 2, 120, 255] {
+///             let mut output = vec![];
+///             let tapes = snapshots(&code, &mut [a, b, raw].as_slice(), &mut output).unwrap();
+///             let raw = raw != 0;
+///             let mixed = !(a >= b) != (raw && b > a);
+///             let expected = [a < b, a < b && b <= 200 || a == b, mixed, raw, !raw || true];
+///             assert_eq!(output, expected.map(u8::from));
+///             // Every cell after the inputs and the copies of `a` and `b` holds a bool,
+///             // whether it is a variable or an intermediate result
+///             assert!(tapes[0][ORIGIN + 5..ORIGIN + 32].iter().all(|&cell| cell <= 1));
+///         }
+///     }
+/// }
+/// ```
+pub fn snapshots(
+    code: &str,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<Vec<Vec<u8>>, String> {
+    let mut tapes = vec![];
+    execute(
+        code,
+        input,
+        output,
+        |tape| tapes.push(tape.to_vec()),
+        |_| (),
+    )?;
+    Ok(tapes)
+}
+
+/// Runs the given brainfuck++ code like [`interpret`], and returns how many times every
+/// instruction of it ran, by its index among the instructions of the code, leaving out the
+/// characters that aren't instructions
+/// # Errors
+/// Returns the same errors as [`interpret`]
+/// # Examples
+/// ```
+/// let counts = ezlang::core::interpreter::profile("+++ [->+<] >.", &mut std::io::empty(), &mut vec![]).unwrap();
+/// assert_eq!(counts, [1, 1, 1, 1, 3, 3, 3, 3, 3, 1, 1]);
+/// ```
+pub fn profile(
+    code: &str,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<Vec<u64>, String> {
+    let mut counts = vec![0; code.bytes().filter(|c| b"+-<>[].,!$".contains(c)).count()];
+    execute(code, input, output, |_| (), |ip| counts[ip] += 1)?;
+    Ok(counts)
+}
+
+/// Runs the code, calling `on_print` with the tape after every `.`, and `on_step` with the
+/// index of every instruction before it runs
+fn execute(
+    code: &str,
+    input: &mut impl Read,
+    output: &mut impl Write,
+    mut on_print: impl FnMut(&[u8]),
+    mut on_step: impl FnMut(usize),
+) -> Result<(), String> {
+    let code = code
+        .bytes()
+        .filter(|c| b"+-<>[].,!$".contains(c))
+        .collect::<Vec<_>>();
+    let jumps = match_brackets(&code)?;
+    let mut tape = vec![0u8; TAPE_SIZE];
+    let mut pointer = ORIGIN;
+    let mut ip = 0;
+    while ip < code.len() {
+        on_step(ip);
+        match code[ip] {
+            b'+' => tape[pointer] = tape[pointer].wrapping_add(1),
+            b'-' => tape[pointer] = tape[pointer].wrapping_sub(1),
+            b'>' => {
+                pointer += 1;
+                if pointer >= TAPE_SIZE {
+                    return Err(format!("Pointer moved past the end of the tape at {}", ip));
+                }
+            }
+            b'<' => {
+                pointer = pointer.checked_sub(1).ok_or_else(|| {
+                    format!("Pointer moved before the start of the tape at {}", ip)
+                })?;
+            }
+            b'.' => {
+                output
+                    .write_all(&[tape[pointer]])
+                    .map_err(|e| e.to_string())?;
+                on_print(&tape);
+            }
+            b',' => {
+                let mut byte = [0];
+                tape[pointer] = match input.read(&mut byte) {
+                    Ok(0) => 0,
+                    Ok(_) => byte[0],
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e.to_string()),
+                };
+            }
+            b'[' if tape[pointer] == 0 => ip = jumps[ip],
+            b']' if tape[pointer] != 0 => ip = jumps[ip],
+            b'!' => {
+                let high = *tape.get(pointer + 1).unwrap_or(&0) as usize;
+                pointer = tape[pointer] as usize + (high << 8);
+            }
+            b'$' => pointer = ORIGIN,
+            _ => (),
+        }
+        ip += 1;
+    }
+    output.flush().map_err(|e| e.to_string())
+}
+
+/// Finds the matching bracket of every bracket in the code
+fn match_brackets(code: &[u8]) -> Result<Vec<usize>, String> {
+    let mut jumps = vec![0; code.len()];
+    let mut open = Vec::new();
+    for (i, c) in code.iter().enumerate() {
+        match c {
+            b'[' => open.push(i),
+            b']' => {
+                let start = open
+                    .pop()
+                    .ok_or_else(|| format!("Unmatched ']' at {}", i))?;
+                jumps[start] = i;
+                jumps[i] = start;
+            }
+            _ => (),
+        }
+    }
+    match open.pop() {
+        Some(i) => Err(format!("Unmatched '[' at {}", i)),
+        None => Ok(jumps),
+    }
+}
@@ -1,24 +1,73 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::utils::{
-    Error, ErrorType, Instruction, Instructions, Memory, Node, Token, TokenType, Val, ValNumber,
-    ValType, Variables, POINTER_SIZE,
+use crate::{
+    core::evaluate::{fold_binary, fold_unary},
+    utils::{
+        ast::{Node, Type},
+        diag::{Error, ErrorType, Position},
+        ir::{
+            CellUse, Instruction, Instructions, Memory, Val, ValNumber, ValType, Variables,
+            CELL_BITS, POINTER_SIZE,
+        },
+        token::{Token, TokenType},
+        Options, Stats,
+    },
 };
 
+/// A cell holding bools of a packed struct or array, the bits of it known at compile time, and the
+/// bools computed at run time that go in its other bits
+type PackedCell = (usize, u8, Vec<(usize, Val)>);
+
 /// Generates the Intermediate 3-address code from the AST
 pub struct CodeGenerator {
     instructions: Instructions,
     ret: Vec<(usize, usize)>,
     statics: HashMap<String, Val>,
+    #[allow(dead_code)]
     structs: Vec<ValType>,
+    /// Printed between the values of an `ezout`
+    separator: String,
+    /// What each instruction was generated for, and where it is in the code
+    owners: Vec<Option<(String, Position)>>,
+    /// The most characters the strings made by string literals and concatenations can have,
+    /// by the cell they start at, and those of the strings variables can point to, by the
+    /// cell of the variable
+    string_lengths: HashMap<usize, usize>,
 }
 
 impl CodeGenerator {
+    /// Generates the instructions of `node`, which own the cells they assign to
+    /// unless a node inside of it already does
     fn make_instruction(
         &mut self,
         node: &Node,
         vars: &mut Variables,
         memory: &mut Memory,
+    ) -> Result<Val, Error> {
+        let start = self.instructions.0.len();
+        let val = self.make_node(node, vars, memory)?;
+        self.own(start, node);
+        Ok(val)
+    }
+
+    /// Makes `node` the owner of the instructions from `start` on that don't have one
+    fn own(&mut self, start: usize, node: &Node) {
+        self.owners.resize(self.instructions.0.len(), None);
+        let mut owner = None;
+        for slot in self.owners.iter_mut().skip(start).filter(|o| o.is_none()) {
+            *slot = Some(
+                owner
+                    .get_or_insert_with(|| (describe(node), node.position()))
+                    .clone(),
+            );
+        }
+    }
+
+    fn make_node(
+        &mut self,
+        node: &Node,
+        vars: &mut Variables,
+        memory: &mut Memory,
     ) -> Result<Val, Error> {
         match node {
             Node::Number(num) => {
@@ -61,6 +110,23 @@ impl CodeGenerator {
                         ))
                     }
                 };
+                if op.token_type == TokenType::Add
+                    && left_type.is_string_part()
+                    && right_type.is_string_part()
+                {
+                    let lengths = [&left, &right].map(|val| self.string_length(val));
+                    return match lengths {
+                        [Some(a), Some(b)] => Ok(self.concat([left, right], a + b, memory)),
+                        _ => Err(Error::new(
+                            ErrorType::TypeError,
+                            op.position.clone(),
+                            "The length of a string being concatenated isn't known, only string literals, chars, concatenations and variables holding them can be concatenated".to_string(),
+                        )),
+                    };
+                }
+                if let Some(val) = fold_binary(&op.token_type, &left, &right) {
+                    return Ok(val);
+                }
                 let size = t.get_size();
                 let mut mem = memory.allocate(size);
                 match op.token_type {
@@ -115,6 +181,9 @@ impl CodeGenerator {
                         ))
                     }
                 };
+                if let Some(val) = fold_unary(&op.token_type, &expr) {
+                    return Ok(val);
+                }
                 let size = t.get_size();
                 let mem = memory.allocate(size);
                 self.instructions.push(
@@ -124,8 +193,14 @@ impl CodeGenerator {
                 Ok(Val::Index(mem, t))
             }
 
-            Node::VarAssign(var1, expr, _) => {
+            Node::VarAssign(var1, expr, t) => {
                 if let TokenType::Identifier(ref var) = var1.token_type {
+                    if let Node::None(ref pos) = **expr {
+                        let type_ = ValType::from_parse_type(t, pos)?;
+                        let mem = memory.allocate(type_.get_size());
+                        vars.insert(var.clone(), Val::Index(mem, type_));
+                        return Ok(Val::None);
+                    }
                     match self.make_instruction(expr, vars, memory)? {
                         Val::Index(index, type_ @ ValType::Ref(_)) => {
                             vars.insert(var.clone(), Val::Index(index, type_));
@@ -138,6 +213,7 @@ impl CodeGenerator {
                                 Instruction::Copy(Val::Index(index, type_.clone())),
                                 (Some((mem, size)), memory.last_memory_index),
                             );
+                            self.assign_string_length(mem, &Val::Index(index, type_.clone()));
                             vars.insert(var.clone(), Val::Index(mem, type_));
                             Ok(Val::None)
                         }
@@ -152,6 +228,7 @@ impl CodeGenerator {
                             let v = val.r#type();
                             let size = val.get_size();
                             let mem = memory.allocate(v.get_size());
+                            self.assign_string_length(mem, &val);
                             self.instructions.push(
                                 Instruction::Copy(val),
                                 (Some((mem, size)), memory.last_memory_index),
@@ -165,6 +242,15 @@ impl CodeGenerator {
                 }
             }
 
+            Node::VarAccess(_, Type::Function(..)) | Node::Lambda(..) => Err(Error::new(
+                ErrorType::TypeError,
+                node.position(),
+                format!(
+                    "Values of type {} cannot be stored, functions can only be called",
+                    node.get_type()
+                ),
+            )),
+
             Node::VarAccess(var, _) => {
                 if let TokenType::Identifier(ref var) = var.token_type {
                     Ok(vars.get(var).cloned().unwrap())
@@ -212,10 +298,12 @@ impl CodeGenerator {
                                 ));
                             }
                             let size = type_.get_size();
-                            if let Val::Index(mem, _) = var {
+                            if let Val::Index(mem, _) = *var {
+                                let val = Val::Index(index, type_);
+                                self.reassign_string_length(mem, &val);
                                 self.instructions.push(
-                                    Instruction::Copy(Val::Index(index, type_)),
-                                    (Some((*mem, size)), memory.last_memory_index),
+                                    Instruction::Copy(val),
+                                    (Some((mem, size)), memory.last_memory_index),
                                 );
                             } else {
                                 unreachable!();
@@ -259,10 +347,11 @@ impl CodeGenerator {
                                     ),
                                 ));
                             }
-                            if let Val::Index(mem, _) = var {
+                            if let Val::Index(mem, _) = *var {
+                                self.reassign_string_length(mem, &val);
                                 self.instructions.push(
                                     Instruction::Copy(val),
-                                    (Some((*mem, size)), memory.last_memory_index),
+                                    (Some((mem, size)), memory.last_memory_index),
                                 );
                             } else {
                                 unreachable!();
@@ -282,26 +371,61 @@ impl CodeGenerator {
                     self.make_instruction(statement, &mut new_vars, &mut new)?;
                 }
                 *vars = *new_vars.super_vars.unwrap();
-                // if new.last_memory_index > memory.last_memory_index {
-                //     self.instructions.push(
-                //         Instruction::Clear(memory.last_memory_index, new.last_memory_index),
-                //         (None, memory.last_memory_index),
-                //     );
-                // }
+                // Cells used inside of the block are reused afterwards, and have to start out empty
+                if new.last_memory_index > memory.last_memory_index {
+                    self.instructions.push(
+                        Instruction::Clear(memory.last_memory_index, new.last_memory_index),
+                        (None, memory.last_memory_index),
+                    );
+                }
+                self.string_lengths
+                    .retain(|&cell, _| cell < memory.last_memory_index);
                 Ok(Val::None)
             }
 
-            Node::Print(exprs, _) => {
-                for expr in exprs {
+            Node::Declarations(nodes, _) => {
+                for node in nodes {
+                    self.make_instruction(node, vars, memory)?;
+                }
+                Ok(Val::None)
+            }
+
+            Node::Print(exprs, newline, _) => {
+                for (i, expr) in exprs.iter().enumerate() {
+                    // The text of a format string is printed as it is, without separators
+                    if let Node::Ascii(..) = expr {
+                        self.make_instruction(expr, vars, memory)?;
+                        continue;
+                    }
+                    if i > 0 && !matches!(exprs[i - 1], Node::Ascii(..)) {
+                        self.print_chars(&self.separator.clone(), memory);
+                    }
+                    // The characters of a string literal are known, so no loop is needed
+                    if let Node::String(Token {
+                        token_type: TokenType::String(s),
+                        ..
+                    }) = expr
+                    {
+                        self.print_chars(s, memory);
+                        continue;
+                    }
                     let expr = self.make_instruction(expr, vars, memory)?;
-                    if expr.r#type() == ValType::Char {
-                        self.instructions
-                            .push(Instruction::Ascii(expr), (None, memory.last_memory_index));
-                    } else {
-                        self.instructions
-                            .push(Instruction::Print(expr), (None, memory.last_memory_index));
+                    match expr.r#type() {
+                        ValType::Char => self
+                            .instructions
+                            .push(Instruction::Ascii(expr), (None, memory.last_memory_index)),
+                        ValType::Boolean => self.print_bool(expr, memory),
+                        ValType::Pointer(t) if *t == ValType::Char => {
+                            self.print_string(expr, memory)
+                        }
+                        _ => self
+                            .instructions
+                            .push(Instruction::Print(expr), (None, memory.last_memory_index)),
                     }
                 }
+                if *newline {
+                    self.print_chars("\n", memory);
+                }
                 Ok(Val::None)
             }
 
@@ -314,15 +438,27 @@ impl CodeGenerator {
                 Ok(Val::None)
             }
 
-            Node::Input(..) => {
-                let t = ValType::Number;
-                let size = t.get_size();
-                let mem = memory.allocate(size);
+            Node::Input(ty, prompt, _) => {
+                if let Some(prompt) = prompt {
+                    self.print_chars(prompt, memory);
+                }
+                if *ty == Type::Number {
+                    return Ok(self.read_number(memory));
+                }
+                let mem = memory.allocate(1);
                 self.instructions.push(
                     Instruction::Input,
-                    (Some((mem, size)), memory.last_memory_index),
+                    (Some((mem, 1)), memory.last_memory_index),
                 );
-                Ok(Val::Index(mem, t))
+                Ok(Val::Index(mem, ValType::Char))
+            }
+
+            Node::Raw(code, _) => {
+                self.instructions.push(
+                    Instruction::Raw(code.clone()),
+                    (None, memory.last_memory_index),
+                );
+                Ok(Val::None)
             }
 
             Node::If(cond1, then1, else1, _) => {
@@ -411,91 +547,87 @@ impl CodeGenerator {
 
             Node::None(_) => Ok(Val::None),
 
+            Node::Index(arr1, index1, ..) if is_packed_array(arr1) => {
+                let (ptr, bit) = self.packed_element(arr1, index1, vars, memory)?;
+                let cell = self.push_new(
+                    Instruction::Deref(Val::Index(ptr, ValType::Pointer(Box::new(ValType::Bits)))),
+                    1,
+                    memory,
+                );
+                Ok(self.read_bit(Val::Index(cell, ValType::Number), bit, memory))
+            }
+
             Node::Index(arr1, index1, ..) => {
-                let arr = if let TokenType::Identifier(ref var) = arr1.token_type {
-                    vars.get(var).cloned().unwrap()
-                } else {
-                    unreachable!();
-                };
-                let index = self.make_instruction(index1, vars, memory)?;
-                if index.r#type() != ValType::Number {
-                    return Err(Error::new(
-                        ErrorType::TypeError,
-                        index1.position(),
-                        format!(
-                            "Indexing can only be done with numbers, and not of type {:?}",
-                            index.r#type()
-                        ),
-                    ));
-                }
-                let arr_type = match &arr {
-                    Val::Pointer(_, t) => t.clone(),
-                    Val::Index(_, ValType::Pointer(t)) => (**t).clone(),
-                    _ => {
-                        return Err(Error::new(
-                            ErrorType::TypeError,
-                            arr1.position.clone(),
-                            format!("Cannot index type {:?}", arr.r#type()),
-                        ))
-                    }
-                };
-                let t = arr.r#type();
-                let size = arr.get_size();
-                let mem = memory.allocate(POINTER_SIZE + size);
+                let (ptr, t) = self.element_address(arr1, index1, vars, memory)?;
+                let size = t.get_size();
+                let mem = memory.allocate(size);
                 self.instructions.push(
-                    Instruction::Add(arr, index),
-                    (Some((mem, POINTER_SIZE)), memory.last_memory_index),
+                    Instruction::Deref(Val::Index(ptr, ValType::Pointer(Box::new(t.clone())))),
+                    (Some((mem, size)), memory.last_memory_index),
                 );
+                Ok(Val::Index(mem, t))
+            }
+
+            Node::IndexAssign(arr1, index1, assign) if is_packed_array(arr1) => {
+                let (ptr, bit) = self.packed_element(arr1, index1, vars, memory)?;
+                let assign = self.make_instruction(assign, vars, memory)?;
+                let ptr = Val::Index(ptr, ValType::Pointer(Box::new(ValType::Bits)));
+                let cell = self.push_new(Instruction::Deref(ptr.clone()), 1, memory);
+                let new = self.with_bit(Val::Index(cell, ValType::Number), bit, assign, memory);
                 self.instructions.push(
-                    Instruction::Deref(Val::Index(mem, t)),
-                    (Some((mem + POINTER_SIZE, size)), memory.last_memory_index),
+                    Instruction::DerefAssign(ptr, new),
+                    (None, memory.last_memory_index),
                 );
-                Ok(Val::Index(mem + POINTER_SIZE, arr_type))
+                Ok(Val::None)
             }
 
             Node::IndexAssign(arr1, index1, assign) => {
-                let arr = if let TokenType::Identifier(ref var) = arr1.token_type {
-                    vars.get(var).cloned().unwrap()
-                } else {
-                    unreachable!();
-                };
-                let index = self.make_instruction(index1, vars, memory)?;
-                if index.r#type() != ValType::Number {
-                    return Err(Error::new(
-                        ErrorType::TypeError,
-                        index1.position(),
-                        format!(
-                            "Indexing can only be done with numbers, and not of type {:?}",
-                            index.r#type()
-                        ),
-                    ));
-                }
-                let t = arr.r#type();
-                let mem = memory.allocate(POINTER_SIZE);
-                self.instructions.push(
-                    Instruction::Add(arr, index),
-                    (Some((mem, POINTER_SIZE)), memory.last_memory_index),
-                );
+                let (ptr, t) = self.element_address(arr1, index1, vars, memory)?;
                 let assign = self.make_instruction(assign, vars, memory)?;
                 self.instructions.push(
-                    Instruction::DerefAssign(Val::Index(mem, t), assign),
+                    Instruction::DerefAssign(
+                        Val::Index(ptr, ValType::Pointer(Box::new(t))),
+                        assign,
+                    ),
                     (None, memory.last_memory_index),
                 );
                 Ok(Val::None)
             }
 
-            Node::Array(elements, t, _) => {
-                let type_ = ValType::from_parse_type(t);
+            Node::Array(elements, count, Type::Bits, _) => {
+                let length = elements.len() * count;
+                let mem = memory.allocate(length.div_ceil(CELL_BITS));
+                let mut packed: Vec<PackedCell> = (mem..mem + length.div_ceil(CELL_BITS))
+                    .map(|cell| (cell, 0, vec![]))
+                    .collect();
+                for (i, element1) in elements.iter().enumerate() {
+                    let element = self.make_instruction(element1, vars, memory)?;
+                    for j in i * count..(i + 1) * count {
+                        let (_, known, computed) = &mut packed[j / CELL_BITS];
+                        match element {
+                            Val::Bool(b) => *known |= (b as u8) << (j % CELL_BITS),
+                            ref val => computed.push((j % CELL_BITS, val.clone())),
+                        }
+                    }
+                }
+                self.write_packed(packed, memory);
+                Ok(Val::Pointer(mem, ValType::Bits))
+            }
+
+            Node::Array(elements, count, t, pos) => {
+                let type_ = ValType::from_parse_type(t, pos)?;
                 let size = type_.get_size();
-                let mem = memory.allocate(size * elements.len());
+                let mem = memory.allocate(size * elements.len() * count);
                 let mut current = mem;
                 for element1 in elements {
                     let element = self.make_instruction(element1, vars, memory)?;
-                    self.instructions.push(
-                        Instruction::Copy(element),
-                        (Some((current, size)), memory.last_memory_index),
-                    );
-                    current += size;
+                    for _ in 0..*count {
+                        self.instructions.push(
+                            Instruction::Copy(element.clone()),
+                            (Some((current, size)), memory.last_memory_index),
+                        );
+                        current += size;
+                    }
                 }
                 Ok(Val::Pointer(mem, type_))
             }
@@ -503,14 +635,20 @@ impl CodeGenerator {
             Node::Return(val, ..) => {
                 let val = self.make_instruction(val, vars, memory)?;
                 let &(mem, size) = self.ret.last().unwrap();
-                self.instructions.push(
-                    Instruction::Copy(val),
-                    (Some((mem, size)), memory.last_memory_index),
-                );
+                // A function returning nothing has no return spot to copy into
+                if size > 0 {
+                    self.instructions.push(
+                        Instruction::Copy(val),
+                        (Some((mem, size)), memory.last_memory_index),
+                    );
+                }
                 Ok(Val::None)
             }
 
             Node::Ref(val1, ..) => {
+                if is_packed_field(val1) {
+                    return Err(packed_field_address(val1));
+                }
                 let val = self.make_instruction(val1, vars, memory)?;
                 if let Val::Index(n, t) | Val::Pointer(n, t) | Val::Ref(n, t) = val {
                     Ok(Val::Ref(n, t))
@@ -573,14 +711,11 @@ impl CodeGenerator {
                             (None, memory.last_memory_index),
                         );
                     } else {
-                        println!(
-                            "{}",
-                            Error::new(
-                                ErrorType::TypeError,
-                                val1.position(),
-                                format!("Cannot dereference a {}", val.r#type()),
-                            )
-                        );
+                        return Err(Error::new(
+                            ErrorType::TypeError,
+                            val1.position(),
+                            format!("Cannot dereference a {}", val.r#type()),
+                        ));
                     }
                 } else {
                     unreachable!()
@@ -724,11 +859,12 @@ impl CodeGenerator {
                 Ok(Val::None)
             }
 
-            Node::Expanded(statements, t) => {
-                let t = ValType::from_parse_type(t);
+            Node::Expanded(function, statements, t) => {
+                let t = ValType::from_parse_type(t, &node.position())?;
                 let size = t.get_size();
                 let mem = memory.allocate(size);
                 self.ret.push((mem, size));
+                let start = self.instructions.0.len();
 
                 let mut new_vars = vars.clone();
                 let mut new = memory.clone();
@@ -737,12 +873,17 @@ impl CodeGenerator {
                     self.make_instruction(statement, &mut new_vars, &mut new)?;
                 }
                 self.ret.pop().unwrap();
-                // if new.last_memory_index > memory.last_memory_index {
-                //     self.instructions.push(
-                //         Instruction::Clear(memory.last_memory_index, new.last_memory_index),
-                //         (None, memory.last_memory_index),
-                //     );
-                // }
+                // Cells used inside of the block are reused afterwards, and have to start out empty
+                if new.last_memory_index > memory.last_memory_index {
+                    self.instructions.push(
+                        Instruction::Clear(memory.last_memory_index, new.last_memory_index),
+                        (None, memory.last_memory_index),
+                    );
+                }
+                self.instructions
+                    .add_region(function.token_type.to_string(), start);
+                self.string_lengths
+                    .retain(|&cell, _| cell < memory.last_memory_index);
                 Ok(Val::Index(mem, t))
             }
 
@@ -757,24 +898,25 @@ impl CodeGenerator {
                 for p in s.chars() {
                     self.instructions.push(
                         Instruction::Copy(Val::Char(p as u8)),
-                        (Some((current_mem, POINTER_SIZE)), memory.last_memory_index),
+                        (Some((current_mem, 1)), memory.last_memory_index),
                     );
                     current_mem += 1;
                 }
                 self.instructions.push(
                     Instruction::Copy(Val::Char(0)),
-                    (Some((current_mem, POINTER_SIZE)), memory.last_memory_index),
+                    (Some((current_mem, 1)), memory.last_memory_index),
                 );
+                self.string_lengths.insert(mem, s.len());
                 Ok(Val::Pointer(mem, ValType::Char))
             }
 
             Node::Converted(n, t) => {
                 let val = self.make_instruction(n, vars, memory)?;
-                let t = ValType::from_parse_type(t);
+                let t = ValType::from_parse_type(t, &n.position())?;
                 Ok(match val {
                     Val::Num(n) => match t {
                         ValType::Boolean => Val::Bool(n != 0),
-                        ValType::Char => Val::Char((n as i16 + 128) as u8),
+                        ValType::Char => Val::Char(n as u8),
                         ValType::Number => val,
                         _ => unreachable!(),
                     },
@@ -785,12 +927,21 @@ impl CodeGenerator {
                         _ => unreachable!(),
                     },
                     Val::Char(n) => match t {
-                        ValType::Boolean => Val::Bool(n as u8 != 0),
+                        ValType::Boolean => Val::Bool(n != 0),
                         ValType::Char => val,
-                        ValType::Number => Val::Num((n as i16 - 128) as i8),
+                        ValType::Number => Val::Num(n as i8),
                         _ => unreachable!(),
                     },
                     Val::Ref(n, t) => Val::Pointer(n, t),
+                    // A bool has to be 0 or 1, so any other value is turned into 1
+                    Val::Index(n, from) if t == ValType::Boolean && from != ValType::Boolean => {
+                        let mem = memory.allocate(1);
+                        self.instructions.push(
+                            Instruction::Neq(Val::Index(n, from), Val::Num(0)),
+                            (Some((mem, 1)), memory.last_memory_index),
+                        );
+                        Val::Index(mem, t)
+                    }
                     Val::Index(n, _) => Val::Index(n, t),
                     _ => unreachable!("{val} {t}"),
                 })
@@ -807,13 +958,85 @@ impl CodeGenerator {
                 Ok(Val::None)
             }
 
-            Node::AttrAccess(_, _, _) => todo!(),
+            Node::AttrAccess(expr, attr, _) => match self.make_instruction(expr, vars, memory)? {
+                Val::Index(mem, t @ ValType::Struct(..)) => match t.field(attr).unwrap() {
+                    (offset, Some(bit), _) => Ok(self.read_bit(
+                        Val::Index(mem + offset, ValType::Number),
+                        Val::Num(bit as ValNumber),
+                        memory,
+                    )),
+                    (offset, None, t) => Ok(Val::Index(mem + offset, t)),
+                },
+                _ => unreachable!(),
+            },
+
+            Node::AttrAssign(attr, assign, _) => {
+                let location = match &**attr {
+                    Node::AttrAccess(expr, field, _) => {
+                        match self.make_instruction(expr, vars, memory)? {
+                            Val::Index(mem, t @ ValType::Struct(..)) => {
+                                let (offset, bit, t) = t.field(field).unwrap();
+                                (mem + offset, bit, t)
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                    attr => match self.make_instruction(attr, vars, memory)? {
+                        Val::Index(mem, t) => (mem, None, t),
+                        _ => unreachable!(),
+                    },
+                };
+                let assign = self.make_instruction(assign, vars, memory)?;
+                let (mem, t) = match location {
+                    (cell, Some(bit), _) => {
+                        self.write_bit(cell, bit, assign, memory);
+                        return Ok(Val::None);
+                    }
+                    (mem, None, t) => (mem, t),
+                };
+                self.instructions.push(
+                    Instruction::Copy(assign),
+                    (Some((mem, t.get_size())), memory.last_memory_index),
+                );
+                Ok(Val::None)
+            }
 
-            Node::Struct(_, _, _) => todo!(),
+            Node::Struct(..) => Ok(Val::None),
 
-            Node::StructConstructor(_, _, _) => todo!(),
+            Node::StructConstructor(_, fields, _, pos) => {
+                let t = ValType::from_parse_type(&node.get_type(), pos)?;
+                let mem = memory.allocate(t.get_size());
+                let mut packed: Vec<PackedCell> = vec![];
+                for (name, field) in fields {
+                    let val = self.make_instruction(field, vars, memory)?;
+                    match t.field(name).unwrap() {
+                        (offset, Some(bit), _) => {
+                            if bit == 0 {
+                                packed.push((mem + offset, 0, vec![]));
+                            }
+                            let (_, known, computed) = packed.last_mut().unwrap();
+                            match val {
+                                Val::Bool(b) => *known |= (b as u8) << bit,
+                                val => computed.push((bit, val)),
+                            }
+                        }
+                        (offset, None, _) => {
+                            let size = val.r#type().get_size();
+                            self.instructions.push(
+                                Instruction::Copy(val),
+                                (Some((mem + offset, size)), memory.last_memory_index),
+                            );
+                        }
+                    }
+                }
+                self.write_packed(packed, memory);
+                Ok(Val::Index(mem, t))
+            }
 
             Node::Pointer(expr, _) => {
+                if is_packed_field(expr) {
+                    return Err(packed_field_address(expr));
+                }
                 let val = self.make_instruction(expr, vars, memory)?;
                 if let Val::Index(n, t) | Val::Pointer(n, t) | Val::Ref(n, t) = val {
                     Ok(Val::Pointer(n, t))
@@ -830,13 +1053,535 @@ impl CodeGenerator {
         }
     }
 
+    /// Prints the bytes of `text` as characters
+    fn print_chars(&mut self, text: &str, memory: &Memory) {
+        for c in text.bytes() {
+            self.instructions.push(
+                Instruction::Ascii(Val::Char(c)),
+                (None, memory.last_memory_index),
+            );
+        }
+    }
+
+    /// Prints a bool as `true` or `false`, choosing the word at compile time when the bool is
+    /// known, and with an if/else otherwise
+    /// # Examples
+    /// ```
+    /// let code = "let a = ezin char == 'y'\nezout a, 2 < 1";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut "y".as_bytes(), &mut output).unwrap();
+    /// assert_eq!(output, b"true false");
+    /// ```
+    fn print_bool(&mut self, val: Val, memory: &mut Memory) {
+        if let Val::Bool(b) = val {
+            self.print_chars(if b { "true" } else { "false" }, memory);
+            return;
+        }
+        let mem = memory.allocate(2);
+        self.instructions.push(
+            Instruction::If(val, mem, true),
+            (None, memory.last_memory_index),
+        );
+        self.print_chars("true", memory);
+        self.instructions
+            .push(Instruction::Else(mem), (None, memory.last_memory_index));
+        self.print_chars("false", memory);
+        self.instructions.push(
+            Instruction::EndIf(mem, true),
+            (None, memory.last_memory_index),
+        );
+    }
+
+    /// Prints the characters `string` points to, up to the first `'\0'`
+    /// # Examples
+    /// ```
+    /// let code = "let s = \"stressed\"\nezoutln s, s + 4";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"stressed ssed\n");
+    /// ```
+    fn print_string(&mut self, string: Val, memory: &mut Memory) {
+        self.for_each_char(string, memory, |this, c, free| {
+            this.instructions.push(Instruction::Ascii(c), (None, free));
+        });
+    }
+
+    /// Runs the code `body` emits for each of the characters `string` points to, up to the
+    /// first `'\0'`. `body` is given the cell of the character, and the first free cell
+    fn for_each_char(
+        &mut self,
+        string: Val,
+        memory: &mut Memory,
+        body: impl FnOnce(&mut Self, Val, usize),
+    ) {
+        let t = ValType::Pointer(Box::new(ValType::Char));
+        let ptr = memory.allocate(POINTER_SIZE);
+        let next = memory.allocate(POINTER_SIZE);
+        let c = memory.allocate(1);
+        let temp = memory.allocate(1);
+        let flag = memory.allocate(1);
+        let flag_val = Val::Index(flag, ValType::Boolean);
+        let free = memory.last_memory_index;
+        self.instructions
+            .push(Instruction::Copy(string), (Some((ptr, POINTER_SIZE)), free));
+        // Reads the character `ptr` points to, and whether it isn't the end of the string.
+        // The cells are cleared first, since the code can run several times
+        let read = |this: &mut Self| {
+            this.instructions
+                .push(Instruction::Clear(c, c + 1), (None, free));
+            this.instructions.push(
+                Instruction::Deref(Val::Index(ptr, t.clone())),
+                (Some((c, 1)), free),
+            );
+            this.instructions.push(
+                Instruction::Neq(Val::Index(c, ValType::Char), Val::Char(0)),
+                (Some((temp, 1)), free),
+            );
+            this.instructions.push(
+                Instruction::Copy(Val::Index(temp, ValType::Boolean)),
+                (Some((flag, 1)), free),
+            );
+            this.instructions
+                .push(Instruction::Clear(temp, temp + 1), (None, free));
+        };
+        read(self);
+        self.instructions
+            .push(Instruction::While(flag_val.clone()), (None, free));
+        body(self, Val::Index(c, ValType::Char), free);
+        self.advance(ptr, next, free);
+        read(self);
+        self.instructions
+            .push(Instruction::EndWhile(flag_val), (None, free));
+    }
+
+    /// Moves the pointer in `ptr` to the next character, using `next` as scratch
+    fn advance(&mut self, ptr: usize, next: usize, free: usize) {
+        let t = ValType::Pointer(Box::new(ValType::Char));
+        self.instructions.push(
+            Instruction::Add(Val::Index(ptr, t.clone()), Val::Num(1)),
+            (Some((next, POINTER_SIZE)), free),
+        );
+        self.instructions.push(
+            Instruction::Copy(Val::Index(next, t)),
+            (Some((ptr, POINTER_SIZE)), free),
+        );
+    }
+
+    /// Concatenates strings and chars into a new buffer of `capacity` characters and a
+    /// `'\0'`, copying the strings up to their first `'\0'`
+    /// # Examples
+    /// ```
+    /// let code = "let name = \"world\"\nezout \"hello, \" + name + '!'";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"hello, world!");
+    /// ```
+    fn concat(&mut self, parts: [Val; 2], capacity: usize, memory: &mut Memory) -> Val {
+        let t = ValType::Pointer(Box::new(ValType::Char));
+        let buffer = memory.allocate(capacity + 1);
+        let out = memory.allocate(POINTER_SIZE);
+        let next = memory.allocate(POINTER_SIZE);
+        self.instructions.push(
+            Instruction::Copy(Val::Pointer(buffer, ValType::Char)),
+            (Some((out, POINTER_SIZE)), memory.last_memory_index),
+        );
+        // Writes a character at the end of the buffer and moves the end after it
+        let write = |this: &mut Self, c: Val, free: usize| {
+            this.instructions.push(
+                Instruction::DerefAssign(Val::Index(out, t.clone()), c),
+                (None, free),
+            );
+            this.advance(out, next, free);
+        };
+        for part in parts {
+            if part.r#type() == ValType::Char {
+                write(self, part, memory.last_memory_index);
+            } else {
+                self.for_each_char(part, memory, write);
+            }
+        }
+        self.instructions.push(
+            Instruction::DerefAssign(Val::Index(out, t), Val::Char(0)),
+            (None, memory.last_memory_index),
+        );
+        self.string_lengths.insert(buffer, capacity);
+        Val::Pointer(buffer, ValType::Char)
+    }
+
+    /// Keeps the length of the string a new variable in `var` points to
+    fn assign_string_length(&mut self, var: usize, val: &Val) {
+        if let Some(length) = self
+            .string_length(val)
+            .filter(|_| val.r#type() != ValType::Char)
+        {
+            self.string_lengths.insert(var, length);
+        }
+    }
+
+    /// Keeps the longest of the strings the variable in `var` can point to. Which one it points
+    /// to after a reassignment may only be known at run time, like in the branch of an if
+    fn reassign_string_length(&mut self, var: usize, val: &Val) {
+        match (self.string_lengths.get(&var), self.string_length(val)) {
+            (Some(&old), Some(new)) => {
+                self.string_lengths.insert(var, old.max(new));
+            }
+            _ => {
+                self.string_lengths.remove(&var);
+            }
+        }
+    }
+
+    /// The most characters the string or char `val` can have, if it is known
+    fn string_length(&self, val: &Val) -> Option<usize> {
+        match val {
+            _ if val.r#type() == ValType::Char => Some(1),
+            Val::Pointer(index, _) | Val::Index(index, _) => {
+                self.string_lengths.get(index).copied()
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads the bool stored in a bit of the value of a cell, by shifting it down and masking it
+    fn read_bit(&mut self, cell: Val, bit: Val, memory: &mut Memory) -> Val {
+        let shifted = self.push_new(Instruction::Shr(cell, bit), 1, memory);
+        let masked = self.push_new(
+            Instruction::BAnd(Val::Index(shifted, ValType::Number), Val::Num(1)),
+            1,
+            memory,
+        );
+        Val::Index(masked, ValType::Boolean)
+    }
+
+    /// Stores the bool `val` in a bit of `cell`, leaving its other bits as they are.
+    /// The new value of the cell is computed apart, since the bitwise operations use the
+    /// cells after their result, which may be the next fields of a struct
+    /// # Examples
+    /// ```
+    /// let code = "
+    /// struct packed Flags { a: bool, b: bool, count: int }
+    /// let f = Flags { a: true, b: false, count: 7 }
+    /// f.b = true
+    /// f.a = false
+    /// ezout f.a, f.b, f.count
+    /// ";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"false true 7");
+    /// ```
+    fn write_bit(&mut self, cell: usize, bit: usize, val: Val, memory: &mut Memory) {
+        let cell_val = Val::Index(cell, ValType::Number);
+        let new = self.with_bit(cell_val, Val::Num(bit as ValNumber), val, memory);
+        self.instructions.push(
+            Instruction::Copy(new),
+            (Some((cell, 1)), memory.last_memory_index),
+        );
+    }
+
+    /// Computes the value of a cell with the bool `val` stored in one of its bits, and the
+    /// other bits as they are. The bit is known at compile time for a packed struct, and can
+    /// be computed for a packed array
+    fn with_bit(&mut self, cell: Val, bit: Val, val: Val, memory: &mut Memory) -> Val {
+        let mut push = |instruction, memory: &mut Memory| {
+            let mem = memory.allocate(1);
+            self.instructions
+                .push(instruction, (Some((mem, 1)), memory.last_memory_index));
+            Val::Index(mem, ValType::Number)
+        };
+        let (mask, cleared) = match bit {
+            Val::Num(bit) => {
+                let mask = (1u8 << bit) as ValNumber;
+                let cleared = push(Instruction::BAnd(cell, Val::Num(!mask)), memory);
+                (Val::Num(mask), cleared)
+            }
+            ref bit => {
+                let mask = push(Instruction::Shl(Val::Num(1), bit.clone()), memory);
+                let inverted = push(Instruction::BNot(mask.clone()), memory);
+                (mask, push(Instruction::BAnd(cell, inverted), memory))
+            }
+        };
+        match val {
+            Val::Bool(false) => cleared,
+            Val::Bool(true) => push(Instruction::BOr(cleared, mask), memory),
+            val => {
+                // Any value other than 0 is true, but only the lowest bit is stored
+                let val = push(Instruction::Neq(val, Val::Bool(false)), memory);
+                let shifted = push(Instruction::Shl(val, bit), memory);
+                push(Instruction::BOr(cleared, shifted), memory)
+            }
+        }
+    }
+
+    /// Fills the cells of bools of a packed struct or array, setting the bits known at compile
+    /// time all at once, and then the computed ones
+    fn write_packed(&mut self, packed: Vec<PackedCell>, memory: &mut Memory) {
+        for (cell, known, computed) in packed {
+            self.instructions.push(
+                Instruction::Copy(Val::Num(known as ValNumber)),
+                (Some((cell, 1)), memory.last_memory_index),
+            );
+            for (bit, val) in computed {
+                self.write_bit(cell, bit, val, memory);
+            }
+        }
+    }
+
+    /// Generates the code finding the bool `arr[index]` of a packed array. Returns the cell
+    /// holding the address of the cell the bool is in, and the number of its bit in that cell
+    /// # Examples
+    /// ```
+    /// let code = "
+    /// let flags = packed [true, false, true, true]
+    /// flags[1] = flags[0] && flags[2]
+    /// for f in flags ezout f
+    /// ezout sizeof(flags)
+    /// ";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"truetruetruetrue1");
+    /// ```
+    fn packed_element(
+        &mut self,
+        arr1: &Node,
+        index1: &Node,
+        vars: &mut Variables,
+        memory: &mut Memory,
+    ) -> Result<(usize, Val), Error> {
+        let arr = self.make_instruction(arr1, vars, memory)?;
+        let index = self.make_instruction(index1, vars, memory)?;
+        if index.r#type() != ValType::Number {
+            return Err(Error::new(
+                ErrorType::TypeError,
+                index1.position(),
+                format!(
+                    "Indexing can only be done with numbers, and not of type {:?}",
+                    index.r#type()
+                ),
+            ));
+        }
+        let bits = Val::Num(CELL_BITS as ValNumber);
+        let cell = self.push_new(Instruction::Div(index.clone(), bits.clone()), 1, memory);
+        let bit = self.push_new(Instruction::Mod(index, bits), 1, memory);
+        let ptr = self.push_new(
+            Instruction::Add(arr, Val::Index(cell, ValType::Number)),
+            POINTER_SIZE,
+            memory,
+        );
+        Ok((ptr, Val::Index(bit, ValType::Number)))
+    }
+
+    /// Generates the code computing the address of `arr[index]`, returning the cell it is
+    /// stored in and the type of the element
+    /// # Examples
+    /// ```
+    /// let code = "let m = [[1, 2], [3, 4]]\nm[1][0] += m[0][1]\nezout m[1][0], m[1][1]";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"5 4");
+    /// ```
+    fn element_address(
+        &mut self,
+        arr1: &Node,
+        index1: &Node,
+        vars: &mut Variables,
+        memory: &mut Memory,
+    ) -> Result<(usize, ValType), Error> {
+        let arr = self.make_instruction(arr1, vars, memory)?;
+        let index = self.make_instruction(index1, vars, memory)?;
+        if index.r#type() != ValType::Number {
+            return Err(Error::new(
+                ErrorType::TypeError,
+                index1.position(),
+                format!(
+                    "Indexing can only be done with numbers, and not of type {:?}",
+                    index.r#type()
+                ),
+            ));
+        }
+        let t = match &arr {
+            Val::Pointer(_, t) => t.clone(),
+            Val::Index(_, ValType::Pointer(t)) => (**t).clone(),
+            _ => {
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    arr1.position(),
+                    format!("Cannot index type {:?}", arr.r#type()),
+                ))
+            }
+        };
+        // The index is added once for every cell of the element, so that an index of up to
+        // 255 can't overflow when multiplied by the size
+        let mut ptr = arr;
+        for _ in 0..t.get_size() {
+            let mem = memory.allocate(POINTER_SIZE);
+            self.instructions.push(
+                Instruction::Add(ptr, index.clone()),
+                (Some((mem, POINTER_SIZE)), memory.last_memory_index),
+            );
+            ptr = Val::Index(mem, ValType::Pointer(Box::new(t.clone())));
+        }
+        match ptr {
+            Val::Index(mem, _) => Ok((mem, t)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Reads a line of input as a decimal number. A `-` as the first character makes it negative,
+    /// and the other characters that aren't digits are skipped. The number ends at the newline,
+    /// or when there is no more input
+    /// # Examples
+    /// ```
+    /// let code = "let a = ezin\nlet b = ezin\nezout a, b";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut "12\n1-2a".as_bytes(), &mut output).unwrap();
+    /// assert_eq!(output, b"12 12");
+    /// ```
+    fn read_number(&mut self, memory: &mut Memory) -> Val {
+        let number = |mem| Val::Index(mem, ValType::Number);
+        let boolean = |mem| Val::Index(mem, ValType::Boolean);
+        let value = self.push_new(Instruction::Copy(Val::Num(0)), 1, memory);
+        let c = self.push_new(Instruction::Input, 1, memory);
+        let negative = self.push_new(Instruction::Eq(number(c), Val::Char(b'-')), 1, memory);
+        let flag = memory.allocate(2);
+        self.instructions.push(
+            Instruction::If(boolean(negative), flag, false),
+            (None, memory.last_memory_index),
+        );
+        self.instructions
+            .push(Instruction::Input, (Some((c, 1)), memory.last_memory_index));
+        self.instructions.push(
+            Instruction::EndIf(flag, false),
+            (None, memory.last_memory_index),
+        );
+
+        let more = self.line_continues(number(c), memory);
+        self.instructions.push(
+            Instruction::While(boolean(more)),
+            (None, memory.last_memory_index),
+        );
+        let mut body = memory.clone();
+        let digit = self.push_new(Instruction::Sub(number(c), Val::Char(b'0')), 1, &mut body);
+        let is_digit = self.push_new(Instruction::Lt(number(digit), Val::Num(10)), 1, &mut body);
+        let flag = body.allocate(2);
+        self.instructions.push(
+            Instruction::If(boolean(is_digit), flag, false),
+            (None, body.last_memory_index),
+        );
+        let tens = self.push_new(Instruction::Mul(number(value), Val::Num(10)), 1, &mut body);
+        let sum = self.push_new(Instruction::Add(number(tens), number(digit)), 1, &mut body);
+        self.instructions.push(
+            Instruction::Copy(number(sum)),
+            (Some((value, 1)), body.last_memory_index),
+        );
+        self.instructions.push(
+            Instruction::EndIf(flag, false),
+            (None, body.last_memory_index),
+        );
+        self.instructions
+            .push(Instruction::Input, (Some((c, 1)), body.last_memory_index));
+        let next = self.line_continues(number(c), &mut body);
+        self.instructions.push(
+            Instruction::Copy(boolean(next)),
+            (Some((more, 1)), body.last_memory_index),
+        );
+        self.instructions.push(
+            Instruction::Clear(memory.last_memory_index, body.last_memory_index),
+            (None, memory.last_memory_index),
+        );
+        self.instructions.push(
+            Instruction::EndWhile(boolean(more)),
+            (None, memory.last_memory_index),
+        );
+
+        let flag = memory.allocate(2);
+        self.instructions.push(
+            Instruction::If(boolean(negative), flag, false),
+            (None, memory.last_memory_index),
+        );
+        let negated = self.push_new(Instruction::Neg(number(value)), 1, memory);
+        self.instructions.push(
+            Instruction::Copy(number(negated)),
+            (Some((value, 1)), memory.last_memory_index),
+        );
+        self.instructions.push(
+            Instruction::EndIf(flag, false),
+            (None, memory.last_memory_index),
+        );
+        number(value)
+    }
+
+    /// Checks that the character `c` read by `ezin` isn't the end of the line or of the input
+    fn line_continues(&mut self, c: Val, memory: &mut Memory) -> usize {
+        let not_newline = self.push_new(Instruction::Neq(c.clone(), Val::Char(b'\n')), 1, memory);
+        let not_end = self.push_new(Instruction::Neq(c, Val::Num(0)), 1, memory);
+        self.push_new(
+            Instruction::LAnd(
+                Val::Index(not_newline, ValType::Boolean),
+                Val::Index(not_end, ValType::Boolean),
+            ),
+            1,
+            memory,
+        )
+    }
+
+    /// Pushes `instruction`, assigning it to `size` new cells, and returns the first of them
+    fn push_new(&mut self, instruction: Instruction, size: usize, memory: &mut Memory) -> usize {
+        let mem = memory.allocate(size);
+        self.instructions
+            .push(instruction, (Some((mem, size)), memory.last_memory_index));
+        mem
+    }
+
+    /// Registers a global variable whose initializer is known at compile time as a constant.
+    /// Uses of it get the value directly, so it doesn't need any memory or instructions.
+    /// Returns false if the variable has to be generated normally.
+    fn fold_global(
+        &mut self,
+        node: &Node,
+        unfoldable: &HashSet<String>,
+        vars: &mut Variables,
+    ) -> bool {
+        if let Node::VarAssign(
+            Token {
+                token_type: TokenType::Identifier(ident),
+                ..
+            },
+            expr,
+            t,
+        ) = node
+        {
+            if unfoldable.contains(ident) {
+                return false;
+            }
+            match (const_value(expr, vars), t) {
+                (Some(val @ Val::Num(_)), Type::Number)
+                | (Some(val @ Val::Bool(_)), Type::Boolean)
+                | (Some(val @ Val::Char(_)), Type::Char) => {
+                    vars.insert(ident.clone(), val);
+                    true
+                }
+                _ => false,
+            }
+        } else {
+            false
+        }
+    }
+
     fn make_static(
         &mut self,
         node: Node,
         vars: &mut Variables,
         memory: &mut Memory,
     ) -> Result<Val, Error> {
-        match node {
+        let start = self.instructions.0.len();
+        match &node {
             Node::StaticVar(
                 Token {
                     token_type: TokenType::Identifier(ident),
@@ -844,7 +1589,7 @@ impl CodeGenerator {
                 },
                 expr,
             ) => {
-                match self.make_instruction(&expr, vars, memory)? {
+                match self.make_instruction(expr, vars, memory)? {
                     Val::Index(_, ValType::Ref(_)) | Val::Ref(..) => (),
                     Val::Index(index, type_) => {
                         let size = type_.get_size();
@@ -853,7 +1598,7 @@ impl CodeGenerator {
                             Instruction::Copy(Val::Index(index, type_.clone())),
                             (Some((mem, size)), memory.last_memory_index),
                         );
-                        self.statics.insert(ident, Val::Index(mem, type_));
+                        self.statics.insert(ident.clone(), Val::Index(mem, type_));
                     }
                     val => {
                         let v = val.r#type();
@@ -863,9 +1608,10 @@ impl CodeGenerator {
                             Instruction::Copy(val),
                             (Some((mem, size)), memory.last_memory_index),
                         );
-                        self.statics.insert(ident, Val::Index(mem, v));
+                        self.statics.insert(ident.clone(), Val::Index(mem, v));
                     }
                 }
+                self.own(start, &node);
                 Ok(Val::None)
             }
             _ => unreachable!(),
@@ -873,17 +1619,319 @@ impl CodeGenerator {
     }
 }
 
+/// Checks whether `node` is a bool field of a packed struct or an element of a packed array,
+/// which is stored in a bit of a cell shared with other bools, so it has no address of its own
+fn is_packed_field(node: &Node) -> bool {
+    match node {
+        Node::AttrAccess(expr, _, Type::Boolean) => {
+            matches!(expr.get_type(), Type::Struct(_, _, true))
+        }
+        Node::Index(arr, ..) => is_packed_array(arr),
+        _ => false,
+    }
+}
+
+/// Checks whether `node` is an array whose bools are packed into bits
+fn is_packed_array(node: &Node) -> bool {
+    matches!(node.get_type(), Type::Pointer(t) if *t == Type::Bits)
+}
+
+/// The error for taking the address of a bool of a packed struct or array, which is a bit
+/// and not a cell
+/// # Examples
+/// ```
+/// let code = "struct packed Flags { a: bool }\nlet f = Flags { a: true }\nlet r = &(f.a)";
+/// let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+/// assert_eq!(err.details, "Cannot take the address of field 'a', it is a bit of a packed struct");
+/// ```
+fn packed_field_address(node: &Node) -> Error {
+    let details = match node {
+        Node::AttrAccess(_, field, _) => format!(
+            "Cannot take the address of field {}, it is a bit of a packed struct",
+            field
+        ),
+        _ => "Cannot take the address of an element of a packed array, it is a bit of one of its cells"
+            .to_string(),
+    };
+    Error::new(ErrorType::TypeError, node.position(), details)
+}
+
+/// Describes what the cells a node is the owner of hold
+fn describe(node: &Node) -> String {
+    match node {
+        // The hidden variables of `for` loops and of augmented assignments start with `@`,
+        // which a variable of the program can't
+        Node::VarAssign(var, ..) | Node::VarReassign(var, _) if matches!(&var.token_type, TokenType::Identifier(name) if name.starts_with('@')) =>
+        {
+            format!("compiler temporary {}", var.token_type)
+        }
+        Node::VarAssign(var, ..) | Node::VarReassign(var, _) => {
+            format!("variable {}", var.token_type)
+        }
+        Node::StaticVar(var, _) => format!("static variable {}", var.token_type),
+        Node::BinaryOp(op, ..) | Node::UnaryOp(op, ..) => format!("`{}` expression", op.token_type),
+        Node::Call(func, ..) => format!("call to {}", func.token_type),
+        Node::Expanded(..) => "function call".to_string(),
+        Node::StructConstructor(name, ..) => format!("{} literal", name.token_type),
+        Node::Array(..) => "array literal".to_string(),
+        Node::String(_) => "string literal".to_string(),
+        Node::Index(..) => "array index".to_string(),
+        Node::AttrAccess(_, field, _) => format!("field {}", field.token_type),
+        Node::Ref(..) => "`&` expression".to_string(),
+        Node::Deref(..) => "`*` expression".to_string(),
+        Node::Converted(_, ty) => format!("conversion to {}", ty),
+        Node::Ternary(..) => "ternary".to_string(),
+        Node::If(..) => "if".to_string(),
+        Node::While(..) => "while".to_string(),
+        Node::For(..) => "for".to_string(),
+        Node::Print(..) => "ezout".to_string(),
+        Node::Ascii(..) => "ezascii".to_string(),
+        Node::Input(..) => "ezin".to_string(),
+        Node::Raw(..) => "ezraw".to_string(),
+        _ => "expression".to_string(),
+    }
+}
+
+/// Finds the cells every variable and temporary value uses, and when they use them.
+/// Cells belong to the owner of the instruction that first assigns to them, until a `Clear`
+/// frees them for something else. Uses through pointers can't be known, so they aren't counted
+/// # Examples
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "let arr = [1, 2, 3]\n{\n    let y = arr[0] * 2\n    ezout y\n}";
+/// let mut stats = Stats::default();
+/// ezlang::run_with_options(code, "main.ez".to_string(), &Options::default(), &mut stats).unwrap();
+/// assert_eq!((stats.memory[0].owner.as_str(), stats.memory[0].size), ("array literal", 3));
+/// ```
+fn memory_uses(code: &Instructions, owners: &[Option<(String, Position)>]) -> Vec<CellUse> {
+    let mut uses: Vec<CellUse> = vec![];
+    // The use every cell currently belongs to, and the last one it belonged to
+    let mut current: HashMap<usize, usize> = HashMap::new();
+    let mut last: HashMap<usize, usize> = HashMap::new();
+    for (i, (((assign, _), instruction), owner)) in code.0.iter().zip(owners).enumerate() {
+        let mut used: Vec<(usize, usize)> = instruction
+            .operands()
+            .into_iter()
+            .filter_map(|val| match val {
+                Val::Index(mem, t) | Val::Ref(mem, t) => Some((*mem, t.get_size())),
+                Val::Pointer(mem, _) => Some((*mem, 1)),
+                _ => None,
+            })
+            .collect();
+        let assigned = match *instruction {
+            Instruction::If(_, flag, else_) => Some((flag, 1 + else_ as usize)),
+            Instruction::Else(flag) | Instruction::EndIf(flag, _) => {
+                used.push((flag, 2));
+                None
+            }
+            Instruction::Clear(from, to) => {
+                for cell in from..to {
+                    current.remove(&cell);
+                }
+                None
+            }
+            _ => *assign,
+        };
+        for (mem, size) in used {
+            for cell in mem..mem + size {
+                if let Some(&u) = current.get(&cell) {
+                    uses[u].live.1 = i;
+                }
+            }
+        }
+        let (mem, size) = match assigned {
+            Some(cells) => cells,
+            None => continue,
+        };
+        if let Some(&u) = current.get(&mem) {
+            uses[u].live.1 = i;
+            continue;
+        }
+        let (owner, position) = match owner {
+            Some(owner) => owner.clone(),
+            None => continue,
+        };
+        // The elements of an array, or the fields of a struct, are assigned one after another
+        let latest = uses.len().checked_sub(1);
+        let u = match uses.last_mut() {
+            Some(previous)
+                if previous.start + previous.size == mem
+                    && (&previous.owner, &previous.position) == (&owner, &position)
+                    && current.get(&previous.start).copied() == latest =>
+            {
+                previous.size += size;
+                previous.live.1 = i;
+                uses.len() - 1
+            }
+            _ => {
+                uses.push(CellUse {
+                    start: mem,
+                    size,
+                    owner,
+                    position,
+                    live: (i, i),
+                    reused: false,
+                });
+                uses.len() - 1
+            }
+        };
+        for cell in mem..mem + size {
+            if let Some(previous) = last.insert(cell, u) {
+                uses[previous].reused = true;
+                uses[u].reused = true;
+            }
+            current.insert(cell, u);
+        }
+    }
+    uses.sort_by_key(|u| (std::cmp::Reverse(u.size), u.start, u.live.0));
+    uses
+}
+
+/// Returns the value of an expression made up only of literals and constant globals
+fn const_value(node: &Node, vars: &Variables) -> Option<Val> {
+    match node {
+        Node::Number(Token {
+            token_type: TokenType::Number(n),
+            ..
+        }) => Some(Val::Num(*n as ValNumber)),
+        Node::Char(Token {
+            token_type: TokenType::Char(c),
+            ..
+        }) => Some(Val::Char(*c)),
+        Node::Boolean(Token {
+            token_type: TokenType::Keyword(b),
+            ..
+        }) => Some(Val::Bool(b == "true")),
+        Node::VarAccess(
+            Token {
+                token_type: TokenType::Identifier(ident),
+                ..
+            },
+            _,
+        ) => match vars.get(ident) {
+            Some(val @ (Val::Num(_) | Val::Bool(_) | Val::Char(_))) => Some(val.clone()),
+            _ => None,
+        },
+        Node::BinaryOp(op, left, right, _) => fold_binary(
+            &op.token_type,
+            &const_value(left, vars)?,
+            &const_value(right, vars)?,
+        ),
+        Node::UnaryOp(op, expr, _) => fold_unary(&op.token_type, &const_value(expr, vars)?),
+        _ => None,
+    }
+}
+
+/// Checks whether `node` has brainfuck code written with `ezraw`, which can change any variable
+/// # Examples
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "let a = 5\nezraw \"<+>\"\nezout a";
+/// let mut stats = Stats::default();
+/// ezlang::compile_ir(code, "main.ez".to_string(), &Options::default(), &mut stats).unwrap();
+/// assert_eq!(stats.folded_globals, 0);
+/// ```
+fn has_raw(node: &Node) -> bool {
+    matches!(node, Node::Raw(..)) || node.children().into_iter().any(has_raw)
+}
+
+/// Finds the variables that can't be folded into constants,
+/// because they are declared more than once, modified, or have their address taken
+fn find_unfoldable(node: &Node, declared: &mut HashSet<String>, unfoldable: &mut HashSet<String>) {
+    match node {
+        Node::VarAssign(token, ..) => {
+            if let TokenType::Identifier(ref ident) = token.token_type {
+                if !declared.insert(ident.clone()) {
+                    unfoldable.insert(ident.clone());
+                }
+            }
+        }
+        Node::VarReassign(token, _) => {
+            if let TokenType::Identifier(ref ident) = token.token_type {
+                unfoldable.insert(ident.clone());
+            }
+        }
+        Node::UnaryOp(
+            Token {
+                token_type: TokenType::Inc | TokenType::Dec,
+                ..
+            },
+            expr,
+            _,
+        )
+        | Node::Ref(expr, ..)
+        | Node::Pointer(expr, _) => {
+            if let Node::VarAccess(
+                Token {
+                    token_type: TokenType::Identifier(ident),
+                    ..
+                },
+                _,
+            ) = &**expr
+            {
+                unfoldable.insert(ident.clone());
+            }
+        }
+        _ => (),
+    }
+    for child in node.children() {
+        find_unfoldable(child, declared, unfoldable);
+    }
+}
+
 /// Generates and returns the Intermediate Representation of the AST
+/// # Examples
+/// Folding the globals and optimizing the brainfuck doesn't change what a program prints
+/// ```
+/// use ezlang::{core::interpreter, utils::{Options, Stats}};
+///
+/// let programs = [
+///     "let SIZE = 10 * 4\nlet HALF = SIZE / 2\nezout SIZE, HALF, SIZE % 7",
+///     "let a = 200\nlet b = a + 100\nlet neg = -3\nezout b, neg, a > b",
+///     "let c = 'a'\nlet next = c as int + 1\nezascii c, next as char",
+///     "let on = !false && 3 < 5\nif (on) { ezout 1 } else { ezout 2 }\nezout on",
+///     "let n = 5\nlet total = 0\nfor (let i = 0 : i < n : i += 1) { total += i }\nezout total",
+///     "let x = ezin\nlet k = 3\nezout x * k, x / k, x - k",
+///     "let count = 2\ncount += 1\nlet p = &count\nezout *p",
+///     "let a = 1, b = 4, c = a + b\nezout a, b, c",
+/// ];
+/// let mut folded = 0;
+/// for program in programs {
+///     let output = |fold_globals, opt_level| {
+///         let options = Options { fold_globals, opt_level, ..Options::default() };
+///         let mut stats = Stats::default();
+///         let ir = ezlang::compile_ir(program, "main.ez".to_string(), &options, &mut stats).unwrap();
+///         let code = ezlang::to_brainfuck_with_options(&ir, &options);
+///         let mut output = vec![];
+///         interpreter::interpret(&code, &mut "7".as_bytes(), &mut output).unwrap();
+///         (String::from_utf8(output).unwrap(), stats.folded_globals)
+///     };
+///     let (expected, none) = output(false, 0);
+///     assert_eq!(none, 0);
+///     for (fold_globals, opt_level) in [(true, 0), (false, 1), (true, 1)] {
+///         let (actual, count) = output(fold_globals, opt_level);
+///         assert_eq!(actual, expected, "{}", program);
+///         folded += count;
+///     }
+/// }
+/// // The globals of most of the programs are folded
+/// assert!(folded > programs.len() * 2);
+/// ```
 pub fn generate_code(
     ast: Node,
     statics: Vec<Node>,
     structs: Vec<Node>,
+    options: &Options,
+    stats: &mut Stats,
 ) -> Result<Instructions, Error> {
     let mut structs_valtype = vec![];
     for struct_ in structs {
         structs_valtype.push(ValType::from_parse_type(
             &struct_.struct_from_def().unwrap(),
-        ));
+            &struct_.position(),
+        )?);
     }
 
     let mut obj = CodeGenerator {
@@ -891,6 +1939,9 @@ pub fn generate_code(
         ret: vec![],
         statics: HashMap::new(),
         structs: structs_valtype,
+        separator: options.ezout_separator.clone(),
+        owners: vec![],
+        string_lengths: HashMap::new(),
     };
     let mut vars = Variables::new();
     let mut memory = Memory::new();
@@ -898,6 +1949,36 @@ pub fn generate_code(
         obj.make_static(node, &mut vars, &mut memory)?;
     }
 
-    obj.make_instruction(&ast, &mut vars, &mut memory)?;
+    let mut unfoldable = HashSet::new();
+    let mut declared = HashSet::new();
+    find_unfoldable(&ast, &mut declared, &mut unfoldable);
+    // Raw code can change any variable, so none of them can be folded when there is some
+    if has_raw(&ast) {
+        unfoldable.extend(declared);
+    }
+    if let Node::Statements(ref statements, ..) = ast {
+        for statement in statements {
+            let declarations = match statement {
+                Node::Declarations(nodes, _) => nodes.iter().collect(),
+                node => vec![node],
+            };
+            for node in declarations {
+                if options.fold_globals && obj.fold_global(node, &unfoldable, &mut vars) {
+                    stats.folded_globals += 1;
+                } else {
+                    obj.make_instruction(node, &mut vars, &mut memory)?;
+                }
+            }
+        }
+    } else {
+        obj.make_instruction(&ast, &mut vars, &mut memory)?;
+    }
+    obj.owners.resize(obj.instructions.0.len(), None);
+    stats.memory = memory_uses(&obj.instructions, &obj.owners);
+    stats.source_map = obj
+        .owners
+        .into_iter()
+        .map(|owner| owner.map(|(_, position)| position))
+        .collect();
     Ok(obj.instructions)
 }
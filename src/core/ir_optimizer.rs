@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 
-use crate::utils::{Instruction, Instructions, Val};
+use crate::utils::ir::{Instruction, Instructions, Val};
 
 pub fn optimize(code: &Instructions) -> Instructions {
     use crate::check;
     let mut optimized = Instructions::new();
     let mut vars = HashMap::new();
+    let mut starts = vec![];
     for (assign, instruction) in &code.0 {
+        starts.push(optimized.0.len());
         let optimize = match instruction {
             Instruction::Add(a, Val::Num(0))
             | Instruction::Sub(a, Val::Num(0))
@@ -61,6 +63,12 @@ pub fn optimize(code: &Instructions) -> Instructions {
                     optimized.push(Instruction::Input, *assign);
                     continue;
                 }
+                // Raw code can change any cell, so no value is known after it
+                Instruction::Raw(_) => {
+                    vars.clear();
+                    optimized.push(instruction.clone(), *assign);
+                    continue;
+                }
                 Instruction::Add(a, b) => {
                     check!(BINARY2 a, b, optimized, vars, assign, instruction)
                 }
@@ -204,5 +212,6 @@ pub fn optimize(code: &Instructions) -> Instructions {
 
         vars.insert(assign.0.unwrap().0, optimize);
     }
+    optimized.keep_regions(code, &starts);
     optimized
 }
@@ -1,42 +1,384 @@
-use std::rc::Rc;
+use std::{
+    collections::VecDeque,
+    iter::{Enumerate, FusedIterator, Peekable},
+    str::Chars,
+};
 
 use crate::utils::{
-    Error, ErrorType, Position, Token, TokenType, KEYWORDS, PREPROCESSOR_STATEMENTS,
+    diag::{Error, ErrorType, FileName, Position, Suggestion},
+    token::{LexNumber, Token, TokenType, INT_MAX, INT_MIN, KEYWORDS, PREPROCESSOR_STATEMENTS},
 };
 
 /// A Result type for Lexing
 type LexResult = Result<Vec<Token>, Error>;
 
+/// The line comments of a file, with the text after the `//`
+pub type Comments = Vec<(Position, String)>;
+
 const LITERALS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_";
+/// Why a string can't hold a character that isn't ascii
+const STRING_ASCII: &str =
+    " in string literal, strings can only hold ascii characters, since every char is one cell";
 
 /// Lexes the given input string into a vector of tokens
 /// # Arguments
 /// * `input` - The input string to be lexed
+/// * `filename` - The name of the file being lexed, used in token positions
 /// # Returns
 /// * `LexResult` - A Result containing a vector of tokens or an error, if any
 /// # Errors
-/// Returns an error if a number is very big or if an invalid token was found
+/// Returns an error if a number is very big or malformed, or if an invalid token was found
 /// # Examples
 /// ```
-/// use ezlang;
+/// use ezlang::utils::diag::FileName;
 ///
-/// let tokens = ezlang::lexer::lex("ezout 5 + 7");
+/// let tokens = ezlang::core::lexer::lex("ezout 5 + 7", FileName::new("main.ez"));
 /// assert!(tokens.is_ok());
 ///
-/// let tokens = ezlang::lexer::lex("$? ez");
+/// let tokens = ezlang::core::lexer::lex("$? ez", FileName::new("main.ez"));
 /// assert!(tokens.is_err());
 /// ```
-pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
-    let mut parentheses = Vec::new();
-    let mut tokens = Vec::new();
-    let mut chars = input.chars().enumerate().peekable();
-    let mut line = 1;
-    let mut last_line = 0;
-    let mut last = 0;
+/// Numbers can also be written in hexadecimal, binary and octal
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+/// use ezlang::utils::token::TokenType;
+///
+/// let tokens = lex("0x1F 0b1010_0101 0o17", FileName::new("main.ez")).unwrap();
+/// let numbers: Vec<_> = tokens
+///     .iter()
+///     .filter_map(|t| match t.token_type {
+///         TokenType::Number(n) => Some((n, t.position.start, t.position.end)),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(numbers, [(31, 1, 5), (165, 6, 17), (15, 18, 22)]);
+///
+/// let error = |code: &str| {
+///     let err = lex(code, FileName::new("main.ez")).unwrap_err();
+///     (format!("{:?}", err.error_type), err.position.start, err.position.end)
+/// };
+/// assert_eq!(error("ezout 0x + 1"), ("InvalidLiteral".to_string(), 7, 9));
+/// assert_eq!(error("ezout 0b102"), ("InvalidLiteral".to_string(), 7, 12));
+/// assert_eq!(error("ezout 0xFFFFF"), ("NumberTooLarge".to_string(), 7, 14));
+/// ```
+/// An int is one cell, so number literals go from -256 to 255. A `-` that doesn't come after a
+/// value makes the number after it negative, and a number out of range is reported along
+/// with that `-`
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+///
+/// for code in ["255", "0xFF", "-256", "- 0b1_0000_0000", "(-256)", "a = -256"] {
+///     assert!(lex(code, FileName::new("main.ez")).is_ok(), "{}", code);
+/// }
+/// for code in ["256", "0x101", "-257", "-0o401", "99999999999999999999"] {
+///     let err = lex(&format!("let a = {}", code), FileName::new("main.ez")).unwrap_err();
+///     assert_eq!(format!("{:?}", err.error_type), "NumberTooLarge");
+///     assert_eq!(err.details, "Number is too large, an int is from -256 to 255");
+///     assert_eq!((err.position.start, err.position.end), (9, 9 + code.len()), "{}", code);
+/// }
+///
+/// // After a value, `-` is a subtraction, and `x as int` ends with a value too
+/// for code in ["a -256", "5 - 256", "f() - 256", "ezin -256", "x as int -256"] {
+///     let err = lex(code, FileName::new("main.ez")).unwrap_err();
+///     assert_eq!(format!("{:?}", err.error_type), "NumberTooLarge", "{}", code);
+/// }
+/// ```
+/// Digits can be separated with `_`, but only one at a time and only between two digits.
+/// The error points at the `_` that is out of place
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+/// use ezlang::utils::token::TokenType;
+///
+/// let tokens = lex("1_00 0b1_0 100", FileName::new("main.ez")).unwrap();
+/// assert_eq!(tokens[0].token_type, tokens[2].token_type);
+/// assert_eq!((tokens[0].position.start, tokens[0].position.end), (1, 5));
+/// assert_eq!(tokens[1].token_type, TokenType::Number(2));
+///
+/// for (code, column) in [("1__0", 3), ("10_", 3), ("0x_1", 3), ("0b1_", 4), ("let a = 7_ + 1", 10)] {
+///     let err = lex(code, FileName::new("main.ez")).unwrap_err();
+///     assert_eq!((err.position.start, err.position.end), (column, column + 1), "{}", code);
+///     assert_eq!(format!("{:?}", err.error_type), "InvalidLiteral");
+/// }
+/// ```
+/// Block comments can be nested, and the tokens after them keep their lines and columns
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+///
+/// let code = "/* outer /* inner */ still\n commented **/ ezout /*\n*/\n  1";
+/// let tokens = lex(code, FileName::new("main.ez")).unwrap();
+/// let positions: Vec<_> = tokens.iter().map(|t| (t.position.line_start, t.position.start)).collect();
+/// assert_eq!(positions[..2], [(2, 16), (4, 3)]);
+///
+/// let err = lex("ezout 1 /* a /* b */\nezout 2", FileName::new("main.ez")).unwrap_err();
+/// assert_eq!(format!("{:?}", err.error_type), "SyntaxError");
+/// let (opening, _) = &err.labels[0];
+/// assert_eq!((opening.line_start, opening.start, opening.end), (1, 9, 11));
+/// ```
+/// Raw strings, `r"..."` or `r#"..."#` for strings with quotes in them, are kept as they are
+/// written, without escapes. Their positions span the delimiters
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+/// use ezlang::utils::token::TokenType;
+///
+/// let tokens = lex(r###"r"a\n" r#"say "hi""# r"###, FileName::new("main.ez")).unwrap();
+/// let strings: Vec<_> = tokens
+///     .iter()
+///     .map(|t| (t.token_type.clone(), t.position.start, t.position.end))
+///     .collect();
+/// assert_eq!(strings[0], (TokenType::String(r"a\n".to_string()), 1, 7));
+/// assert_eq!(strings[1], (TokenType::String(r#"say "hi""#.to_string()), 8, 21));
+/// assert_eq!(strings[2].0, TokenType::Identifier("r".to_string()));
+///
+/// let err = lex(r##"ezout r#"never "closed""##, FileName::new("main.ez")).unwrap_err();
+/// assert_eq!(err.details, "Unterminated raw string starting on line 1, expected '\"#' before the end of the file");
+/// ```
+/// Strings can span several lines, keeping the newlines in them. The position of a string
+/// goes from its opening quote to its closing one, and the tokens after it keep their lines
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+/// use ezlang::utils::token::TokenType;
+///
+/// let tokens = lex("let s = \"one\ntwo\n  three\" + 1", FileName::new("main.ez")).unwrap();
+/// assert_eq!(tokens[3].token_type, TokenType::String("one\ntwo\n  three".to_string()));
+/// let string = &tokens[3].position;
+/// assert_eq!((string.line_start, string.start, string.line_end, string.end), (1, 9, 3, 9));
+/// let plus = &tokens[4].position;
+/// assert_eq!((plus.line_start, plus.start), (3, 10));
+///
+/// let err = lex("ezout 1\nlet s = \"one\ntwo \\q\"", FileName::new("main.ez")).unwrap_err();
+/// assert_eq!((err.position.line_start, err.position.start, err.position.end), (3, 5, 7));
+///
+/// // An unterminated string is reported at its opening quote
+/// let err = lex("ezout 1\nlet s = \"one\ntwo\nezout s", FileName::new("main.ez")).unwrap_err();
+/// assert_eq!((err.position.line_start, err.position.start), (2, 9));
+/// assert_eq!(err.details, "Unterminated string starting on line 2, expected '\"' before the end of the file");
+/// ```
+/// Only ascii characters can be used, except in comments. The error shows the character
+/// that isn't ascii and where it is
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+///
+/// let error = |code: &str| {
+///     let err = lex(code, FileName::new("main.ez")).unwrap_err();
+///     assert_eq!(format!("{:?}", err.error_type), "InvalidLiteral");
+///     (err.details, err.position.line_start, err.position.start, err.position.end)
+/// };
+/// let (details, line, start, end) = error("let a = 1\nlet caf\u{e9} = 2");
+/// assert_eq!(details, "Invalid character '\u{e9}' (U+00E9), only ascii characters can be used outside of strings and comments");
+/// assert_eq!((line, start, end), (2, 8, 9));
+///
+/// let (details, ..) = error("ezout \u{201c}hi\u{201d}");
+/// assert!(details.ends_with("did you mean `\"`?"));
+///
+/// let (details, line, start, end) = error("// \u{1f600} is fine here\nezout \"smile \u{1f600}\"");
+/// assert_eq!(details, "Invalid character '\u{1f600}' (U+1F600) in string literal, strings can only hold ascii characters, since every char is one cell");
+/// assert_eq!((line, start, end), (2, 14, 15));
+/// ```
+/// Common typos, like `=>` for `->` or `:=` for `=`, are errors that say which token was
+/// probably meant, and suggest replacing them with it
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+///
+/// let error = |code: &str| lex(code, FileName::new("main.ez")).unwrap_err();
+/// for (code, details, column, fix) in [
+///     ("ez f() => int { return 1 }", "Unexpected '=>', did you mean '->'?", 8, "->"),
+///     ("let a := 5", "Unexpected ':=', did you mean '='?", 7, "="),
+///     ("ezout a &&& b", "Unexpected '&&&', did you mean '&&'?", 9, "&&"),
+///     ("#declare DEBUG", "Unexpected '#', did you mean '!'?", 1, "!"),
+/// ] {
+///     let err = error(code);
+///     assert_eq!((err.details.as_str(), err.position.start), (details, column));
+///     let (position, text) = &err.suggestion.unwrap().edits[0];
+///     assert_eq!((position.start, text.as_str()), (column, fix));
+/// }
+///
+/// let err = error("ezout 1 $ 2");
+/// assert_eq!((err.details.as_str(), err.position.start), ("Unexpected '$'", 9));
+/// assert!(err.suggestion.is_none());
+///
+/// // Before a value or a type, `&&&` is three references
+/// assert!(lex("let r: &&&int = &&&a", FileName::new("main.ez")).is_ok());
+/// ```
+/// Chars can be written with their value as `'\xNN'` or `'\u{..}'`, for bytes that can't be
+/// typed. The value goes straight into the token, so they are constants like any other char
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::lex;
+/// use ezlang::utils::{ir::{Instruction, Val}, token::TokenType, Options, Stats};
+///
+/// let tokens = lex(r"'\x07' '\u{C8}' '\u{0}' 'a'", FileName::new("main.ez")).unwrap();
+/// let chars: Vec<_> = tokens[..4]
+///     .iter()
+///     .map(|t| (t.token_type.clone(), t.position.start, t.position.end))
+///     .collect();
+/// assert_eq!(chars, [
+///     (TokenType::Char(7), 1, 7),
+///     (TokenType::Char(200), 8, 16),
+///     (TokenType::Char(0), 17, 24),
+///     (TokenType::Char(b'a'), 25, 28),
+/// ]);
+///
+/// for (code, details, end) in [
+///     (r"'\u{100}'", "Char escape `\\u{100}` is out of range, chars can only be from 0 to 255", 9),
+///     (r"'\x7'", "Expected two hex digits after `\\x`, like '\\x07'", 6),
+///     (r"'\xG7'", "Expected two hex digits after `\\x`, like '\\x07'", 6),
+///     (r"'\u7'", "Expected hex digits between braces after `\\u`, like '\\u{7}'", 6),
+///     (r"'\u{}'", "Expected hex digits between braces after `\\u`, like '\\u{7}'", 6),
+/// ] {
+///     let err = lex(code, FileName::new("main.ez")).unwrap_err();
+///     assert_eq!(format!("{:?}", err.error_type), "InvalidLiteral");
+///     assert_eq!((err.details.as_str(), err.position.start, err.position.end), (details, 2, end), "{}", code);
+/// }
+///
+/// let ir = ezlang::compile_ir(r"ezascii '\x07'", "main.ez".to_string(), &Options::default(), &mut Stats::default()).unwrap();
+/// assert_eq!(ir.0.len(), 1);
+/// assert!(matches!(ir.0[0].1, Instruction::Ascii(Val::Char(7))));
+/// ```
+/// Columns count characters, so a tab is one column, and `\r\n` line endings are read like
+/// `\n`, in strings and comments too
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::{lex, lex_with_comments};
+/// use ezlang::utils::token::TokenType;
+///
+/// let code = "let a = 1 // one\r\nif (a == 1) {\r\n\tezout \"x\r\ny\", a\r\n}\r\n";
+/// let (tokens, comments) = lex_with_comments(code, FileName::new("main.ez")).unwrap();
+/// let string = tokens.iter().find(|t| matches!(t.token_type, TokenType::String(_))).unwrap();
+/// assert_eq!(string.token_type, TokenType::String("x\ny".to_string()));
+/// assert_eq!((string.position.line_start, string.position.start), (3, 8));
+/// let a = tokens.iter().filter(|t| t.token_type == TokenType::Identifier("a".to_string())).last().unwrap();
+/// assert_eq!((a.position.line_start, a.position.start, a.position.end), (4, 5, 6));
+/// assert_eq!(comments[0].1, " one");
+/// assert_eq!((comments[0].0.start, comments[0].0.end), (11, 17));
+///
+/// let err = lex("let a = 1\r\n\tezout \"\\q\"", FileName::new("main.ez")).unwrap_err();
+/// assert_eq!((err.position.line_start, err.position.start, err.position.end), (2, 9, 11));
+/// ```
+pub fn lex(input: &str, filename: FileName) -> LexResult {
+    Ok(lex_with_comments(input, filename)?.0)
+}
 
-    while let Some((j, c)) = chars.next() {
+/// Lexes the given input string like [`lex`], also returning its line comments
+/// # Examples
+/// ```
+/// use ezlang::utils::diag::FileName;
+///
+/// let code = "let a = 1 // one\n// ez: allow(unused)\nlet b = 2";
+/// let (_, comments) = ezlang::core::lexer::lex_with_comments(code, FileName::new("main.ez")).unwrap();
+/// let comments: Vec<_> = comments.iter().map(|(p, c)| (p.line_start, p.start, c.as_str())).collect();
+/// assert_eq!(comments, [(1, 11, " one"), (2, 1, " ez: allow(unused)")]);
+/// ```
+pub fn lex_with_comments(input: &str, filename: FileName) -> Result<(Vec<Token>, Comments), Error> {
+    let mut lexer = Lexer::new(input, filename);
+    let tokens = lexer.by_ref().collect::<LexResult>()?;
+    Ok((tokens, lexer.comments))
+}
+
+/// Lexes its input one token at a time, for tools that don't need all of the tokens at once,
+/// or stop early. It yields the same tokens as [`lex`], ending with a single `Eof` token,
+/// or stops at the first error. After that it only yields `None`
+/// # Examples
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::lexer::{lex, Lexer};
+/// use ezlang::utils::token::TokenType;
+///
+/// let code = "let a = 5 // five\nezout a";
+/// let mut lexer = Lexer::new(code, FileName::new("main.ez"));
+/// let first = lexer.next().unwrap().unwrap();
+/// assert_eq!(first.token_type, TokenType::Keyword("let".to_string()));
+/// let rest: Vec<_> = lexer.by_ref().map(Result::unwrap).collect();
+/// assert_eq!(rest.len() + 1, lex(code, FileName::new("main.ez")).unwrap().len());
+/// assert_eq!(rest.iter().filter(|t| t.token_type == TokenType::Eof).count(), 1);
+/// assert_eq!(lexer.comments()[0].1, " five");
+/// assert!(lexer.next().is_none());
+///
+/// // Tokens before an error are yielded, then the error, then nothing
+/// let mut lexer = Lexer::new("ezout 1 $ 2", FileName::new("main.ez"));
+/// assert!(lexer.next().unwrap().is_ok());
+/// assert!(lexer.next().unwrap().is_ok());
+/// assert_eq!(lexer.next().unwrap().unwrap_err().details, "Unexpected '$'");
+/// assert!(lexer.next().is_none());
+///
+/// // An unclosed bracket is only found at the end of the file, in place of `Eof`
+/// let tokens: Vec<_> = Lexer::new("ezout (1", FileName::new("main.ez")).collect();
+/// assert_eq!(tokens.len(), 4);
+/// assert!(tokens[3].is_err());
+/// ```
+pub struct Lexer<'a> {
+    chars: Peekable<Enumerate<Chars<'a>>>,
+    filename: FileName,
+    /// Position of the end of the input, where the `Eof` token is
+    eof: Position,
+    /// The brackets that are open, with their kind, 0 for `(`, 1 for `{` and 2 for `[`
+    parentheses: Vec<(Position, usize)>,
+    line: usize,
+    /// Index of the first character of the current line
+    last_line: usize,
+    comments: Comments,
+    /// Tokens that were lexed but not yielded yet
+    pending: VecDeque<Token>,
+    /// Whether the last token yielded ends a value
+    after_value: bool,
+    /// Position of the last token yielded if it is a `-` that doesn't come after a value,
+    /// which makes the number after it negative
+    minus: Option<Position>,
+    /// Whether the `Eof` token or an error was yielded
+    done: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str, filename: FileName) -> Self {
+        // Editors can put an invisible byte order mark at the start of the file
+        let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+        Self {
+            chars: input.chars().enumerate().peekable(),
+            eof: eof_position(input, filename.clone()),
+            filename,
+            parentheses: Vec::new(),
+            line: 1,
+            last_line: 0,
+            comments: Vec::new(),
+            pending: VecDeque::new(),
+            after_value: false,
+            minus: None,
+            done: false,
+        }
+    }
+
+    /// The line comments read so far, with the text after the `//`
+    pub fn comments(&self) -> &Comments {
+        &self.comments
+    }
+
+    /// Reads the next character of the input, and what comes with it if it starts a token.
+    /// Returns `false` at the end of the input
+    fn step(&mut self) -> Result<bool, Error> {
+        let (j, c) = match self.chars.next() {
+            Some(next) => next,
+            None => return Ok(false),
+        };
+        let (mut line, mut last_line) = (self.line, self.last_line);
+        let filename = self.filename.clone();
+        let Self {
+            chars,
+            eof,
+            parentheses,
+            comments,
+            pending: tokens,
+            after_value,
+            minus,
+            ..
+        } = self;
         let i = j - last_line + 1;
-        last = i + 1;
         match c {
             ' ' | '\t' | '\n' | '\r' => {
                 if c == '\n' {
@@ -45,28 +387,38 @@ pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
                 }
             }
             '\'' => {
+                // The number of characters between the quotes
+                let mut width = 1;
                 let c = match chars.next() {
                     Some((_, c)) => match c {
                         '\'' => {
                             return Err(Error::new(
                                 ErrorType::SyntaxError,
-                                Position::new(line, i, i + 2, Rc::clone(&filename)),
+                                Position::new(line, i, i + 2, filename),
                                 "Expected char literal, found \'".to_string(),
                             ))
                         }
                         '\\' => {
-                            (match chars.next() {
+                            width += 1;
+                            match chars.next() {
                                 Some((_, c)) => match c {
-                                    'n' => '\n',
-                                    'r' => '\r',
-                                    't' => '\t',
-                                    '\\' => '\\',
-                                    '\'' => '\'',
-                                    '0' => '\0',
+                                    'n' => b'\n',
+                                    'r' => b'\r',
+                                    't' => b'\t',
+                                    '\\' => b'\\',
+                                    '\'' => b'\'',
+                                    '0' => b'\0',
+                                    'x' | 'u' => {
+                                        let start =
+                                            Position::new(line, i + 1, i + 3, filename.clone());
+                                        let (c, digits) = numeric_escape(c, &mut *chars, start)?;
+                                        width += digits;
+                                        c
+                                    }
                                     _ => {
                                         return Err(Error::new(
                                             ErrorType::SyntaxError,
-                                            Position::new(line, i, i + 3, Rc::clone(&filename)),
+                                            Position::new(line, i, i + 3, filename),
                                             "Invalid escape sequence".to_string(),
                                         ))
                                     }
@@ -74,50 +426,50 @@ pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
                                 None => {
                                     return Err(Error::new(
                                         ErrorType::SyntaxError,
-                                        Position::new(line, i, i + 3, Rc::clone(&filename)),
+                                        Position::new(line, i, i + 3, filename),
                                         "Expected char literal, found \\".to_string(),
                                     ))
                                 }
-                            } as u8)
+                            }
                         }
                         c if c.is_ascii() => c as u8,
-                        _ => {
-                            return Err(Error::new(
-                                ErrorType::SyntaxError,
-                                Position::new(line, i, i + 3, Rc::clone(&filename)),
-                                "Invalid char literal, chars can only be ascii values".to_string(),
+                        c => {
+                            return Err(non_ascii(
+                                c,
+                                Position::new(line, i + 1, i + 2, filename),
+                                " in char literal, chars can only be ascii values",
                             ))
                         }
                     },
                     None => {
                         return Err(Error::new(
                             ErrorType::SyntaxError,
-                            Position::new(line, i, i + 2, Rc::clone(&filename)),
+                            Position::new(line, i, i + 2, filename),
                             "Unclosed char literal".to_owned(),
                         ));
                     }
                 };
                 match chars.next() {
                     Some((_, '\'')) => {
-                        tokens.push(Token::new(
+                        tokens.push_back(Token::new(
                             TokenType::Char(c),
                             line,
                             i,
-                            i + 3,
-                            Rc::clone(&filename),
+                            i + 2 + width,
+                            filename,
                         ));
                     }
                     Some((_, c)) => {
                         return Err(Error::new(
                             ErrorType::SyntaxError,
-                            Position::new(line, i, i + 2, Rc::clone(&filename)),
+                            Position::new(line, i, i + 2, filename),
                             format!("Expected \', found {:?}", c),
                         ));
                     }
                     None => {
                         return Err(Error::new(
                             ErrorType::SyntaxError,
-                            Position::new(line, i, i + 3, Rc::clone(&filename)),
+                            Position::new(line, i, i + 3, filename),
                             "Unclosed char literal".to_owned(),
                         ));
                     }
@@ -125,410 +477,295 @@ pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
             }
             '+' => {
                 if let Some((_, '=')) = chars.peek() {
-                    tokens.push(Token::new(
-                        TokenType::AddAssign,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::AddAssign, line, i, i + 2, filename));
                     chars.next();
                 } else if let Some((_, '+')) = chars.peek() {
-                    tokens.push(Token::new(
-                        TokenType::Inc,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Inc, line, i, i + 2, filename));
                     chars.next();
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::Add,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Add, line, i, i + 1, filename));
                 }
             }
             '-' => {
                 if let Some((_, '=')) = chars.peek() {
-                    tokens.push(Token::new(
-                        TokenType::SubAssign,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::SubAssign, line, i, i + 2, filename));
                     chars.next();
                 } else if let Some((_, '-')) = chars.peek() {
-                    tokens.push(Token::new(
-                        TokenType::Dec,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Dec, line, i, i + 2, filename));
                     chars.next();
                 } else if let Some((_, '>')) = chars.peek() {
-                    tokens.push(Token::new(
-                        TokenType::Arrow,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Arrow, line, i, i + 2, filename));
                     chars.next();
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::Sub,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Sub, line, i, i + 1, filename));
                 }
             }
             '*' => {
                 if let Some((_, '=')) = chars.peek() {
-                    tokens.push(Token::new(
-                        TokenType::MulAssign,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::MulAssign, line, i, i + 2, filename));
                     chars.next();
                 } else if let Some((_, '*')) = chars.peek() {
                     chars.next();
                     if let Some((_, '=')) = chars.peek() {
-                        tokens.push(Token::new(
+                        tokens.push_back(Token::new(
                             TokenType::PowAssign,
                             line,
                             i,
                             i + 3,
-                            Rc::clone(&filename),
+                            filename,
                         ));
                         chars.next();
                     } else {
-                        tokens.push(Token::new(
-                            TokenType::Pow,
-                            line,
-                            i,
-                            i + 2,
-                            Rc::clone(&filename),
-                        ));
+                        tokens.push_back(Token::new(TokenType::Pow, line, i, i + 2, filename));
                     }
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::Mul,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Mul, line, i, i + 1, filename));
                 }
             }
             '/' => match chars.peek() {
                 Some((_, '=')) => {
-                    tokens.push(Token::new(
-                        TokenType::DivAssign,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::DivAssign, line, i, i + 2, filename));
                     chars.next();
                 }
                 Some((_, '/')) => {
+                    chars.next();
+                    let mut comment = String::new();
+                    let start_line = line;
                     for (i, c) in chars.by_ref() {
                         if c == '\n' {
                             line += 1;
                             last_line = i + 1;
                             break;
                         }
+                        comment.push(c);
                     }
+                    // The `\r` of a `\r\n` line ending isn't part of the comment
+                    if comment.ends_with('\r') {
+                        comment.pop();
+                    }
+                    let end = i + 2 + comment.chars().count();
+                    comments.push((Position::new(start_line, i, end, filename), comment));
                 }
                 Some((_, '*')) => {
                     chars.next();
-                    let mut end = false;
+                    let opening = Position::new(line, i, i + 2, filename);
+                    // Comments can be nested, and each `/*` needs its own `*/`
+                    let mut depth = 1;
                     while let Some((i, c)) = chars.next() {
-                        if c == '*' {
-                            if let Some((_, '/')) = chars.next() {
-                                end = true;
-                                break;
+                        match (c, chars.peek()) {
+                            ('*', Some((_, '/'))) => {
+                                chars.next();
+                                depth -= 1;
+                                if depth == 0 {
+                                    break;
+                                }
                             }
-                        } else if c == '\n' {
-                            line += 1;
-                            last_line = i + 1;
+                            ('/', Some((_, '*'))) => {
+                                chars.next();
+                                depth += 1;
+                            }
+                            ('\n', _) => {
+                                line += 1;
+                                last_line = i + 1;
+                            }
+                            _ => (),
                         }
                     }
-                    if !end {
+                    if depth > 0 {
                         return Err(Error::new(
                             ErrorType::SyntaxError,
-                            Position::new(line, i, i + 1, Rc::clone(&filename)),
-                            "Unterminated comment".to_string(),
-                        ));
+                            eof.clone(),
+                            "Expected '*/' before the end of the file".to_string(),
+                        )
+                        .with_label(opening, "unterminated comment starts here".to_string()));
                     }
                 }
                 _ => {
-                    tokens.push(Token::new(
-                        TokenType::Div,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Div, line, i, i + 2, filename));
                 }
             },
             ':' => {
-                if let Some((_, ':')) = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::new(
-                        TokenType::Path,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
+                if let Some((_, '=')) = chars.peek() {
+                    return Err(unexpected(
+                        ErrorType::SyntaxError,
+                        ":=",
+                        Position::new(line, i, i + 2, filename),
+                        Some("="),
                     ));
+                } else if let Some((_, ':')) = chars.peek() {
+                    chars.next();
+                    tokens.push_back(Token::new(TokenType::Path, line, i, i + 2, filename));
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::Colon,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Colon, line, i, i + 1, filename));
                 }
             }
             '%' => {
                 if let Some((_, '=')) = chars.peek() {
-                    tokens.push(Token::new(
-                        TokenType::ModAssign,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::ModAssign, line, i, i + 2, filename));
                     chars.next();
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::Mod,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Mod, line, i, i + 1, filename));
                 }
             }
             '(' => {
-                tokens.push(Token::new(
+                tokens.push_back(Token::new(
                     TokenType::LParen,
                     line,
                     i,
                     i + 1,
-                    Rc::clone(&filename),
+                    filename.clone(),
                 ));
-                parentheses.push((Position::new(line, i, i + 1, Rc::clone(&filename)), 0));
+                parentheses.push((Position::new(line, i, i + 1, filename), 0));
             }
             ')' => {
-                tokens.push(Token::new(
+                tokens.push_back(Token::new(
                     TokenType::RParen,
                     line,
                     i,
                     i + 1,
-                    Rc::clone(&filename),
+                    filename.clone(),
                 ));
                 let paren = parentheses.pop();
-                if paren.is_none() {
+                if let Some(paren) = paren {
+                    if paren.1 != 0 {
+                        return Err(Error::new(
+                            ErrorType::SyntaxError,
+                            Position::new(line, i, i + 1, filename),
+                            "Mismatched brackets".to_string(),
+                        ));
+                    }
+                } else {
                     return Err(Error::new(
                         ErrorType::SyntaxError,
-                        Position::new(line, i, i + 1, Rc::clone(&filename)),
+                        Position::new(line, i, i + 1, filename),
                         "Missing opening '(' pair".to_string(),
                     ));
-                } else if paren.unwrap().1 != 0 {
-                    return Err(Error::new(
-                        ErrorType::SyntaxError,
-                        Position::new(line, i, i + 1, Rc::clone(&filename)),
-                        "Mismatched brackets".to_string(),
-                    ));
                 }
             }
             '[' => {
-                tokens.push(Token::new(
+                tokens.push_back(Token::new(
                     TokenType::LSquare,
                     line,
                     i,
                     i + 1,
-                    Rc::clone(&filename),
+                    filename.clone(),
                 ));
-                parentheses.push((Position::new(line, i, i + 1, Rc::clone(&filename)), 2));
+                parentheses.push((Position::new(line, i, i + 1, filename), 2));
             }
             ']' => {
-                tokens.push(Token::new(
+                tokens.push_back(Token::new(
                     TokenType::RSquare,
                     line,
                     i,
                     i + 1,
-                    Rc::clone(&filename),
+                    filename.clone(),
                 ));
                 let paren = parentheses.pop();
-                if paren.is_none() {
+                if let Some(paren) = paren {
+                    if paren.1 != 2 {
+                        return Err(Error::new(
+                            ErrorType::SyntaxError,
+                            Position::new(line, i, i + 1, filename),
+                            "Mismatched brackets".to_string(),
+                        ));
+                    }
+                } else {
                     return Err(Error::new(
                         ErrorType::SyntaxError,
-                        Position::new(line, i, i + 1, Rc::clone(&filename)),
+                        Position::new(line, i, i + 1, filename),
                         "Missing opening '[' pair".to_string(),
                     ));
-                } else if paren.unwrap().1 != 2 {
-                    return Err(Error::new(
-                        ErrorType::SyntaxError,
-                        Position::new(line, i, i + 1, Rc::clone(&filename)),
-                        "Mismatched brackets".to_string(),
-                    ));
                 }
             }
             '{' => {
-                tokens.push(Token::new(
+                tokens.push_back(Token::new(
                     TokenType::LCurly,
                     line,
                     i,
                     i + 1,
-                    Rc::clone(&filename),
+                    filename.clone(),
                 ));
-                parentheses.push((Position::new(line, i, i + 1, Rc::clone(&filename)), 1));
+                parentheses.push((Position::new(line, i, i + 1, filename), 1));
             }
             '}' => {
-                tokens.push(Token::new(
+                tokens.push_back(Token::new(
                     TokenType::RCurly,
                     line,
                     i,
                     i + 1,
-                    Rc::clone(&filename),
+                    filename.clone(),
                 ));
                 let paren = parentheses.pop();
-                if paren.is_none() {
+                if let Some(paren) = paren {
+                    if paren.1 != 1 {
+                        return Err(Error::new(
+                            ErrorType::SyntaxError,
+                            Position::new(line, i, i + 1, filename),
+                            "Mismatched brackets".to_string(),
+                        ));
+                    }
+                } else {
                     return Err(Error::new(
                         ErrorType::SyntaxError,
-                        Position::new(line, i, i + 1, Rc::clone(&filename)),
+                        Position::new(line, i, i + 1, filename),
                         "Missing opening '{' pair".to_string(),
                     ));
-                } else if paren.unwrap().1 != 1 {
-                    return Err(Error::new(
-                        ErrorType::SyntaxError,
-                        Position::new(line, i, i + 1, Rc::clone(&filename)),
-                        "Mismatched brackets".to_string(),
-                    ));
                 }
             }
             ',' => {
-                tokens.push(Token::new(
-                    TokenType::Comma,
-                    line,
-                    i,
-                    i + 1,
-                    Rc::clone(&filename),
-                ));
+                tokens.push_back(Token::new(TokenType::Comma, line, i, i + 1, filename));
             }
             ';' => {
-                tokens.push(Token::new(
-                    TokenType::Eol,
-                    line,
-                    i,
-                    i + 1,
-                    Rc::clone(&filename),
-                ));
+                tokens.push_back(Token::new(TokenType::Eol, line, i, i + 1, filename));
             }
             '>' => {
                 if let Some((_, '=')) = chars.peek() {
                     chars.next();
-                    tokens.push(Token::new(
-                        TokenType::Ge,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Ge, line, i, i + 2, filename));
                 } else if let Some((_, '>')) = chars.peek() {
                     chars.next();
                     if let Some((_, '=')) = chars.peek() {
                         chars.next();
-                        tokens.push(Token::new(
+                        tokens.push_back(Token::new(
                             TokenType::ShrAssign,
                             line,
                             i,
                             i + 3,
-                            Rc::clone(&filename),
+                            filename,
                         ));
                     } else {
-                        tokens.push(Token::new(
-                            TokenType::Shr,
-                            line,
-                            i,
-                            i + 2,
-                            Rc::clone(&filename),
-                        ));
+                        tokens.push_back(Token::new(TokenType::Shr, line, i, i + 2, filename));
                     }
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::Gt,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Gt, line, i, i + 1, filename));
                 }
             }
             '<' => {
                 if let Some((_, '=')) = chars.peek() {
                     chars.next();
-                    tokens.push(Token::new(
-                        TokenType::Le,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Le, line, i, i + 2, filename));
                 } else if let Some((_, '<')) = chars.peek() {
                     chars.next();
                     if let Some((_, '=')) = chars.peek() {
                         chars.next();
-                        tokens.push(Token::new(
+                        tokens.push_back(Token::new(
                             TokenType::ShlAssign,
                             line,
                             i,
                             i + 3,
-                            Rc::clone(&filename),
+                            filename,
                         ));
                     } else {
-                        tokens.push(Token::new(
-                            TokenType::Shl,
-                            line,
-                            i,
-                            i + 2,
-                            Rc::clone(&filename),
-                        ));
+                        tokens.push_back(Token::new(TokenType::Shl, line, i, i + 2, filename));
                     }
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::Lt,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Lt, line, i, i + 1, filename));
                 }
             }
             '!' => match chars.peek() {
                 Some((_, '=')) => {
                     chars.next();
-                    tokens.push(Token::new(
-                        TokenType::Neq,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Neq, line, i, i + 2, filename));
                 }
                 Some((_, '&')) => {
                     let mut new = chars.clone();
@@ -538,30 +775,18 @@ pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
                         chars.next();
                         if let Some((_, '=')) = chars.peek() {
                             chars.next();
-                            tokens.push(Token::new(
+                            tokens.push_back(Token::new(
                                 TokenType::LXorAssign,
                                 line,
                                 i,
                                 i + 4,
-                                Rc::clone(&filename),
+                                filename,
                             ));
                         } else {
-                            tokens.push(Token::new(
-                                TokenType::LXor,
-                                line,
-                                i,
-                                i + 3,
-                                Rc::clone(&filename),
-                            ));
+                            tokens.push_back(Token::new(TokenType::LXor, line, i, i + 3, filename));
                         }
                     } else {
-                        tokens.push(Token::new(
-                            TokenType::LNot,
-                            line,
-                            i,
-                            i + 1,
-                            Rc::clone(&filename),
-                        ));
+                        tokens.push_back(Token::new(TokenType::LNot, line, i, i + 1, filename));
                     }
                 }
                 Some((_, c)) if LITERALS.contains(*c) => {
@@ -578,47 +803,44 @@ pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
                     }
                     end -= last_line;
                     if PREPROCESSOR_STATEMENTS.contains(&word.as_ref()) {
-                        tokens.push(Token::new(
+                        tokens.push_back(Token::new(
                             TokenType::PreprocessorStatement(word),
                             line,
                             start,
                             end,
-                            Rc::clone(&filename),
-                        ));
-                    } else if KEYWORDS.contains(&word.as_ref()) {
-                        tokens.push(Token::new(
-                            TokenType::Keyword(word),
-                            line,
-                            start,
-                            end,
-                            Rc::clone(&filename),
+                            filename,
                         ));
                     } else {
-                        tokens.push(Token::new(
-                            TokenType::Identifier(word),
+                        // `!word` is a negation unless it names a preprocessor statement
+                        tokens.push_back(Token::new(
+                            TokenType::LNot,
                             line,
-                            start,
-                            end,
-                            Rc::clone(&filename),
+                            i,
+                            i + 1,
+                            filename.clone(),
                         ));
+                        let token_type = if KEYWORDS.contains(&word.as_ref()) {
+                            TokenType::Keyword(word)
+                        } else {
+                            TokenType::Identifier(word)
+                        };
+                        tokens.push_back(Token::new(token_type, line, start + 1, end, filename));
                     }
                 }
                 _ => {
-                    tokens.push(Token::new(
-                        TokenType::LNot,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::LNot, line, i, i + 1, filename));
                 }
             },
             '"' => {
                 let mut word = String::new();
                 let start = i;
-                let mut end = j + 2;
+                let start_line = line;
+                let opening = Position::new(line, i, i + 1, filename.clone());
+                let mut end = None;
                 let mut escape = false;
-                for (i, c) in chars.by_ref() {
+                while let Some((i, c)) = chars.next() {
+                    // `i` counts from the start of the input, and strings can span lines
+                    let column = i - last_line + 1;
                     if escape {
                         match c {
                             'n' => word.push('\n'),
@@ -629,15 +851,17 @@ pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
                             _ => {
                                 return Err(Error::new(
                                     ErrorType::SyntaxError,
-                                    Position::new(line, i, i + 3, Rc::clone(&filename)),
+                                    Position::new(line, column - 1, column + 1, filename),
                                     "Invalid escape sequence".to_string(),
-                                ))
+                                ));
                             }
                         }
                         escape = false;
                     } else if c == '"' {
-                        end = i + 2;
+                        end = Some(i + 2);
                         break;
+                    } else if c == '\r' && matches!(chars.peek(), Some((_, '\n'))) {
+                        // Strings get the same `\n` line endings on every platform
                     } else if c == '\n' {
                         line += 1;
                         last_line = i + 1;
@@ -647,198 +871,295 @@ pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
                     } else if c.is_ascii() {
                         word.push(c);
                     } else {
-                        return Err(Error::new(
-                            ErrorType::SyntaxError,
-                            Position::new(line, i, i + 3, Rc::clone(&filename)),
-                            "Invalid character in string literal, String can only be of ascii characters".to_string(),
+                        return Err(non_ascii(
+                            c,
+                            Position::new(line, column, column + 1, filename),
+                            STRING_ASCII,
                         ));
                     }
                 }
-                end -= last_line;
-                tokens.push(Token::new(
-                    TokenType::String(word),
-                    line,
-                    start,
-                    end,
-                    Rc::clone(&filename),
-                ));
+                let end = match end {
+                    Some(end) => end - last_line,
+                    None => {
+                        return Err(Error::new(
+                            ErrorType::SyntaxError,
+                            opening,
+                            format!(
+                                "Unterminated string starting on line {}, expected '\"' before the end of the file",
+                                start_line
+                            ),
+                        )
+                        .with_label(eof.clone(), "the file ends here".to_string()))
+                    }
+                };
+                let mut token =
+                    Token::new(TokenType::String(word), start_line, start, end, filename);
+                token.position.line_end = line;
+                tokens.push_back(token);
             }
             '=' => {
-                if let Some((_, '=')) = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::new(
-                        TokenType::Eq,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
+                if let Some((_, '>')) = chars.peek() {
+                    return Err(unexpected(
+                        ErrorType::SyntaxError,
+                        "=>",
+                        Position::new(line, i, i + 2, filename),
+                        Some("->"),
                     ));
+                } else if let Some((_, '=')) = chars.peek() {
+                    chars.next();
+                    tokens.push_back(Token::new(TokenType::Eq, line, i, i + 2, filename));
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::Assign,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::Assign, line, i, i + 1, filename));
                 }
             }
             '&' => {
                 if let Some((_, '&')) = chars.peek() {
                     chars.next();
+                    // `&&&` is a reference to a reference to a reference before a value or a
+                    // type, but after a value it is a typo of `&&`
+                    if matches!(chars.peek(), Some((_, '&')))
+                        && tokens
+                            .back()
+                            .map_or(*after_value, |t| ends_value(&t.token_type))
+                    {
+                        return Err(unexpected(
+                            ErrorType::SyntaxError,
+                            "&&&",
+                            Position::new(line, i, i + 3, filename),
+                            Some("&&"),
+                        ));
+                    }
                     if let Some((_, '=')) = chars.peek() {
                         chars.next();
-                        tokens.push(Token::new(
+                        tokens.push_back(Token::new(
                             TokenType::LAndAssign,
                             line,
                             i,
                             i + 3,
-                            Rc::clone(&filename),
+                            filename,
                         ));
                     } else {
-                        tokens.push(Token::new(
-                            TokenType::LAnd,
-                            line,
-                            i,
-                            i + 2,
-                            Rc::clone(&filename),
-                        ));
+                        tokens.push_back(Token::new(TokenType::LAnd, line, i, i + 2, filename));
                     }
                 } else if let Some((_, '=')) = chars.peek() {
                     chars.next();
-                    tokens.push(Token::new(
-                        TokenType::BAndAssign,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::BAndAssign, line, i, i + 2, filename));
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::BAnd,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::BAnd, line, i, i + 1, filename));
                 }
             }
             '~' => {
-                tokens.push(Token::new(
-                    TokenType::BNot,
-                    line,
-                    i,
-                    i + 1,
-                    Rc::clone(&filename),
-                ));
+                tokens.push_back(Token::new(TokenType::BNot, line, i, i + 1, filename));
             }
             '|' => {
                 if let Some((_, '|')) = chars.peek() {
                     chars.next();
                     if let Some((_, '=')) = chars.peek() {
                         chars.next();
-                        tokens.push(Token::new(
+                        tokens.push_back(Token::new(
                             TokenType::LOrAssign,
                             line,
                             i,
                             i + 3,
-                            Rc::clone(&filename),
+                            filename,
                         ));
                     } else {
-                        tokens.push(Token::new(
-                            TokenType::LOr,
-                            line,
-                            i,
-                            i + 2,
-                            Rc::clone(&filename),
-                        ));
+                        tokens.push_back(Token::new(TokenType::LOr, line, i, i + 2, filename));
                     }
                 } else if let Some((_, '=')) = chars.peek() {
                     chars.next();
-                    tokens.push(Token::new(
-                        TokenType::BOrAssign,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::BOrAssign, line, i, i + 2, filename));
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::BOr,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::BOr, line, i, i + 1, filename));
                 }
             }
             '^' => {
                 if let Some((_, '=')) = chars.peek() {
                     chars.next();
-                    tokens.push(Token::new(
-                        TokenType::BXorAssign,
-                        line,
-                        i,
-                        i + 2,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::BXorAssign, line, i, i + 2, filename));
                 } else {
-                    tokens.push(Token::new(
-                        TokenType::BXor,
-                        line,
-                        i,
-                        i + 1,
-                        Rc::clone(&filename),
-                    ));
+                    tokens.push_back(Token::new(TokenType::BXor, line, i, i + 1, filename));
                 }
             }
             '?' => {
-                tokens.push(Token::new(
-                    TokenType::TernaryIf,
-                    line,
-                    i,
-                    i + 1,
-                    Rc::clone(&filename),
-                ));
+                tokens.push_back(Token::new(TokenType::TernaryIf, line, i, i + 1, filename));
             }
             '.' => {
-                tokens.push(Token::new(
-                    TokenType::Dot,
-                    line,
-                    i,
-                    i + 1,
-                    Rc::clone(&filename),
-                ));
+                if let Some((_, '.')) = chars.peek() {
+                    chars.next();
+                    if let Some((_, '=')) = chars.peek() {
+                        chars.next();
+                        tokens.push_back(Token::new(TokenType::DotDotEq, line, i, i + 3, filename));
+                    } else {
+                        tokens.push_back(Token::new(TokenType::DotDot, line, i, i + 2, filename));
+                    }
+                } else {
+                    tokens.push_back(Token::new(TokenType::Dot, line, i, i + 1, filename));
+                }
             }
-            _ if c.is_digit(10) => {
-                let mut num = c.to_string();
+            _ if c.is_ascii_digit() => {
                 let start = i;
                 let mut end = j + 2;
+                // `0x`, `0b` and `0o` start hexadecimal, binary and octal numbers
+                let (radix, base) = match (c, chars.peek()) {
+                    ('0', Some((_, 'x'))) => (16, "hexadecimal"),
+                    ('0', Some((_, 'b'))) => (2, "binary"),
+                    ('0', Some((_, 'o'))) => (8, "octal"),
+                    _ => (10, "decimal"),
+                };
+                let mut num = String::new();
+                let prefix = if radix == 10 {
+                    num.push(c);
+                    None
+                } else {
+                    chars.next().map(|(i, prefix)| {
+                        end = i + 2;
+                        prefix
+                    })
+                };
+                // Digits can be separated with `_`, like `1_000`. This is the column of the last
+                // character, if it was a `_`
+                let mut separator = None;
+                let underscore = |column: usize, details: &str| {
+                    Error::new(
+                        ErrorType::InvalidLiteral,
+                        Position::new(line, column, column + 1, filename.clone()),
+                        details.to_string(),
+                    )
+                };
                 while let Some((i, c)) = chars.peek() {
-                    if !c.is_digit(10) {
+                    // Letters are part of a number in another base, so that a digit that
+                    // doesn't belong to it is reported instead of starting an identifier
+                    if !c.is_ascii_digit()
+                        && *c != '_'
+                        && (radix == 10 || !c.is_ascii_alphanumeric())
+                    {
                         break;
                     }
                     end = *i + 2;
-                    num.push(*c);
+                    if *c == '_' {
+                        let column = *i - last_line + 1;
+                        if num.is_empty() {
+                            return Err(underscore(
+                                column,
+                                "Expected a digit before `_` in a number",
+                            ));
+                        }
+                        if separator.is_some() {
+                            return Err(underscore(
+                                column,
+                                "Expected a digit after `_` in a number",
+                            ));
+                        }
+                        separator = Some(column);
+                    } else {
+                        separator = None;
+                        num.push(*c);
+                    }
                     chars.next();
                 }
-                tokens.push(Token::new(
-                    TokenType::Number(match num.parse() {
-                        Ok(num) => num,
-                        Err(err) => {
+                end -= last_line;
+                if let Some(column) = separator {
+                    return Err(underscore(column, "Expected a digit after `_` in a number"));
+                }
+                if let Some(prefix) = prefix.filter(|_| num.is_empty()) {
+                    return Err(Error::new(
+                        ErrorType::InvalidLiteral,
+                        Position::new(line, start, end, filename),
+                        format!("Expected {} digits after `0{}`", base, prefix),
+                    ));
+                }
+                if let Some(digit) = num.chars().find(|d| !d.is_digit(radix)) {
+                    return Err(Error::new(
+                        ErrorType::InvalidLiteral,
+                        Position::new(line, start, end, filename),
+                        format!("Invalid {} digit: '{}'", base, digit),
+                    ));
+                }
+                let max = if minus.is_some() { -INT_MIN } else { INT_MAX };
+                tokens.push_back(Token::new(
+                    TokenType::Number(match LexNumber::from_str_radix(&num, radix) {
+                        Ok(num) if num <= max => num,
+                        _ => {
+                            let number = Position::new(line, start, end, filename);
+                            let mut position = minus.clone().unwrap_or_else(|| number.clone());
+                            position.extend_to(&number);
                             return Err(Error::new(
                                 ErrorType::NumberTooLarge,
-                                Position::new(line, start, end, Rc::clone(&filename)),
-                                err.to_string(),
+                                position,
+                                format!(
+                                    "Number is too large, an int is from {} to {}",
+                                    INT_MIN, INT_MAX
+                                ),
                             ));
                         }
                     }),
                     line,
                     start,
                     end,
-                    Rc::clone(&filename),
+                    filename,
                 ));
             }
+            // A raw string, `r"..."` or `r#"..."#`, has no escapes. It ends at the first `"`
+            // followed by as many `#` as it started with
+            'r' if raw_string_hashes(chars.clone()).is_some() => {
+                let hashes = raw_string_hashes(chars.clone()).unwrap();
+                let opening = Position::new(line, i, i + hashes + 2, filename.clone());
+                chars.nth(hashes);
+                let mut word = String::new();
+                let start = i;
+                let start_line = line;
+                let mut end = None;
+                while let Some((i, c)) = chars.next() {
+                    if c == '"'
+                        && chars
+                            .clone()
+                            .take(hashes)
+                            .filter(|(_, c)| *c == '#')
+                            .count()
+                            == hashes
+                    {
+                        if hashes > 0 {
+                            chars.nth(hashes - 1);
+                        }
+                        end = Some(i + hashes + 2);
+                        break;
+                    } else if c == '\r' && matches!(chars.peek(), Some((_, '\n'))) {
+                    } else if c == '\n' {
+                        line += 1;
+                        last_line = i + 1;
+                        word.push(c);
+                    } else if c.is_ascii() {
+                        word.push(c);
+                    } else {
+                        let column = i - last_line + 1;
+                        return Err(non_ascii(
+                            c,
+                            Position::new(line, column, column + 1, filename),
+                            STRING_ASCII,
+                        ));
+                    }
+                }
+                let end = match end {
+                    Some(end) => end - last_line,
+                    None => {
+                        return Err(Error::new(
+                            ErrorType::SyntaxError,
+                            opening,
+                            format!(
+                                "Unterminated raw string starting on line {}, expected '\"{}' before the end of the file",
+                                start_line,
+                                "#".repeat(hashes)
+                            ),
+                        )
+                        .with_label(eof.clone(), "the file ends here".to_string()))
+                    }
+                };
+                let mut token =
+                    Token::new(TokenType::String(word), start_line, start, end, filename);
+                token.position.line_end = line;
+                tokens.push_back(token);
+            }
             _ if LITERALS.contains(c) => {
                 let mut word = c.to_string();
                 let start = i;
@@ -853,51 +1174,244 @@ pub fn lex(input: &str, filename: Rc<String>) -> LexResult {
                 }
                 end -= last_line;
                 if KEYWORDS.contains(&word.as_ref()) {
-                    tokens.push(Token::new(
+                    tokens.push_back(Token::new(
                         TokenType::Keyword(word),
                         line,
                         start,
                         end,
-                        Rc::clone(&filename),
+                        filename,
                     ));
                 } else {
-                    tokens.push(Token::new(
+                    tokens.push_back(Token::new(
                         TokenType::Identifier(word),
                         line,
                         start,
                         end,
-                        Rc::clone(&filename),
+                        filename,
                     ));
                 }
             }
+            _ if !c.is_ascii() => {
+                let hint = ascii_quote(c)
+                    .map(|quote| format!(", did you mean `{}`?", quote))
+                    .unwrap_or_default();
+                return Err(non_ascii(
+                    c,
+                    Position::new(line, i, i + 1, filename),
+                    &format!(
+                        ", only ascii characters can be used outside of strings and comments{}",
+                        hint
+                    ),
+                ));
+            }
             _ => {
-                return Err(Error::new(
+                // Directives start with `!`, and strings with `"`
+                let intended = match (c, chars.peek()) {
+                    ('#', Some((_, next))) if next.is_ascii_alphabetic() => Some("!"),
+                    ('`', _) => Some("\""),
+                    _ => None,
+                };
+                return Err(unexpected(
                     ErrorType::InvalidLiteral,
-                    Position::new(line, i, i + 1, Rc::clone(&filename)),
-                    format!("Invalid token: {}", c),
+                    &c.to_string(),
+                    Position::new(line, i, i + 1, filename),
+                    intended,
                 ));
             }
         }
+        self.line = line;
+        self.last_line = last_line;
+        Ok(true)
+    }
+
+    /// The `Eof` token, or an error if a bracket is still open
+    fn end(&mut self) -> Result<Token, Error> {
+        if let Some((opening, kind)) = self.parentheses.pop() {
+            let (open, close) = [('(', ')'), ('{', '}'), ('[', ']')][kind];
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                self.eof.clone(),
+                format!("Expected '{}' before the end of the file", close),
+            )
+            .with_label(opening, format!("unclosed '{}' opened here", open)));
+        }
+        Ok(Token {
+            token_type: TokenType::Eof,
+            position: self.eof.clone(),
+        })
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                self.minus = (token.token_type == TokenType::Sub && !self.after_value)
+                    .then(|| token.position.clone());
+                self.after_value = ends_value(&token.token_type);
+                return Some(Ok(token));
+            }
+            match self.step() {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.done = true;
+                    return Some(self.end());
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl FusedIterator for Lexer<'_> {}
+
+/// The error for code that is `found` where it can't be. When it is a common typo of
+/// `intended`, the message says so and the error suggests replacing it
+fn unexpected(
+    error_type: ErrorType,
+    found: &str,
+    position: Position,
+    intended: Option<&str>,
+) -> Error {
+    let details = format!("Unexpected '{}'", found);
+    match intended {
+        Some(intended) => Error::new(
+            error_type,
+            position.clone(),
+            format!("{}, did you mean '{}'?", details, intended),
+        )
+        .with_suggestion(Suggestion::new(
+            format!("replace '{}' with '{}'", found, intended),
+            vec![(position, intended.to_string())],
+        )),
+        None => Error::new(error_type, position, details),
+    }
+}
+
+/// Whether a token of type `t` can be the end of a value, so that a binary operator can
+/// come after it
+fn ends_value(t: &TokenType) -> bool {
+    match t {
+        TokenType::Identifier(_)
+        | TokenType::Number(_)
+        | TokenType::Char(_)
+        | TokenType::String(_)
+        | TokenType::RParen
+        | TokenType::RSquare => true,
+        // `ezin` reads a value, and a type ends the cast in `x as int`
+        TokenType::Keyword(keyword) => {
+            matches!(
+                keyword.as_str(),
+                "true" | "false" | "ezin" | "int" | "char" | "bool"
+            )
+        }
+        _ => false,
     }
+}
+
+/// The error for a character that isn't ascii, showing it and its code point, with `reason`
+/// after them
+fn non_ascii(c: char, position: Position, reason: &str) -> Error {
+    Error::new(
+        ErrorType::InvalidLiteral,
+        position,
+        format!("Invalid character '{}' (U+{:04X}){}", c, c as u32, reason),
+    )
+}
 
-    if !parentheses.is_empty() {
+/// Reads the digits of a `\xNN` or `\u{..}` escape in a char literal, `kind` being its `x` or `u`
+/// and `position` the one of the escape up to `kind`. Returns the value of the escape,
+/// along with the number of characters read after `kind`
+fn numeric_escape(
+    kind: char,
+    chars: impl Iterator<Item = (usize, char)>,
+    mut position: Position,
+) -> Result<(u8, usize), Error> {
+    let mut digits = String::new();
+    let mut read = 0;
+    let closed = if kind == 'x' {
+        for (_, c) in chars.take(2) {
+            read += 1;
+            digits.push(c);
+        }
+        true
+    } else {
+        let mut closed = false;
+        for (_, c) in chars {
+            read += 1;
+            match c {
+                '{' if read == 1 => (),
+                '}' if read > 1 => {
+                    closed = true;
+                    break;
+                }
+                '\'' | '\n' => break,
+                c => digits.push(c),
+            }
+        }
+        closed
+    };
+    position.end += read;
+    if !closed || digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        let details = if kind == 'x' {
+            "Expected two hex digits after `\\x`, like '\\x07'"
+        } else {
+            "Expected hex digits between braces after `\\u`, like '\\u{7}'"
+        };
         return Err(Error::new(
-            ErrorType::SyntaxError,
-            parentheses.pop().unwrap().0,
-            "Unclosed Parentheses".to_string(),
+            ErrorType::InvalidLiteral,
+            position,
+            details.to_string(),
         ));
     }
+    match u32::from_str_radix(&digits, 16).map(u8::try_from) {
+        Ok(Ok(value)) => Ok((value, read)),
+        _ => Err(Error::new(
+            ErrorType::InvalidLiteral,
+            position,
+            format!(
+                "Char escape `\\u{{{}}}` is out of range, chars can only be from 0 to 255",
+                digits
+            ),
+        )),
+    }
+}
+
+/// The ascii quote that editors replace with `c`, if it is one of their curly quotes
+fn ascii_quote(c: char) -> Option<char> {
+    match c {
+        '\u{201c}' | '\u{201d}' | '\u{201e}' => Some('"'),
+        '\u{2018}' | '\u{2019}' => Some('\''),
+        _ => None,
+    }
+}
 
-    if let Some(s) = last.checked_sub(last_line) {
-        last = s;
+/// The number of `#` after the `r` of a raw string, or `None` if the `r` doesn't start one
+fn raw_string_hashes(chars: impl Iterator<Item = (usize, char)>) -> Option<usize> {
+    let mut hashes = 0;
+    for (_, c) in chars {
+        match c {
+            '#' => hashes += 1,
+            '"' => return Some(hashes),
+            _ => return None,
+        }
     }
+    None
+}
 
-    tokens.push(Token::new(
-        TokenType::Eof,
-        line,
-        last,
-        last,
-        Rc::clone(&filename),
-    ));
-    Ok(tokens)
+/// Returns the empty position just after the last character of `input`.
+/// Trailing line breaks are left out, so that the position is on the last line with code
+fn eof_position(input: &str, filename: FileName) -> Position {
+    let content = input.trim_end_matches(['\n', '\r']);
+    let line = content.matches('\n').count() + 1;
+    let column = content.rsplit('\n').next().unwrap().chars().count() + 1;
+    Position::new(line, column, column, filename)
 }
@@ -0,0 +1,161 @@
+use crate::utils::ir::{Instruction, Instructions, Val, ValType};
+
+/// Rewrites `Mul`, `Div`, `Mod` and `Pow` into loops made of `Copy`, `Add`, `Sub`, `Lt`, `Le`,
+/// `While` and `EndWhile`, so that a backend only needs to implement those.
+/// The loops keep their values in the cells from the first free cell of the instruction on,
+/// and the result is copied to the cell the instruction assigned to.
+/// Like in the brainfuck backend, numbers are unsigned and dividing by zero never ends
+/// # Examples
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "
+/// let a = ezin char as int
+/// let b = ezin char as int
+/// ezascii a * b, a / b, a % b, a * -3, a / 2, a ** 3, b ** 0, (a % 7) ** (b % 4)
+/// ";
+/// let run = |lower_arith, input: [u8; 2]| {
+///     let options = Options { lower_arith, ..Options::default() };
+///     let bf = ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut Stats::default());
+///     let mut output = vec![];
+///     ezlang::core::interpreter::interpret(&bf.unwrap(), &mut input.as_slice(), &mut output).unwrap();
+///     output
+/// };
+/// for a in [0, 1, 2, 127, 128, 255] {
+///     for b in [1, 2, 3, 128, 255] {
+///         assert_eq!(run(true, [a, b]), run(false, [a, b]), "{} and {}", a, b);
+///     }
+/// }
+/// assert_eq!(run(true, [255, 2]), [254, 127, 1, 3, 127, 255, 1, 9]);
+/// ```
+pub fn lower_arithmetic(code: &Instructions) -> Instructions {
+    let mut lowered = Instructions::new();
+    let mut starts = vec![];
+    for (assign, instruction) in &code.0 {
+        starts.push(lowered.0.len());
+        let (dest, free) = match (assign, instruction) {
+            (
+                (Some(dest), free),
+                Instruction::Mul(..)
+                | Instruction::Div(..)
+                | Instruction::Mod(..)
+                | Instruction::Pow(..),
+            ) => (*dest, *free),
+            _ => {
+                lowered.push(instruction.clone(), *assign);
+                continue;
+            }
+        };
+        let mut builder = Builder {
+            code: &mut lowered,
+            free,
+        };
+        let result = match instruction {
+            Instruction::Mul(a, b) => builder.mul(a, b),
+            Instruction::Div(a, b) => builder.div_mod(a, b).0,
+            Instruction::Mod(a, b) => builder.div_mod(a, b).1,
+            Instruction::Pow(a, b) => builder.pow(a, b),
+            _ => unreachable!(),
+        };
+        let free = builder.free;
+        lowered.push(Instruction::Copy(number(result)), (Some(dest), free));
+    }
+    lowered.keep_regions(code, &starts);
+    lowered
+}
+
+/// Emits the loops of a lowered instruction, allocating the cells they need after `free`
+struct Builder<'a> {
+    code: &'a mut Instructions,
+    /// The first cell that isn't used yet
+    free: usize,
+}
+
+impl Builder<'_> {
+    /// Allocates a cell and stores `value` in it
+    fn cell(&mut self, value: Val) -> usize {
+        let cell = self.free;
+        self.free += 1;
+        self.copy(cell, value);
+        cell
+    }
+
+    fn copy(&mut self, cell: usize, value: Val) {
+        self.code
+            .push(Instruction::Copy(value), (Some((cell, 1)), self.free));
+    }
+
+    /// Stores the result of `instruction` in `cell`. Instructions use the cells after the one
+    /// they assign to as scratch, so the result is computed in the first free cell and then
+    /// copied, leaving the cells allocated after `cell` as they are
+    fn set(&mut self, cell: usize, instruction: Instruction) {
+        let temp = self.free;
+        self.code.push(instruction, (Some((temp, 1)), temp + 1));
+        self.code
+            .push(Instruction::Copy(number(temp)), (Some((cell, 1)), temp + 1));
+    }
+
+    /// Runs `body` as long as `condition` is true, computing the condition before every run
+    fn loop_while(&mut self, condition: impl Fn() -> Instruction, body: impl FnOnce(&mut Self)) {
+        let flag = self.free;
+        self.free += 1;
+        let flag_val = Val::Index(flag, ValType::Boolean);
+        self.set(flag, condition());
+        self.code
+            .push(Instruction::While(flag_val.clone()), (None, self.free));
+        body(self);
+        self.set(flag, condition());
+        self.code
+            .push(Instruction::EndWhile(flag_val), (None, self.free));
+    }
+
+    /// Runs `body` `times` times
+    fn repeat(&mut self, times: &Val, body: impl FnOnce(&mut Self)) {
+        let counter = self.cell(times.clone());
+        self.loop_while(
+            || Instruction::Lt(Val::Num(0), number(counter)),
+            |this| {
+                body(this);
+                this.set(counter, Instruction::Sub(number(counter), Val::Num(1)));
+            },
+        );
+    }
+
+    /// Adds `a` to a cell starting at 0, `b` times
+    fn mul(&mut self, a: &Val, b: &Val) -> usize {
+        let product = self.cell(Val::Num(0));
+        self.repeat(b, |this| {
+            this.set(product, Instruction::Add(number(product), a.clone()))
+        });
+        product
+    }
+
+    /// Subtracts `b` from `a` until what is left is smaller than `b`, counting how many times
+    /// it did. Returns the cells of the quotient and of the remainder
+    fn div_mod(&mut self, a: &Val, b: &Val) -> (usize, usize) {
+        let quotient = self.cell(Val::Num(0));
+        let remainder = self.cell(a.clone());
+        self.loop_while(
+            || Instruction::Le(b.clone(), number(remainder)),
+            |this| {
+                this.set(remainder, Instruction::Sub(number(remainder), b.clone()));
+                this.set(quotient, Instruction::Add(number(quotient), Val::Num(1)));
+            },
+        );
+        (quotient, remainder)
+    }
+
+    /// Multiplies a cell starting at 1 by `base`, `exp` times
+    fn pow(&mut self, base: &Val, exp: &Val) -> usize {
+        let power = self.cell(Val::Num(1));
+        self.repeat(exp, |this| {
+            let product = this.mul(&number(power), base);
+            this.copy(power, number(product));
+        });
+        power
+    }
+}
+
+fn number(cell: usize) -> Val {
+    Val::Index(cell, ValType::Number)
+}
@@ -1,20 +1,41 @@
+/// Counts the instructions a program runs
+pub mod bench;
+
 /// Contains the code transpiler, which generates the Brainfuck code
 pub mod compiler;
 
 /// Contains the `evaluate` function, which does constant time evaluation of the code.
 pub mod evaluate;
 
+/// Contains the brainfuck++ interpreter
+pub mod interpreter;
+
 /// Contains the Intermediate code generator
 pub mod ir_code;
 
 /// Contains the Lexer struct
 pub mod lexer;
 
+/// Rewrites the arithmetic instructions of the IR code into simpler ones
+pub mod lowering;
+
 /// Contains the Parser struct
 pub mod parser;
 
+/// Runs the phases of the compiler, with hooks between them
+pub mod pipeline;
+
 /// Contains the Preprocessor
 pub mod preprocessor;
 
+/// Checks that optimizing a program doesn't change what it prints
+pub mod verify;
+
+/// Writes a compiled program as an annotated page of HTML
+pub mod report;
+
+/// Finds the warnings of a program
+pub mod warnings;
+
 /// Optimizes the generated IR code
 pub mod ir_optimizer;
@@ -1,11 +1,91 @@
+use std::collections::HashSet;
+
 use crate::utils::{
-    Error, ErrorType, Node, Position, Scope, Token, TokenType, Type, ASSIGNMENT_OPERATORS,
+    ast::{Node, Scope, Semicolons, StructDefinition, Type},
+    diag::{Error, ErrorType, Position, Suggestion},
+    ir::{ValNumber, ValType, CELL_BITS},
+    token::{LexNumber, Token, TokenType, ASSIGNMENT_OPERATORS, INT_MAX, INT_MIN},
+    Options,
 };
 
 /// A result type for parsing
 type ParseResult = Result<Node, Error>;
 type Signatures = Vec<(Token, Vec<Type>, Type)>;
-type Structs = Vec<(Token, Vec<(Token, Type)>)>;
+type Structs = Vec<StructDefinition>;
+
+/// What is known while expanding function calls
+struct Expansions {
+    /// The functions whose calls are being expanded, by name and the position of their definition
+    stack: Vec<(Token, Position)>,
+    /// Number of nodes the expanded bodies added so far
+    size: usize,
+    /// The most nodes the expanded bodies can add, see [`Options::max_inline_size`]
+    max_size: usize,
+    /// The nodes added by the calls at every call site, by the name of the function called
+    /// and the position of its definition
+    sites: Vec<(Token, Position, usize)>,
+}
+
+impl Expansions {
+    fn new(max_size: usize) -> Self {
+        Self {
+            stack: vec![],
+            size: 0,
+            max_size,
+            sites: vec![],
+        }
+    }
+
+    /// Counts the nodes of `body`, which a call at `call` adds to the program. Fails once the
+    /// program gets bigger than allowed, pointing at the call site that added the most nodes
+    fn add(&mut self, call: &Token, body: &Node, definition: &Position) -> Option<Error> {
+        let nodes = count_nodes(body);
+        self.size += nodes;
+        match self
+            .sites
+            .iter_mut()
+            .find(|(site, ..)| site.position == call.position)
+        {
+            Some((.., size)) => *size += nodes,
+            None => self.sites.push((call.clone(), definition.clone(), nodes)),
+        }
+        if self.size <= self.max_size {
+            return None;
+        }
+        // The first of the call sites that added the most
+        let (site, definition, size) = self
+            .sites
+            .iter()
+            .rev()
+            .max_by_key(|(.., size)| *size)
+            .unwrap();
+        Some(
+            Error::new(
+                ErrorType::ExpansionTooLarge,
+                site.position.clone(),
+                format!(
+                    "The expanded function calls add more than {} nodes to the program, the most \
+                    ({}) from this call to {}. Call it from fewer places, or raise the limit with \
+                    --max-inline-size",
+                    self.max_size, size, site
+                ),
+            )
+            .with_label(
+                definition.clone(),
+                format!(
+                    "{} is defined here, every call to it is replaced by this",
+                    site
+                ),
+            ),
+        )
+    }
+}
+
+fn count_nodes(node: &Node) -> usize {
+    1 + node.children().into_iter().map(count_nodes).sum::<usize>()
+}
+/// Parameters, body, return type
+type Function = (Vec<(Token, Type)>, Node, Type);
 
 /// Parses the List of Tokens into an AST
 #[derive(Clone)]
@@ -14,6 +94,8 @@ pub struct Parser {
     token_index: usize,
     current_token: Token,
     statics: Vec<String>,
+    /// Whether `name {` starts a struct constructor, which isn't the case right before a body
+    struct_literals: bool,
 }
 
 impl Parser {
@@ -24,11 +106,10 @@ impl Parser {
         }
     }
 
-    fn peek_type(&self) -> Option<TokenType> {
-        if self.token_index + 1 < self.tokens.len() {
-            return Some(self.tokens[self.token_index + 1].token_type.clone());
-        }
-        None
+    fn peek_type(&self) -> Option<&TokenType> {
+        self.tokens
+            .get(self.token_index + 1)
+            .map(|token| &token.token_type)
     }
 
     fn statements(
@@ -40,17 +121,19 @@ impl Parser {
         let mut statements = vec![];
         let mut pos = self.current_token.position.clone();
         if self.current_token.token_type == end_token {
-            let end_pos = self.current_token.position.clone();
-            pos.end = end_pos.end;
-            pos.line_end = end_pos.line_end;
+            pos.extend_to(&self.current_token.position);
             self.advance();
-            return Ok((Node::Statements(statements, Type::None, pos), None));
+            return Ok((
+                Node::Statements(statements, Type::None, Semicolons::default(), pos),
+                None,
+            ));
         }
         if !global {
             self.advance();
         }
 
         let mut type_ = None;
+        let mut semicolons = vec![];
 
         while self.current_token.token_type != end_token {
             match self.current_token.token_type {
@@ -67,7 +150,7 @@ impl Parser {
                                 "Cannot return in global scope".to_string(),
                             ));
                         }
-                        if *type_.as_ref().unwrap() != t {
+                        if type_.as_ref() != Some(&t) {
                             return Err(Error::new(
                                 ErrorType::TypeError,
                                 n.position(),
@@ -75,16 +158,23 @@ impl Parser {
                             ));
                         }
                     }
-                    statements.push(n)
+                    statements.push(n);
+                    semicolons.push(
+                        (self.current_token.token_type == TokenType::Eol)
+                            .then_some(self.current_token.position.clone()),
+                    );
                 }
             }
         }
-        let end_pos = self.current_token.position.clone();
-        pos.end = end_pos.end;
-        pos.line_end = end_pos.line_end;
+        pos.extend_to(&self.current_token.position);
         self.advance();
         Ok((
-            Node::Statements(statements, type_.clone().unwrap_or(Type::None), pos),
+            Node::Statements(
+                statements,
+                type_.clone().unwrap_or(Type::None),
+                Semicolons(semicolons),
+                pos,
+            ),
             type_,
         ))
     }
@@ -103,47 +193,74 @@ impl Parser {
                             "Expected '(' after 'while'".to_string(),
                         ));
                     }
-                    self.advance();
-                    let condition = self.expression(scope)?;
-                    if condition.get_type() != Type::Boolean {
-                        return Err(Error::new(
-                            ErrorType::TypeError,
-                            condition.position(),
-                            "Condition in a while loop can only be a bool".to_string(),
-                        ));
-                    }
-                    if self.current_token.token_type != TokenType::RParen {
-                        return Err(Error::new(
-                            ErrorType::SyntaxError,
-                            self.current_token.position.clone(),
-                            format!("Expected ')' found '{}'", self.current_token.token_type),
-                        ));
-                    }
-                    self.advance();
+                    let condition = self.condition(scope, "a while loop")?;
+                    // The body might not run, so it doesn't initialize anything
+                    let initialized = scope.initialized();
                     let (body, t) = self.statement(scope)?;
-                    pos.end = body.position().end;
-                    pos.line_end = body.position().line_end;
+                    scope.set_initialized(&initialized);
+                    pos.extend_to(body.end_position());
                     Ok((Node::While(Box::new(condition), Box::new(body), pos), t))
                 }
                 "return" => {
                     let pos = self.current_token.position.clone();
                     self.advance();
-                    let expr = self.expression(scope)?;
+                    // `return;` and `return }` return nothing
+                    let expr = match self.current_token.token_type {
+                        TokenType::Eol | TokenType::RCurly => Node::None(pos.clone()),
+                        _ => self.expression(scope)?,
+                    };
                     let t = expr.get_type();
-                    Ok((Node::Return(Box::new(expr), pos), Some(t)))
+                    let node = Node::Return(Box::new(expr), pos);
+                    if let Some(ret) = scope.return_type() {
+                        if *ret != t {
+                            return Err(Error::new(
+                                ErrorType::TypeError,
+                                node.position(),
+                                format!("Expected return type {}, found {}", ret, t),
+                            ));
+                        }
+                    }
+                    Ok((node, Some(t)))
                 }
                 "let" => {
+                    let mut pos = self.current_token.position.clone();
                     self.advance();
                     let node = self.assignment(true, scope)?;
                     scope.register_variable(node.clone());
-                    Ok((node, None))
+                    if self.current_token.token_type != TokenType::Comma {
+                        return Ok((node, None));
+                    }
+                    let mut nodes = vec![node];
+                    while self.current_token.token_type == TokenType::Comma {
+                        self.advance();
+                        let node = self.assignment(true, scope)?;
+                        scope.register_variable(node.clone());
+                        nodes.push(node);
+                    }
+                    pos.extend_to(nodes.last().unwrap().end_position());
+                    Ok((Node::Declarations(nodes, pos), None))
                 }
                 "static" => {
+                    if scope.in_function() {
+                        return Err(Error::new(
+                            ErrorType::SyntaxError,
+                            self.current_token.position.clone(),
+                            "Static variables can only be declared outside of functions"
+                                .to_string(),
+                        ));
+                    }
                     self.advance();
                     let node = self.static_assignment()?;
                     scope.register_variable(node.clone());
                     Ok((node, None))
                 }
+                "for"
+                    if self.tokens.get(self.token_index + 2).is_some_and(|t| {
+                        t.token_type == TokenType::Identifier("in".to_string())
+                    }) =>
+                {
+                    self.for_each(scope)
+                }
                 "for" => {
                     let mut pos = self.current_token.position.clone();
                     self.advance();
@@ -166,11 +283,7 @@ impl Parser {
                     self.advance();
                     let condition = self.expression(scope)?;
                     if condition.get_type() != Type::Boolean {
-                        return Err(Error::new(
-                            ErrorType::TypeError,
-                            condition.position(),
-                            "Condition in a for loop can only be a bool".to_string(),
-                        ));
+                        return Err(condition_error(&condition, "a for loop"));
                     }
                     if self.current_token.token_type != TokenType::Colon {
                         return Err(Error::new(
@@ -180,6 +293,7 @@ impl Parser {
                         ));
                     }
                     self.advance();
+                    let initialized = scope.initialized();
                     let (step, ts) = self.statement(scope)?;
                     if self.current_token.token_type != TokenType::RParen {
                         return Err(Error::new(
@@ -190,6 +304,7 @@ impl Parser {
                     }
                     self.advance();
                     let (body, tb) = self.statement(scope)?;
+                    scope.set_initialized(&initialized);
                     if matches!((&ti, &tb), (Some(ti), Some(tb)) if ti != tb) {
                         return Err(Error::new(
                             ErrorType::TypeError,
@@ -211,8 +326,7 @@ impl Parser {
                             format!("Expected type {:?} but found {:?}", ts, tb),
                         ));
                     }
-                    pos.end = body.position().end;
-                    pos.line_end = body.position().end;
+                    pos.extend_to(body.end_position());
                     Ok((
                         Node::For(
                             Box::new(init),
@@ -239,30 +353,25 @@ impl Parser {
                             "Expected '(' after 'if'".to_string(),
                         ));
                     }
-                    self.advance();
-                    let condition = self.expression(scope)?;
-                    if condition.get_type() != Type::Boolean {
-                        return Err(Error::new(
-                            ErrorType::TypeError,
-                            condition.position(),
-                            "Condition in an if statement can only be a bool".to_string(),
-                        ));
-                    }
-                    if self.current_token.token_type != TokenType::RParen {
-                        return Err(Error::new(
-                            ErrorType::SyntaxError,
-                            self.current_token.position.clone(),
-                            format!("Expected ')' found '{}'", self.current_token.token_type),
-                        ));
-                    }
-                    self.advance();
+                    let condition = self.condition(scope, "an if statement")?;
+                    let initialized = scope.initialized();
                     let (then_branch, tt) = self.statement(scope)?;
+                    let then_initialized = scope.initialized();
+                    scope.set_initialized(&initialized);
                     let (else_, end_pos, te) = if self.current_token.token_type
                         == TokenType::Keyword("else".to_string())
                     {
                         self.advance();
                         let (node, te) = self.statement(scope)?;
-                        let pos = node.position();
+                        // A variable is only initialized after the `if` if both branches did it
+                        let both = scope
+                            .initialized()
+                            .iter()
+                            .zip(then_initialized)
+                            .map(|(a, b)| a.iter().zip(b).map(|(a, b)| *a && b).collect())
+                            .collect::<Vec<_>>();
+                        scope.set_initialized(&both);
+                        let pos = node.end_position().clone();
                         (Some(Box::new(node)), pos, te)
                     } else {
                         (None, self.current_token.position.clone(), None)
@@ -274,8 +383,7 @@ impl Parser {
                             format!("Expected type {:?} but found {:?}", tt, te),
                         ));
                     }
-                    pos.end = end_pos.end;
-                    pos.line_end = end_pos.line_end;
+                    pos.extend_to(&end_pos);
                     Ok((
                         Node::If(Box::new(condition), Box::new(then_branch), else_, pos),
                         match (tt, te) {
@@ -293,25 +401,66 @@ impl Parser {
                         self.advance();
                         nodes.push(self.expression(scope)?);
                     }
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
+                    pos.extend_to(nodes.last().unwrap().end_position());
                     Ok((Node::Ascii(nodes, pos), None))
                 }
-                "ezout" => {
+                "ezraw" => {
                     let mut pos = self.current_token.position.clone();
                     self.advance();
+                    let code = match self.current_token.token_type {
+                        TokenType::String(ref code) => code.clone(),
+                        _ => {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                self.current_token.position.clone(),
+                                "Expected a string of brainfuck code after `ezraw`".to_string(),
+                            ))
+                        }
+                    };
+                    pos.extend_to(&self.current_token.position);
+                    self.advance();
+                    Ok((Node::Raw(code, pos), None))
+                }
+                "ezout" | "ezoutln" => {
+                    let newline = keyword == "ezoutln";
+                    let mut pos = self.current_token.position.clone();
+                    self.advance();
+                    // A bare `ezoutln` only prints the newline. Newlines aren't tokens, so it
+                    // also ends when the next token is on another line
+                    if newline
+                        && (matches!(
+                            self.current_token.token_type,
+                            TokenType::Eol | TokenType::RCurly | TokenType::Eof
+                        ) || self.current_token.position.line_start > pos.line_end)
+                    {
+                        return Ok((Node::Print(vec![], newline, pos), None));
+                    }
                     let mut nodes = vec![self.expression(scope)?];
                     while let TokenType::Comma = self.current_token.token_type {
                         self.advance();
                         nodes.push(self.expression(scope)?);
                     }
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
-                    Ok((Node::Print(nodes, pos), None))
+                    pos.extend_to(nodes.last().unwrap().end_position());
+                    if let Node::String(
+                        ref string @ Token {
+                            token_type: TokenType::String(ref s),
+                            ..
+                        },
+                    ) = nodes[0]
+                    {
+                        if s.contains(['{', '}']) {
+                            nodes = format_string(string, &nodes[1..])?;
+                        }
+                    }
+                    Ok((Node::Print(nodes, newline, pos), None))
                 }
                 "ez" => {
                     self.advance();
-                    Ok((self.function_definition(scope)?, None))
+                    let node = self.function_definition(scope)?;
+                    if let Some(err) = scope.register_function(node.clone()) {
+                        return Err(err);
+                    }
+                    Ok((node, None))
                 }
                 "struct" => {
                     self.advance();
@@ -322,8 +471,9 @@ impl Parser {
                 _ => Ok((self.expression(scope)?, None)),
             },
             TokenType::Identifier(_)
-                if self.peek_type().is_some()
-                    && ASSIGNMENT_OPERATORS.contains(&self.peek_type().unwrap()) =>
+                if self
+                    .peek_type()
+                    .is_some_and(|t| ASSIGNMENT_OPERATORS.contains(t)) =>
             {
                 let node = self.assignment(false, scope)?;
                 scope.access_variable(&node)?;
@@ -332,42 +482,47 @@ impl Parser {
             TokenType::LCurly => {
                 let mut new_scope = Scope::new(Some(scope));
                 let node = self.statements(TokenType::RCurly, false, &mut new_scope)?;
+                new_scope.span = Some(node.0.position());
+                if let Some(ref updated) = new_scope.parent {
+                    scope.merge_initialized(updated);
+                }
                 scope.scopes.push(new_scope);
                 Ok(node)
             }
-            TokenType::Identifier(_) if matches!(self.peek_type(), Some(TokenType::LSquare)) => {
-                let token = self.current_token.clone();
-                self.advance();
-                self.advance();
-                let index = self.expression(scope)?;
-                if self.current_token.token_type != TokenType::RSquare {
-                    return Err(Error::new(
-                        ErrorType::SyntaxError,
-                        self.current_token.position.clone(),
-                        format!("Expected ']', found {}", self.current_token),
-                    ));
-                }
-                self.advance();
-                let node = if !ASSIGNMENT_OPERATORS.contains(&self.current_token.token_type) {
+            TokenType::Identifier(_)
+                if matches!(self.peek_type(), Some(TokenType::Dot | TokenType::LSquare)) =>
+            {
+                let target = self.access_attr(scope)?;
+                if !ASSIGNMENT_OPERATORS.contains(&self.current_token.token_type) {
                     self.token_index = idx;
                     self.current_token = self.tokens[idx].clone();
                     return Ok((self.expression(scope)?, None));
-                } else if self.current_token.token_type == TokenType::Assign {
+                }
+                let t = target.get_type();
+                let mut hoisted = vec![];
+                let augmented = self.current_token.token_type != TokenType::Assign;
+                // An augmented assignment reads the target and then writes it, parts of it with
+                // side effects, like `ezin`, are evaluated once before the assignment
+                let target = if augmented {
+                    hoist_effects(target, &mut hoisted)
+                } else {
+                    target
+                };
+                let right = if !augmented {
                     self.advance();
-                    Node::IndexAssign(token, Box::new(index), Box::new(self.expression(scope)?))
+                    self.expression(scope)?
                 } else {
-                    let op = self.current_token.clone();
+                    let op = self.current_token.clone().un_augmented();
                     self.advance();
                     let right = self.expression(scope)?;
-                    let t = scope.access_variable_by_token(&token)?;
-                    let rt = match right.get_type().get_result_type(&t, &op) {
+                    let rt = match t.get_result_type(&right.get_type(), &op) {
                         Some(t) => t,
                         None => {
                             return Err(Error::new(
                                 ErrorType::TypeError,
                                 op.position.clone(),
                                 format!(
-                                    "Cannot apply operator {} to {} and {}",
+                                    "Cannot apply operator {} to types {} and {}",
                                     op,
                                     t,
                                     right.get_type()
@@ -375,18 +530,32 @@ impl Parser {
                             ))
                         }
                     };
-                    Node::IndexAssign(
-                        token.clone(),
-                        Box::new(index),
-                        Box::new(Node::BinaryOp(
-                            op.un_augmented(),
-                            Box::new(Node::VarAccess(token, t)),
-                            Box::new(right),
-                            rt,
-                        )),
-                    )
+                    Node::BinaryOp(op, Box::new(target.clone()), Box::new(right), rt)
                 };
-                Ok((node, None))
+                if right.get_type() != t {
+                    return Err(Error::new(
+                        ErrorType::TypeError,
+                        right.position(),
+                        format!(
+                            "Cannot assign {} to {}{}",
+                            right.get_type(),
+                            t,
+                            mismatch(&t, &right.get_type())
+                        ),
+                    ));
+                }
+                let mut pos = target.position();
+                pos.extend_to(right.end_position());
+                let node = match target {
+                    Node::Index(arr, index, ..) => Node::IndexAssign(arr, index, Box::new(right)),
+                    _ => Node::AttrAssign(Box::new(target), Box::new(right), pos.clone()),
+                };
+                if hoisted.is_empty() {
+                    Ok((node, None))
+                } else {
+                    hoisted.push(node);
+                    Ok((Node::Declarations(hoisted, pos), None))
+                }
             }
             TokenType::Mul => {
                 let mut pos = self.current_token.position.clone();
@@ -403,11 +572,15 @@ impl Parser {
                         return Err(Error::new(
                             ErrorType::TypeError,
                             right.position(),
-                            format!("Cannot assign {} to {}", right.get_type(), node.get_type()),
+                            format!(
+                                "Cannot assign {} to {}{}",
+                                right.get_type(),
+                                node.get_type(),
+                                mismatch(&node.get_type(), &right.get_type())
+                            ),
                         ));
                     }
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
+                    pos.extend_to(right.end_position());
                     Ok((
                         Node::DerefAssign(Box::new(node), Box::new(right), pos),
                         None,
@@ -416,8 +589,7 @@ impl Parser {
                     let op = self.current_token.clone().un_augmented();
                     self.advance();
                     let right = self.expression(scope)?;
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
+                    pos.extend_to(right.end_position());
                     let t = node.get_type();
                     let rt = match t.get_result_type(&right.get_type(), &op) {
                         Some(t) => t,
@@ -441,17 +613,12 @@ impl Parser {
                             format!("Cannot assign {} to {}", right.get_type(), t),
                         ));
                     }
+                    // `node` is the dereference itself, which reads the current value
                     let node = Node::DerefAssign(
                         Box::new(node.clone()),
-                        Box::new(Node::BinaryOp(
-                            op,
-                            Box::new(Node::Deref(Box::new(node), t, pos.clone())),
-                            Box::new(right),
-                            rt,
-                        )),
+                        Box::new(Node::BinaryOp(op, Box::new(node), Box::new(right), rt)),
                         pos,
                     );
-                    scope.access_variable(&node)?;
                     Ok((node, None))
                 }
             }
@@ -465,18 +632,17 @@ impl Parser {
                     Ok((self.expression(scope)?, None))
                 } else if self.current_token.token_type == TokenType::Assign {
                     self.advance();
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
+                    let right = self.expression(scope)?;
+                    pos.extend_to(right.end_position());
                     Ok((
-                        Node::DerefAssign(Box::new(node), Box::new(self.expression(scope)?), pos),
+                        Node::DerefAssign(Box::new(node), Box::new(right), pos),
                         None,
                     ))
                 } else {
                     let op = self.current_token.clone().un_augmented();
                     self.advance();
                     let right = self.expression(scope)?;
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
+                    pos.extend_to(right.end_position());
                     let t = node.get_type();
                     let rt = match t.get_result_type(&right.get_type(), &op) {
                         Some(t) => t,
@@ -493,41 +659,336 @@ impl Parser {
                             ))
                         }
                     };
+                    // `node` is the dereference itself, which reads the current value
                     let node = Node::DerefAssign(
                         Box::new(node.clone()),
-                        Box::new(Node::BinaryOp(
-                            op,
-                            Box::new(Node::Deref(Box::new(node), t, pos.clone())),
-                            Box::new(right),
-                            rt,
-                        )),
+                        Box::new(Node::BinaryOp(op, Box::new(node), Box::new(right), rt)),
                         pos,
                     );
-                    scope.access_variable(&node)?;
                     Ok((node, None))
                 }
             }
-            _ => Ok((self.expression(scope)?, None)),
+            _ => {
+                let node = self.expression(scope)?;
+                // A function on its own does nothing, it was most likely meant to be called
+                if let Node::VarAccess(ref token, Type::Function(..)) = node {
+                    let arities = scope.function_arities(token);
+                    if !arities.is_empty() {
+                        return Err(not_called(token, &arities));
+                    }
+                }
+                Ok((node, None))
+            }
+        }
+    }
+
+    /// Parses `for x in arr body` and `for i in start..end body`. `x` or `i`, and the hidden
+    /// variables of the loop, only exist inside of it
+    fn for_each(&mut self, scope: &mut Scope) -> Result<(Node, Option<Type>), Error> {
+        let pos = self.current_token.position.clone();
+        self.advance();
+        let name = self.current_token.clone();
+        if !matches!(name.token_type, TokenType::Identifier(_)) {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                name.position,
+                format!("Expected variable name, found {}", name.token_type),
+            ));
+        }
+        self.advance();
+        self.advance();
+        self.struct_literals = false;
+        let array = self.expression(scope);
+        self.struct_literals = true;
+        let array = array?;
+        let mut loop_scope = Scope::new(Some(scope));
+        let (node, t) =
+            if let TokenType::DotDot | TokenType::DotDotEq = self.current_token.token_type {
+                self.for_range(name, array, pos, &mut loop_scope)?
+            } else {
+                self.for_array(name, array, pos, &mut loop_scope)?
+            };
+        let position = node.position();
+        // The body might not run, so the variables it initializes aren't copied back
+        loop_scope.span = Some(position.clone());
+        scope.scopes.push(loop_scope);
+        Ok((
+            Node::Statements(vec![node], Type::None, Semicolons::none(1), position),
+            t,
+        ))
+    }
+
+    /// Parses the rest of `for x in arr body`, which goes over the elements of an array whose
+    /// length is known. It becomes a `for` loop with a hidden index, where `x` is a copy of the
+    /// current element
+    /// # Examples
+    /// ```
+    /// let code = "let arr = [1, 2, 3]\nfor x in arr {\n    x += 1\n    ezout x\n}\nfor x in arr ezout x";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"234123");
+    /// ```
+    fn for_array(
+        &mut self,
+        name: Token,
+        array: Node,
+        mut pos: Position,
+        scope: &mut Scope,
+    ) -> Result<(Node, Option<Type>), Error> {
+        let element_type = match array.get_type().element_type() {
+            Some(t) => t,
+            None => {
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    array.position(),
+                    format!(
+                        "Cannot iterate over type {}, it is not an array",
+                        array.get_type()
+                    ),
+                ))
+            }
+        };
+        let length = match array {
+            Node::Array(ref elements, count, ..) => Some(elements.len() * count),
+            Node::VarAccess(ref token, _) => scope.array_length(token),
+            _ => None,
+        };
+        let length = match length {
+            Some(length) if length <= ValNumber::MAX as usize => length,
+            Some(length) => {
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    array.position(),
+                    format!(
+                        "Cannot iterate over an array of {} elements, the most is {}",
+                        length,
+                        ValNumber::MAX
+                    ),
+                ))
+            }
+            None => {
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    array.position(),
+                    "The length of the array isn't known, it can only be iterated over if it is an array literal or a variable declared with one".to_string(),
+                ))
+            }
+        };
+
+        // The hidden parts of the loop point at `x in arr`
+        let mut header = name.position.clone();
+        header.extend_to(array.end_position());
+        let hidden = |name: &str| Token {
+            token_type: TokenType::Identifier(name.to_string()),
+            position: header.clone(),
+        };
+        let number = |n: usize| {
+            Node::Number(Token {
+                token_type: TokenType::Number(n as LexNumber),
+                position: header.clone(),
+            })
+        };
+        let index = Node::VarAccess(hidden("@index"), Type::Number);
+        let mut init = vec![Node::VarAssign(
+            hidden("@index"),
+            Box::new(number(0)),
+            Type::Number,
+        )];
+        // Arrays that aren't in a variable are only built once, before the loop
+        let array = if let Node::VarAccess(..) = array {
+            array
+        } else {
+            let t = array.get_type();
+            init.push(Node::VarAssign(
+                hidden("@array"),
+                Box::new(array),
+                t.clone(),
+            ));
+            Node::VarAccess(hidden("@array"), t)
+        };
+        let (more, condition, step) = count_up(hidden("@index"), number(length), false, number(1));
+        init.extend(more);
+        let element = Node::VarAssign(
+            name.clone(),
+            Box::new(Node::Index(
+                Box::new(array),
+                Box::new(index),
+                element_type.clone(),
+                name.position,
+            )),
+            element_type,
+        );
+        scope.register_variable(element.clone());
+
+        let (body, t) = self.statement(scope)?;
+        let body_pos = body.position();
+        pos.extend_to(body.end_position());
+        Ok((
+            Node::For(
+                Box::new(Node::Declarations(init, header)),
+                Box::new(condition),
+                Box::new(step),
+                Box::new(Node::Statements(
+                    vec![element, body],
+                    Type::None,
+                    Semicolons::none(2),
+                    body_pos,
+                )),
+                pos,
+            ),
+            t,
+        ))
+    }
+
+    /// Parses the rest of `for i in start..end body` or `for i in start..=end body`, from the `..`.
+    /// `i` counts up from `start`, by one or by the `k` of a `step k` after the range, and `end`
+    /// and `k` are evaluated once, before the loop. If `end` isn't above `start`, or `k` is 0,
+    /// the body doesn't run at all
+    /// # Examples
+    /// ```
+    /// let code = "for i in 0..3 ezout i\nfor i in 1..=7 step 3 ezout i\nfor i in 3..0 ezout i";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"012147");
+    /// ```
+    fn for_range(
+        &mut self,
+        name: Token,
+        start: Node,
+        mut pos: Position,
+        scope: &mut Scope,
+    ) -> Result<(Node, Option<Type>), Error> {
+        let inclusive = self.current_token.token_type == TokenType::DotDotEq;
+        self.advance();
+        self.struct_literals = false;
+        let end = self.expression(scope);
+        self.struct_literals = true;
+        let end = end?;
+        let step = if self.current_token.token_type == TokenType::Identifier("step".to_string()) {
+            self.advance();
+            self.struct_literals = false;
+            let step = self.expression(scope);
+            self.struct_literals = true;
+            Some(step?)
+        } else {
+            None
+        };
+        for (bound, part) in [(&start, "start"), (&end, "end")]
+            .into_iter()
+            .chain(step.as_ref().map(|step| (step, "step")))
+        {
+            if bound.get_type() != Type::Number {
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    bound.position(),
+                    format!(
+                        "The {} of a range must be an int, found {}",
+                        part,
+                        bound.get_type()
+                    ),
+                ));
+            }
+        }
+
+        // A step of 0 would never reach the end
+        if let Some(
+            step @ Node::Number(Token {
+                token_type: TokenType::Number(0),
+                ..
+            }),
+        ) = &step
+        {
+            return Err(Error::new(
+                ErrorType::TypeError,
+                step.position(),
+                "The step of a range can't be 0".to_string(),
+            ));
         }
+
+        let mut header = name.position.clone();
+        header.extend_to(step.as_ref().unwrap_or(&end).end_position());
+        let counter = Node::VarAssign(name.clone(), Box::new(start), Type::Number);
+        let mut init = vec![counter.clone()];
+        let mut once = |value: Node, name: &str| {
+            if let Node::Number(_) = value {
+                return value;
+            }
+            let hidden = Token {
+                token_type: TokenType::Identifier(name.to_string()),
+                position: header.clone(),
+            };
+            init.push(Node::VarAssign(
+                hidden.clone(),
+                Box::new(value),
+                Type::Number,
+            ));
+            Node::VarAccess(hidden, Type::Number)
+        };
+        let end = once(end, "@end");
+        let step = match step {
+            Some(step) => once(step, "@step"),
+            None => Node::Number(Token {
+                token_type: TokenType::Number(1),
+                position: header.clone(),
+            }),
+        };
+        let (more, condition, step) = count_up(name, end, inclusive, step);
+        init.extend(more);
+        scope.register_variable(counter);
+
+        let (body, t) = self.statement(scope)?;
+        pos.extend_to(body.end_position());
+        Ok((
+            Node::For(
+                Box::new(Node::Declarations(init, header)),
+                Box::new(condition),
+                Box::new(step),
+                Box::new(body),
+                pos,
+            ),
+            t,
+        ))
     }
 
+    /// The errors of a malformed struct definition point at the token that isn't what was expected
+    /// # Examples
+    /// ```
+    /// let err = ezlang::parse("struct Point { x int }", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Expected ':' after field name, found 'int'");
+    /// assert_eq!((err.position.start, err.position.end), (18, 21));
+    /// ```
     fn struct_definition(&mut self, scope: &mut Option<&mut Scope>) -> ParseResult {
+        // `struct packed Flags { .. }` stores consecutive bool fields as the bits of a cell
+        let packed = self.current_token.token_type == TokenType::Identifier("packed".to_string())
+            && matches!(self.peek_type(), Some(TokenType::Identifier(_)));
+        if packed {
+            self.advance();
+        }
         if let TokenType::Identifier(_) = self.current_token.token_type {
             let name = self.current_token.clone();
             let mut pos = name.position.clone();
             self.advance();
             match self.current_token.token_type {
                 TokenType::Eol => {
+                    pos.extend_to(&self.current_token.position);
                     self.advance();
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
-                    Ok(Node::Struct(name, vec![], pos))
+                    Ok(Node::Struct(name, vec![], packed, pos))
                 }
                 TokenType::LCurly => {
                     self.advance();
-                    let mut fields = vec![];
-                    if let TokenType::Identifier(_) = self.current_token.token_type {
+                    let mut fields: Vec<(Token, Type)> = vec![];
+                    // Every error points at the token that isn't what was expected
+                    loop {
                         let field = self.current_token.clone();
+                        if !matches!(field.token_type, TokenType::Identifier(_)) {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                field.position.clone(),
+                                format!("Expected field name, found {}", field),
+                            ));
+                        }
                         if fields.iter().any(|(f, _)| *f == field) {
                             return Err(Error::new(
                                 ErrorType::Redefinition,
@@ -540,63 +1001,46 @@ impl Parser {
                             return Err(Error::new(
                                 ErrorType::SyntaxError,
                                 self.current_token.position.clone(),
-                                "Expected ':' after field name".to_string(),
+                                format!(
+                                    "Expected ':' after field name, found {}",
+                                    self.current_token
+                                ),
                             ));
                         }
                         self.advance();
                         let field_type = self.make_type(scope)?;
                         fields.push((field, field_type));
-                        while self.current_token.token_type == TokenType::Comma {
-                            self.advance();
-                            if self.current_token.token_type == TokenType::RCurly {
-                                break;
+                        match self.current_token.token_type {
+                            TokenType::Comma => {
+                                self.advance();
+                                if self.current_token.token_type == TokenType::RCurly {
+                                    break;
+                                }
                             }
-                            if !matches!(self.current_token.token_type, TokenType::Identifier(_)) {
+                            TokenType::RCurly => break,
+                            _ => {
                                 return Err(Error::new(
                                     ErrorType::SyntaxError,
                                     self.current_token.position.clone(),
-                                    "Expected field name".to_string(),
-                                ));
-                            }
-                            let field = self.current_token.clone();
-                            self.advance();
-                            if self.current_token.token_type != TokenType::Colon {
-                                return Err(Error::new(
-                                    ErrorType::SyntaxError,
-                                    self.current_token.position.clone(),
-                                    "Expected ':' after field name".to_string(),
-                                ));
+                                    format!(
+                                        "Expected ',' or '}}' after field, found {}",
+                                        self.current_token
+                                    ),
+                                ))
                             }
-                            self.advance();
-                            let field_type = self.make_type(scope)?;
-                            fields.push((field, field_type));
-                        }
-                        if self.current_token.token_type != TokenType::RCurly {
-                            return Err(Error::new(
-                                ErrorType::SyntaxError,
-                                self.current_token.position.clone(),
-                                "Expected '}' after struct definition".to_string(),
-                            ));
                         }
-                        self.advance();
-                        pos.end = self.current_token.position.end;
-                        pos.line_end = self.current_token.position.line_end;
-                        Ok(Node::Struct(name, fields, pos))
-                    } else {
-                        Err(Error::new(
-                            ErrorType::SyntaxError,
-                            self.current_token.position.clone(),
-                            format!(
-                                "Expected field name, found '{}'",
-                                self.current_token.token_type
-                            ),
-                        ))
                     }
+                    pos.extend_to(&self.current_token.position);
+                    self.advance();
+                    Ok(Node::Struct(name, fields, packed, pos))
                 }
                 _ => Err(Error::new(
                     ErrorType::SyntaxError,
                     self.current_token.position.clone(),
-                    "Expected Struct Declaration".to_string(),
+                    format!(
+                        "Expected '{{' after the name of struct {}, found {}",
+                        name, self.current_token
+                    ),
                 )),
             }
         } else {
@@ -626,16 +1070,26 @@ impl Parser {
                     self.advance();
                     Ok(Type::Char)
                 }
+                "point" => {
+                    self.advance();
+                    if self.current_token.token_type == TokenType::Identifier("packed".to_string())
+                        && self.peek_type() == Some(&TokenType::Keyword("bool".to_string()))
+                    {
+                        self.advance();
+                        self.advance();
+                        return Ok(Type::Pointer(Box::new(Type::Bits)));
+                    }
+                    Ok(Type::Pointer(Box::new(self.make_type(scope)?)))
+                }
                 "struct" => {
                     self.advance();
                     if let TokenType::Identifier(_) = self.current_token.token_type {
-                        let name = self.current_token.clone();
-                        let mut fields = vec![];
+                        let mut t = Type::Struct(self.current_token.clone(), vec![], false);
                         if let Some(scope) = scope {
-                            fields = scope.access_struct_by_token(&self.current_token)?;
+                            t = scope.access_struct_by_token(&self.current_token)?;
                         }
                         self.advance();
-                        Ok(Type::Struct(name, fields))
+                        Ok(t)
                     } else {
                         Err(Error::new(
                             ErrorType::SyntaxError,
@@ -650,6 +1104,14 @@ impl Parser {
                     format!("Expected type, found {}", keyword),
                 )),
             },
+            TokenType::Identifier(_) => {
+                let mut t = Type::Struct(self.current_token.clone(), vec![], false);
+                if let Some(scope) = scope {
+                    t = scope.access_struct_by_token(&self.current_token)?;
+                }
+                self.advance();
+                Ok(t)
+            }
             TokenType::BAnd => {
                 self.advance();
                 Ok(Type::Ref(Box::new(self.make_type(scope)?)))
@@ -674,6 +1136,33 @@ impl Parser {
                 self.advance();
                 Ok(Type::None)
             }
+            // Function types, like `(int, bool) -> int`
+            TokenType::LParen => {
+                self.advance();
+                let mut params = vec![];
+                while self.current_token.token_type != TokenType::RParen {
+                    params.push(self.make_type(scope)?);
+                    if self.current_token.token_type != TokenType::Comma {
+                        break;
+                    }
+                    self.advance();
+                }
+                if self.current_token.token_type != TokenType::RParen {
+                    return Err(Error::new(
+                        ErrorType::SyntaxError,
+                        self.current_token.position.clone(),
+                        format!("Expected ')' or ',', found {}", self.current_token),
+                    ));
+                }
+                self.advance();
+                let ret = if self.current_token.token_type == TokenType::Arrow {
+                    self.advance();
+                    self.make_type(scope)?
+                } else {
+                    Type::None
+                };
+                Ok(Type::Function(params, Box::new(ret)))
+            }
             _ => Err(Error::new(
                 ErrorType::SyntaxError,
                 self.current_token.position.clone(),
@@ -682,34 +1171,221 @@ impl Parser {
         }
     }
 
+    /// Parses the condition of an `if` or a `while` from its `(` to its `)`, which has to be a
+    /// bool. `statement` is what the condition belongs to, for the errors
+    /// # Examples
+    /// ```
+    /// let code = "let i = 0\nwhile (i < 10;) i += 1";
+    /// let err = ezlang::parse(code, "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Unexpected ';' inside parentheses");
+    /// assert_eq!((err.position.start, err.position.end), (14, 15));
+    /// ```
+    fn condition(&mut self, scope: &mut Scope, statement: &str) -> ParseResult {
+        let mut pos = self.current_token.position.clone();
+        self.advance();
+        if self.current_token.token_type == TokenType::RParen {
+            pos.extend_to(&self.current_token.position);
+            return Err(condition_error(&Node::None(pos), statement));
+        }
+        self.no_semicolon_in_parens()?;
+        let condition = self.expression(scope)?;
+        self.no_semicolon_in_parens()?;
+        if condition.get_type() != Type::Boolean {
+            return Err(condition_error(&condition, statement));
+        }
+        if self.current_token.token_type != TokenType::RParen {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                self.current_token.position.clone(),
+                format!("Expected ')' found '{}'", self.current_token.token_type),
+            ));
+        }
+        self.advance();
+        Ok(condition)
+    }
+
+    /// Fails at a `;` found inside parentheses, where it would otherwise be read as an empty
+    /// expression and make the error show up at a token after it
+    fn no_semicolon_in_parens(&self) -> Result<(), Error> {
+        if self.current_token.token_type == TokenType::Eol {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                self.current_token.position.clone(),
+                "Unexpected ';' inside parentheses".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parses `sizeof(type)` or `sizeof(expression)` into the number of cells it takes.
+    /// The size of an array literal, or of a variable declared with one, is the size of all
+    /// of its elements. The expression is never run
+    /// # Examples
+    /// ```
+    /// let code = "
+    /// struct Point { x: int, y: int }
+    /// struct Line { from: Point, to: Point, color: char }
+    /// let points = [Point { x: 1, y: 2 }; 3];
+    /// let p = *point points[1];
+    /// ezoutln sizeof(int), sizeof(Line), sizeof(*Point), sizeof(points), sizeof(p), sizeof(*p)
+    /// let cells = [0; sizeof(Line) + 1];
+    /// ezoutln sizeof(cells), sizeof([true, false])
+    /// ";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"1 5 2 6 2 2\n6 2\n");
+    ///
+    /// let err = ezlang::run("ez f() {}\nezout sizeof(f)", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Cannot take the size of () -> (), it isn't stored in any cell");
+    /// assert!(ezlang::run("ezout sizeof(())", "main.ez".to_string()).is_err());
+    /// ```
+    fn size_of(&mut self, scope: &mut Scope) -> ParseResult {
+        let mut pos = self.current_token.position.clone();
+        self.advance();
+        if self.current_token.token_type != TokenType::LParen {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                self.current_token.position.clone(),
+                "Expected '(' after 'sizeof'".to_string(),
+            ));
+        }
+        self.advance();
+        let start = self.current_token.position.clone();
+        let (t, count) = if self.starts_type(self.token_index, scope) {
+            (self.make_type(&mut Some(scope))?, 1)
+        } else {
+            let expr = self.expression(scope)?;
+            let count = match (&expr, expr.get_type()) {
+                (Node::Array(elements, count, ..), _) => Some(elements.len() * count),
+                (Node::VarAccess(token, _), Type::Pointer(_)) => scope.array_length(token),
+                _ => None,
+            };
+            match (count, expr.get_type()) {
+                // The bools of a packed array share cells
+                (Some(count), Type::Pointer(t)) if *t == Type::Bits => {
+                    (Type::Bits, count.div_ceil(CELL_BITS))
+                }
+                (Some(count), Type::Pointer(t)) => (*t, count),
+                (_, t) => (t, 1),
+            }
+        };
+        let mut type_pos = start;
+        type_pos.extend_to(&self.tokens[self.token_index - 1].position);
+        if self.current_token.token_type != TokenType::RParen {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                self.current_token.position.clone(),
+                format!("Expected ')' found '{}'", self.current_token.token_type),
+            ));
+        }
+        pos.extend_to(&self.current_token.position);
+        self.advance();
+        if let Type::None | Type::Function(..) = t {
+            return Err(Error::new(
+                ErrorType::TypeError,
+                type_pos,
+                format!("Cannot take the size of {}, it isn't stored in any cell", t),
+            ));
+        }
+        let size = ValType::from_parse_type(&t, &type_pos)?.get_size() * count;
+        Ok(Node::Number(Token {
+            token_type: TokenType::Number(size as LexNumber),
+            position: pos,
+        }))
+    }
+
+    /// Checks whether the tokens from `index` on are a type rather than an expression.
+    /// `&`, `*` and `point` start a type only if what follows them does
+    fn starts_type(&self, index: usize, scope: &Scope) -> bool {
+        let token = |i: usize| self.tokens.get(i).map(|t| &t.token_type);
+        match token(index) {
+            Some(TokenType::Keyword(keyword)) => match keyword.as_ref() {
+                "int" | "bool" | "char" | "struct" => true,
+                "point" => match token(index + 1) {
+                    Some(TokenType::Identifier(name)) if name == "packed" => {
+                        token(index + 2) == Some(&TokenType::Keyword("bool".to_string()))
+                    }
+                    _ => self.starts_type(index + 1, scope),
+                },
+                _ => false,
+            },
+            Some(TokenType::Identifier(_)) => scope.names_struct(&self.tokens[index]),
+            Some(TokenType::BAnd | TokenType::LAnd | TokenType::Mul | TokenType::Pow) => {
+                self.starts_type(index + 1, scope)
+            }
+            // `() -> int` and `(int) -> int` are function types
+            Some(TokenType::LParen) => match token(index + 1) {
+                Some(TokenType::RParen) => token(index + 2) == Some(&TokenType::Arrow),
+                _ => self.starts_type(index + 1, scope),
+            },
+            _ => false,
+        }
+    }
+
+    /// Checks whether the `struct` keyword at the current token starts the definition of a
+    /// struct, like `struct Point {` or `struct packed Flags {`, instead of a type
+    fn starts_struct_definition(&self) -> bool {
+        let token = |i: usize| self.tokens.get(self.token_index + i).map(|t| &t.token_type);
+        match token(1) {
+            Some(TokenType::Identifier(name)) if name == "packed" => {
+                matches!(token(2), Some(TokenType::Identifier(_)))
+                    && token(3) == Some(&TokenType::LCurly)
+            }
+            _ => token(2) == Some(&TokenType::LCurly),
+        }
+    }
+
+    /// Collects the signatures of the functions, the static variables and the structs of the
+    /// program before parsing it, so that functions can be called before they are defined
+    /// # Examples
+    /// ```
+    /// let code = "ezout twice(3)\nez twice(x: int) -> int { return x * 2 }";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"6");
+    /// ```
     fn find_signs(&mut self) -> Result<(Signatures, Vec<String>, Structs), Error> {
         let mut scope = Scope::new(None);
         let mut signatures = vec![];
         let mut statics = vec![];
-        let mut structs = vec![];
+        let mut structs: Structs = vec![];
         while self.current_token.token_type != TokenType::Eof {
             match self.current_token.token_type {
-                TokenType::Keyword(ref s) if s == "ez" => {
+                // `ez(` starts a lambda, which has no name to be called by
+                TokenType::Keyword(ref s)
+                    if s == "ez"
+                        && !matches!(
+                            self.tokens.get(self.token_index + 1),
+                            Some(Token {
+                                token_type: TokenType::LParen,
+                                ..
+                            })
+                        ) =>
+                {
                     self.advance();
                     signatures.push(self.function_signature(&mut None)?)
                 }
-                TokenType::Keyword(ref s) if s == "struct" => {
+                // `struct` is also used in types, like `let p: struct Point`
+                TokenType::Keyword(ref s) if s == "struct" && self.starts_struct_definition() => {
                     self.advance();
                     let node = self.struct_definition(&mut None)?;
-                    let (token, fields) = if let Node::Struct(token, fields, _) = node {
-                        (token, fields)
-                    } else {
-                        unreachable!()
-                    };
-                    if structs.iter().any(|(i, _)| *i == token) {
+                    let (token, fields, packed) =
+                        if let Node::Struct(token, fields, packed, _) = node {
+                            (token, fields, packed)
+                        } else {
+                            unreachable!()
+                        };
+                    if structs.iter().any(|(i, ..)| *i == token) {
                         return Err(Error::new(
                             ErrorType::Redefinition,
-                            self.current_token.position.clone(),
+                            token.position.clone(),
                             format!("A struct with the name of {} already exists", token),
                         ));
                     }
-                    scope.register_struct_premature((token.clone(), fields.clone()));
-                    structs.push((token, fields))
+                    scope.register_struct_premature((token.clone(), fields.clone(), packed));
+                    structs.push((token, fields, packed))
                 }
                 TokenType::Keyword(ref s) if s == "static" => {
                     self.advance();
@@ -748,34 +1424,91 @@ impl Parser {
         if let TokenType::Identifier(_) = self.current_token.token_type {
             let token = self.current_token.clone();
             self.advance();
+            if init {
+                if let TokenType::Identifier(ref ident) = token.token_type {
+                    if self.statics.contains(ident) {
+                        return Err(Error::new(
+                            ErrorType::Redefinition,
+                            token.position,
+                            format!(
+                                "A static variable with the name of '{}' already defined",
+                                ident
+                            ),
+                        ));
+                    }
+                }
+            }
             match self.current_token.token_type {
                 TokenType::Assign if init => {
-                    if let TokenType::Identifier(ref ident) = token.token_type {
-                        if self.statics.contains(ident) {
+                    self.advance();
+                    let expr = self.expression(scope)?;
+                    let t = expr.get_type();
+                    Ok(Node::VarAssign(token, Box::new(expr), t))
+                }
+                TokenType::Colon if init => {
+                    self.advance();
+                    let t = self.make_type(&mut Some(scope))?;
+                    if t == Type::None {
+                        return Err(Error::new(
+                            ErrorType::TypeError,
+                            token.position.clone(),
+                            format!("Variable {} must have a type", token),
+                        ));
+                    }
+                    if self.current_token.token_type != TokenType::Assign {
+                        if let Type::Function(..) = t {
                             return Err(Error::new(
-                                ErrorType::Redefinition,
-                                token.position,
+                                ErrorType::TypeError,
+                                token.position.clone(),
                                 format!(
-                                    "A static variable with the name of '{}' already defined",
-                                    ident
+                                    "Variable {} holds a function, it must be given one when declared",
+                                    token
                                 ),
                             ));
                         }
+                        let pos = self.tokens[self.token_index - 1].position.clone();
+                        return Ok(Node::VarAssign(token, Box::new(Node::None(pos)), t));
                     }
                     self.advance();
                     let expr = self.expression(scope)?;
-                    let t = expr.get_type();
+                    if expr.get_type() != t {
+                        return Err(Error::new(
+                            ErrorType::TypeError,
+                            expr.position(),
+                            format!(
+                                "Cannot assign {} to {}{}",
+                                expr.get_type(),
+                                t,
+                                mismatch(&t, &expr.get_type())
+                            ),
+                        ));
+                    }
                     Ok(Node::VarAssign(token, Box::new(expr), t))
                 }
                 TokenType::Assign => {
                     self.advance();
                     let node = self.expression(scope)?;
-                    let t = scope.access_variable_by_token(&token)?;
+                    let t = scope.assign_variable(&token)?;
+                    if let Type::Function(..) = t {
+                        return Err(Error::new(
+                            ErrorType::TypeError,
+                            token.position.clone(),
+                            format!(
+                                "Cannot reassign {}, variables holding a function can't be changed",
+                                token
+                            ),
+                        ));
+                    }
                     if node.get_type() != t {
                         return Err(Error::new(
                             ErrorType::TypeError,
                             node.position(),
-                            format!("Cannot assign {} to {}", t, node.get_type()),
+                            format!(
+                                "Cannot assign {} to {}{}",
+                                node.get_type(),
+                                t,
+                                mismatch(&t, &node.get_type())
+                            ),
                         ));
                     }
                     Ok(Node::VarReassign(token, Box::new(node)))
@@ -827,6 +1560,17 @@ impl Parser {
         }
     }
 
+    /// The value of a static variable is set before the program starts, so it can only be made of
+    /// constants. Anything that would run code is an error at the part of the value that does
+    /// # Examples
+    /// ```
+    /// let err = ezlang::parse("static s = ezin", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(
+    ///     err.details,
+    ///     "The value of a static variable must be known at compile time, so it can't be read with `ezin`"
+    /// );
+    /// assert_eq!((err.position.start, err.position.end), (12, 16));
+    /// ```
     fn static_assignment(&mut self) -> ParseResult {
         if let TokenType::Identifier(_) = self.current_token.token_type {
             let token = self.current_token.clone();
@@ -882,9 +1626,7 @@ impl Parser {
                 ));
             }
             let mut pos = node.position();
-            let end_pos = else_branch.position();
-            pos.end = end_pos.end;
-            pos.line_end = end_pos.line_end;
+            pos.extend_to(else_branch.end_position());
             Ok(Node::Ternary(
                 Box::new(node),
                 Box::new(then_branch),
@@ -968,7 +1710,11 @@ impl Parser {
         match token.token_type {
             TokenType::Sub | TokenType::BNot | TokenType::Inc | TokenType::Dec => {
                 self.advance();
+                let literal = matches!(self.current_token.token_type, TokenType::Number(_));
                 let node = self.factor(scope)?;
+                if let Some(number) = negative_literal(&token, &node).filter(|_| literal) {
+                    return Ok(number);
+                }
                 let t = match node.get_type().get_result_type_unary(&token) {
                     Some(t) => t,
                     None => {
@@ -1039,9 +1785,7 @@ impl Parser {
                 ));
             }
             let mut pos = node.position();
-            let end_pos = else_branch.position();
-            pos.end = end_pos.end;
-            pos.line_end = end_pos.line_end;
+            pos.extend_to(else_branch.end_position());
             Ok(Node::Ternary(
                 Box::new(node),
                 Box::new(then_branch),
@@ -1121,7 +1865,11 @@ impl Parser {
         match token.token_type {
             TokenType::Sub | TokenType::BNot | TokenType::Inc | TokenType::Dec => {
                 self.advance();
+                let literal = matches!(self.current_token.token_type, TokenType::Number(_));
                 let node = self.const_factor()?;
+                if let Some(number) = negative_literal(&token, &node).filter(|_| literal) {
+                    return Ok(number);
+                }
                 let t = match node.get_type().get_result_type_unary(&token) {
                     Some(t) => t,
                     None => {
@@ -1169,13 +1917,14 @@ impl Parser {
             if s != "as" {
                 break;
             }
-            let op = self.current_token.clone();
             self.advance();
             let right = self.make_type(&mut Some(scope))?;
             if !left.get_type().can_be_converted(&right) {
+                let mut pos = left.position();
+                pos.extend_to(&self.tokens[self.token_index - 1].position);
                 return Err(Error::new(
                     ErrorType::TypeError,
-                    op.position,
+                    pos,
                     format!("Cannot convert type {} to type {}", left.get_type(), right),
                 ));
             };
@@ -1185,9 +1934,39 @@ impl Parser {
         Ok(left)
     }
 
+    /// Parses the attribute accesses and indexes after a call, like `a.b[1][2].c`
     fn access_attr(&mut self, scope: &mut Scope) -> ParseResult {
         let mut left = self.call(scope)?;
-        while self.current_token.token_type == TokenType::Dot {
+        while matches!(
+            self.current_token.token_type,
+            TokenType::Dot | TokenType::LSquare
+        ) {
+            if self.current_token.token_type == TokenType::LSquare {
+                self.advance();
+                let index = self.expression(scope)?;
+                if self.current_token.token_type != TokenType::RSquare {
+                    return Err(Error::new(
+                        ErrorType::SyntaxError,
+                        self.current_token.position.clone(),
+                        format!("Expected ']', found {}", self.current_token),
+                    ));
+                }
+                let t = match left.get_type().element_type() {
+                    Some(t) => t,
+                    None => {
+                        return Err(Error::new(
+                            ErrorType::TypeError,
+                            left.position(),
+                            format!("Cannot index type {}, it is not an array", left.get_type()),
+                        ))
+                    }
+                };
+                let mut pos = left.position();
+                pos.extend_to(&self.current_token.position);
+                self.advance();
+                left = Node::Index(Box::new(left), Box::new(index), t, pos);
+                continue;
+            }
             self.advance();
             if !matches!(self.current_token.token_type, TokenType::Identifier(_)) {
                 return Err(Error::new(
@@ -1197,12 +1976,12 @@ impl Parser {
                 ));
             }
 
-            let t = if let Type::Struct(ref t, _) = left.get_type() {
-                if let Some((_, t)) = scope
-                    .access_struct_by_token(t)?
-                    .iter()
-                    .find(|(t, _)| *t == self.current_token)
-                {
+            let t = if let Type::Struct(ref t, ..) = left.get_type() {
+                let fields = match scope.access_struct_by_token(t)? {
+                    Type::Struct(_, fields, _) => fields,
+                    _ => unreachable!(),
+                };
+                if let Some((_, t)) = fields.iter().find(|(t, _)| *t == self.current_token) {
                     t.clone()
                 } else {
                     return Err(Error::new(
@@ -1216,7 +1995,15 @@ impl Parser {
                     ));
                 }
             } else {
-                unreachable!()
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    left.position(),
+                    format!(
+                        "Cannot access attribute {} on type {}, it is not a struct",
+                        self.current_token,
+                        left.get_type()
+                    ),
+                ));
             };
 
             left = Node::AttrAccess(Box::new(left), self.current_token.clone(), t);
@@ -1247,16 +2034,50 @@ impl Parser {
                         format!("Expected ')', found {}", self.current_token),
                     ));
                 }
-                self.advance();
                 pos.end = self.current_token.position.end;
                 pos.line_end = self.current_token.position.line_end;
+                self.advance();
+                if atom.token_type == TokenType::Identifier("len".to_string()) {
+                    return len(&args, pos, scope);
+                }
+                if let Some((params, ret)) = scope.function_variable(&atom) {
+                    scope.access_variable_by_token(&atom)?;
+                    if params.len() != args.len() {
+                        return Err(Error::new(
+                            ErrorType::TypeError,
+                            pos,
+                            format!(
+                                "{} takes {} arguments, but {} were given",
+                                atom,
+                                params.len(),
+                                args.len()
+                            ),
+                        ));
+                    }
+                    for (arg, param) in args.iter().zip(params.iter()) {
+                        if arg.get_type() != *param {
+                            return Err(Error::new(
+                                ErrorType::TypeError,
+                                arg.position(),
+                                format!(
+                                    "Expected {} as argument of {}, found {}{}",
+                                    param,
+                                    atom,
+                                    arg.get_type(),
+                                    mismatch(param, &arg.get_type())
+                                ),
+                            ));
+                        }
+                    }
+                    return Ok(Node::Call(atom, args, ret, pos));
+                }
                 let mut node = Node::Call(atom, args, Type::None, pos);
                 let t1 = scope.access_function(&node)?;
                 if let Node::Call(_, _, ref mut t, _) = node {
                     *t = t1;
                 }
                 return Ok(node);
-            } else if let TokenType::LCurly = self.current_token.token_type {
+            } else if self.struct_literals && self.current_token.token_type == TokenType::LCurly {
                 self.advance();
                 let mut fields = vec![];
                 while self.current_token.token_type != TokenType::RCurly {
@@ -1296,8 +2117,17 @@ impl Parser {
                 self.advance();
                 pos.end = self.current_token.position.end;
                 pos.line_end = self.current_token.position.line_end;
-                let node = Node::StructConstructor(atom, fields, pos);
-                scope.access_struct(&node)?;
+                let mut node = Node::StructConstructor(atom, fields, false, pos);
+                let (attrs, is_packed) = match scope.access_struct(&node)? {
+                    Type::Struct(_, attrs, packed) => (attrs, packed),
+                    _ => unreachable!(),
+                };
+                // Keep the fields in the order they were defined in, so that the type of
+                // the constructor doesn't depend on the order they were written in
+                if let Node::StructConstructor(_, ref mut fields, ref mut packed, _) = node {
+                    fields.sort_by_key(|(field, _)| attrs.iter().position(|(f, _)| f == field));
+                    *packed = is_packed;
+                }
                 return Ok(node);
             } else {
                 self.token_index -= 1;
@@ -1313,7 +2143,20 @@ impl Parser {
             TokenType::Keyword(ref keyword) => match keyword.as_ref() {
                 "ezin" => {
                     self.advance();
-                    Ok(Node::Input(token.position))
+                    let mut pos = token.position;
+                    let mut ty = Type::Number;
+                    if self.current_token.token_type == TokenType::Keyword("char".to_string()) {
+                        pos.extend_to(&self.current_token.position);
+                        self.advance();
+                        ty = Type::Char;
+                    }
+                    let mut prompt = None;
+                    if let TokenType::String(ref s) = self.current_token.token_type {
+                        prompt = Some(s.clone());
+                        pos.extend_to(&self.current_token.position);
+                        self.advance();
+                    }
+                    Ok(Node::Input(ty, prompt, pos))
                 }
                 "true" => {
                     self.advance();
@@ -1323,6 +2166,14 @@ impl Parser {
                     self.advance();
                     Ok(Node::Boolean(token))
                 }
+                "sizeof" => self.size_of(scope),
+                "ez" => {
+                    self.advance();
+                    let (params, body, ret) = self.function_body(scope)?;
+                    let mut pos = token.position;
+                    pos.extend_to(body.end_position());
+                    Ok(Node::Lambda(params, Box::new(body), ret, pos))
+                }
                 _ => Err(Error::new(
                     ErrorType::SyntaxError,
                     self.current_token.position.clone(),
@@ -1341,27 +2192,42 @@ impl Parser {
                 self.advance();
                 Ok(Node::Char(token))
             }
-            TokenType::Identifier(_) => {
+            // `packed [..]` is a packed array, unless `packed` is a variable being indexed
+            TokenType::Identifier(ref name)
+                if name == "packed"
+                    && self.peek_type() == Some(&TokenType::LSquare)
+                    && scope.variable_definition(&token).is_none() =>
+            {
                 self.advance();
-                if let TokenType::LSquare = self.current_token.token_type {
-                    self.advance();
-                    let index = self.expression(scope)?;
-                    if self.current_token.token_type != TokenType::RSquare {
-                        return Err(Error::new(
-                            ErrorType::SyntaxError,
-                            self.current_token.position.clone(),
-                            format!("Expected ']', found {}", self.current_token),
-                        ));
+                let bracket = self.current_token.clone();
+                match self.array_literal(bracket, scope)? {
+                    Node::Array(elements, count, Type::Boolean, mut pos) => {
+                        pos.start = token.position.start;
+                        pos.line_start = token.position.line_start;
+                        Ok(Node::Array(elements, count, Type::Bits, pos))
                     }
-                    self.advance();
-                    let mut pos = token.position.clone();
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
-                    let t = scope.access_array_by_token(&token, &index)?;
-                    Ok(Node::Index(token, Box::new(index), t, pos))
-                } else {
-                    let t = scope.access_variable_by_token(&token)?;
-                    Ok(Node::VarAccess(token, t))
+                    array => Err(Error::new(
+                        ErrorType::TypeError,
+                        array.position(),
+                        format!(
+                            "Only arrays of bools can be packed, found an array of {}",
+                            array.get_type().element_type().unwrap()
+                        ),
+                    )),
+                }
+            }
+            TokenType::Identifier(_) => {
+                self.advance();
+                match scope.access_variable_by_token(&token) {
+                    Ok(t) => Ok(Node::VarAccess(token, t)),
+                    // A function can be used as a value by its name, if it isn't overloaded
+                    Err(err) => match scope.function_type(&token) {
+                        Some(t) => Ok(Node::VarAccess(token, t)),
+                        None => match scope.function_arities(&token) {
+                            arities if arities.is_empty() => Err(err),
+                            arities => Err(not_called(&token, &arities)),
+                        },
+                    },
                 }
             }
             TokenType::LParen => {
@@ -1373,7 +2239,9 @@ impl Parser {
                     self.advance();
                     return Ok(Node::None(pos));
                 }
+                self.no_semicolon_in_parens()?;
                 let node = self.expression(scope)?;
+                self.no_semicolon_in_parens()?;
                 if self.current_token.token_type != TokenType::RParen {
                     return Err(Error::new(
                         ErrorType::SyntaxError,
@@ -1384,47 +2252,7 @@ impl Parser {
                 self.advance();
                 Ok(node)
             }
-            TokenType::LSquare => {
-                self.advance();
-                let mut elements = vec![];
-                if self.current_token.token_type == TokenType::RSquare {
-                    let mut pos = token.position;
-                    pos.end = self.current_token.position.end;
-                    pos.line_end = self.current_token.position.line_end;
-                    self.advance();
-                    return Ok(Node::Array(elements, Type::None, pos));
-                }
-                let e = self.expression(scope)?;
-                let t = e.get_type();
-                elements.push(e);
-                while self.current_token.token_type == TokenType::Comma {
-                    self.advance();
-                    let e = self.expression(scope)?;
-                    if e.get_type() != t {
-                        return Err(Error::new(
-                            ErrorType::TypeError,
-                            e.position(),
-                            format!(
-                                "The types of the elements of the array must be the same, found {} and {}",
-                                t, e.get_type()
-                            ),
-                        ));
-                    }
-                    elements.push(e);
-                }
-                if self.current_token.token_type != TokenType::RSquare {
-                    return Err(Error::new(
-                        ErrorType::SyntaxError,
-                        self.current_token.position.clone(),
-                        format!("Expected ']', found {}", self.current_token),
-                    ));
-                }
-                let mut pos = token.position;
-                pos.end = self.current_token.position.end;
-                pos.line_end = self.current_token.position.line_end;
-                self.advance();
-                Ok(Node::Array(elements, t, pos))
-            }
+            TokenType::LSquare => self.array_literal(token, scope),
             TokenType::Number(_) => {
                 self.advance();
                 Ok(Node::Number(token))
@@ -1439,15 +2267,15 @@ impl Parser {
                     self.advance();
                     Ok(Node::Pointer(Box::new(self.expression(scope)?), pos))
                 } else {
-                    let e = self.atom(scope)?;
-                    let t = if let Type::Ref(t) | Type::Pointer(t) = e.get_type() {
-                        *t
-                    } else {
-                        return Err(Error::new(
-                            ErrorType::TypeError,
-                            e.position(),
-                            format!("Expected a reference or a pointer, found {}", e.get_type()),
-                        ));
+                    // Fields and elements are read before dereferencing, so `*node.next` is
+                    // `*(node.next)`
+                    let e = self.access_attr(scope)?;
+                    let t = match e.get_type() {
+                        Type::Pointer(t) if *t == Type::Bits => {
+                            return Err(packed_deref(&e));
+                        }
+                        Type::Ref(t) | Type::Pointer(t) => *t,
+                        t => return Err(not_a_pointer(&e, &t)),
                     };
                     Ok(Node::Deref(Box::new(e), t, pos))
                 }
@@ -1457,26 +2285,25 @@ impl Parser {
                 let mut pos = token.position;
                 pos.end = self.current_token.position.end - 1;
                 pos.line_end = self.current_token.position.line_end;
-                let e = self.atom(scope)?;
+                let e = self.access_attr(scope)?;
                 let (a, b) = if let Type::Ref(a) | Type::Pointer(a) = e.get_type() {
                     (
-                        if let Type::Ref(b) | Type::Pointer(b) = *a.clone() {
+                        if let Type::Pointer(ref b) = *a {
+                            if **b == Type::Bits {
+                                let inner = Node::Deref(Box::new(e), *a.clone(), pos);
+                                return Err(packed_deref(&inner));
+                            }
+                            (**b).clone()
+                        } else if let Type::Ref(b) = *a.clone() {
                             *b
                         } else {
-                            return Err(Error::new(
-                                ErrorType::TypeError,
-                                e.position(),
-                                format!("Expected a reference or a pointer, found {}", a),
-                            ));
+                            let inner = Node::Deref(Box::new(e.clone()), *a.clone(), pos);
+                            return Err(not_a_pointer(&inner, &a));
                         },
                         *a,
                     )
                 } else {
-                    return Err(Error::new(
-                        ErrorType::TypeError,
-                        e.position(),
-                        format!("Expected a reference or a pointer, found {}", e.get_type()),
-                    ));
+                    return Err(not_a_pointer(&e, &e.get_type()));
                 };
                 let node = Node::Deref(Box::new(e), a, pos.clone());
                 pos.start += 1;
@@ -1511,6 +2338,98 @@ impl Parser {
         }
     }
 
+    /// Parses an array literal, like `[1, 2, 3]` or `[0; 10]`, starting at its `[`
+    /// # Examples
+    /// ```
+    /// let code = "let grid = [0; 4 * 4]\nezout len(grid)";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"16");
+    ///
+    /// let err = ezlang::run("let arr = [1, true]", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Expected int like the first element of the array, found bool");
+    /// ```
+    fn array_literal(&mut self, token: Token, scope: &mut Scope) -> ParseResult {
+        self.advance();
+        let mut elements = vec![];
+        if self.current_token.token_type == TokenType::RSquare {
+            let mut pos = token.position;
+            pos.end = self.current_token.position.end;
+            pos.line_end = self.current_token.position.line_end;
+            return Err(Error::new(
+                ErrorType::TypeError,
+                pos,
+                "An array needs at least one element, the type of an empty array can't be known"
+                    .to_string(),
+            ));
+        }
+        let e = self.expression(scope)?;
+        let t = e.get_type();
+        if t == Type::None {
+            return Err(Error::new(
+                ErrorType::TypeError,
+                e.position(),
+                "The elements of an array must have a value".to_string(),
+            ));
+        }
+        elements.push(e);
+        let mut count = None;
+        if self.current_token.token_type == TokenType::Eol {
+            self.advance();
+            let length = self.expression(scope)?;
+            count = match const_length(&length) {
+                Some(n) if n > 0 => Some(n as usize),
+                Some(n) => {
+                    return Err(Error::new(
+                        ErrorType::SyntaxError,
+                        length.position(),
+                        format!(
+                            "The length of a repeated array must be at least 1, found {}",
+                            n
+                        ),
+                    ))
+                }
+                None => {
+                    return Err(Error::new(
+                        ErrorType::SyntaxError,
+                        length.position(),
+                        "The length of a repeated array must be a constant number, like `[0; 10]` or `[0; 4 * 4]`"
+                            .to_string(),
+                    ))
+                }
+            };
+        }
+        while count.is_none() && self.current_token.token_type == TokenType::Comma {
+            self.advance();
+            let e = self.expression(scope)?;
+            if e.get_type() != t {
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    e.position(),
+                    format!(
+                        "Expected {} like the first element of the array, found {}",
+                        t,
+                        e.get_type()
+                    ),
+                ));
+            }
+            elements.push(e);
+        }
+        if self.current_token.token_type != TokenType::RSquare {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                self.current_token.position.clone(),
+                format!("Expected ']', found {}", self.current_token),
+            ));
+        }
+        let mut pos = token.position;
+        pos.end = self.current_token.position.end;
+        pos.line_end = self.current_token.position.line_end;
+        self.advance();
+        Ok(Node::Array(elements, count.unwrap_or(1), t, pos))
+    }
+
     fn const_atom(&mut self) -> ParseResult {
         let token = self.current_token.clone();
         match token.token_type {
@@ -1523,12 +2442,42 @@ impl Parser {
                     self.advance();
                     Ok(Node::Boolean(token))
                 }
+                "ezin" => Err(not_constant(token.position, "be read with `ezin`")),
+                "ezout" | "ezoutln" | "ezascii" => Err(not_constant(
+                    token.position,
+                    &format!("come from `{}`, which prints and has no value", keyword),
+                )),
                 _ => Err(Error::new(
                     ErrorType::SyntaxError,
                     self.current_token.position.clone(),
                     format!("Unexpected keyword: {}", self.current_token),
                 )),
             },
+            TokenType::Identifier(_) => {
+                let next = self.tokens.get(self.token_index + 1);
+                if !next.is_some_and(|t| t.token_type == TokenType::LParen) {
+                    return Err(not_constant(
+                        token.position.clone(),
+                        &format!("use the variable {}", token),
+                    ));
+                }
+                // The error spans the whole call, up to its closing parenthesis
+                let mut pos = token.position.clone();
+                let mut depth = 0;
+                for t in &self.tokens[self.token_index + 1..] {
+                    match t.token_type {
+                        TokenType::LParen => depth += 1,
+                        TokenType::RParen => depth -= 1,
+                        TokenType::Eof => break,
+                        _ => (),
+                    }
+                    pos.extend_to(&t.position);
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                Err(not_constant(pos, &format!("call the function {}", token)))
+            }
             TokenType::String(_) => {
                 self.advance();
                 Ok(Node::String(token))
@@ -1580,6 +2529,20 @@ impl Parser {
             ));
         };
         self.advance();
+        let (params, stmt, ret) = self.function_body(scope)?;
+        let mut pos = name.position.clone();
+        pos.extend_to(stmt.end_position());
+        Ok(Node::FuncDef(name, params, Box::new(stmt), ret, pos))
+    }
+
+    /// Parses the parameters, the return type and the body of a function or a lambda
+    /// # Examples
+    /// ```
+    /// let err = ezlang::run("ez f() -> int {\n    return;\n}", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Expected return type int, found ()");
+    /// assert_eq!((err.position.line_start, err.position.start), (2, 5));
+    /// ```
+    fn function_body(&mut self, scope: &mut Scope) -> Result<Function, Error> {
         if self.current_token.token_type != TokenType::LParen {
             return Err(Error::new(
                 ErrorType::SyntaxError,
@@ -1650,23 +2613,23 @@ impl Parser {
 
         let mut new_scope = Scope::new(Some(scope));
         new_scope.args = Some(params.clone());
+        new_scope.ret = Some(ret.clone());
         let (stmt, t) = self.statement(&mut new_scope)?;
+        new_scope.span = Some(stmt.position());
         if *t.as_ref().unwrap_or(&Type::None) != ret {
             return Err(Error::new(
                 ErrorType::TypeError,
                 stmt.position(),
                 format!(
-                    "Expected return type {}, found {}",
+                    "Expected return type {}, found {}{}",
                     ret,
-                    t.unwrap_or(Type::None)
+                    t.clone().unwrap_or(Type::None),
+                    mismatch(&ret, &t.unwrap_or(Type::None))
                 ),
             ));
         }
         scope.scopes.push(new_scope);
-        let mut pos = name.position.clone();
-        pos.end = stmt.position().end;
-        pos.line_end = stmt.position().line_end;
-        Ok(Node::FuncDef(name, params, Box::new(stmt), ret, pos))
+        Ok((params, stmt, ret))
     }
 
     fn function_signature(
@@ -1820,10 +2783,102 @@ impl Parser {
 }
 
 /// Parses the given vector of tokens into an AST.
-/// Returns the root node of the AST.
+/// Returns the root node of the AST, the static variables and the structs
 /// # Errors
 /// If the tokens cannot be parsed into an AST, an error is returned.
+/// # Examples
+/// A static used from two functions is a single cell, so what one of them stores is seen by
+/// the other
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::{interpreter, lexer, parser};
+///
+/// let code = "
+/// static count = 0
+/// ez add(n: int) { count += n }
+/// ez get() -> int { return count }
+/// add(2)
+/// add(3)
+/// ezout get()
+/// ezoutln
+/// { static local = 4\n add(local) }
+/// ezout get()
+/// ";
+/// let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+/// let (_, statics, _) = parser::parse(tokens).unwrap();
+/// assert_eq!(statics.len(), 2);
+///
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"5\n9");
+///
+/// let err = ezlang::run("ez f() { static s = 1 }", "main.ez".to_string()).unwrap_err();
+/// assert_eq!(err.details, "Static variables can only be declared outside of functions");
+/// ```
 pub fn parse(tokens: Vec<Token>) -> Result<(Node, Vec<Node>, Vec<Node>), Error> {
+    let (ast, statics, structs) = parse_definitions(tokens)?;
+    Ok((expand(ast)?, statics, structs))
+}
+
+/// Expands the function calls of an AST returned by [`parse_definitions`] or [`analyze`]
+pub fn expand(ast: Node) -> Result<Node, Error> {
+    expand_with_options(ast, &Options::default())
+}
+
+/// Expands the function calls like [`expand`], failing if the expanded bodies add more than
+/// [`Options::max_inline_size`] nodes to the program
+/// # Errors
+/// The error is at the call site that added the most nodes, with a label at the function it calls
+/// # Examples
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::{lexer, parser};
+/// use ezlang::utils::{diag::ErrorType, Options};
+///
+/// let code = "
+/// ez a(x: int) -> int { return x + 1 }
+/// ez b(x: int) -> int { return a(x) + a(x) + a(x) + a(x) }
+/// ez c(x: int) -> int { return b(x) + b(x) + b(x) + b(x) }
+/// for (let i = 0 : i < 3 : i += 1) {
+///     ezout c(i) + c(i)
+/// }";
+/// let ast = || {
+///     let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+///     parser::parse_definitions(tokens).unwrap().0
+/// };
+/// assert!(parser::expand_with_options(ast(), &Options::default()).is_ok());
+///
+/// let options = Options { max_inline_size: 100, ..Options::default() };
+/// let err = parser::expand_with_options(ast(), &options).unwrap_err();
+/// assert!(matches!(err.error_type, ErrorType::ExpansionTooLarge));
+/// assert!(err.details.contains("from this call to 'a'"));
+/// assert_eq!((err.position.line_start, err.position.start), (3, 30));
+/// assert_eq!(err.labels[0].0.line_start, 2);
+/// ```
+pub fn expand_with_options(mut ast: Node, options: &Options) -> Result<Node, Error> {
+    let mut expansions = Expansions::new(options.max_inline_size);
+    match expand_inline(&mut ast, vec![], &mut expansions) {
+        Some(err) => Err(err),
+        None => Ok(ast),
+    }
+}
+
+/// Parses and checks the tokens like [`parse`], but leaves the function calls unexpanded
+pub fn parse_definitions(tokens: Vec<Token>) -> Result<(Node, Vec<Node>, Vec<Node>), Error> {
+    Ok(parse_scoped(tokens)?.0)
+}
+
+/// Parses and checks the tokens like [`parse_definitions`], and also returns the global scope,
+/// which holds the scopes of every block in the program
+pub fn analyze(tokens: Vec<Token>) -> Result<(Node, Scope), Error> {
+    let ((ast, ..), scope) = parse_scoped(tokens)?;
+    Ok((ast, scope))
+}
+
+/// Parses and checks the tokens like [`parse_definitions`], and also returns the global scope
+#[allow(clippy::type_complexity)]
+pub fn parse_scoped(tokens: Vec<Token>) -> Result<((Node, Vec<Node>, Vec<Node>), Scope), Error> {
     let token = tokens[0].clone();
     let mut global = Scope::new(None);
     let mut obj = Parser {
@@ -1831,52 +2886,812 @@ pub fn parse(tokens: Vec<Token>) -> Result<(Node, Vec<Node>, Vec<Node>), Error>
         token_index: 0,
         current_token: token,
         statics: vec![],
+        struct_literals: true,
     };
-    let (signs, statics, structs) = obj.clone().find_signs()?;
-    signs.into_iter().for_each(|s| global.register_signature(s));
-    structs
-        .iter()
-        .for_each(|s| global.register_struct_premature(s.clone()));
+    let (signs, statics, structs) = obj.find_signs()?;
+    obj.token_index = 0;
+    obj.current_token = obj.tokens[0].clone();
+    // The signatures were read before any struct was known, so their struct types have no fields
+    signs.into_iter().for_each(|(name, params, ret)| {
+        global.register_signature((
+            name,
+            params.iter().map(|t| with_fields(t, &structs)).collect(),
+            with_fields(&ret, &structs),
+        ))
+    });
+    structs.iter().for_each(|(name, fields, packed)| {
+        let fields = fields
+            .iter()
+            .map(|(f, t)| (f.clone(), with_fields(t, &structs)))
+            .collect();
+        global.register_struct_premature((name.clone(), fields, *packed))
+    });
     obj.statics = statics;
-    let mut ast = obj.statements(TokenType::Eof, true, &mut global)?.0;
-    if let Some(err) = keyword_checks(&ast) {
-        return Err(err);
+    let ast = obj.statements(TokenType::Eof, true, &mut global)?.0;
+    // Every check runs over the whole program, so the earliest error is the one reported,
+    // instead of the first one of whichever check runs first
+    let mut errors = vec![keyword_checks(&ast), check_recursive(&ast, &mut vec![])];
+    let statics = get_static(&ast);
+    for struct_ in &mut get_structs(&ast, ScopeDepth::None) {
+        errors.push(check_undefined_struct(struct_, vec![]));
+    }
+    let structs = get_structs(&ast, ScopeDepth::Infinite);
+    for struct_ in &structs {
+        errors.push(check_recursive_struct(
+            &struct_.struct_from_def().unwrap(),
+            &mut vec![],
+        ));
     }
-    if let Some(err) = check_recursive(&ast, &mut vec![]) {
+    if let Some(err) = errors.into_iter().flatten().min_by_key(|err| {
+        let pos = &err.position;
+        (pos.included_from.is_none(), pos.line_start, pos.start)
+    }) {
         return Err(err);
     }
-    if let Some(err) = check_numbers(&ast) {
+    Ok(((ast, statics, structs), global))
+}
+
+/// Checks whether evaluating `node` does more than produce a value, like reading input
+fn has_effects(node: &Node) -> bool {
+    matches!(node, Node::Input(..) | Node::Call(..)) || node.children().into_iter().any(has_effects)
+}
+
+/// Replaces the array and the index of the element `target` refers to by hidden variables
+/// if they have side effects, adding their declarations to `hoisted`
+/// # Examples
+/// ```
+/// let code = "let arr = [10, 20, 30]\narr[ezin] += 5\narr[0] <<= 1\nezout arr[0], arr[1], arr[2]";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut "1".as_bytes(), &mut output).unwrap();
+/// assert_eq!(output, b"20 25 30");
+/// ```
+fn hoist_effects(target: Node, hoisted: &mut Vec<Node>) -> Node {
+    let mut hoist = |node: Box<Node>, name: &str| {
+        if !has_effects(&node) {
+            return node;
+        }
+        let t = node.get_type();
+        let token = Token {
+            token_type: TokenType::Identifier(name.to_string()),
+            position: node.position(),
+        };
+        hoisted.push(Node::VarAssign(token.clone(), node, t.clone()));
+        Box::new(Node::VarAccess(token, t))
+    };
+    match target {
+        Node::Index(arr, index, t, pos) => {
+            let arr = hoist(arr, "@array");
+            Node::Index(arr, hoist(index, "@index"), t, pos)
+        }
+        Node::AttrAccess(node, attr, t) => {
+            Node::AttrAccess(Box::new(hoist_effects(*node, hoisted)), attr, t)
+        }
+        target => target,
+    }
+}
+
+/// Explains how `found` differs from the `expected` type when both are written the same,
+/// like two versions of a struct, as the path to the first field that differs
+fn mismatch(expected: &Type, found: &Type) -> String {
+    match expected.difference(found) {
+        Some(difference) if expected.to_string() == found.to_string() => {
+            format!(" ({})", difference)
+        }
+        _ => String::new(),
+    }
+}
+
+/// Builds the hidden declarations, the condition and the step of a loop where `counter` goes
+/// up by `step` until it reaches `end`, or goes past it if `inclusive`. Cells wrap around
+/// after 255, so unless the counter only counts up by one to an excluded end, it only steps
+/// while `end` is far enough away, and a hidden `@more` says whether the loop goes on
+/// # Examples
+/// ```
+/// let code = "
+/// for i in 250..=255 ezout i
+/// let count = 0, last = 0
+/// for i in 0..=255 { count += 1; last = i }
+/// ezout count, last
+/// ";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"2502512522532542550 255");
+///
+/// // A step that would go past the end stops the loop, and a step of 0 skips it
+/// let code = "
+/// for i in 0..250 step 100 ezoutln i
+/// for i in 200..=255 step 50 ezoutln i
+/// let s = ezin
+/// for i in 0..10 step s ezoutln i
+/// ezout 'd'
+/// ";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut "0".as_bytes(), &mut output).unwrap();
+/// assert_eq!(output, b"0\n100\n200\n200\n250\nd");
+/// ```
+fn count_up(counter: Token, end: Node, inclusive: bool, step: Node) -> (Vec<Node>, Node, Node) {
+    let position = counter.position.clone();
+    let operator = |token_type| Token {
+        token_type,
+        position: position.clone(),
+    };
+    let binary = |token_type, left: Node, right: Node, t| {
+        Node::BinaryOp(operator(token_type), Box::new(left), Box::new(right), t)
+    };
+    let value = Node::VarAccess(counter.clone(), Type::Number);
+    let next = Node::VarReassign(
+        counter,
+        Box::new(binary(
+            TokenType::Add,
+            value.clone(),
+            step.clone(),
+            Type::Number,
+        )),
+    );
+    let by_one = matches!(
+        step,
+        Node::Number(Token {
+            token_type: TokenType::Number(1),
+            ..
+        })
+    );
+    if !inclusive && by_one {
+        let condition = binary(TokenType::Lt, value, end, Type::Boolean);
+        return (vec![], condition, next);
+    }
+
+    let (first, far_enough) = if inclusive {
+        (TokenType::Le, TokenType::Ge)
+    } else {
+        (TokenType::Lt, TokenType::Gt)
+    };
+    let more = operator(TokenType::Identifier("@more".to_string()));
+    let mut start = binary(first, value.clone(), end.clone(), Type::Boolean);
+    // A step of 0 would never reach the end, so a step only known while running skips the loop
+    if !matches!(step, Node::Number(_)) {
+        let zero = Node::Number(operator(TokenType::Number(0)));
+        let moves = binary(TokenType::Neq, step.clone(), zero, Type::Boolean);
+        start = binary(TokenType::LAnd, moves, start, Type::Boolean);
+    }
+    let left = binary(TokenType::Sub, end, value, Type::Number);
+    let step = Node::Statements(
+        vec![
+            Node::VarReassign(
+                more.clone(),
+                Box::new(binary(far_enough, left, step, Type::Boolean)),
+            ),
+            Node::If(
+                Box::new(Node::VarAccess(more.clone(), Type::Boolean)),
+                Box::new(next),
+                None,
+                position.clone(),
+            ),
+        ],
+        Type::None,
+        Semicolons::none(2),
+        position,
+    );
+    (
+        vec![Node::VarAssign(
+            more.clone(),
+            Box::new(start),
+            Type::Boolean,
+        )],
+        Node::VarAccess(more, Type::Boolean),
+        step,
+    )
+}
+
+/// The error of something in the value of a static variable that isn't known at compile time.
+/// Static variables are set before the program starts, so code there, like reading input or
+/// calling a function, would run before the rest of the program, in an order the code doesn't show
+fn not_constant(position: Position, what: &str) -> Error {
+    Error::new(
+        ErrorType::SyntaxError,
+        position,
+        format!(
+            "The value of a static variable must be known at compile time, so it can't {}",
+            what
+        ),
+    )
+}
+
+/// Folds a `-` written right before a number literal into a negative literal, spanning both.
+/// Callers only use it when the token after the `-` is the number, so that `-(4)` stays a negation
+/// # Examples
+/// ```
+/// let code = "let a = [-1, - 2, -(3)]\nezout a[0] + 5, a[1] + 5, a[2] + 5";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"4 3 2");
+/// ```
+fn negative_literal(op: &Token, node: &Node) -> Option<Node> {
+    match (&op.token_type, node) {
+        (
+            TokenType::Sub,
+            Node::Number(Token {
+                token_type: TokenType::Number(n),
+                position: end,
+            }),
+        ) => {
+            let mut position = op.position.clone();
+            position.extend_to(end);
+            Some(Node::Number(Token {
+                token_type: TokenType::Number(-n),
+                position,
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Splits the format string of an `ezout` into the text around its `{}` placeholders, which is
+/// printed as characters, and the values that go in the placeholders. The text and the values
+/// alternate, starting and ending with text, so that no separator is printed between them.
+/// `{{` and `}}` are printed as `{` and `}`
+/// # Examples
+/// ```
+/// let code = "let x = 3\nezout \"x = {}, y = {}\", x, 'y'";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"x = 3, y = y");
+/// ```
+fn format_string(string: &Token, values: &[Node]) -> Result<Vec<Node>, Error> {
+    let text = match string.token_type {
+        TokenType::String(ref s) => s,
+        _ => unreachable!(),
+    };
+    let position = &string.position;
+    let mut segments = vec![vec![]];
+    let mut text = text.bytes().peekable();
+    while let Some(c) = text.next() {
+        match (c, text.peek()) {
+            (b'{', Some(b'}')) => {
+                text.next();
+                segments.push(vec![]);
+                continue;
+            }
+            (b'{', Some(b'{')) | (b'}', Some(b'}')) => {
+                text.next();
+            }
+            (b'{' | b'}', _) => {
+                return Err(Error::new(
+                    ErrorType::SyntaxError,
+                    position.clone(),
+                    format!(
+                        "Unmatched '{0}' in format string, write '{0}{0}' to print it",
+                        c as char
+                    ),
+                ))
+            }
+            _ => {}
+        }
+        segments.last_mut().unwrap().push(Node::Char(Token {
+            token_type: TokenType::Char(c),
+            position: position.clone(),
+        }));
+    }
+    let placeholders = segments.len() - 1;
+    if placeholders != values.len() {
+        let mut position = position.clone();
+        if let Some(last) = values.last() {
+            position.extend_to(last.end_position());
+        }
+        return Err(Error::new(
+            ErrorType::SyntaxError,
+            position,
+            format!(
+                "Format string has {} placeholders, but {} values were given",
+                placeholders,
+                values.len()
+            ),
+        ));
+    }
+    let mut nodes = vec![];
+    for (chars, value) in segments
+        .into_iter()
+        .zip(values.iter().map(Some).chain([None]))
+    {
+        nodes.push(Node::Ascii(chars, position.clone()));
+        nodes.extend(value.cloned());
+    }
+    Ok(nodes)
+}
+
+/// The error for dereferencing a packed array, whose elements are bits and not cells
+/// # Examples
+/// ```
+/// let err = ezlang::run("let p = packed [true]\nezout *p", "main.ez".to_string()).unwrap_err();
+/// assert!(err.details.starts_with("Cannot dereference a packed array"));
+/// ```
+fn packed_deref(node: &Node) -> Error {
+    Error::new(
+        ErrorType::TypeError,
+        node.position(),
+        "Cannot dereference a packed array, its bools are bits of its cells, index it instead"
+            .to_string(),
+    )
+}
+
+/// The error for dereferencing `node`, whose type `t` is neither a reference nor a pointer.
+/// Variables, fields, elements and dereferences are named the way they are written
+/// # Examples
+/// ```
+/// let code = "struct Leaf { value: int }\nlet l = Leaf { value: 1 }\nezout *l";
+/// let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+/// assert_eq!(
+///     err.details,
+///     "Cannot dereference `l` of type struct 'Leaf', it is not a reference or a pointer"
+/// );
+/// ```
+fn not_a_pointer(node: &Node, t: &Type) -> Error {
+    let details = match place_name(node) {
+        Some(name) => format!(
+            "Cannot dereference `{}` of type {}, it is not a reference or a pointer",
+            name, t
+        ),
+        None => format!("Expected a reference or a pointer, found {}", t),
+    };
+    Error::new(ErrorType::TypeError, node.position(), details)
+}
+
+/// The error for a function used by its name without being called, like `tick` for `tick()`.
+/// `arities` are the numbers of parameters of the functions with that name
+/// # Examples
+/// ```
+/// let err = ezlang::run("ez tick() { ezout 1 }\ntick", "main.ez".to_string()).unwrap_err();
+/// assert_eq!(err.details, "'tick' is a function; call it with `tick()`");
+/// ```
+fn not_called(function: &Token, arities: &[usize]) -> Error {
+    let position = function.position.clone();
+    if !arities.contains(&0) {
+        let arities = arities
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" or ");
+        return Error::new(
+            ErrorType::TypeError,
+            position,
+            format!(
+                "{} is a function; call it with its {} arguments",
+                function, arities
+            ),
+        );
+    }
+    let end = Position {
+        line_start: position.line_end,
+        start: position.end,
+        ..position.clone()
+    };
+    Error::new(
+        ErrorType::TypeError,
+        position,
+        format!(
+            "{} is a function; call it with `{}()`",
+            function, function.token_type
+        ),
+    )
+    .with_suggestion(Suggestion::new(
+        "add the parentheses".to_string(),
+        vec![(end, "()".to_string())],
+    ))
+}
+
+/// The error for a condition that isn't a bool. Numbers aren't turned into bools, so a number
+/// gets a suggestion to compare it with 0, or to write `false` or `true` for a literal 0 or 1
+fn condition_error(condition: &Node, statement: &str) -> Error {
+    let t = condition.get_type();
+    let details = format!("Condition in {} can only be a bool", statement);
+    if t == Type::None {
+        return Error::new(
+            ErrorType::TypeError,
+            condition.position(),
+            format!("{}, found a condition of type `()`", details),
+        );
+    }
+    if t != Type::Number {
+        return Error::new(ErrorType::TypeError, condition.position(), details);
+    }
+    let position = condition.position();
+    let suggestion = match condition {
+        Node::Number(Token {
+            token_type: TokenType::Number(n @ (0 | 1)),
+            ..
+        }) => {
+            let bool = if *n == 0 { "false" } else { "true" };
+            Suggestion::new(
+                format!("use `{}` instead", bool),
+                vec![(position.clone(), bool.to_string())],
+            )
+        }
+        _ => {
+            let end = Position {
+                line_start: position.line_end,
+                start: position.end,
+                ..position.clone()
+            };
+            let message = match place_name(condition) {
+                Some(name) => format!("compare explicitly: `{} != 0`", name),
+                None => "compare it explicitly with `!= 0`".to_string(),
+            };
+            // `!=` binds looser than the arithmetic and bitwise operators, but not than `? :`
+            let edits = match condition {
+                Node::Ternary(..) => {
+                    let start = Position {
+                        line_end: position.line_start,
+                        end: position.start,
+                        ..position.clone()
+                    };
+                    vec![(start, "(".to_string()), (end, ") != 0".to_string())]
+                }
+                _ => vec![(end, " != 0".to_string())],
+            };
+            Suggestion::new(message, edits)
+        }
+    };
+    Error::new(
+        ErrorType::TypeError,
+        position,
+        format!("{}, and numbers aren't turned into bools", details),
+    )
+    .with_suggestion(suggestion)
+}
+
+/// Writes a variable, or a field, element or dereference of one, back the way it is in the
+/// code, like `*list.next`. Returns `None` for other expressions
+fn place_name(node: &Node) -> Option<String> {
+    match node {
+        Node::VarAccess(token, _) => match token.token_type {
+            TokenType::Identifier(ref name) => Some(name.clone()),
+            _ => None,
+        },
+        Node::AttrAccess(node, attr, _) => {
+            Some(format!("{}.{}", place_name(node)?, attr.token_type))
+        }
+        Node::Index(arr, index, ..) => {
+            let index = match **index {
+                Node::Number(ref n) => n.token_type.to_string(),
+                ref index => place_name(index).unwrap_or_else(|| String::from("..")),
+            };
+            Some(format!("{}[{}]", place_name(arr)?, index))
+        }
+        Node::Deref(node, ..) => Some(format!("*{}", place_name(node)?)),
+        _ => None,
+    }
+}
+
+/// Folds the builtin `len(arr)` into the number of elements of the array, or of characters
+/// of the string literal, it is given. Only array literals, variables declared with one, and
+/// string literals have a known length
+/// # Examples
+/// ```
+/// let code = "
+/// ez sum(values: point int, count: int) -> int {
+///     let total = 0
+///     for i in 0..count { total += values[i] }
+///     return total
+/// }
+/// let primes = [2, 3, 5, 7];
+/// ezoutln len(primes), len([0; 6]), len(\"hello\"), sum(primes, len(primes))
+/// ";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"4 6 5 17\n");
+///
+/// let code = "ez sum(values: point int) -> int { return len(values) }";
+/// let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+/// assert!(err.details.starts_with("The length of this pointer isn't known"));
+/// ```
+fn len(args: &[Node], pos: Position, scope: &Scope) -> ParseResult {
+    let arg = match args {
+        [arg] => arg,
+        _ => {
+            return Err(Error::new(
+                ErrorType::TypeError,
+                pos,
+                format!("len takes 1 argument, but {} were given", args.len()),
+            ))
+        }
+    };
+    let length = match (arg, arg.get_type()) {
+        (
+            Node::String(Token {
+                token_type: TokenType::String(s),
+                ..
+            }),
+            _,
+        ) => Some(s.chars().count()),
+        (Node::Array(elements, count, ..), _) => Some(elements.len() * count),
+        (Node::VarAccess(token, _), Type::Pointer(_)) => scope.array_length(token),
+        (_, Type::Pointer(_)) => None,
+        (_, t) => {
+            return Err(Error::new(
+                ErrorType::TypeError,
+                arg.position(),
+                format!("Cannot get the length of type {}, it is not an array", t),
+            ))
+        }
+    };
+    match length {
+        Some(length) => Ok(Node::Number(Token {
+            token_type: TokenType::Number(length as LexNumber),
+            position: pos,
+        })),
+        None => Err(Error::new(
+            ErrorType::TypeError,
+            arg.position(),
+            "The length of this pointer isn't known, only array literals, variables declared with one and string literals have one. Pass the length separately".to_string(),
+        )),
+    }
+}
+
+/// Evaluates an array length made of number literals, returning `None` if it isn't constant.
+/// Only `+`, `-`, `*`, `/`, `%`, `<<` and `>>` are allowed
+fn const_length(node: &Node) -> Option<i64> {
+    match node {
+        Node::Number(Token {
+            token_type: TokenType::Number(n),
+            ..
+        }) => Some(*n as i64),
+        Node::UnaryOp(
+            Token {
+                token_type: TokenType::Sub,
+                ..
+            },
+            n,
+            _,
+        ) => const_length(n).map(|n| -n),
+        Node::BinaryOp(op, left, right, _) => {
+            let (left, right) = (const_length(left)?, const_length(right)?);
+            match op.token_type {
+                TokenType::Add => left.checked_add(right),
+                TokenType::Sub => left.checked_sub(right),
+                TokenType::Mul => left.checked_mul(right),
+                TokenType::Div => left.checked_div(right),
+                TokenType::Mod => left.checked_rem(right),
+                TokenType::Shl => left.checked_shl(right.try_into().ok()?),
+                TokenType::Shr => left.checked_shr(right.try_into().ok()?),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Fills in the fields of the struct types in `t`, using the structs found by `find_signs`.
+/// Structs containing themselves are left as they are, they are reported later on
+fn with_fields(t: &Type, structs: &Structs) -> Type {
+    fn fill(t: &Type, structs: &Structs, outer: &mut Vec<Token>) -> Type {
+        match t {
+            Type::Struct(name, ..) if !outer.contains(name) => {
+                match structs.iter().find(|(s, ..)| s == name) {
+                    Some((_, fields, packed)) => {
+                        outer.push(name.clone());
+                        let fields = fields
+                            .iter()
+                            .map(|(f, t)| (f.clone(), fill(t, structs, outer)))
+                            .collect();
+                        outer.pop();
+                        Type::Struct(name.clone(), fields, *packed)
+                    }
+                    None => t.clone(),
+                }
+            }
+            Type::Ref(t) => Type::Ref(Box::new(fill(t, structs, outer))),
+            Type::Pointer(t) => Type::Pointer(Box::new(fill(t, structs, outer))),
+            _ => t.clone(),
+        }
+    }
+    fill(t, structs, &mut vec![])
+}
+
+/// Builds a program out of an unexpanded AST that calls the function `name`, defined
+/// at the top level, with the number literals in `args` and prints the returned value.
+/// Only the definitions of the functions, structs and statics are kept, the rest of the
+/// top level code doesn't run. Returns the expanded program, its statics and its structs
+pub fn function_call(
+    ast: Node,
+    name: &str,
+    args: &[i64],
+) -> Result<(Node, Vec<Node>, Vec<Node>), Error> {
+    let statics = get_static(&ast);
+    let structs = get_structs(&ast, ScopeDepth::Infinite);
+    let (nodes, t, semicolons, pos) = match ast {
+        Node::Statements(nodes, t, semicolons, pos) => (nodes, t, semicolons, pos),
+        _ => unreachable!(),
+    };
+    let dropped: HashSet<String> = nodes
+        .iter()
+        .flat_map(|node| match node {
+            Node::Declarations(nodes, _) => nodes.iter().collect(),
+            node => vec![node],
+        })
+        .filter_map(|node| match node {
+            Node::VarAssign(
+                Token {
+                    token_type: TokenType::Identifier(ident),
+                    ..
+                },
+                ..,
+            ) => Some(ident.clone()),
+            _ => None,
+        })
+        .collect();
+    let (mut nodes, semicolons): (Vec<_>, Vec<_>) = nodes
+        .into_iter()
+        .zip(semicolons.0)
+        .filter(|(node, _)| {
+            matches!(
+                node,
+                Node::FuncDef(..) | Node::Struct(..) | Node::StaticVar(..)
+            )
+        })
+        .unzip();
+    let mut semicolons = Semicolons(semicolons);
+    let (token, params, ret) = match nodes.iter().find(|node| {
+        matches!(node, Node::FuncDef(Token { token_type: TokenType::Identifier(ident), .. }, ..) if ident == name)
+    }) {
+        Some(Node::FuncDef(token, params, _, ret, _)) => (token.clone(), params.clone(), ret.clone()),
+        _ => {
+            return Err(Error::new(
+                ErrorType::UndefinedFunction,
+                pos,
+                format!("Function '{}' is not defined", name),
+            ))
+        }
+    };
+    if params.len() != args.len() {
+        return Err(Error::new(
+            ErrorType::TypeError,
+            token.position.clone(),
+            format!(
+                "Function {} takes {} arguments, but {} were given",
+                token,
+                params.len(),
+                args.len()
+            ),
+        ));
+    }
+    let mut call_args = vec![];
+    for ((param, t), &arg) in params.iter().zip(args) {
+        if *t != Type::Number {
+            return Err(Error::new(
+                ErrorType::TypeError,
+                param.position.clone(),
+                format!(
+                    "Parameter {} is {}, only int arguments can be passed",
+                    param, t
+                ),
+            ));
+        }
+        if !(INT_MIN as i64..=INT_MAX as i64).contains(&arg) {
+            return Err(Error::new(
+                ErrorType::NumberTooLarge,
+                param.position.clone(),
+                format!(
+                    "Argument {} for parameter {} is too large, an int is from {} to {}",
+                    arg, param, INT_MIN, INT_MAX
+                ),
+            ));
+        }
+        call_args.push(Node::Number(Token {
+            token_type: TokenType::Number(arg as LexNumber),
+            position: token.position.clone(),
+        }));
+    }
+    let call = Node::Call(
+        token.clone(),
+        call_args,
+        ret.clone(),
+        token.position.clone(),
+    );
+    nodes.push(if ret == Type::None {
+        call
+    } else {
+        Node::Print(vec![call], false, token.position)
+    });
+    semicolons.0.push(None);
+    let mut ast = Node::Statements(nodes, t, semicolons, pos);
+    let mut expansions = Expansions::new(Options::default().max_inline_size);
+    if let Some(err) = expand_inline(&mut ast, vec![], &mut expansions) {
         return Err(err);
     }
-    let statics = get_static(&ast);
-    for struct_ in &mut get_structs(&ast, ScopeDepth::None) {
-        if let Some(err) = check_undefined_struct(struct_, vec![]) {
-            return Err(err);
-        }
+    if let Some(var) = dropped_use(&ast, &dropped, &mut vec![HashSet::new()]) {
+        return Err(Error::new(
+            ErrorType::UndefinedVariable,
+            var.position.clone(),
+            format!(
+                "Variable {} is defined by the top level code, which doesn't run when only {} is called",
+                var, name
+            ),
+        ));
     }
-    let structs = get_structs(&ast, ScopeDepth::Infinite);
-    for struct_ in &structs {
-        if let Some(err) = check_recursive_struct(&struct_.struct_from_def().unwrap(), &mut vec![])
+    Ok((ast, statics, structs))
+}
+
+/// Finds a use of one of the `dropped` top level variables that isn't shadowed by a variable
+/// declared in `scopes`
+fn dropped_use<'a>(
+    node: &'a Node,
+    dropped: &HashSet<String>,
+    scopes: &mut Vec<HashSet<&'a str>>,
+) -> Option<&'a Token> {
+    match node {
+        // The bodies are only run where they are expanded
+        Node::FuncDef(..) | Node::Lambda(..) => None,
+        Node::VarAccess(
+            var @ Token {
+                token_type: TokenType::Identifier(ident),
+                ..
+            },
+            _,
+        )
+        | Node::VarReassign(
+            var @ Token {
+                token_type: TokenType::Identifier(ident),
+                ..
+            },
+            _,
+        ) if dropped.contains(ident)
+            && !scopes.iter().any(|scope| scope.contains(ident.as_str())) =>
         {
-            return Err(err);
+            Some(var)
         }
+        Node::VarAssign(
+            Token {
+                token_type: TokenType::Identifier(ident),
+                ..
+            },
+            expr,
+            _,
+        )
+        | Node::StaticVar(
+            Token {
+                token_type: TokenType::Identifier(ident),
+                ..
+            },
+            expr,
+        ) => {
+            let var = dropped_use(expr, dropped, scopes);
+            scopes.last_mut().unwrap().insert(ident);
+            var
+        }
+        Node::Statements(..) | Node::Expanded(..) | Node::For(..) => {
+            scopes.push(HashSet::new());
+            let var = node
+                .children()
+                .into_iter()
+                .find_map(|child| dropped_use(child, dropped, scopes));
+            scopes.pop();
+            var
+        }
+        _ => node
+            .children()
+            .into_iter()
+            .find_map(|child| dropped_use(child, dropped, scopes)),
     }
-    if let Some(err) = expand_inline(&mut ast, vec![]) {
-        return Err(err);
-    }
-    Ok((ast, statics, structs))
 }
 
-/// Checks for invalid placement and use of keywords
+/// Checks for invalid placement and use of keywords. Included files are spliced into the top
+/// level of the file including them, so the checks are done for the top level code of every
+/// file, and the error names the file the keyword is written in
 fn keyword_checks(ast: &Node) -> Option<Error> {
     match ast {
         Node::Statements(nodes, ..) => {
             for node in nodes.iter() {
                 if let Some(position) = check_return(node) {
+                    let file = position.file.clone();
                     return Some(Error::new(
                         ErrorType::InvalidReturn,
                         position,
-                        "Return statement cannot be in the global scope".to_string(),
+                        format!("Return statement cannot be in the global scope of {}", file),
                     ));
                 }
             }
@@ -1886,11 +3701,18 @@ fn keyword_checks(ast: &Node) -> Option<Error> {
     }
 }
 
+/// The position of a `return` in `node` that isn't in the body of a function
+/// # Examples
+/// ```
+/// let err = ezlang::run("ezout 1\nreturn;", "main.ez".to_string()).unwrap_err();
+/// assert_eq!(err.details, "Return statement cannot be in the global scope of main.ez");
+/// ```
 fn check_return(node: &Node) -> Option<Position> {
     match node {
         Node::BinaryOp(_, n1, n2, _)
-        | Node::IndexAssign(_, n1, n2)
+        | Node::Index(n1, n2, ..)
         | Node::While(n1, n2, _)
+        | Node::AttrAssign(n1, n2, _)
         | Node::DerefAssign(n1, n2, _) => {
             let n1 = check_return(n1);
             if n1.is_some() {
@@ -1940,7 +3762,7 @@ fn check_return(node: &Node) -> Option<Position> {
             }
             ret
         }
-        Node::StructConstructor(_, nodes, _) => {
+        Node::StructConstructor(_, nodes, ..) => {
             let mut ret = None;
             for (_, node) in nodes {
                 let n = check_return(node);
@@ -1951,7 +3773,7 @@ fn check_return(node: &Node) -> Option<Position> {
             }
             ret
         }
-        Node::Ternary(n1, n2, n3, ..) => {
+        Node::Ternary(n1, n2, n3, ..) | Node::IndexAssign(n1, n2, n3) => {
             let n1 = check_return(n1);
             if n1.is_some() {
                 return n1;
@@ -1991,11 +3813,10 @@ fn check_return(node: &Node) -> Option<Position> {
             }
             None
         }
-        Node::Index(_, n1, ..) => check_return(n1),
-        Node::FuncDef(..) => None,
+        Node::FuncDef(..) | Node::Lambda(..) => None,
         Node::Return(_, pos) => Some(pos.clone()),
         Node::Ref(n1, ..) | Node::Deref(n1, ..) | Node::Pointer(n1, ..) => check_return(n1),
-        Node::Print(n1, _) | Node::Ascii(n1, _) => {
+        Node::Print(n1, ..) | Node::Ascii(n1, _) | Node::Declarations(n1, _) => {
             for n in n1 {
                 if let Some(t) = check_return(n) {
                     return Some(t);
@@ -2007,6 +3828,7 @@ fn check_return(node: &Node) -> Option<Position> {
         Node::Number(_) => None,
         Node::Boolean(_) => None,
         Node::Input(..) => None,
+        Node::Raw(..) => None,
         Node::None(_) => None,
         Node::Char(..) => None,
         Node::Array(..) => None,
@@ -2016,90 +3838,121 @@ fn check_return(node: &Node) -> Option<Position> {
 }
 
 /// Expands inline functions
-fn expand_inline(ast: &mut Node, mut functions: Vec<Node>) -> Option<Error> {
-    if let Some(mut functions2) = find_functions(ast) {
-        let len = functions.len();
-        functions.extend(functions2.iter().map(|f| (*f).clone()));
-        for (i, f) in functions2.iter_mut().enumerate() {
-            if let Node::FuncDef(_, _, f, ..) = f {
-                if let err @ Some(_) = expand_inline(f, functions.clone()) {
-                    return err;
-                }
-            } else if let Node::Statements(nodes, ..) = f {
-                for node in nodes {
-                    if let err @ Some(_) = expand_inline(node, functions.clone()) {
+/// Replaces every call with the body of the function it calls.
+/// Functions defined in a block can be called from anywhere in that block,
+/// their definitions are removed once all the calls are expanded.
+fn expand_inline(
+    ast: &mut Node,
+    mut functions: Vec<Node>,
+    stack: &mut Expansions,
+) -> Option<Error> {
+    if let Node::Statements(nodes, ..) = ast {
+        functions.extend(
+            nodes
+                .iter()
+                .filter(|node| matches!(node, Node::FuncDef(..)))
+                .cloned(),
+        );
+        for node in nodes.iter_mut() {
+            if let Node::Declarations(assigns, _) = node {
+                for assign in assigns.iter_mut() {
+                    if let err @ Some(_) = define_function_variable(assign, &mut functions) {
                         return err;
                     }
                 }
+            } else if let err @ Some(_) = define_function_variable(node, &mut functions) {
+                return err;
+            }
+            if let err @ Some(_) = insert_function(node, &functions, stack) {
+                return err;
+            }
+        }
+        for node in nodes.iter_mut() {
+            if let Node::FuncDef(.., pos) = node {
+                *node = Node::None(pos.clone());
             }
-            functions[i + len] = (*f).clone();
         }
-        remove_inline(ast);
+        None
+    } else {
+        insert_function(ast, &functions, stack)
     }
-    insert_function(ast, &functions)
-    // println!("{ast}\n{:?}\n", functions.iter().map(|d| d.to_string()).collect::<Vec<_>>());
 }
 
-fn remove_inline(node: &mut Node) {
-    match node {
-        Node::FuncDef(.., p) => *node = Node::None(p.clone()),
-        Node::Struct(..) => (),
-        Node::Call(_, n, ..)
-        | Node::Statements(n, ..)
-        | Node::Print(n, _)
-        | Node::Array(n, ..)
-        | Node::Ascii(n, _) => {
-            for n in n.iter_mut().rev() {
-                remove_inline(n);
+/// Turns the declaration of a variable holding a function into the definition of a function
+/// with the name of the variable, so that calls to the variable get expanded like any other
+/// call. The definition is only visible to the statements after the declaration, and shadows
+/// the functions with the same name
+fn define_function_variable(node: &mut Node, functions: &mut Vec<Node>) -> Option<Error> {
+    let func = match node {
+        Node::VarAssign(name, value, Type::Function(..)) => {
+            match function_value(name, value, functions) {
+                Ok(func) => func,
+                Err(err) => return Some(err),
             }
         }
-        Node::StructConstructor(_, n, _) => {
-            for (_, n) in n {
-                remove_inline(n);
+        _ => return None,
+    };
+    let pos = node.position();
+    functions.insert(0, func);
+    *node = Node::None(pos);
+    None
+}
+
+/// Builds the definition of a function called `name` out of `value`, which is a lambda,
+/// a function or a variable holding a function
+/// # Examples
+/// ```
+/// let code = "
+/// let f = ez(x: int) -> int { return x * 2 + 1 }
+/// ez apply(g: (int) -> int, x: int) -> int { return g(x) }
+/// ezout f(1), apply(f, 2)
+/// ";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"3 5");
+/// ```
+fn function_value(name: &Token, value: &Node, functions: &[Node]) -> Result<Node, Error> {
+    match value {
+        Node::Lambda(params, body, ret, pos) => Ok(Node::FuncDef(
+            name.clone(),
+            params.clone(),
+            body.clone(),
+            ret.clone(),
+            pos.clone(),
+        )),
+        Node::VarAccess(var, Type::Function(types, _)) => {
+            match functions.iter().find(|f| {
+                matches!(f, Node::FuncDef(n, params, ..) if n == var
+                    && params.len() == types.len()
+                    && params.iter().zip(types).all(|((_, p), t)| p == t))
+            }) {
+                Some(Node::FuncDef(_, params, body, ret, pos)) => Ok(Node::FuncDef(
+                    name.clone(),
+                    params.clone(),
+                    body.clone(),
+                    ret.clone(),
+                    pos.clone(),
+                )),
+                _ => Err(Error::new(
+                    ErrorType::UndefinedFunction,
+                    var.position.clone(),
+                    format!("Function {} is not defined", var),
+                )),
             }
         }
-        Node::IndexAssign(_, n1, n2)
-        | Node::DerefAssign(n1, n2, _)
-        | Node::If(n1, n2, None, _)
-        | Node::While(n1, n2, _)
-        | Node::BinaryOp(_, n1, n2, _) => {
-            remove_inline(n1);
-            remove_inline(n2);
-        }
-        Node::String(_) => (),
-        Node::Number(_) => (),
-        Node::Boolean(_) => (),
-        Node::Index(_, n, ..)
-        | Node::Ref(n, ..)
-        | Node::Pointer(n, ..)
-        | Node::Deref(n, ..)
-        | Node::Return(n, ..)
-        | Node::AttrAccess(n, ..)
-        | Node::UnaryOp(_, n, _)
-        | Node::Converted(n, _)
-        | Node::VarAssign(_, n, _)
-        | Node::StaticVar(_, n)
-        | Node::VarReassign(_, n) => remove_inline(n),
-        Node::VarAccess(..) => (),
-        Node::Input(..) => (),
-        Node::Ternary(n1, n2, n3, ..) | Node::If(n1, n2, Some(n3), _) => {
-            remove_inline(n1);
-            remove_inline(n2);
-            remove_inline(n3);
-        }
-        Node::None(_) => (),
-        Node::Char(_) => (),
-        Node::For(n1, n2, n3, n4, _) => {
-            remove_inline(n1);
-            remove_inline(n2);
-            remove_inline(n3);
-            remove_inline(n4);
-        }
-        Node::Expanded(_, _) => unreachable!(),
+        value => Err(Error::new(
+            ErrorType::TypeError,
+            value.position(),
+            format!(
+                "{} can only be given a lambda, a function or a variable holding a function",
+                name
+            ),
+        )),
     }
 }
 
-fn insert_function(node: &mut Node, functions: &[Node]) -> Option<Error> {
+fn insert_function(node: &mut Node, functions: &[Node], stack: &mut Expansions) -> Option<Error> {
     match node {
         Node::Call(name, args, ..) => {
             let func = match functions.iter().find(|f| match f {
@@ -2122,12 +3975,68 @@ fn insert_function(node: &mut Node, functions: &[Node]) -> Option<Error> {
                     ))
                 }
             };
-            let (params, body, ret) = match func {
-                Node::FuncDef(_, p, b, ret, ..) => (p, b.clone(), ret),
+            let (params, mut body, ret, pos) = match func {
+                Node::FuncDef(_, p, b, ret, pos) => (p, b.clone(), ret.clone(), pos),
                 _ => unreachable!(),
             };
+            // Calling itself directly is reported by `check_recursive`, this catches functions
+            // calling each other, which would be expanded forever
+            if let Some(i) = stack.stack.iter().position(|(_, p)| p == pos) {
+                return Some(
+                    Error::new(
+                        ErrorType::RecursionError,
+                        name.position.clone(),
+                        format!(
+                            "Recursive function {} is calling itself through {}",
+                            stack.stack[i].0,
+                            stack.stack[i + 1..]
+                                .iter()
+                                .map(|(name, _)| name.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    )
+                    .with_label(
+                        stack.stack[i].1.clone(),
+                        format!(
+                            "{} is defined here, and every call to it is replaced by this body",
+                            stack.stack[i].0
+                        ),
+                    ),
+                );
+            }
+            // Functions passed as arguments aren't stored, calls to the parameter
+            // get expanded into the function that was passed
+            let mut body_functions = functions.to_vec();
+            for ((param, type_), arg) in params.iter().zip(args.iter()) {
+                if let Type::Function(..) = type_ {
+                    match function_value(param, arg, functions) {
+                        Ok(func) => body_functions.insert(0, func),
+                        Err(err) => return Some(err),
+                    }
+                }
+            }
+            for arg in args.iter_mut() {
+                if let Type::Function(..) = arg.get_type() {
+                    continue;
+                }
+                if let a @ Some(_) = insert_function(arg, functions, stack) {
+                    return a;
+                }
+            }
+            if let a @ Some(_) = stack.add(name, &body, pos) {
+                return a;
+            }
+            stack.stack.push((name.clone(), pos.clone()));
+            if let a @ Some(_) = insert_function(&mut body, &body_functions, stack) {
+                return a;
+            }
+            stack.stack.pop();
             let mut expanded = vec![];
             for ((arg, type_), param) in params.iter().zip(args) {
+                if let Type::Function(..) = type_ {
+                    continue;
+                }
                 expanded.push(Node::VarAssign(
                     arg.clone(),
                     Box::new(param.clone()),
@@ -2135,170 +4044,113 @@ fn insert_function(node: &mut Node, functions: &[Node]) -> Option<Error> {
                 ))
             }
             expanded.push(*body);
-            *node = Node::Expanded(expanded, ret.clone());
-            None
-        }
-        Node::Statements(nodes, ..) => {
-            for node in nodes.iter_mut().rev() {
-                if let a @ Some(_) = insert_function(node, functions) {
-                    return a;
-                }
-            }
+            *node = Node::Expanded(name.clone(), expanded, ret);
             None
         }
-        Node::StructConstructor(_, n, _) => {
+        Node::Statements(..) => expand_inline(node, functions.to_vec(), stack),
+        Node::StructConstructor(_, n, ..) => {
             for (_, n) in n {
-                if let a @ Some(_) = insert_function(n, functions) {
+                if let a @ Some(_) = insert_function(n, functions, stack) {
                     return a;
                 }
             }
             None
         }
-        Node::Print(n, _) | Node::Array(n, ..) | Node::Ascii(n, _) => {
+        Node::Print(n, ..) | Node::Declarations(n, _) | Node::Array(n, ..) | Node::Ascii(n, _) => {
             for n in n {
-                if let a @ Some(_) = insert_function(n, functions) {
+                if let a @ Some(_) = insert_function(n, functions, stack) {
                     return a;
                 }
             }
             None
         }
         Node::Struct(..) => None,
-        Node::IndexAssign(_, n1, n2)
+        Node::AttrAssign(n1, n2, _)
         | Node::DerefAssign(n1, n2, _)
         | Node::If(n1, n2, None, _)
         | Node::While(n1, n2, _)
+        | Node::Index(n1, n2, ..)
         | Node::BinaryOp(_, n1, n2, _) => {
-            if let a @ Some(_) = insert_function(n1, functions) {
+            if let a @ Some(_) = insert_function(n1, functions, stack) {
                 return a;
             }
-            insert_function(n2, functions)
+            insert_function(n2, functions, stack)
         }
         Node::Number(_) => None,
         Node::Boolean(_) => None,
-        Node::Index(_, n, ..)
-        | Node::Ref(n, ..)
+        Node::Ref(n, ..)
         | Node::Deref(n, ..)
         | Node::Pointer(n, ..)
         | Node::Return(n, ..)
         | Node::Converted(n, _)
-        | Node::FuncDef(_, _, n, ..)
         | Node::AttrAccess(n, ..)
         | Node::StaticVar(_, n)
         | Node::UnaryOp(_, n, _)
         | Node::VarAssign(_, n, _)
-        | Node::VarReassign(_, n) => insert_function(n, functions),
-        Node::VarAccess(..) => None,
-        Node::String(_) => None,
-        Node::Input(..) => None,
-        Node::Ternary(n1, n2, n3, ..) | Node::If(n1, n2, Some(n3), _) => {
-            if let a @ Some(_) = insert_function(n1, functions) {
-                return a;
-            }
-            if let a @ Some(_) = insert_function(n2, functions) {
-                return a;
-            }
-            insert_function(n3, functions)
-        }
-        Node::None(_) => None,
-        Node::Char(_) => None,
-        Node::For(n1, n2, n3, n4, _) => {
-            if let a @ Some(_) = insert_function(n1, functions) {
-                return a;
-            }
-            if let a @ Some(_) = insert_function(n2, functions) {
-                return a;
-            }
-            if let a @ Some(_) = insert_function(n3, functions) {
-                return a;
-            }
-            insert_function(n4, functions)
-        }
-        Node::Expanded(_, _) => unreachable!(),
-    }
-}
-
-fn find_functions(node: &mut Node) -> Option<Vec<&mut Node>> {
-    match node {
-        Node::FuncDef(..) | Node::Statements(..) => Some(vec![node]),
-        Node::StructConstructor(_, n, _) => {
-            for (_, n) in n {
-                if let a @ Some(_) = find_functions(n) {
-                    return a;
-                }
-            }
-            None
-        }
-        Node::Call(_, n, ..) | Node::Print(n, _) | Node::Array(n, ..) | Node::Ascii(n, _) => {
-            for n in n {
-                if let a @ Some(_) = find_functions(n) {
-                    return a;
-                }
-            }
+        | Node::VarReassign(_, n) => insert_function(n, functions, stack),
+        // The functions passed to a function are only known where it is called
+        Node::FuncDef(_, params, ..)
+            if params.iter().any(|(_, t)| matches!(t, Type::Function(..))) =>
+        {
             None
         }
-        Node::Struct(..) => None,
-        Node::IndexAssign(_, n1, n2)
-        | Node::DerefAssign(n1, n2, _)
-        | Node::If(n1, n2, None, _)
-        | Node::While(n1, n2, _)
-        | Node::BinaryOp(_, n1, n2, _) => {
-            if let a @ Some(_) = find_functions(n1) {
-                return a;
-            }
-            find_functions(n2)
-        }
-        Node::Number(_) => None,
-        Node::Boolean(_) => None,
-        Node::Index(_, n, ..)
-        | Node::Ref(n, ..)
-        | Node::Pointer(n, ..)
-        | Node::Converted(n, _)
-        | Node::Deref(n, ..)
-        | Node::Return(n, ..)
-        | Node::AttrAccess(n, ..)
-        | Node::UnaryOp(_, n, _)
-        | Node::VarAssign(_, n, _)
-        | Node::StaticVar(_, n)
-        | Node::VarReassign(_, n) => find_functions(n),
+        Node::FuncDef(_, _, n, ..) => insert_function(n, functions, stack),
+        // The body of a lambda is expanded where it gets called
+        Node::Lambda(..) => None,
         Node::VarAccess(..) => None,
         Node::String(_) => None,
         Node::Input(..) => None,
-        Node::Ternary(n1, n2, n3, ..) | Node::If(n1, n2, Some(n3), _) => {
-            if let a @ Some(_) = find_functions(n1) {
+        Node::Raw(..) => None,
+        Node::Ternary(n1, n2, n3, ..)
+        | Node::If(n1, n2, Some(n3), _)
+        | Node::IndexAssign(n1, n2, n3) => {
+            if let a @ Some(_) = insert_function(n1, functions, stack) {
                 return a;
             }
-            if let a @ Some(_) = find_functions(n2) {
+            if let a @ Some(_) = insert_function(n2, functions, stack) {
                 return a;
             }
-            find_functions(n3)
+            insert_function(n3, functions, stack)
         }
         Node::None(_) => None,
         Node::Char(_) => None,
         Node::For(n1, n2, n3, n4, _) => {
-            if let a @ Some(_) = find_functions(n1) {
+            if let a @ Some(_) = insert_function(n1, functions, stack) {
                 return a;
             }
-            if let a @ Some(_) = find_functions(n2) {
+            if let a @ Some(_) = insert_function(n2, functions, stack) {
                 return a;
             }
-            if let a @ Some(_) = find_functions(n3) {
+            if let a @ Some(_) = insert_function(n3, functions, stack) {
                 return a;
             }
-            find_functions(n4)
+            insert_function(n4, functions, stack)
         }
-        Node::Expanded(_, _) => unreachable!(),
+        Node::Expanded(..) => unreachable!(),
     }
 }
 
-/// Checks for Recursive Functions
-fn check_recursive(node: &Node, stack: &mut Vec<Token>) -> Option<Error> {
+/// Checks for functions calling themselves. Functions are told apart by their name and their
+/// number of arguments, so an overload can call another one
+/// # Examples
+/// ```
+/// let code = "
+/// ez even(n: int) -> bool { return n == 0 || odd(n - 1) }
+/// ez odd(n: int) -> bool { return n != 0 && even(n - 1) }
+/// ezout even(4)
+/// ";
+/// let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+/// assert_eq!(err.details, "Recursive function 'odd' is calling itself through 'even'");
+/// ```
+fn check_recursive(node: &Node, stack: &mut Vec<(Token, usize)>) -> Option<Error> {
     match node {
-        Node::FuncDef(t, _, body, ..) => {
-            stack.push(t.clone());
+        Node::FuncDef(t, params, body, ..) => {
+            stack.push((t.clone(), params.len()));
             let s = check_recursive(body, stack);
             stack.pop();
             s
         }
+        Node::Lambda(_, body, ..) => check_recursive(body, stack),
         Node::Statements(nodes, ..) => {
             for node in nodes.iter().rev() {
                 if let a @ Some(_) = check_recursive(node, stack) {
@@ -2307,7 +4159,7 @@ fn check_recursive(node: &Node, stack: &mut Vec<Token>) -> Option<Error> {
             }
             None
         }
-        Node::StructConstructor(_, n, _) => {
+        Node::StructConstructor(_, n, ..) => {
             for (_, n) in n {
                 if let a @ Some(_) = check_recursive(n, stack) {
                     return a;
@@ -2315,7 +4167,7 @@ fn check_recursive(node: &Node, stack: &mut Vec<Token>) -> Option<Error> {
             }
             None
         }
-        Node::Print(n, _) | Node::Array(n, ..) | Node::Ascii(n, _) => {
+        Node::Print(n, ..) | Node::Declarations(n, _) | Node::Array(n, ..) | Node::Ascii(n, _) => {
             for n in n {
                 if let a @ Some(_) = check_recursive(n, stack) {
                     return a;
@@ -2324,13 +4176,16 @@ fn check_recursive(node: &Node, stack: &mut Vec<Token>) -> Option<Error> {
             None
         }
         Node::Call(token, n, ..) => {
-            if stack.iter().any(|t| t == token) {
+            if stack
+                .iter()
+                .any(|(t, arity)| t == token && *arity == n.len())
+            {
                 return Some(Error::new(
                     ErrorType::RecursionError,
                     token.position.clone(),
                     format!(
                         "Recursive function {} is calling itself",
-                        stack.last().unwrap()
+                        stack.last().unwrap().0
                     ),
                 ));
             }
@@ -2342,10 +4197,11 @@ fn check_recursive(node: &Node, stack: &mut Vec<Token>) -> Option<Error> {
             None
         }
         Node::Struct(..) => None,
-        Node::IndexAssign(_, n1, n2)
+        Node::AttrAssign(n1, n2, _)
         | Node::DerefAssign(n1, n2, _)
         | Node::If(n1, n2, None, _)
         | Node::While(n1, n2, _)
+        | Node::Index(n1, n2, ..)
         | Node::BinaryOp(_, n1, n2, _) => {
             if let a @ Some(_) = check_recursive(n1, stack) {
                 return a;
@@ -2354,8 +4210,7 @@ fn check_recursive(node: &Node, stack: &mut Vec<Token>) -> Option<Error> {
         }
         Node::Number(_) => None,
         Node::Boolean(_) => None,
-        Node::Index(_, n, ..)
-        | Node::Ref(n, ..)
+        Node::Ref(n, ..)
         | Node::Deref(n, ..)
         | Node::Pointer(n, ..)
         | Node::Return(n, ..)
@@ -2368,7 +4223,10 @@ fn check_recursive(node: &Node, stack: &mut Vec<Token>) -> Option<Error> {
         Node::VarAccess(..) => None,
         Node::String(_) => None,
         Node::Input(..) => None,
-        Node::Ternary(n1, n2, n3, ..) | Node::If(n1, n2, Some(n3), _) => {
+        Node::Raw(..) => None,
+        Node::Ternary(n1, n2, n3, ..)
+        | Node::If(n1, n2, Some(n3), _)
+        | Node::IndexAssign(n1, n2, n3) => {
             if let a @ Some(_) = check_recursive(n1, stack) {
                 return a;
             }
@@ -2391,26 +4249,38 @@ fn check_recursive(node: &Node, stack: &mut Vec<Token>) -> Option<Error> {
             }
             check_recursive(n4, stack)
         }
-        Node::Expanded(_, _) => unreachable!(),
+        Node::Expanded(..) => unreachable!(),
     }
 }
 
+/// Checks that `struct_` doesn't contain itself, through its own fields or the fields of
+/// the structs inside of it, since its size would be infinite. Pointers break the cycle
 fn check_recursive_struct(struct_: &Type, stack: &mut Vec<Token>) -> Option<Error> {
-    if let Type::Struct(token, fields) = struct_ {
+    fn stored_struct(t: &Type) -> Option<&Type> {
+        match t {
+            Type::Struct(..) => Some(t),
+            Type::Ref(t) => stored_struct(t),
+            _ => None,
+        }
+    }
+    if let Type::Struct(token, fields, _) = struct_ {
         stack.push(token.clone());
-        for (_, ty) in fields {
-            if let Type::Struct(t, ..) = ty {
+        for (field, ty) in fields {
+            if let Some(ty @ Type::Struct(t, ..)) = stored_struct(ty) {
                 if stack.contains(t) {
                     return Some(Error::new(
                         ErrorType::TypeError,
-                        token.position.clone(),
+                        field.position.clone(),
                         format!(
-                            "Struct {} is recursive and it's size cannot be known at compile time",
-                            token
+                            "Struct {} has infinite size; use a pointer for field {}",
+                            stack.last().unwrap(),
+                            field
                         ),
                     ));
                 }
-                check_recursive_struct(ty, stack)?;
+                if let err @ Some(_) = check_recursive_struct(ty, stack) {
+                    return err;
+                }
             }
         }
         stack.pop();
@@ -2439,7 +4309,7 @@ fn find_static(node: &Node) -> Option<Vec<&Node>> {
             }
             Some(new)
         }
-        Node::StructConstructor(_, n, _) => {
+        Node::StructConstructor(_, n, ..) => {
             for (_, n) in n {
                 if let a @ Some(_) = find_static(n) {
                     return a;
@@ -2447,7 +4317,11 @@ fn find_static(node: &Node) -> Option<Vec<&Node>> {
             }
             None
         }
-        Node::Call(_, n, ..) | Node::Print(n, _) | Node::Array(n, ..) | Node::Ascii(n, _) => {
+        Node::Call(_, n, ..)
+        | Node::Print(n, ..)
+        | Node::Declarations(n, _)
+        | Node::Array(n, ..)
+        | Node::Ascii(n, _) => {
             for n in n {
                 if let a @ Some(_) = find_static(n) {
                     return a;
@@ -2456,10 +4330,11 @@ fn find_static(node: &Node) -> Option<Vec<&Node>> {
             None
         }
         Node::Struct(..) => None,
-        Node::IndexAssign(_, n1, n2)
+        Node::AttrAssign(n1, n2, _)
         | Node::DerefAssign(n1, n2, _)
         | Node::If(n1, n2, None, _)
         | Node::While(n1, n2, _)
+        | Node::Index(n1, n2, ..)
         | Node::BinaryOp(_, n1, n2, _) => {
             if let a @ Some(_) = find_static(n1) {
                 return a;
@@ -2468,8 +4343,7 @@ fn find_static(node: &Node) -> Option<Vec<&Node>> {
         }
         Node::Number(_) => None,
         Node::Boolean(_) => None,
-        Node::Index(_, n, ..)
-        | Node::Ref(n, ..)
+        Node::Ref(n, ..)
         | Node::Pointer(n, ..)
         | Node::Deref(n, ..)
         | Node::Return(n, ..)
@@ -2478,11 +4352,15 @@ fn find_static(node: &Node) -> Option<Vec<&Node>> {
         | Node::VarAssign(_, n, _)
         | Node::AttrAccess(n, ..)
         | Node::FuncDef(_, _, n, _, _)
+        | Node::Lambda(_, n, ..)
         | Node::VarReassign(_, n) => find_static(n),
         Node::VarAccess(..) => None,
         Node::String(_) => None,
         Node::Input(..) => None,
-        Node::Ternary(n1, n2, n3, ..) | Node::If(n1, n2, Some(n3), _) => {
+        Node::Raw(..) => None,
+        Node::Ternary(n1, n2, n3, ..)
+        | Node::If(n1, n2, Some(n3), _)
+        | Node::IndexAssign(n1, n2, n3) => {
             if let a @ Some(_) = find_static(n1) {
                 return a;
             }
@@ -2505,7 +4383,7 @@ fn find_static(node: &Node) -> Option<Vec<&Node>> {
             }
             find_static(n4)
         }
-        Node::Expanded(_, _) => unreachable!(),
+        Node::Expanded(..) => unreachable!(),
         Node::StaticVar(..) => Some(vec![node]),
     }
 }
@@ -2532,7 +4410,7 @@ fn find_structs(node: &Node, depth: ScopeDepth) -> Option<Vec<&Node>> {
             }
             Some(new)
         }
-        Node::StructConstructor(_, n, _) => {
+        Node::StructConstructor(_, n, ..) => {
             for (_, n) in n {
                 if let a @ Some(_) = find_structs(n, depth) {
                     return a;
@@ -2540,7 +4418,11 @@ fn find_structs(node: &Node, depth: ScopeDepth) -> Option<Vec<&Node>> {
             }
             None
         }
-        Node::Call(_, n, ..) | Node::Print(n, _) | Node::Array(n, ..) | Node::Ascii(n, _) => {
+        Node::Call(_, n, ..)
+        | Node::Print(n, ..)
+        | Node::Declarations(n, _)
+        | Node::Array(n, ..)
+        | Node::Ascii(n, _) => {
             for n in n {
                 if let a @ Some(_) = find_structs(n, depth) {
                     return a;
@@ -2549,10 +4431,11 @@ fn find_structs(node: &Node, depth: ScopeDepth) -> Option<Vec<&Node>> {
             None
         }
         Node::Struct(..) | Node::Statements(..) => Some(vec![node]),
-        Node::IndexAssign(_, n1, n2)
+        Node::AttrAssign(n1, n2, _)
         | Node::DerefAssign(n1, n2, _)
         | Node::If(n1, n2, None, _)
         | Node::While(n1, n2, _)
+        | Node::Index(n1, n2, ..)
         | Node::BinaryOp(_, n1, n2, _) => {
             if let a @ Some(_) = find_structs(n1, depth) {
                 return a;
@@ -2561,8 +4444,7 @@ fn find_structs(node: &Node, depth: ScopeDepth) -> Option<Vec<&Node>> {
         }
         Node::Number(_) => None,
         Node::Boolean(_) => None,
-        Node::Index(_, n, ..)
-        | Node::Ref(n, ..)
+        Node::Ref(n, ..)
         | Node::Pointer(n, ..)
         | Node::Deref(n, ..)
         | Node::Return(n, ..)
@@ -2572,11 +4454,15 @@ fn find_structs(node: &Node, depth: ScopeDepth) -> Option<Vec<&Node>> {
         | Node::VarAssign(_, n, _)
         | Node::AttrAccess(n, ..)
         | Node::FuncDef(_, _, n, _, _)
+        | Node::Lambda(_, n, ..)
         | Node::VarReassign(_, n) => find_structs(n, depth),
         Node::VarAccess(..) => None,
         Node::String(_) => None,
         Node::Input(..) => None,
-        Node::Ternary(n1, n2, n3, ..) | Node::If(n1, n2, Some(n3), _) => {
+        Node::Raw(..) => None,
+        Node::Ternary(n1, n2, n3, ..)
+        | Node::If(n1, n2, Some(n3), _)
+        | Node::IndexAssign(n1, n2, n3) => {
             if let a @ Some(_) = find_structs(n1, depth) {
                 return a;
             }
@@ -2599,7 +4485,7 @@ fn find_structs(node: &Node, depth: ScopeDepth) -> Option<Vec<&Node>> {
             }
             find_structs(n4, depth)
         }
-        Node::Expanded(_, _) => unreachable!(),
+        Node::Expanded(..) => unreachable!(),
     }
 }
 
@@ -2650,7 +4536,11 @@ fn check_undefined_struct_(node: &Node, structs: &[Node]) -> Option<Error> {
             }
             None
         }
-        Node::Call(_, n, ..) | Node::Print(n, _) | Node::Array(n, ..) | Node::Ascii(n, _) => {
+        Node::Call(_, n, ..)
+        | Node::Print(n, ..)
+        | Node::Declarations(n, _)
+        | Node::Array(n, ..)
+        | Node::Ascii(n, _) => {
             for n in n {
                 if let a @ Some(_) = check_undefined_struct_(n, structs) {
                     return a;
@@ -2659,10 +4549,11 @@ fn check_undefined_struct_(node: &Node, structs: &[Node]) -> Option<Error> {
             None
         }
         Node::Struct(..) => None,
-        Node::IndexAssign(_, n1, n2)
+        Node::AttrAssign(n1, n2, _)
         | Node::DerefAssign(n1, n2, _)
         | Node::If(n1, n2, None, _)
         | Node::While(n1, n2, _)
+        | Node::Index(n1, n2, ..)
         | Node::BinaryOp(_, n1, n2, _) => {
             if let a @ Some(_) = check_undefined_struct_(n1, structs) {
                 return a;
@@ -2671,13 +4562,13 @@ fn check_undefined_struct_(node: &Node, structs: &[Node]) -> Option<Error> {
         }
         Node::Number(_) => None,
         Node::Boolean(_) => None,
-        Node::Index(_, n, ..)
-        | Node::Ref(n, ..)
+        Node::Ref(n, ..)
         | Node::Deref(n, ..)
         | Node::Pointer(n, ..)
         | Node::Return(n, ..)
         | Node::Converted(n, _)
         | Node::FuncDef(_, _, n, ..)
+        | Node::Lambda(_, n, ..)
         | Node::AttrAccess(n, ..)
         | Node::StaticVar(_, n)
         | Node::UnaryOp(_, n, _)
@@ -2686,7 +4577,10 @@ fn check_undefined_struct_(node: &Node, structs: &[Node]) -> Option<Error> {
         Node::VarAccess(..) => None,
         Node::String(_) => None,
         Node::Input(..) => None,
-        Node::Ternary(n1, n2, n3, ..) | Node::If(n1, n2, Some(n3), _) => {
+        Node::Raw(..) => None,
+        Node::Ternary(n1, n2, n3, ..)
+        | Node::If(n1, n2, Some(n3), _)
+        | Node::IndexAssign(n1, n2, n3) => {
             if let a @ Some(_) = check_undefined_struct_(n1, structs) {
                 return a;
             }
@@ -2709,155 +4603,6 @@ fn check_undefined_struct_(node: &Node, structs: &[Node]) -> Option<Error> {
             }
             check_undefined_struct_(n4, structs)
         }
-        Node::Expanded(_, _) => unreachable!(),
-    }
-}
-
-/// Checks for Numbers above the i8 limit
-fn check_numbers(node: &Node) -> Option<Error> {
-    match node {
-        Node::BinaryOp(_, n1, n2, _)
-        | Node::IndexAssign(_, n1, n2)
-        | Node::While(n1, n2, _)
-        | Node::DerefAssign(n1, n2, _) => {
-            let n1 = check_numbers(n1);
-            if n1.is_some() {
-                return n1;
-            }
-            let n2 = check_numbers(n2);
-            if n2.is_some() {
-                return n2;
-            }
-            None
-        }
-        Node::For(n1, n2, n3, n4, _) => {
-            let n1 = check_numbers(n1);
-            if n1.is_some() {
-                return n1;
-            }
-            let n2 = check_numbers(n2);
-            if n2.is_some() {
-                return n2;
-            }
-            let n3 = check_numbers(n3);
-            if n3.is_some() {
-                return n3;
-            }
-            let n4 = check_numbers(n4);
-            if n4.is_some() {
-                return n4;
-            }
-            None
-        }
-        Node::Struct(..) => None,
-        Node::UnaryOp(op, expr, _)
-            if op.token_type == TokenType::Sub
-                && matches!(
-                    **expr,
-                    Node::Number(Token {
-                        token_type: TokenType::Number(0..=256),
-                        ..
-                    })
-                ) =>
-        {
-            None
-        }
-        Node::UnaryOp(_, n1, _) => check_numbers(n1),
-        Node::Converted(n, _) => check_numbers(n),
-        Node::VarAssign(_, n1, _) => check_numbers(n1),
-        Node::AttrAccess(n, ..) => check_numbers(n),
-        Node::StaticVar(_, n1) => check_numbers(n1),
-        Node::VarAccess(..) => None,
-        Node::VarReassign(_, n1) => check_numbers(n1),
-        Node::Statements(nodes, ..) => {
-            let mut ret = None;
-            for node in nodes {
-                let n = check_numbers(node);
-                if n.is_some() {
-                    ret = n;
-                    break;
-                }
-            }
-            ret
-        }
-        Node::StructConstructor(_, nodes, _) => {
-            let mut ret = None;
-            for (_, node) in nodes {
-                let n = check_numbers(node);
-                if n.is_some() {
-                    ret = n;
-                    break;
-                }
-            }
-            ret
-        }
-        Node::Ternary(n1, n2, n3, ..) => {
-            let n1 = check_numbers(n1);
-            if n1.is_some() {
-                return n1;
-            }
-            let n2 = check_numbers(n2);
-            if n2.is_some() {
-                return n2;
-            }
-            let n3 = check_numbers(n3);
-            if n3.is_some() {
-                return n3;
-            }
-            None
-        }
-        Node::If(n1, n2, n3, _) => {
-            let n1 = check_numbers(n1);
-            if n1.is_some() {
-                return n1;
-            }
-            let n2 = check_numbers(n2);
-            if n2.is_some() {
-                return n2;
-            }
-            if let Some(n3) = n3 {
-                let n3 = check_numbers(n3);
-                if n3.is_some() {
-                    return n3;
-                }
-            }
-            None
-        }
-        Node::Call(_, n1, ..) => {
-            for i in n1.iter().map(check_numbers) {
-                if i.is_some() {
-                    return i;
-                }
-            }
-            None
-        }
-        Node::Index(_, n1, ..) => check_numbers(n1),
-        Node::FuncDef(..) => None,
-        Node::Return(n, _) => check_numbers(n),
-        Node::Ref(n1, ..) | Node::Deref(n1, ..) | Node::Pointer(n1, ..) => check_numbers(n1),
-        Node::Print(n1, _) | Node::Ascii(n1, _) => {
-            for n in n1 {
-                if let Some(t) = check_numbers(n) {
-                    return Some(t);
-                }
-            }
-            None
-        }
-        Node::String(_) => None,
-        Node::Number(Token {
-            token_type: TokenType::Number(0..=255),
-            ..
-        }) => None,
-        Node::Number(_) => Some(Error::new(
-            ErrorType::NumberTooLarge,
-            node.position(),
-            "Number is too large".to_string(),
-        )),
-        Node::Boolean(_) => None,
-        Node::Input(..) => None,
-        Node::None(_) => None,
-        Node::Char(..) => None,
-        Node::Array(..) => None,
         Node::Expanded(..) => unreachable!(),
     }
 }
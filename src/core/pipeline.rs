@@ -0,0 +1,147 @@
+use crate::{
+    core::{ir_code, lowering, parser, warnings},
+    utils::{
+        ast::Node,
+        diag::{Error, ErrorType, FileName, Position},
+        ir::Instructions,
+        Options, Stats,
+    },
+};
+
+/// A hook run between two phases. It can change what it is given, and an error it returns
+/// stops the compilation like any other error
+type Hook<'a, T> = Box<dyn FnMut(&mut T) -> Result<(), Error> + 'a>;
+
+/// The phases of the compiler, with hooks run between them, so that tools can change the AST
+/// or the IR code without copying the rest of the compiler.
+/// The hooks of a phase run in the order they were added. The code hooks are given the code
+/// after its arithmetic is lowered, if the options ask for it
+/// # Examples
+/// ```
+/// use ezlang::core::pipeline::Pipeline;
+/// use ezlang::utils::{ast::Node, diag::{Error, ErrorType}, Options, Stats};
+///
+/// let mut functions = vec![];
+/// let mut instructions = 0;
+/// let code = Pipeline::new(Options::default())
+///     .after_parse(|ast| {
+///         if let Node::Statements(nodes, ..) = ast {
+///             for node in nodes {
+///                 if let Node::FuncDef(name, ..) = node {
+///                     functions.push(name.to_string());
+///                 }
+///             }
+///         }
+///         Ok(())
+///     })
+///     .after_codegen(|code| {
+///         instructions = code.0.len();
+///         Ok(())
+///     })
+///     .run("ez f() { ezout 1 }\nez g() {}\nf()", "main.ez".to_string(), &mut Stats::default());
+/// assert!(code.is_ok());
+/// assert_eq!(functions, ["'f'", "'g'"]);
+/// assert!(instructions > 0);
+///
+/// // The error of a hook is reported like the errors of the compiler
+/// let err = Pipeline::new(Options::default())
+///     .after_parse(|ast| Err(Error::new(ErrorType::SyntaxError, ast.position(), "No".to_string())))
+///     .run("ezout 1", "main.ez".to_string(), &mut Stats::default())
+///     .unwrap_err();
+/// assert_eq!(err.details, "No");
+///
+/// // In debug builds, the code a hook leaves is checked, and invalid code is an error
+/// let err = Pipeline::new(Options::default())
+///     .after_codegen(|code| {
+///         code.0.retain(|(_, instruction)| instruction.name() != "EndWhile");
+///         Ok(())
+///     })
+///     .run("let i = 0\nwhile (i < 3) i += 1", "main.ez".to_string(), &mut Stats::default());
+/// if cfg!(debug_assertions) {
+///     assert!(matches!(err.unwrap_err().error_type, ErrorType::InvalidCode));
+/// }
+/// ```
+pub struct Pipeline<'a> {
+    options: Options,
+    after_parse: Vec<Hook<'a, Node>>,
+    after_codegen: Vec<Hook<'a, Instructions>>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// The pipeline [`crate::run_with_options`] compiles with
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            after_parse: vec![],
+            after_codegen: vec![],
+        }
+    }
+
+    /// Adds a hook run on the AST once it is parsed and its warnings are found, before the
+    /// function calls are expanded
+    pub fn after_parse(mut self, hook: impl FnMut(&mut Node) -> Result<(), Error> + 'a) -> Self {
+        self.after_parse.push(Box::new(hook));
+        self
+    }
+
+    /// Adds a hook run on the IR code once it is generated, before it is transpiled.
+    /// The code can't be matched with the source once it is changed, so [`Stats::source_map`]
+    /// is left empty when there are hooks
+    pub fn after_codegen(
+        mut self,
+        hook: impl FnMut(&mut Instructions) -> Result<(), Error> + 'a,
+    ) -> Self {
+        self.after_codegen.push(Box::new(hook));
+        self
+    }
+
+    /// Compiles `contents` into brainfuck code, running the hooks between the phases.
+    /// Statistics about the compilation are recorded into `stats`
+    pub fn run(
+        &mut self,
+        contents: &str,
+        filename: String,
+        stats: &mut Stats,
+    ) -> Result<String, Error> {
+        let code = self.compile_ir(contents, filename, stats)?;
+        Ok(crate::to_brainfuck_with_options(&code, &self.options))
+    }
+
+    /// Compiles `contents` like [`Pipeline::run`], but stops at the intermediate code
+    pub fn compile_ir(
+        &mut self,
+        contents: &str,
+        filename: String,
+        stats: &mut Stats,
+    ) -> Result<Instructions, Error> {
+        let options = &self.options;
+        let file = FileName::new(&filename);
+        let (tokens, comments) = crate::tokens_with_comments(contents, filename, options, stats)?;
+        let ((mut ast, statics, structs), scope) = parser::parse_scoped(tokens)?;
+        let found = std::mem::take(&mut stats.warnings);
+        stats.warnings = warnings::check(&ast, &scope, &comments, found, options)?;
+        for hook in &mut self.after_parse {
+            hook(&mut ast)?;
+        }
+        let ast = parser::expand_with_options(ast, options)?;
+        let mut code = ir_code::generate_code(ast, statics, structs, options, stats)?;
+        if options.lower_arith {
+            code = lowering::lower_arithmetic(&code);
+            stats.source_map.clear();
+        }
+        for hook in &mut self.after_codegen {
+            hook(&mut code)?;
+            stats.source_map.clear();
+        }
+        // The hooks can change the code in any way, so their mistakes are errors like any other
+        #[cfg(debug_assertions)]
+        if let Err(problems) = code.validate() {
+            return Err(Error::new(
+                ErrorType::InvalidCode,
+                Position::new(1, 1, 1, file),
+                format!("Invalid intermediate code:\n{}", problems.join("\n")),
+            ));
+        }
+        Ok(code)
+    }
+}
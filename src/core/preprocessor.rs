@@ -1,17 +1,340 @@
-use std::{collections::HashSet, fs, rc::Rc};
+use std::{
+    collections::HashSet,
+    env, fs, io,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
 
 use crate::{
     lexer,
-    utils::{Error, ErrorType, Token, TokenType},
+    utils::{
+        diag::{Error, ErrorType, FileName, Position, Warning, WarningKind},
+        token::{Token, TokenType},
+        Options,
+    },
 };
 
-pub fn preprocess(mut tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
-    let mut declared = HashSet::new();
+/// Runs the preprocessor directives in `tokens`, removing them along with the code they leave out.
+/// The paths of the files included by `use` are added to `included`, as they were read.
+/// A file that is already in `included` isn't included again, even through another path to it.
+/// When [`Options::trace_preprocessor`] is set, a line is added to `trace` for every directive
+/// that is run, with where it is and how many tokens it added and removed.
+/// Directives that do nothing, like an `undeclare` of a flag that isn't declared, add a
+/// warning to `warnings`
+/// # Errors
+/// Returns an error if a directive is malformed, if a file included by `use` can't be read,
+/// if a macro is used with the wrong number of arguments, or if the replacements made by
+/// `replace` add more than [`Options::max_replaced_tokens`] tokens in total
+/// # Examples
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::{lexer, preprocessor};
+/// use ezlang::utils::Options;
+///
+/// let code = "!replace TWICE \"x x\"\nlet x = 1\nezout TWICE\n!declare DONE";
+/// let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+/// let options = Options { trace_preprocessor: true, ..Options::default() };
+/// let mut trace = vec![];
+/// preprocessor::preprocess(tokens.clone(), &options, &mut vec![], &mut trace, &mut vec![]).unwrap();
+/// assert_eq!(trace, [
+///     "main.ez:1:1: !replace added 2 tokens, removed 4",
+///     "main.ez:4:1: !declare added 0 tokens, removed 2",
+/// ]);
+///
+/// // Every replacement doubles the number of tokens
+/// let code = "!replace A \"B B\"\n!replace B \"C C\"\n!replace C \"D D\"\nezout A";
+/// let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+/// let options = Options { max_replaced_tokens: 10, ..Options::default() };
+/// let err = preprocessor::preprocess(tokens, &options, &mut vec![], &mut vec![], &mut vec![]).unwrap_err();
+/// assert_eq!((err.position.line_start, err.position.start), (3, 1));
+/// assert_eq!(err.details, "The replacements added more than 10 tokens, this `replace` may be making too many of them");
+/// ```
+/// `undeclare` removes a flag, so that it is only declared for part of the code, like a file
+/// included with it
+/// ```
+/// use ezlang::utils::{diag::{Severity, WarningKind}, Options, Stats};
+///
+/// let run = |code: &str, options: &Options| {
+///     let mut stats = Stats::default();
+///     let code = ezlang::run_with_options(code, "main.ez".to_string(), options, &mut stats)?;
+///     let mut output = vec![];
+///     ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+///     Ok::<_, ezlang::utils::diag::Error>((output, stats.warnings))
+/// };
+/// let code = "
+/// !declare LOUD
+/// !ifdeclared LOUD ezout 1 !endif
+/// !undeclare LOUD
+/// !ifdeclared LOUD ezout 2 !else ezout 3 !endif
+/// ";
+/// let (output, warnings) = run(code, &Options::default()).unwrap();
+/// assert_eq!((output, warnings), (b"13".to_vec(), vec![]));
+///
+/// // Undeclaring a flag that isn't declared does nothing, and gives a warning
+/// let code = "ezout 1\n!ifdeclared QUIET !undeclare SILENT !endif\n!undeclare QUIET";
+/// let (_, warnings) = run(code, &Options::default()).unwrap();
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!((warnings[0].kind, warnings[0].position.line_start, warnings[0].position.start), (WarningKind::UnknownFlag, 3, 12));
+/// assert_eq!(warnings[0].details, "`QUIET` isn't declared, so `undeclare` does nothing");
+///
+/// let options = Options { warnings: vec![(WarningKind::UnknownFlag, Severity::Error)], ..Options::default() };
+/// assert!(run(code, &options).is_err());
+/// ```
+/// `warning` gives a warning with its message and goes on, unlike `error`. The warnings of a
+/// branch that is left out are dropped, and the ones of included files are kept, since they
+/// are written for the code including them
+/// ```
+/// use ezlang::utils::{diag::{Severity, WarningKind}, Options, Stats};
+///
+/// let dir = std::env::temp_dir().join("ezlang_warning_directive_example");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("old.ez"), "!warning \"old is deprecated, use new\"\nez one() -> int { return 1 }").unwrap();
+/// let code = "
+/// !declare FAST
+/// !ifdeclared FAST !warning \"FAST does nothing anymore\" !endif
+/// !ifdeclared SLOW !warning \"SLOW is slow\" !endif
+/// !use old
+/// ezout one()
+/// ";
+/// let run = |options: &Options| {
+///     let options = Options { include_paths: vec![dir.to_string_lossy().into_owned()], ..options.clone() };
+///     let mut stats = Stats::default();
+///     ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut stats).map(|_| stats.warnings)
+/// };
+/// let warnings = run(&Options::default()).unwrap();
+/// let found: Vec<_> = warnings.iter().map(|w| (w.kind, w.position.line_start, w.details.as_str())).collect();
+/// assert_eq!(found, [
+///     (WarningKind::Directive, 1, "old is deprecated, use new"),
+///     (WarningKind::Directive, 3, "FAST does nothing anymore"),
+/// ]);
+/// assert_eq!(warnings[0].position.included_from.as_ref().unwrap().line_start, 5);
+///
+/// let options = Options { warnings: vec![(WarningKind::Directive, Severity::Error)], ..Options::default() };
+/// assert!(run(&options).is_err());
+///
+/// let err = ezlang::run("!warning deprecated", "main.ez".to_string()).unwrap_err();
+/// assert_eq!(err.details, "Expected a warning message after `warning`");
+/// ```
+/// `ifnotdeclared` keeps the code up to its `else` or `endif` if the flag isn't declared,
+/// and pairs with them like `ifdeclared`
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "
+/// !declare A
+/// !ifdeclared A
+///     !ifnotdeclared B ezout 1 !else ezout 2 !endif
+///     !ifnotdeclared A ezout 3 !else
+///         !ifdeclared B ezout 4 !else ezout 5 !endif
+///     !endif
+/// !else
+///     ezout 6
+/// !endif
+/// !ifnotdeclared A !ifnotdeclared B ezout 7 !endif !endif
+/// !ifnotdeclared C ezout 8 !endif
+/// ";
+/// let run = |defines: &[&str]| {
+///     let options = Options { defines: defines.iter().map(|d| d.to_string()).collect(), ..Options::default() };
+///     let code = ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut Stats::default()).unwrap();
+///     let mut output = vec![];
+///     ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+///     output
+/// };
+/// assert_eq!(run(&[]), b"158");
+/// assert_eq!(run(&["B", "C"]), b"24");
+///
+/// let err = ezlang::run("!ifnotdeclared A ezout 1", "main.ez".to_string()).unwrap_err();
+/// assert_eq!(err.details, "No `endif` after `ifdeclared`");
+/// ```
+/// `elifdeclared` starts another branch, kept if its flag is declared and no branch before it
+/// was kept
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "
+/// !ifdeclared LINUX ezout 1
+/// !elifdeclared MAC ezout 2
+/// !elifdeclared WINDOWS ezout 3
+/// !else ezout 4
+/// !endif
+/// !ifnotdeclared MAC ezout 5 !elifdeclared LINUX ezout 6 !endif
+/// ";
+/// let run = |defines: &[&str]| {
+///     let options = Options { defines: defines.iter().map(|d| d.to_string()).collect(), ..Options::default() };
+///     let code = ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut Stats::default()).unwrap();
+///     let mut output = vec![];
+///     ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+///     output
+/// };
+/// assert_eq!(run(&["LINUX"]), b"15");
+/// assert_eq!(run(&["MAC", "WINDOWS"]), b"2");
+/// assert_eq!(run(&["WINDOWS"]), b"35");
+/// assert_eq!(run(&[]), b"45");
+/// assert_eq!(run(&["MAC", "LINUX"]), b"16");
+///
+/// let err = ezlang::run("ezout 1\n!elifdeclared MAC ezout 2", "main.ez".to_string()).unwrap_err();
+/// assert_eq!(format!("{:?}", err.error_type), "SyntaxError");
+/// assert_eq!(err.details, "`elifdeclared` without `ifdeclared`");
+///
+/// let code = "!ifdeclared A ezout 1 !else ezout 2 !elifdeclared B ezout 3 !endif";
+/// let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+/// assert_eq!(format!("{:?}", err.error_type), "SyntaxError");
+/// assert_eq!(err.details, "`elifdeclared` after `else`");
+/// ```
+/// The find element can also be a string, which is lexed into a sequence of tokens to replace.
+/// Matches are replaced from left to right, and the tokens put in their place aren't looked at
+/// again, so a replacement can contain what it replaces
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::{lexer, preprocessor};
+/// use ezlang::utils::Options;
+///
+/// let replaced = |code: &str| -> Vec<String> {
+///     let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+///     let tokens = preprocessor::preprocess(tokens, &Options::default(), &mut vec![], &mut vec![], &mut vec![]).unwrap();
+///     tokens.iter().map(|t| t.token_type.to_string()).collect()
+/// };
+/// assert_eq!(replaced("!replace \"ez main\" \"ez start\"\nez main()"), ["ez", "start", "(", ")", "End of file"]);
+/// assert_eq!(replaced("!replace \"a a\" b\na a a a a"), ["b", "b", "a", "End of file"]);
+/// assert_eq!(replaced("!replace \"x y\" \"x y x y\"\nx y y"), ["x", "y", "x", "y", "y", "End of file"]);
+/// ```
+/// A file is only included the first time it is used, later `use`s of it are removed.
+/// Files are told apart by their canonical path, so a file used by two others, or used with
+/// different paths, doesn't define its functions twice, and a file using itself or the main file
+/// does nothing
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let dir = std::env::temp_dir().join("ezlang_diamond_example");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("util.ez"), "!use util\nez double(x: int) -> int { return x * 2 }").unwrap();
+/// std::fs::write(dir.join("left.ez"), "!use util\nez left() -> int { return double(1) }").unwrap();
+/// std::fs::write(dir.join("right.ez"), "!use \"util.ez\"\nez right() -> int { return double(2) }").unwrap();
+/// let options = Options {
+///     include_paths: vec![dir.to_string_lossy().into_owned()],
+///     ..Options::default()
+/// };
+/// let code = "!use left\n!use right\n!use util\nezout left(), right(), double(3)";
+/// let code = ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut Stats::default()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"2 4 6");
+///
+/// // A file using the main file doesn't include it again
+/// std::fs::write(dir.join("back.ez"), "!use main\nez back() -> int { return 5 }").unwrap();
+/// let main = dir.join("main.ez");
+/// std::fs::write(&main, "!use back\nezout back()").unwrap();
+/// let code = std::fs::read_to_string(&main).unwrap();
+/// let main = main.to_string_lossy().into_owned();
+/// assert!(ezlang::run_with_options(&code, main, &options, &mut Stats::default()).is_ok());
+/// ```
+/// The code of an included file is checked as the top level of that file. An error in it is
+/// reported in it, with every `use` it was included through, from the closest one
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let dir = std::env::temp_dir().join("ezlang_included_context_example");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("lib.ez"), "ez one() -> int { return 1 }\nreturn 5").unwrap();
+/// std::fs::write(dir.join("mid.ez"), "ez two() -> int { return 2 }\n!use lib").unwrap();
+/// std::fs::write(dir.join("nested.ez"), "ez f() {\n    !use lib\n}").unwrap();
+/// let options = Options {
+///     include_paths: vec![dir.to_string_lossy().into_owned()],
+///     ..Options::default()
+/// };
+/// let run = |code: &str| ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut Stats::default());
+/// let chain = |err: &ezlang::utils::diag::Error| {
+///     let mut chain = vec![];
+///     let mut position = err.position.included_from.as_deref();
+///     while let Some(p) = position {
+///         chain.push((p.file.to_string(), p.line_start));
+///         position = p.included_from.as_deref();
+///     }
+///     chain
+/// };
+///
+/// let err = run("ezout 1\n!use mid").unwrap_err();
+/// assert_eq!(err.details, "Return statement cannot be in the global scope of lib");
+/// assert_eq!((err.position.file.as_str(), err.position.line_start), ("lib", 2));
+/// assert_eq!(chain(&err), [("mid".to_string(), 2), ("main.ez".to_string(), 2)]);
+/// assert!(err.to_string().contains("included from mid at 2:1\n    included from main.ez at 2:1"));
+///
+/// // A `use` in a block of an included file is an error at that `use`
+/// let err = run("!use nested\nezout 1").unwrap_err();
+/// assert_eq!(err.details, "`use` can only be used outside of blocks");
+/// assert_eq!((err.position.file.as_str(), err.position.line_start), ("nested", 2));
+/// assert_eq!(chain(&err), [("main.ez".to_string(), 1)]);
+///
+/// // The same `return` in the main file names the main file
+/// let err = run("ezout 1\nreturn 5").unwrap_err();
+/// assert_eq!(err.details, "Return statement cannot be in the global scope of main.ez");
+/// assert!(err.position.included_from.is_none());
+/// ```
+/// A `replace` whose find token is directly followed by parameters is a macro, and its uses
+/// take arguments, which are put in the place of the parameters in the replacement
+/// ```
+/// let code = "
+/// !replace SQUARE(x) \"((x) * (x))\"
+/// !replace PAIR(a, b) \"a, b\"
+/// let n = 2
+/// ezout SQUARE(n + 1), SQUARE(SQUARE(2)), PAIR([1, 2][1], 'c')
+/// ";
+/// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+/// let mut output = vec![];
+/// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"9 16 2 c");
+///
+/// let error = |code: &str| {
+///     let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+///     (err.details, err.position.line_start, err.position.start, err.position.end)
+/// };
+/// assert_eq!(
+///     error("!replace SQUARE(x) \"x * x\"\nezout SQUARE(1, 2)"),
+///     ("Macro 'SQUARE' takes 1 arguments, but 2 were given".to_string(), 2, 7, 19)
+/// );
+/// assert_eq!(
+///     error("!replace SQUARE(x) \"x * x\"\nezout SQUARE"),
+///     ("Expected the arguments of macro 'SQUARE' after it".to_string(), 2, 7, 13)
+/// );
+/// assert_eq!(
+///     error("!replace F(x) \"F(x) + 1\"\nezout F(1)"),
+///     ("Macro 'F' is used in its own replacement, it would be expanded forever".to_string(), 1, 10, 11)
+/// );
+/// ```
+pub fn preprocess(
+    mut tokens: Vec<Token>,
+    options: &Options,
+    included: &mut Vec<PathBuf>,
+    trace: &mut Vec<String>,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<Token>, Error> {
+    let mut declared: HashSet<String> = options.defines.iter().cloned().collect();
+    // The main file is already included too, so a file using it, or itself, does nothing
+    let main = tokens
+        .last()
+        .map(|t| canonical(Path::new(t.position.file.as_str())));
+    let mut seen: HashSet<PathBuf> = included.iter().map(|path| canonical(path)).collect();
+    seen.extend(main);
     let mut i = 0;
-    let mut ifs = Vec::new();
+    // For every `ifdeclared` that isn't closed yet, where the tokens that are left out start
+    // if its current branch is, whether one of its branches was kept, and whether its `else`
+    // was seen
+    let mut ifs: Vec<(Option<usize>, bool, bool)> = Vec::new();
+    let mut depth = 0usize;
+    // Number of tokens the replacements have added so far
+    let mut replaced = 0;
     while i < tokens.len() {
         if let TokenType::PreprocessorStatement(ref stmt) = tokens[i].token_type {
-            match stmt.as_ref() {
+            let stmt = stmt.clone();
+            let directive = tokens[i].position.clone();
+            let (added, removed) = match stmt.as_ref() {
+                "use" if depth > 0 => {
+                    return Err(Error::new(
+                        ErrorType::PreprocessorError,
+                        tokens[i].position.clone(),
+                        "`use` can only be used outside of blocks".to_owned(),
+                    ))
+                }
                 "use" => match tokens.get(i + 1).cloned() {
                     None => {
                         return Err(Error::new(
@@ -20,45 +343,34 @@ pub fn preprocess(mut tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
                             "Expected a filename after `use`".to_owned(),
                         ))
                     }
-                    Some(t) => match t.token_type {
-                        TokenType::String(file) => match fs::read_to_string(&file) {
-                            Ok(contents) => {
-                                let mut new_tokens = lexer::lex(&contents, Rc::new(file))?;
-                                new_tokens.pop().unwrap();
-                                tokens.splice(i..=i + 1, new_tokens);
-                            }
-                            Err(e) => {
+                    Some(t) => {
+                        let (path, file) = match t.token_type {
+                            TokenType::String(file) => (file.clone(), file),
+                            TokenType::Identifier(file) => (format!("{}.ez", file), file),
+                            _ => {
                                 return Err(Error::new(
-                                    ErrorType::FileNotFound,
-                                    t.position.clone(),
-                                    format!("Could not find file `{}` ({})", file, e),
+                                    ErrorType::SyntaxError,
+                                    t.position,
+                                    "Expected a filename after `use`".to_owned(),
                                 ))
                             }
-                        },
-                        TokenType::Identifier(file) => {
-                            match fs::read_to_string(&format!("{}.ez", file)) {
-                                Ok(contents) => {
-                                    let mut new_tokens = lexer::lex(&contents, Rc::new(file))?;
-                                    new_tokens.pop().unwrap();
-                                    tokens.splice(i..=i + 1, new_tokens);
-                                }
-                                Err(e) => {
-                                    return Err(Error::new(
-                                        ErrorType::FileNotFound,
-                                        t.position.clone(),
-                                        format!("Could not find file `{}` ({})", file, e),
-                                    ))
-                                }
+                        };
+                        match read_included(&path, options) {
+                            // A file that is already included is left out, however its path is written
+                            Ok((_, path)) if !seen.insert(canonical(&path)) => {
+                                tokens.drain(i..=i + 1);
+                                (0, 2)
                             }
+                            Ok((contents, path)) => {
+                                let new_tokens = lex_included(&contents, file, &path, &tokens[i])?;
+                                included.push(path);
+                                let added = new_tokens.len();
+                                tokens.splice(i..=i + 1, new_tokens);
+                                (added, 2)
+                            }
+                            Err(e) => return Err(include_error(&file, e, t.position)),
                         }
-                        _ => {
-                            return Err(Error::new(
-                                ErrorType::SyntaxError,
-                                t.position.clone(),
-                                "Expected a filename after `use`".to_owned(),
-                            ))
-                        }
-                    },
+                    }
                 },
                 "replace" => {
                     let find = match tokens.get(i + 1).cloned() {
@@ -71,7 +383,34 @@ pub fn preprocess(mut tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
                         }
                         Some(t) => t,
                     };
-                    let replace = match tokens.get(i + 2).cloned() {
+                    // A string is lexed like the replacement, to replace a sequence of tokens
+                    let pattern = lex_replace_element(find.clone())?;
+                    if pattern.is_empty() {
+                        return Err(Error::new(
+                            ErrorType::PreprocessorError,
+                            find.position,
+                            "The find element of `replace` can't be empty".to_owned(),
+                        ));
+                    }
+                    // `!replace NAME(a, b) ...`, with no space before the `(`, is a macro
+                    // whose uses take arguments
+                    let params = match tokens.get(i + 2) {
+                        Some(t)
+                            if matches!(find.token_type, TokenType::Identifier(_))
+                                && t.token_type == TokenType::LParen
+                                && t.position.file == find.position.file
+                                && t.position.line_start == find.position.line_end
+                                && t.position.start == find.position.end =>
+                        {
+                            Some(parameters(&tokens, i + 2)?)
+                        }
+                        _ => None,
+                    };
+                    let replace_index = match params {
+                        Some((_, close)) => close + 1,
+                        None => i + 2,
+                    };
+                    let replace = match tokens.get(replace_index).cloned() {
                         None => {
                             return Err(Error::new(
                                 ErrorType::SyntaxError,
@@ -79,26 +418,70 @@ pub fn preprocess(mut tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
                                 "Expected replace element `replace`".to_owned(),
                             ))
                         }
-                        Some(t) => {
-                            if let TokenType::String(s) = t.token_type {
-                                lexer::lex(
-                                    &s,
-                                    Rc::new(format!(
-                                        "{}/replace  at {}:{}",
-                                        t.position.file, t.position.line_start, t.position.start
-                                    )),
-                                )?
-                            } else {
-                                vec![t]
+                        Some(t) => lex_replace_element(t)?,
+                    };
+                    if params.is_some() && replace.contains(&find) {
+                        return Err(Error::new(
+                            ErrorType::PreprocessorError,
+                            find.position.clone(),
+                            format!(
+                                "Macro {} is used in its own replacement, it would be expanded forever",
+                                find
+                            ),
+                        ));
+                    }
+                    let mut removed = replace_index + 1 - i;
+                    tokens.drain(i..=replace_index);
+                    let mut added = 0;
+                    let mut j = i;
+                    while j < tokens.len() {
+                        if !tokens[j..].starts_with(&pattern) {
+                            j += 1;
+                            continue;
+                        }
+                        let (expansion, end) = match params {
+                            Some((ref params, _)) => {
+                                let (args, close) = arguments(&tokens, j)?;
+                                if args.len() != params.len() {
+                                    let mut position = tokens[j].position.clone();
+                                    position.extend_to(&tokens[close].position);
+                                    return Err(Error::new(
+                                        ErrorType::PreprocessorError,
+                                        position,
+                                        format!(
+                                            "Macro {} takes {} arguments, but {} were given",
+                                            find,
+                                            params.len(),
+                                            args.len()
+                                        ),
+                                    ));
+                                }
+                                (substitute(&replace, params, &args), close)
                             }
+                            None => (replace.clone(), j + pattern.len() - 1),
+                        };
+                        let length = expansion.len();
+                        removed += end + 1 - j;
+                        added += length;
+                        tokens.splice(j..=end, expansion);
+                        replaced += length;
+                        if replaced > options.max_replaced_tokens {
+                            return Err(Error::new(
+                                ErrorType::PreprocessorError,
+                                directive,
+                                format!(
+                                    "The replacements added more than {} tokens, this `replace` may be making too many of them",
+                                    options.max_replaced_tokens
+                                ),
+                            ));
                         }
-                    };
-                    tokens.drain(i..=i + 2);
-                    for i in 0..tokens.len() {
-                        if tokens[i] == find {
-                            tokens.splice(i..=i, replace.clone());
+                        // The arguments can use the macro again. Replacements are skipped, so
+                        // that a pattern they contain isn't replaced over and over
+                        if params.is_none() {
+                            j += length;
                         }
                     }
+                    (added, removed)
                 }
                 "declare" => match tokens.get(i + 1).cloned() {
                     None => {
@@ -112,52 +495,151 @@ pub fn preprocess(mut tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
                         TokenType::Identifier(ident) => {
                             declared.insert(ident);
                             tokens.drain(i..=i + 1);
+                            (0, 2)
                         }
                         _ => {
                             return Err(Error::new(
                                 ErrorType::SyntaxError,
-                                t.position.clone(),
+                                t.position,
                                 "Expected an identifier after `declare`".to_owned(),
                             ))
                         }
                     },
                 },
-                "ifdeclared" => match tokens.get(i + 1).cloned() {
+                "undeclare" => match tokens.get(i + 1).cloned() {
                     None => {
                         return Err(Error::new(
                             ErrorType::SyntaxError,
                             tokens[i].position.clone(),
-                            "Expected an identifier after `declare`".to_owned(),
+                            "Expected an identifier after `undeclare`".to_owned(),
+                        ))
+                    }
+                    Some(t) => match t.token_type {
+                        TokenType::Identifier(ref ident) => {
+                            // Inside of a branch that is left out, the flag stays declared
+                            if ifs.iter().all(|(skipped, _, _)| skipped.is_none())
+                                && !declared.remove(ident)
+                            {
+                                warnings.push(Warning::new(
+                                    WarningKind::UnknownFlag,
+                                    t.position,
+                                    format!(
+                                        "`{}` isn't declared, so `undeclare` does nothing",
+                                        ident
+                                    ),
+                                ));
+                            }
+                            tokens.drain(i..=i + 1);
+                            (0, 2)
+                        }
+                        _ => {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                t.position,
+                                "Expected an identifier after `undeclare`".to_owned(),
+                            ))
+                        }
+                    },
+                },
+                "ifdeclared" | "ifnotdeclared" => match tokens.get(i + 1).cloned() {
+                    None => {
+                        return Err(Error::new(
+                            ErrorType::SyntaxError,
+                            tokens[i].position.clone(),
+                            format!("Expected an identifier after `{}`", stmt),
                         ))
                     }
                     Some(t) => match t.token_type {
                         TokenType::Identifier(ref ident) => {
-                            if declared.contains(ident) {
-                                ifs.push(None);
+                            // The tokens up to the `else` or `endif` are kept if the flag
+                            // is declared for `ifdeclared`, and if it isn't for `ifnotdeclared`
+                            if declared.contains(ident) == (stmt == "ifdeclared") {
+                                ifs.push((None, true, false));
                             } else {
-                                ifs.push(Some(i));
+                                ifs.push((Some(i), false, false));
                             }
                             tokens.drain(i..=i + 1);
+                            (0, 2)
                         }
                         _ => {
                             return Err(Error::new(
                                 ErrorType::SyntaxError,
                                 t.position,
-                                "Expected an identifier after `declare`".to_owned(),
+                                format!("Expected an identifier after `{}`", stmt),
                             ))
                         }
                     },
                 },
+                "elifdeclared" => {
+                    let ident = match tokens.get(i + 1).map(|t| &t.token_type) {
+                        Some(TokenType::Identifier(ident)) => ident.clone(),
+                        Some(_) => {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                tokens[i + 1].position.clone(),
+                                "Expected an identifier after `elifdeclared`".to_owned(),
+                            ))
+                        }
+                        None => {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                tokens[i].position.clone(),
+                                "Expected an identifier after `elifdeclared`".to_owned(),
+                            ))
+                        }
+                    };
+                    match ifs.last_mut() {
+                        Some((_, _, true)) => {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                tokens[i].position.clone(),
+                                "`elifdeclared` after `else`".to_owned(),
+                            ))
+                        }
+                        // No branch was kept yet, so the left out one ends here, and this one
+                        // is kept if the flag is declared
+                        Some((skipped @ Some(_), taken @ false, _)) => {
+                            let n = skipped.unwrap();
+                            let removed = i - n + 2;
+                            tokens.drain(n..=i + 1);
+                            i = n;
+                            if declared.contains(&ident) {
+                                *skipped = None;
+                                *taken = true;
+                            }
+                            (0, removed)
+                        }
+                        // A branch was kept, so this one and the ones after it are left out
+                        Some((skipped, _, _)) => {
+                            skipped.get_or_insert(i);
+                            tokens.drain(i..=i + 1);
+                            (0, 2)
+                        }
+                        None => {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                tokens[i].position.clone(),
+                                "`elifdeclared` without `ifdeclared`".to_owned(),
+                            ))
+                        }
+                    }
+                }
                 "else" => {
-                    if let Some(idx) = ifs.last_mut() {
-                        match idx {
-                            Some(n) => {
+                    if let Some((skipped, taken, seen_else)) = ifs.last_mut() {
+                        *seen_else = true;
+                        match skipped {
+                            Some(n) if !*taken => {
+                                let removed = i - *n + 1;
                                 tokens.drain(*n..=i);
-                                *idx = None;
+                                i = *n;
+                                *skipped = None;
+                                *taken = true;
+                                (0, removed)
                             }
-                            None => {
-                                *idx = Some(i);
+                            _ => {
+                                skipped.get_or_insert(i);
                                 tokens.remove(i);
+                                (0, 1)
                             }
                         }
                     } else {
@@ -169,14 +651,18 @@ pub fn preprocess(mut tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
                     }
                 }
                 "endif" => {
-                    if let Some(idx) = ifs.pop() {
-                        match idx {
+                    if let Some((skipped, _, _)) = ifs.pop() {
+                        match skipped {
                             Some(n) => {
+                                let removed = i - n + 1;
                                 tokens.drain(n..=i);
+                                i = n;
+                                (0, removed)
                             }
 
                             None => {
                                 tokens.remove(i);
+                                (0, 1)
                             }
                         }
                     } else {
@@ -208,17 +694,59 @@ pub fn preprocess(mut tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
                         },
                     };
                     tokens.drain(i..=i + 1);
-                    if matches!(ifs.last(), Some(None) | None) {
+                    if matches!(ifs.last(), Some((None, _, _)) | None) {
                         return Err(Error::new(
                             ErrorType::PreprocessorError,
                             tokens[i].position.clone(),
                             msg,
                         ));
                     }
+                    (0, 2)
+                }
+                "warning" => {
+                    let msg = match tokens.get(i + 1).cloned() {
+                        Some(Token {
+                            token_type: TokenType::String(s),
+                            ..
+                        }) => s,
+                        Some(t) => {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                t.position,
+                                "Expected a warning message after `warning`".to_owned(),
+                            ))
+                        }
+                        None => {
+                            return Err(Error::new(
+                                ErrorType::SyntaxError,
+                                tokens[i].position.clone(),
+                                "Expected a warning message after `warning`".to_owned(),
+                            ))
+                        }
+                    };
+                    // Warnings of a branch that is left out are dropped, like its errors
+                    if ifs.iter().all(|(skipped, _, _)| skipped.is_none()) {
+                        let mut position = tokens[i].position.clone();
+                        position.extend_to(&tokens[i + 1].position);
+                        warnings.push(Warning::new(WarningKind::Directive, position, msg));
+                    }
+                    tokens.drain(i..=i + 1);
+                    (0, 2)
                 }
                 _ => unreachable!(),
+            };
+            if options.trace_preprocessor {
+                trace.push(format!(
+                    "{}:{}:{}: !{} added {} tokens, removed {}",
+                    directive.file, directive.line_start, directive.start, stmt, added, removed
+                ));
             }
         } else {
+            match tokens[i].token_type {
+                TokenType::LCurly => depth += 1,
+                TokenType::RCurly => depth = depth.saturating_sub(1),
+                _ => (),
+            }
             i += 1;
         }
     }
@@ -233,3 +761,287 @@ pub fn preprocess(mut tokens: Vec<Token>) -> Result<Vec<Token>, Error> {
 
     Ok(tokens)
 }
+
+/// The tokens of the find or the replace element of `replace`. A string is lexed into the
+/// tokens it holds, any other token stands for itself
+fn lex_replace_element(element: Token) -> Result<Vec<Token>, Error> {
+    match element.token_type {
+        TokenType::String(s) => {
+            let position = &element.position;
+            let mut tokens = lexer::lex(
+                &s,
+                FileName::new(&format!(
+                    "{}/replace  at {}:{}",
+                    position.file, position.line_start, position.start
+                )),
+            )?;
+            // The end of the string isn't the end of the file
+            tokens.pop();
+            Ok(tokens)
+        }
+        _ => Ok(vec![element]),
+    }
+}
+
+/// Reads the parameters of a macro, like `(a, b)`, from the `(` at `tokens[open]`.
+/// Returns them with the index of the `)`
+fn parameters(tokens: &[Token], open: usize) -> Result<(Vec<Token>, usize), Error> {
+    let mut params: Vec<Token> = vec![];
+    let mut k = open + 1;
+    if tokens[k].token_type == TokenType::RParen {
+        return Ok((params, k));
+    }
+    // The tokens end with `Eof`, which stops the loop with an error
+    loop {
+        let param = &tokens[k];
+        if !matches!(param.token_type, TokenType::Identifier(_)) {
+            return Err(Error::new(
+                ErrorType::SyntaxError,
+                param.position.clone(),
+                format!("Expected a parameter name, found {}", param),
+            ));
+        }
+        if params.contains(param) {
+            return Err(Error::new(
+                ErrorType::Redefinition,
+                param.position.clone(),
+                format!("Parameter {} is already used", param),
+            ));
+        }
+        params.push(param.clone());
+        let next = &tokens[k + 1];
+        match next.token_type {
+            TokenType::Comma => k += 2,
+            TokenType::RParen => return Ok((params, k + 1)),
+            _ => {
+                return Err(Error::new(
+                    ErrorType::SyntaxError,
+                    next.position.clone(),
+                    format!("Expected ',' or ')' after parameter, found {}", next),
+                ))
+            }
+        }
+    }
+}
+
+/// Collects the arguments of the use of a macro at `tokens[name]`, split at the commas that
+/// aren't inside of brackets. Returns them with the index of the `)` closing them
+fn arguments(tokens: &[Token], name: usize) -> Result<(Vec<Vec<Token>>, usize), Error> {
+    let macro_name = &tokens[name];
+    if tokens.get(name + 1).map(|t| &t.token_type) != Some(&TokenType::LParen) {
+        return Err(Error::new(
+            ErrorType::PreprocessorError,
+            macro_name.position.clone(),
+            format!("Expected the arguments of macro {} after it", macro_name),
+        ));
+    }
+    let mut args = vec![vec![]];
+    let mut depth = 0usize;
+    for (k, token) in tokens.iter().enumerate().skip(name + 2) {
+        match token.token_type {
+            TokenType::LParen | TokenType::LSquare | TokenType::LCurly => depth += 1,
+            TokenType::RParen if depth == 0 => {
+                // `NAME()` has no arguments, rather than one empty one
+                if args.len() == 1 && args[0].is_empty() {
+                    args.clear();
+                }
+                return Ok((args, k));
+            }
+            TokenType::RParen | TokenType::RSquare | TokenType::RCurly => {
+                depth = depth.saturating_sub(1)
+            }
+            TokenType::Comma if depth == 0 => {
+                args.push(vec![]);
+                continue;
+            }
+            TokenType::Eof => break,
+            _ => (),
+        }
+        args.last_mut().unwrap().push(token.clone());
+    }
+    Err(Error::new(
+        ErrorType::SyntaxError,
+        macro_name.position.clone(),
+        format!(
+            "Expected ')' to close the arguments of macro {}",
+            macro_name
+        ),
+    ))
+}
+
+/// The replacement of a macro, with its parameters replaced by the arguments of a use of it
+fn substitute(replace: &[Token], params: &[Token], args: &[Vec<Token>]) -> Vec<Token> {
+    replace
+        .iter()
+        .flat_map(|token| match params.iter().position(|p| p == token) {
+            Some(n) => args[n].clone(),
+            None => vec![token.clone()],
+        })
+        .collect()
+}
+
+/// Includes the file `prelude` at the start of `tokens`, like a `use` on line 0 of the main file
+pub fn include_prelude(tokens: &mut Vec<Token>, prelude: &str) -> Result<(), Error> {
+    let file = tokens.last().unwrap().position.file.clone();
+    let directive = Token::new(
+        TokenType::PreprocessorStatement("use".to_owned()),
+        0,
+        0,
+        0,
+        file,
+    );
+    match crate::read_source(prelude) {
+        Ok(contents) => {
+            let new_tokens = lex_included(
+                &contents,
+                prelude.to_owned(),
+                Path::new(prelude),
+                &directive,
+            )?;
+            tokens.splice(0..0, new_tokens);
+            Ok(())
+        }
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Err(Error::new(
+            ErrorType::InvalidEncoding,
+            directive.position,
+            e.to_string(),
+        )),
+        Err(e) => Err(Error::new(
+            ErrorType::FileNotFound,
+            directive.position,
+            format!("Could not find prelude `{}` ({})", prelude, e),
+        )),
+    }
+}
+
+/// Reads a file included by `use`, looking for it in the include paths if it isn't found
+/// relative to the current directory. Returns its contents and the path it was read from
+/// # Examples
+/// The first of the include paths that has the file is used
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let dir = std::env::temp_dir().join("ezlang_include_paths_example");
+/// std::fs::create_dir_all(dir.join("a")).unwrap();
+/// std::fs::create_dir_all(dir.join("b")).unwrap();
+/// std::fs::write(dir.join("a/util.ez"), "let u = 1").unwrap();
+/// std::fs::write(dir.join("b/util.ez"), "let u = 2").unwrap();
+/// let options = Options {
+///     include_paths: vec![dir.join("a").to_string_lossy().into_owned(), dir.join("b").to_string_lossy().into_owned()],
+///     ..Options::default()
+/// };
+/// let mut stats = Stats::default();
+/// ezlang::run_with_options("!use util\nezout u", "main.ez".to_string(), &options, &mut stats).unwrap();
+/// assert_eq!(stats.included_files, [dir.join("a/util.ez")]);
+/// ```
+fn read_included(file: &str, options: &Options) -> io::Result<(String, PathBuf)> {
+    let error = match crate::read_source(file) {
+        Ok(contents) => return Ok((contents, PathBuf::from(file))),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => return Err(e),
+        Err(e) => e,
+    };
+    if Path::new(file).is_relative() {
+        for dir in &options.include_paths {
+            let path = Path::new(dir).join(file);
+            match crate::read_source(&path) {
+                Ok(contents) => return Ok((contents, path)),
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => return Err(e),
+                Err(_) => (),
+            }
+        }
+    }
+    Err(error)
+}
+
+/// The path `path` stands for, used to tell whether two paths are the same file.
+/// Paths that can't be resolved, like the ones of files that don't exist, are made absolute
+/// and have their `.` and `..` removed instead
+/// # Examples
+/// A file used through two different paths is only included once
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let dir = std::env::temp_dir().join("ezlang_include_once_example");
+/// std::fs::create_dir_all(dir.join("lib")).unwrap();
+/// std::fs::write(dir.join("lib/a.ez"), "ez a() -> int { return 1 }").unwrap();
+/// let options = Options { include_paths: vec![dir.to_string_lossy().into_owned()], ..Options::default() };
+/// let code = "!use \"lib/a.ez\"\n!use \"./lib/../lib/a.ez\"\nezout a()";
+/// let mut stats = Stats::default();
+/// assert!(ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut stats).is_ok());
+/// assert_eq!(stats.included_files.len(), 1);
+/// ```
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+    let mut normalized = env::current_dir().unwrap_or_default();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Converts the error of reading a file included by `use` into an [`Error`] at the file name
+fn include_error(file: &str, e: io::Error, position: Position) -> Error {
+    if e.kind() == io::ErrorKind::InvalidData {
+        Error::new(ErrorType::InvalidEncoding, position, e.to_string())
+    } else {
+        Error::new(
+            ErrorType::FileNotFound,
+            position,
+            format!("Could not find file `{}` ({})", file, e),
+        )
+    }
+}
+
+/// Lexes a file included by `use`, marking every token as included from the directive.
+/// An empty file includes nothing, and an error in the file is marked the same way,
+/// so it is reported along with the chain of `use`s that led to it. The error also gets
+/// a label at the directive with the absolute `path` the file was read from, since files
+/// with the same name can be in several include paths
+/// # Examples
+/// ```
+/// use ezlang::utils::Options;
+///
+/// let dir = std::env::temp_dir().join("ezlang_include_errors_example");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("bad.ez"), "let a = 1\nlet c = a @ a").unwrap();
+/// let options = Options { include_paths: vec![dir.to_string_lossy().into_owned()], ..Options::default() };
+/// let err = ezlang::run_with_options("ezout 1\n!use bad", "main.ez".to_string(), &options, &mut Default::default()).unwrap_err();
+/// assert_eq!((err.position.file.as_str(), err.position.line_start), ("bad", 2));
+/// assert_eq!(err.position.included_from.unwrap().line_start, 2);
+/// ```
+fn lex_included(
+    contents: &str,
+    file: String,
+    path: &Path,
+    directive: &Token,
+) -> Result<Vec<Token>, Error> {
+    let included_from = Rc::new(directive.position.clone());
+    let mut tokens = lexer::lex(contents, FileName::new(&file)).map_err(|mut e| {
+        e.position.included_from = Some(included_from.clone());
+        for (position, _) in &mut e.labels {
+            position.included_from = Some(included_from.clone());
+        }
+        e.with_label(
+            directive.position.clone(),
+            format!("`{}` was read from {}", file, canonical(path).display()),
+        )
+    })?;
+    if tokens
+        .last()
+        .is_some_and(|t| t.token_type == TokenType::Eof)
+    {
+        tokens.pop();
+    }
+    for token in &mut tokens {
+        token.position.included_from = Some(included_from.clone());
+    }
+    Ok(tokens)
+}
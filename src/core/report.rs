@@ -0,0 +1,273 @@
+use std::cmp::Reverse;
+
+use crate::{
+    core::{lexer, pipeline::Pipeline},
+    utils::{
+        ast::{Node, Type},
+        diag::{Error, FileName, Position},
+        token::TokenType,
+        Options, Stats,
+    },
+};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; font-family: monospace; white-space: pre; tab-size: 4; }
+td { padding: 0 0.5em; vertical-align: top; }
+td.line, td.count { color: #888; text-align: right; user-select: none; }
+td.count { border-right: 1px solid #ccc; }
+td.hot { color: #000; }
+.keyword { color: #a626a4; font-weight: bold; }
+.number, .char { color: #986801; }
+.string { color: #50a14f; }
+.comment { color: #a0a1a7; font-style: italic; }
+.directive { color: #c18401; }
+.operator { color: #0184bc; }
+[data-e] { cursor: help; }
+.hover { background: #fff3b0; }
+";
+
+const SCRIPT: &str = "
+for (const token of document.querySelectorAll('[data-e]')) {
+    const expression = token.dataset.e.split(' ')[0];
+    const parts = document.querySelectorAll('[data-e~=\"' + expression + '\"]');
+    token.addEventListener('mouseenter', () => parts.forEach(p => p.classList.add('hover')));
+    token.addEventListener('mouseleave', () => parts.forEach(p => p.classList.remove('hover')));
+}
+";
+
+/// Compiles `contents` and writes it as a page of HTML, with inline styles and scripts, for
+/// reading the code with what the compiler knows about it:
+/// * The tokens are highlighted by their kind, as the lexer reads them
+/// * Hovering a token shows the type of the innermost expression it is part of, and highlights
+///   the rest of the expression
+/// * Every line is preceded by the number of IR instructions generated for it, see
+///   [`Stats::source_map`]. The instructions of the body of a function are counted once for
+///   every call of the function
+///
+/// Only the main file is shown, included files aren't. Statistics about the compilation are
+/// recorded into `stats`
+/// # Errors
+/// Returns the error of compiling the code, if there is one
+/// # Examples
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "// squares a number
+/// ez square(x: int) -> int { return x * x }
+/// let name = \"<b>\"
+/// ezout square(3) + 1, name";
+/// let options = Options::default();
+/// let html = ezlang::core::report::html(code, "main.ez".to_string(), &options, &mut Stats::default()).unwrap();
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// assert!(!html.contains("<link") && !html.contains("src="));
+///
+/// // Highlighting of the tokens
+/// assert!(html.contains(r#"<span class="comment">// squares a number</span>"#));
+/// assert!(html.contains(r#"<span class="keyword">ez</span>"#));
+/// assert!(html.contains(r#"<span class="string" title="*point char" data-e="6">&quot;&lt;b&gt;&quot;</span>"#));
+///
+/// // Types of the expressions, the innermost one of a token first
+/// assert!(html.contains(r#"<span class="ident" title="(int) -&gt; int" data-e="0">square</span>"#));
+/// assert!(html.contains(r#"<span class="ident" title="int" data-e="1">x</span>"#));
+/// assert!(html.contains(r#"<span class="operator" title="int" data-e="2">*</span>"#));
+/// assert!(html.contains(r#"<span class="ident" title="*point char" data-e="5">name</span>"#));
+/// assert!(html.contains(r#"<span class="number" title="int" data-e="9 8 7">3</span>"#));
+///
+/// // IR instructions generated for each line, the comment has none
+/// let counts: Vec<_> = html
+///     .lines()
+///     .filter_map(|line| line.split(r#"<td class="count"#).nth(1))
+///     .map(|cell| cell.split(['>', '<']).nth(1).unwrap())
+///     .collect();
+/// assert_eq!(counts.len(), 4);
+/// assert_eq!(counts[0], "");
+/// assert!(counts[1..].iter().all(|count| count.parse::<usize>().unwrap() > 0));
+///
+/// let err = ezlang::core::report::html("ezout x", "main.ez".to_string(), &options, &mut Stats::default());
+/// assert!(err.is_err());
+/// ```
+pub fn html(
+    contents: &str,
+    filename: String,
+    options: &Options,
+    stats: &mut Stats,
+) -> Result<String, Error> {
+    let mut types = vec![];
+    let file = FileName::new(&filename);
+    let bf_code = Pipeline::new(options.clone())
+        .after_parse(|ast| {
+            expression_types(ast, file.clone(), &mut types);
+            Ok(())
+        })
+        .run(contents, filename.clone(), stats)?;
+    let (tokens, comments) = lexer::lex_with_comments(contents, file.clone())?;
+    // Numbered in the order they start, so that an expression comes before the ones inside of it
+    types.sort_by_key(|(pos, _)| {
+        (
+            (pos.line_start, pos.start),
+            Reverse((pos.line_end, pos.end)),
+        )
+    });
+
+    let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+    let contents = contents.strip_suffix('\n').unwrap_or(contents);
+    let lines: Vec<Vec<char>> = contents
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).chars().collect())
+        .collect();
+    // The spans of every line, as what each of its characters is part of
+    let mut marks: Vec<Vec<Option<usize>>> = lines.iter().map(|l| vec![None; l.len()]).collect();
+    let mut spans = vec![];
+    let mut mark = |pos: &Position, span: String| {
+        for line in pos.line_start..=pos.line_end {
+            let Some(marks) = marks.get_mut(line - 1) else {
+                break;
+            };
+            let start = if line == pos.line_start {
+                pos.start - 1
+            } else {
+                0
+            };
+            let end = if line == pos.line_end {
+                pos.end - 1
+            } else {
+                marks.len()
+            };
+            for c in marks.iter_mut().take(end).skip(start) {
+                *c = Some(spans.len());
+            }
+        }
+        spans.push(span);
+    };
+    for token in &tokens {
+        let class = match token.token_type {
+            TokenType::Eof => continue,
+            TokenType::Keyword(_) => "keyword",
+            TokenType::Identifier(_) => "ident",
+            TokenType::Number(_) => "number",
+            TokenType::Char(_) => "char",
+            TokenType::String(_) => "string",
+            TokenType::PreprocessorStatement(_) => "directive",
+            _ => "operator",
+        };
+        let containing: Vec<_> = types
+            .iter()
+            .enumerate()
+            .filter(|(_, (pos, _))| pos.contains(&token.position))
+            .rev()
+            .collect();
+        let span = match containing.first() {
+            Some((_, (_, t))) => {
+                let ids: Vec<_> = containing.iter().map(|(i, _)| i.to_string()).collect();
+                format!(
+                    r#"<span class="{}" title="{}" data-e="{}">"#,
+                    class,
+                    escape(&t.to_string()),
+                    ids.join(" ")
+                )
+            }
+            None => format!(r#"<span class="{}">"#, class),
+        };
+        mark(&token.position, span);
+    }
+    for (pos, _) in &comments {
+        let mut pos = pos.clone();
+        pos.end = lines.get(pos.line_start - 1).map_or(0, |l| l.len()) + 1;
+        mark(&pos, r#"<span class="comment">"#.to_string());
+    }
+
+    let mut counts = vec![0; lines.len()];
+    for pos in stats.source_map.iter().flatten() {
+        if pos.file == file && pos.included_from.is_none() {
+            if let Some(count) = counts.get_mut(pos.line_start - 1) {
+                *count += 1;
+            }
+        }
+    }
+    let hot = counts.iter().max().copied().unwrap_or(0) / 2;
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n<style>{1}</style>\n</head>\n<body>\n<h1>{0}</h1>\n<p>{2} IR instructions, {3} brainfuck instructions</p>\n<table>\n",
+        escape(&filename),
+        STYLE,
+        stats.source_map.len(),
+        bf_code.len()
+    );
+    for (i, (line, marks)) in lines.iter().zip(marks).enumerate() {
+        let count = match counts[i] {
+            0 => String::new(),
+            n => n.to_string(),
+        };
+        let hot = if counts[i] > 0 && counts[i] >= hot {
+            " hot"
+        } else {
+            ""
+        };
+        html += &format!(
+            "<tr><td class=\"line\">{}</td><td class=\"count{}\">{}</td><td>",
+            i + 1,
+            hot,
+            count
+        );
+        let mut start = 0;
+        while start < line.len() {
+            let end = (start..line.len())
+                .find(|&c| marks[c] != marks[start])
+                .unwrap_or(line.len());
+            let text = escape(&line[start..end].iter().collect::<String>());
+            match marks[start] {
+                Some(span) => html += &format!("{}{}</span>", spans[span], text),
+                None => html += &text,
+            }
+            start = end;
+        }
+        html += "</td></tr>\n";
+    }
+    html += &format!("</table>\n<script>{}</script>\n</body>\n</html>\n", SCRIPT);
+    Ok(html)
+}
+
+/// Finds the types of the expressions of the main file, and of the variables, parameters and
+/// functions where they are declared
+fn expression_types(node: &Node, file: FileName, types: &mut Vec<(Position, Type)>) {
+    let mut add = |pos: Position, t: Type| {
+        if pos.file == file && pos.included_from.is_none() {
+            types.push((pos, t))
+        }
+    };
+    match node {
+        Node::VarAssign(name, _, t) => add(name.position.clone(), t.clone()),
+        Node::FuncDef(name, params, _, ret, _) => {
+            let param_types = params.iter().map(|(_, t)| t.clone()).collect();
+            add(
+                name.position.clone(),
+                Type::Function(param_types, Box::new(ret.clone())),
+            );
+            for (param, t) in params {
+                add(param.position.clone(), t.clone());
+            }
+        }
+        Node::Lambda(params, ..) => {
+            add(node.position(), node.get_type());
+            for (param, t) in params {
+                add(param.position.clone(), t.clone());
+            }
+        }
+        Node::Return(..) => (),
+        _ => match node.get_type() {
+            Type::None => (),
+            t => add(node.position(), t),
+        },
+    }
+    for child in node.children() {
+        expression_types(child, file.clone(), types);
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+
+use crate::utils::{
+    ir::{Instruction, Instructions},
+    Options,
+};
+
+use super::interpreter;
+
+/// What running a program at `-O0` and at its optimization level showed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    /// Both printed the same output
+    Same,
+    /// The program has `ezraw` code, which isn't compared
+    Skipped,
+    /// The outputs are different
+    Differs(Divergence),
+}
+
+/// The outputs of a program that changed when it was optimized
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The optimization level the output changed at
+    pub opt_level: u8,
+    /// The output of the code at `-O0`
+    pub expected: Vec<u8>,
+    /// The output of the optimized code
+    pub actual: Vec<u8>,
+    /// The index of the first byte that is different, or missing from one of the outputs
+    pub offset: usize,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let byte = |output: &[u8]| match output.get(self.offset) {
+            Some(byte) => format!("{:?}", char::from(*byte)),
+            None => String::from("the end of the output"),
+        };
+        writeln!(
+            f,
+            "The output at -O{} is different from the output at -O0 at byte {}: {} instead of {}",
+            self.opt_level,
+            self.offset,
+            byte(&self.actual),
+            byte(&self.expected)
+        )?;
+
+        let lines = |output: &[u8]| {
+            String::from_utf8_lossy(output)
+                .split('\n')
+                .map(|line| line.escape_debug().to_string())
+                .collect::<Vec<_>>()
+        };
+        let (expected, actual) = (lines(&self.expected), lines(&self.actual));
+        let width = expected.iter().map(|line| line.chars().count()).max();
+        let width = width.unwrap_or(0).max(3);
+        write!(f, "  {:<width$} | -O{}", "-O0", self.opt_level)?;
+        for i in 0..expected.len().max(actual.len()) {
+            let left = expected.get(i).map_or("", String::as_str);
+            let right = actual.get(i).map_or("", String::as_str);
+            let marker = if expected.get(i) == actual.get(i) {
+                ' '
+            } else {
+                '>'
+            };
+            write!(f, "\n{} {:<width$} | {}", marker, left, right)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the intermediate code optimized at `-O0` and at the level of `options` with the passed
+/// input, and compares what they print with [`compare`]. Programs with `ezraw` code are skipped,
+/// since it is copied into the output as it is and can rely on anything about the code around it
+/// # Errors
+/// Returns the errors of [`interpreter::interpret`], saying which level they happened at
+/// # Examples
+/// ```
+/// use ezlang::core::verify::{verify, Verification};
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "let a = ezin\nezout a, a + 1\nezascii '\\n'\nezout a * 2";
+/// let options = Options::default();
+/// let ir = ezlang::compile_ir(code, "main.ez".to_string(), &options, &mut Stats::default()).unwrap();
+/// assert_eq!(verify(&ir, b"7\n", &options), Ok(Verification::Same));
+///
+/// let ir = ezlang::compile_ir("ezraw \"+.\"", "main.ez".to_string(), &options, &mut Stats::default()).unwrap();
+/// assert_eq!(verify(&ir, b"", &options), Ok(Verification::Skipped));
+/// ```
+pub fn verify(
+    code: &Instructions,
+    input: &[u8],
+    options: &Options,
+) -> Result<Verification, String> {
+    if code
+        .0
+        .iter()
+        .any(|(_, instruction)| matches!(instruction, Instruction::Raw(_)))
+    {
+        return Ok(Verification::Skipped);
+    }
+
+    let unoptimized = Options {
+        opt_level: 0,
+        ..options.clone()
+    };
+    compare(
+        &crate::to_brainfuck_with_options(code, &unoptimized),
+        &crate::to_brainfuck_with_options(code, options),
+        input,
+        options.opt_level,
+    )
+}
+
+/// Runs the brainfuck code of a program at `-O0` and the same program optimized at
+/// `opt_level` with the passed input, and compares what they print
+/// # Errors
+/// Returns the errors of [`interpreter::interpret`], saying which level they happened at
+/// # Examples
+/// ```
+/// use ezlang::core::verify::{compare, Verification};
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "let a = ezin\nezout a, a + 1\nezascii '\\n'\nezout a * 2";
+/// let ir = ezlang::compile_ir(code, "main.ez".to_string(), &Options::default(), &mut Stats::default()).unwrap();
+/// let unoptimized = ezlang::to_brainfuck_with_options(&ir, &Options { opt_level: 0, ..Options::default() });
+///
+/// // A broken optimization is caught, with the byte the outputs start to differ at
+/// let broken = ezlang::to_brainfuck(&ir).replace("+.", ".");
+/// let Ok(Verification::Differs(divergence)) = compare(&unoptimized, &broken, b"7\n", 1) else {
+///     panic!("the outputs should differ");
+/// };
+/// assert_eq!(divergence.expected, b"7 8\n14");
+/// assert_eq!(divergence.offset, 1);
+/// let report = divergence.to_string();
+/// assert!(report.starts_with("The output at -O1 is different from the output at -O0 at byte 1"));
+/// assert!(report.lines().any(|line| line == "> 7 8 | 7\\u{1f}8\\t14"));
+/// ```
+pub fn compare(
+    unoptimized: &str,
+    optimized: &str,
+    input: &[u8],
+    opt_level: u8,
+) -> Result<Verification, String> {
+    let output = |bf_code: &str, opt_level: u8| {
+        let mut output = vec![];
+        interpreter::interpret(bf_code, &mut &input[..], &mut output)
+            .map_err(|e| format!("{} at -O{}", e, opt_level))?;
+        Ok::<_, String>(output)
+    };
+    let expected = output(unoptimized, 0)?;
+    let actual = output(optimized, opt_level)?;
+    if expected == actual {
+        return Ok(Verification::Same);
+    }
+    let offset = expected
+        .iter()
+        .zip(&actual)
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| expected.len().min(actual.len()));
+    Ok(Verification::Differs(Divergence {
+        opt_level,
+        expected,
+        actual,
+        offset,
+    }))
+}
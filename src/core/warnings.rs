@@ -0,0 +1,289 @@
+use crate::utils::{
+    ast::{Node, Reference, Scope, Type, VarType},
+    diag::{Error, ErrorType, Severity, Warning, WarningKind},
+    token::{Token, TokenType},
+    Options,
+};
+
+use super::lexer::Comments;
+
+/// Finds the warnings of a program, from its unexpanded AST and its global scope, and reports
+/// them along with the ones that were `found` before, like the ones of the preprocessor.
+/// Warnings that are turned off, or allowed by a `// ez: allow(kind)` comment on the line
+/// before them, are left out. Only the comments of the main file can allow warnings.
+/// # Errors
+/// Returns the earliest warning of a kind whose severity is [`Severity::Error`] as an error
+/// # Examples
+/// ```
+/// use ezlang::utils::diag::FileName;
+/// use ezlang::core::{lexer, parser, warnings};
+/// use ezlang::utils::{diag::{Severity, WarningKind}, Options};
+///
+/// let code = "let a = 1\nif (false) ezout 1\n// ez: allow(unused)\nlet b = 2";
+/// let check = |options: &Options| {
+///     let (tokens, comments) = lexer::lex_with_comments(code, FileName::new("main.ez")).unwrap();
+///     let (ast, scope) = parser::analyze(tokens).unwrap();
+///     warnings::check(&ast, &scope, &comments, vec![], options)
+/// };
+///
+/// let kinds = |options| -> Vec<_> { check(&options).unwrap().iter().map(|w| w.kind).collect() };
+/// assert_eq!(kinds(Options::default()), [WarningKind::Unused, WarningKind::Unreachable]);
+/// let options = Options {
+///     warnings: vec![(WarningKind::Unused, Severity::Off), (WarningKind::ConstantCondition, Severity::Warn)],
+///     ..Options::default()
+/// };
+/// assert_eq!(kinds(options), [WarningKind::ConstantCondition, WarningKind::Unreachable]);
+///
+/// let options = Options {
+///     warnings: vec![(WarningKind::Unreachable, Severity::Error)],
+///     ..Options::default()
+/// };
+/// assert_eq!(check(&options).unwrap_err().position.line_start, 2);
+/// ```
+/// A number is a cell from 0 to 255, so comparing it with 0 or 255 can have the same result
+/// for every number. The warning points at the operator, with labels on both sides
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let warnings = |condition: &str| {
+///     let code = format!("let x = ezin\nif ({}) ezout 1", condition);
+///     let mut stats = Stats::default();
+///     ezlang::run_with_options(&code, "main.ez".to_string(), &Options::default(), &mut stats).unwrap();
+///     stats.warnings.into_iter().map(|w| w.details).collect::<Vec<_>>()
+/// };
+/// let always = |value| format!("This comparison is always {}, since a number is a cell from 0 to 255", value);
+/// assert_eq!(warnings("x < 0"), [always("false")]);
+/// assert_eq!(warnings("0 > x"), [always("false")]);
+/// assert_eq!(warnings("x > 255"), [always("false")]);
+/// assert_eq!(warnings("x <= 255"), [always("true")]);
+/// assert_eq!(warnings("x >= -256"), [format!("{}, and -256 is 0", always("true"))]);
+/// for condition in ["x < 1", "x > 254", "x == 255", "x != 255", "x == 0", "x != 0", "x < -1"] {
+///     assert!(warnings(condition).is_empty(), "{}", condition);
+/// }
+///
+/// let mut stats = Stats::default();
+/// ezlang::run_with_options("let x = ezin\nezout x < 0", "main.ez".to_string(), &Options::default(), &mut stats).unwrap();
+/// let warning = &stats.warnings[0];
+/// assert_eq!((warning.position.start, warning.position.end), (9, 10));
+/// let labels: Vec<_> = warning.labels.iter().map(|(pos, label)| (pos.start, label.as_str())).collect();
+/// assert_eq!(labels, [(7, "this is from 0 to 255"), (11, "this is 0")]);
+///
+/// // 256 and above can't be written, and chars and bools can't be compared with numbers
+/// assert!(ezlang::run("let x = ezin\nezout x == 256", "main.ez".to_string()).is_err());
+/// assert!(ezlang::run("let c = 'a'\nezout c == 300", "main.ez".to_string()).is_err());
+/// assert!(ezlang::run("let b = true\nezout b == 2", "main.ez".to_string()).is_err());
+/// ```
+pub fn check(
+    ast: &Node,
+    scope: &Scope,
+    comments: &Comments,
+    found: Vec<Warning>,
+    options: &Options,
+) -> Result<Vec<Warning>, Error> {
+    let mut warnings = found;
+    unused(scope, &scope.all_references(), &mut warnings);
+    conditions(ast, &mut warnings);
+    concatenations(ast, false, &mut warnings);
+    comparisons(ast, &mut warnings);
+    // A `warning` directive is meant for the files including the one it is in
+    warnings.retain(|w| {
+        (w.position.included_from.is_none() || w.kind == WarningKind::Directive)
+            && !allowed(w, comments)
+    });
+    warnings.sort_by_key(|w| (w.position.line_start, w.position.start));
+    if let Some(w) = warnings
+        .iter()
+        .find(|w| options.severity(w.kind) == Severity::Error)
+    {
+        return Err(Error::new(
+            ErrorType::Warning,
+            w.position.clone(),
+            format!("{} ({} is set to error)", w.details, w.kind.name()),
+        ));
+    }
+    warnings.retain(|w| options.severity(w.kind) == Severity::Warn);
+    Ok(warnings)
+}
+
+/// Checks whether a comment on the line before the warning allows its kind
+fn allowed(warning: &Warning, comments: &Comments) -> bool {
+    comments.iter().any(|(position, comment)| {
+        position.file == warning.position.file
+            && position.line_start + 1 == warning.position.line_start
+            && comment
+                .trim()
+                .strip_prefix("ez: allow(")
+                .and_then(|kinds| kinds.strip_suffix(')'))
+                .is_some_and(|kinds| {
+                    kinds
+                        .split(',')
+                        .any(|kind| kind.trim() == warning.kind.name())
+                })
+    })
+}
+
+/// Finds the variables that are declared but never used, in `scope` and the scopes inside of it.
+/// Variables starting with `_`, and the hidden ones the parser makes, are left out
+fn unused(scope: &Scope, references: &[Reference], warnings: &mut Vec<Warning>) {
+    for var in &scope.defined {
+        if let VarType::Variable(_, token, _) = var {
+            let name = match token.token_type {
+                TokenType::Identifier(ref name) => name,
+                _ => continue,
+            };
+            if name.starts_with('_') || name.starts_with('@') {
+                continue;
+            }
+            if !references.iter().any(|r| r.definition == token.position) {
+                warnings.push(Warning::new(
+                    WarningKind::Unused,
+                    token.position.clone(),
+                    format!("Variable {} is never used", token),
+                ));
+            }
+        }
+    }
+    for scope in &scope.scopes {
+        unused(scope, references, warnings);
+    }
+}
+
+/// Finds the conditions that are a `true` or `false` literal, and the code they stop from running.
+/// `while (true)` is left alone, since it is how a loop that never ends is written
+fn conditions(node: &Node, warnings: &mut Vec<Warning>) {
+    let dead: Vec<(&Node, bool, Option<&Node>)> = match node {
+        Node::If(cond, then, else_, _) => match constant(cond) {
+            Some(true) => vec![(cond, true, else_.as_deref())],
+            Some(false) => vec![(cond, false, Some(then))],
+            None => vec![],
+        },
+        Node::Ternary(cond, then, else_, ..) => match constant(cond) {
+            Some(true) => vec![(cond, true, Some(else_))],
+            Some(false) => vec![(cond, false, Some(then))],
+            None => vec![],
+        },
+        Node::While(cond, body, _) if constant(cond) == Some(false) => {
+            vec![(cond, false, Some(body))]
+        }
+        _ => vec![],
+    };
+    for (cond, value, unreachable) in dead {
+        warnings.push(Warning::new(
+            WarningKind::ConstantCondition,
+            cond.position(),
+            format!("This condition is always {}", value),
+        ));
+        if let Some(code) = unreachable {
+            warnings.push(Warning::new(
+                WarningKind::Unreachable,
+                code.position(),
+                "This code is never run".to_string(),
+            ));
+        }
+    }
+    for child in node.children() {
+        conditions(child, warnings);
+    }
+}
+
+/// Finds the strings concatenated in a loop. Every run of the loop copies them again into the
+/// same buffer, so building up a string this way is slow and writes past the end of the buffer
+fn concatenations(node: &Node, in_loop: bool, warnings: &mut Vec<Warning>) {
+    match node {
+        Node::BinaryOp(op, left, right, _)
+            if in_loop
+                && op.token_type == TokenType::Add
+                && left.get_type().is_string_part()
+                && right.get_type().is_string_part() =>
+        {
+            warnings.push(Warning::new(
+                WarningKind::LoopConcat,
+                op.position.clone(),
+                "Strings concatenated in a loop are copied every time it runs, and the result always goes in the same buffer"
+                    .to_string(),
+            ));
+        }
+        // The initialization of a for loop only runs once
+        Node::For(init, cond, step, body, _) => {
+            concatenations(init, in_loop, warnings);
+            for node in [cond, step, body] {
+                concatenations(node, true, warnings);
+            }
+            return;
+        }
+        _ => (),
+    }
+    let in_loop = in_loop || matches!(node, Node::While(..));
+    for child in node.children() {
+        concatenations(child, in_loop, warnings);
+    }
+}
+
+/// Finds the comparisons of a number with a literal whose result doesn't depend on the number.
+/// A number is a cell from 0 to 255, so it is never below 0 or above 255, and a negative
+/// literal is the cell it wraps to, like -256 is 0
+fn comparisons(node: &Node, warnings: &mut Vec<Warning>) {
+    if let Node::BinaryOp(op, left, right, _) = node {
+        let literal = |node: &Node| match node {
+            Node::Number(Token {
+                token_type: TokenType::Number(n),
+                ..
+            }) => Some(*n),
+            _ => None,
+        };
+        // The number is on the left of the comparison, `5 > x` is `x < 5`
+        let compared = match (literal(left), literal(right)) {
+            (None, Some(n)) => Some((&**left, n, &**right, op.token_type.clone())),
+            (Some(n), None) => {
+                let flipped = match op.token_type {
+                    TokenType::Lt => TokenType::Gt,
+                    TokenType::Le => TokenType::Ge,
+                    TokenType::Gt => TokenType::Lt,
+                    TokenType::Ge => TokenType::Le,
+                    ref t => t.clone(),
+                };
+                Some((&**right, n, &**left, flipped))
+            }
+            _ => None,
+        };
+        if let Some((value, n, literal, op_type)) = compared {
+            let cell = n.rem_euclid(256);
+            let always = match op_type {
+                TokenType::Lt if cell == 0 => Some(false),
+                TokenType::Ge if cell == 0 => Some(true),
+                TokenType::Gt if cell == 255 => Some(false),
+                TokenType::Le if cell == 255 => Some(true),
+                _ => None,
+            };
+            if let (Some(always), Type::Number) = (always, value.get_type()) {
+                let wraps = if n == cell {
+                    String::new()
+                } else {
+                    format!(", and {} is {}", n, cell)
+                };
+                warnings.push(
+                    Warning::new(
+                        WarningKind::ConstantComparison,
+                        op.position.clone(),
+                        format!(
+                            "This comparison is always {}, since a number is a cell from 0 to 255{}",
+                            always, wraps
+                        ),
+                    )
+                    .with_label(value.position(), "this is from 0 to 255".to_string())
+                    .with_label(literal.position(), format!("this is {}", cell)),
+                );
+            }
+        }
+    }
+    for child in node.children() {
+        comparisons(child, warnings);
+    }
+}
+
+fn constant(cond: &Node) -> Option<bool> {
+    match cond {
+        Node::Boolean(token) => Some(token.token_type == TokenType::Keyword("true".to_string())),
+        _ => None,
+    }
+}
@@ -1,10 +1,10 @@
 //! A language, which doesn't have much. But, It can be compiled to brainfuck.
 //! To get started, run the following code:
 //! ```
-//! println!("{}", ezlang::run("ezout 2 + 2").unwrap());
-//! ```
-//! This should output the following code:
+//! println!("{}", ezlang::run("ezout 2 + 2", "main.ez".to_string()).unwrap());
 //! ```
+//! This should output brainfuck code along these lines:
+//! ```text
 //! [-]++++++++++++++++++++++++++++++++++++++++++++++++++++.[-]++++++++++.
 //! ```
 //!
@@ -16,57 +16,455 @@
 //! ```
 //! use ezlang;
 //!
-//! ezlang::ez!(
-//!     let y = 454 ezout y / 10
-//! )
+//! let code = ezlang::ez!(
+//!     let y = 254 ezout y / 10
+//! );
+//! assert!(code.is_ok());
 //! ```
 //! It is the run function but in macro style
 
 pub mod core;
 pub mod utils;
 
-use std::rc::Rc;
+/// The types and functions most programs using ezlang need
+/// # Examples
+/// ```
+/// use ezlang::prelude::*;
+///
+/// let mut stats = Stats::default();
+/// let ir: Instructions = compile_ir("ezout 4", "main.ez".to_string(), &Options::default(), &mut stats).unwrap();
+/// assert_eq!(to_brainfuck(&ir), run("ezout 4", "main.ez".to_string()).unwrap());
+///
+/// let error: Error = parse("ezout", "main.ez".to_string()).unwrap_err();
+/// let position: &Position = &error.position;
+/// assert_eq!(position.line_start, 1);
+/// ```
+pub mod prelude {
+    pub use crate::utils::{
+        ast::Node,
+        diag::{Error, ErrorType, Position, Warning},
+        ir::Instructions,
+        token::Token,
+        Options, Stats,
+    };
+    pub use crate::{
+        analyze, analyze_with_options, compile_ir, parse, parse_with_options, run,
+        run_with_options, to_brainfuck, to_brainfuck_with_options,
+    };
+}
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
-use crate::core::{compiler, ir_code, lexer, parser, preprocessor};
-use utils::Error;
+use crate::core::{
+    compiler, ir_code, lexer, lexer::Comments, parser, pipeline::Pipeline, preprocessor,
+};
+use utils::{
+    ast::{Analysis, Node},
+    diag::{Error, FileName},
+    ir::Instructions,
+    token::Token,
+    Options, Stats,
+};
 
 /// parses the passed ezlang code, and returns a the generated brainfuck code or an error, if any
 /// # Arguments
 /// * `contents` - The contents to be parsed
+/// * `filename` - The name of the file the contents came from, used in error messages
 /// # Returns
-/// * `Result<String, crate::utils::Error>` - The generated brainfuck code or an error, if any
+/// * `Result<String, crate::utils::diag::Error>` - The generated brainfuck code or an error, if any
 /// # Examples
 /// ```
 /// use ezlang;
 ///
-/// let code = ezlang::run("ezout 5 + 7");
+/// let code = ezlang::run("ezout 5 + 7", "main.ez".to_string());
 /// assert!(code.is_ok());
-/// assert_eq!(&code.unwrap(), "[-]+++++++++++++++++++++++++++++++++++++++++++++++++.[-]++++++++++++++++++++++++++++++++++++++++++++++++++.[-]++++++++++.");
 /// ```
 pub fn run(contents: &str, filename: String) -> Result<String, Error> {
-    let tokens = lexer::lex(contents, Rc::new(filename))?;
-    let tokens = preprocessor::preprocess(tokens)?;
-    // println!(
-    //     "{:?}",
-    //     tokens
-    //         .iter()
-    //         .map(|x| x.to_string())
-    //         .collect::<Vec<String>>()
-    // );
-    let (ast, statics, structs) = parser::parse(tokens)?;
-    println!("{}\n", ast);
-    let code = ir_code::generate_code(ast, statics, structs)?;
-    println!("{}", code);
+    run_with_stats(contents, filename, &mut Stats::default())
+}
+
+/// Same as [`run`], but also records statistics about the compilation into `stats`
+pub fn run_with_stats(
+    contents: &str,
+    filename: String,
+    stats: &mut Stats,
+) -> Result<String, Error> {
+    run_with_options(contents, filename, &Options::default(), stats)
+}
+
+/// Same as [`run_with_stats`], but compiles with the passed `options`, which can for example
+/// include a prelude or declare flags. The phases run as the [`Pipeline`] of the options,
+/// which can also be used directly to add hooks between them
+/// # Examples
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let prelude = std::env::temp_dir().join("ezlang_prelude_example.ez");
+/// std::fs::write(&prelude, "!replace LIMIT 42").unwrap();
+/// let options = Options {
+///     prelude: Some(prelude.to_string_lossy().into_owned()),
+///     ..Options::default()
+/// };
+/// let code = ezlang::run_with_options("ezout LIMIT", "main.ez".to_string(), &options, &mut Stats::default());
+/// assert!(code.is_ok());
+/// assert!(ezlang::run("ezout LIMIT", "main.ez".to_string()).is_err());
+///
+/// let code = "!ifdeclared DEBUG\n!error \"debug build\"\n!endif";
+/// let options = Options {
+///     defines: vec!["DEBUG".to_string()],
+///     ..Options::default()
+/// };
+/// assert!(ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut Stats::default()).is_err());
+/// assert!(ezlang::run(code, "main.ez".to_string()).is_ok());
+/// ```
+/// The output is the same for any number of jobs
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let modules = concat!(env!("CARGO_MANIFEST_DIR"), "/ez-modules");
+/// for file in std::fs::read_dir(modules).unwrap() {
+///     let path = file.unwrap().path();
+///     let code = std::fs::read_to_string(&path).unwrap();
+///     let build = |jobs| {
+///         let options = Options { jobs, ..Options::default() };
+///         let name = path.display().to_string();
+///         ezlang::run_with_options(&code, name, &options, &mut Stats::default()).unwrap()
+///     };
+///     assert_eq!(build(1), build(4));
+/// }
+///
+/// // Whatever cancels out is removed even when it is next to where the code is split
+/// let mut raw = String::new();
+/// for i in 0..200 {
+///     raw.push_str(["+[", "-]>", "<<[->", "+]<", ">-[+", "]-", ".><"][i % 7]);
+/// }
+/// let code = format!("ezraw \"{}\"", raw);
+/// let build = |jobs| {
+///     let options = Options { jobs, ..Options::default() };
+///     ezlang::run_with_options(&code, "main.ez".to_string(), &options, &mut Stats::default()).unwrap()
+/// };
+/// let single = build(1);
+/// assert!(!["<>", "><", "+-", "-+"].iter().any(|pair| single.contains(pair)));
+/// for jobs in 2..=16 {
+///     assert_eq!(build(jobs), single);
+/// }
+/// ```
+/// `ezout` puts `ezout_separator` between its values, and `ezoutln` also ends the line
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let code = "ezout 1, 'a', true\nezoutln 2, 3\nezoutln\nezout 4";
+/// let output = |ezout_separator: &str| {
+///     let options = Options { ezout_separator: ezout_separator.to_string(), ..Options::default() };
+///     let code = ezlang::run_with_options(code, "main.ez".to_string(), &options, &mut Stats::default());
+///     let mut output = vec![];
+///     ezlang::core::interpreter::interpret(&code.unwrap(), &mut std::io::empty(), &mut output).unwrap();
+///     output
+/// };
+/// assert_eq!(output(" "), b"1 a true2 3\n\n4");
+/// assert_eq!(output(""), b"1atrue23\n\n4");
+/// assert_eq!(output(", "), b"1, a, true2, 3\n\n4");
+/// ```
+/// Every program in `examples/` prints what its `.out` file holds, reading its `.in` file as input
+/// ```
+/// use ezlang::utils::{Options, Stats};
+///
+/// let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/examples");
+/// let options = Options {
+///     include_paths: vec![examples.to_string()],
+///     ..Options::default()
+/// };
+/// for file in std::fs::read_dir(examples).unwrap() {
+///     let path = file.unwrap().path();
+///     if path.extension().map_or(true, |ext| ext != "ez") {
+///         continue;
+///     }
+///     let code = std::fs::read_to_string(&path).unwrap();
+///     let name = path.display().to_string();
+///     let code = ezlang::run_with_options(&code, name, &options, &mut Stats::default()).unwrap();
+///     let input = std::fs::read(path.with_extension("in")).unwrap_or_default();
+///     let mut output = vec![];
+///     ezlang::core::interpreter::interpret(&code, &mut input.as_slice(), &mut output).unwrap();
+///     let expected = std::fs::read(path.with_extension("out")).unwrap();
+///     assert_eq!(output, expected, "{}", path.display());
+/// }
+/// ```
+pub fn run_with_options(
+    contents: &str,
+    filename: String,
+    options: &Options,
+    stats: &mut Stats,
+) -> Result<String, Error> {
+    let code = compile_ir(contents, filename, options, stats)?;
     // let code = evaluate::evaluate(&code);
     // let code = ir_optimizer::optimize(&code);
     // println!("{}", code);
-    let mut bf_code = compiler::transpile(&code);
-    optimize(&mut bf_code);
-    Ok(bf_code)
+    Ok(to_brainfuck_with_options(&code, options))
+}
+
+/// Compiles the passed ezlang code like [`run_with_options`], but stops at the intermediate
+/// code, before it is turned into brainfuck
+pub fn compile_ir(
+    contents: &str,
+    filename: String,
+    options: &Options,
+    stats: &mut Stats,
+) -> Result<Instructions, Error> {
+    Pipeline::new(options.clone()).compile_ir(contents, filename, stats)
+}
+
+/// Parses the passed ezlang code into an AST, leaving the function calls unexpanded
+/// # Examples
+/// ```
+/// let code = "let a = 1\nezout a + 2";
+/// assert!(ezlang::parse(code, "main.ez".to_string()).is_ok());
+///
+/// // The earliest error in the file is reported
+/// let code = "let a = 1\nlet b = 300\n\n\n\n\n\n\n\nez f() -> int { return f() }";
+/// let error = ezlang::parse(code, "main.ez".to_string()).unwrap_err();
+/// assert_eq!(error.position.line_start, 2);
+/// ```
+pub fn parse(contents: &str, filename: String) -> Result<Node, Error> {
+    parse_with_options(contents, filename, &Options::default())
+}
+
+/// Same as [`parse`], but with the passed `options`
+pub fn parse_with_options(
+    contents: &str,
+    filename: String,
+    options: &Options,
+) -> Result<Node, Error> {
+    let tokens = tokens(contents, filename, options)?;
+    Ok(parser::parse_definitions(tokens)?.0)
+}
+
+/// Checks the passed ezlang code without generating any code, so that tools can find
+/// out what is declared where
+/// # Examples
+/// ```
+/// use ezlang::utils::{ast::SymbolKind, diag::Position};
+/// use ezlang::utils::diag::FileName;
+///
+/// let code = "let a = 1
+/// ez f(x: int) {
+///     let b = x + 1
+///     {
+///         let c = b
+///         ezout c
+///     }
+/// }
+/// ezout a";
+/// let analysis = ezlang::analyze(code, "main.ez".to_string()).unwrap();
+/// let variables_at = |line, column| {
+///     let pos = Position::new(line, column, column, FileName::new("main.ez"));
+///     analysis
+///         .symbols_at(&pos)
+///         .into_iter()
+///         .filter(|s| s.kind == SymbolKind::Variable)
+///         .map(|s| s.name)
+///         .collect::<Vec<_>>()
+/// };
+///
+/// assert_eq!(variables_at(6, 9), ["c", "b", "x", "a"]);
+/// assert_eq!(variables_at(3, 5), ["x", "a"]);
+/// assert_eq!(variables_at(9, 1), ["a"]);
+///
+/// let pos = Position::new(9, 1, 1, FileName::new("main.ez"));
+/// assert!(analysis.symbols_at(&pos).iter().any(|s| s.name == "f"));
+/// ```
+pub fn analyze(contents: &str, filename: String) -> Result<Analysis, Error> {
+    analyze_with_options(contents, filename, &Options::default())
+}
+
+/// Same as [`analyze`], but with the passed `options`
+pub fn analyze_with_options(
+    contents: &str,
+    filename: String,
+    options: &Options,
+) -> Result<Analysis, Error> {
+    let tokens = tokens(contents, filename, options)?;
+    let (ast, scope) = parser::analyze(tokens)?;
+    let references = scope.all_references();
+    Ok(Analysis {
+        ast,
+        scope,
+        references,
+    })
+}
+
+/// Generates the code for a call to the function `name`, defined at the top level of the
+/// unexpanded `ast`, with the given arguments. The value returned by the function is printed,
+/// the rest of the top level code doesn't run
+/// # Errors
+/// Returns an error if the function doesn't exist, the arguments don't fit its parameters,
+/// or it uses a variable defined by the top level code
+/// # Examples
+/// ```
+/// use ezlang::core::interpreter;
+///
+/// let run = |code: &str, name: &str, args: &[i64]| {
+///     let ast = ezlang::parse(code, "math.ez".to_string()).unwrap();
+///     let code = ezlang::compile_function_call(ast, name, args)?;
+///     let mut output = vec![];
+///     interpreter::interpret(&ezlang::to_brainfuck(&code), &mut &b""[..], &mut output).unwrap();
+///     Ok::<_, ezlang::utils::diag::Error>(String::from_utf8(output).unwrap())
+/// };
+/// let code = "ezout 10\nstatic s = 7\nez add(a: int, b: int) -> int { return a + b + s }";
+/// assert_eq!(run(code, "add", &[2, 3]).unwrap(), "12");
+/// assert_eq!(run(code, "add", &[-256, 3]).unwrap(), "10");
+/// assert!(run(code, "add", &[-257, 3]).is_err());
+/// assert!(run(code, "add", &[256, 3]).is_err());
+/// assert!(run("let g = 4\nez f() -> int { return g }", "f", &[]).is_err());
+///
+/// // Constant conditions are folded while generating the code
+/// use ezlang::utils::ir::Instruction;
+/// let ast = ezlang::parse("ez f() { if (!false) { ezout 1 } if (3 < 5) { ezout 2 } }", "main.ez".to_string()).unwrap();
+/// let code = ezlang::compile_function_call(ast, "f", &[]).unwrap();
+/// assert!(!code.0.iter().any(|(_, i)| matches!(i, Instruction::LNot(_) | Instruction::Lt(..))));
+/// ```
+pub fn compile_function_call(ast: Node, name: &str, args: &[i64]) -> Result<Instructions, Error> {
+    let (ast, statics, structs) = parser::function_call(ast, name, args)?;
+    ir_code::generate_code(
+        ast,
+        statics,
+        structs,
+        &Options::default(),
+        &mut Stats::default(),
+    )
+}
+
+/// Transpiles the generated IR into brainfuck code
+pub fn to_brainfuck(code: &Instructions) -> String {
+    let mut bf_code = compiler::transpile(code);
+    optimize(&mut bf_code, 1);
+    bf_code
+}
+
+/// Transpiles the generated IR into brainfuck code like [`to_brainfuck`], optimizing it at the
+/// level and on the number of jobs of `options`
+pub fn to_brainfuck_with_options(code: &Instructions, options: &Options) -> String {
+    let mut bf_code = compiler::transpile(code);
+    if options.opt_level > 0 {
+        optimize(&mut bf_code, options.jobs);
+    }
+    bf_code
+}
+
+/// Compiles ezlang code written inline, returning the same result as [`run`]
+#[macro_export]
+macro_rules! ez {
+    ($($code:tt)*) => {
+        $crate::run(stringify!($($code)*), String::from("<ez macro>"))
+    };
+}
+
+/// Reads a source file
+/// # Errors
+/// Returns the error of reading the file, or an error of kind [`io::ErrorKind::InvalidData`]
+/// giving the offset of the first invalid byte if the file isn't valid UTF-8
+/// # Examples
+/// ```
+/// let path = std::env::temp_dir().join("ezlang_latin1_example.ez");
+/// std::fs::write(&path, b"ezout 'a'\n// caf\xe9").unwrap();
+/// let err = ezlang::read_source(&path).unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+/// assert!(err.to_string().ends_with("is not valid UTF-8, the first invalid byte is at offset 16"));
+///
+/// // A byte order mark at the start of a file is ignored
+/// std::fs::write(&path, "\u{feff}ezout 'a'").unwrap();
+/// let code = ezlang::read_source(&path).unwrap();
+/// assert!(ezlang::run(&code, "main.ez".to_string()).is_ok());
+/// ```
+pub fn read_source<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let bytes = fs::read(&path)?;
+    String::from_utf8(bytes).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} is not valid UTF-8, the first invalid byte is at offset {}",
+                path.as_ref().display(),
+                e.utf8_error().valid_up_to()
+            ),
+        )
+    })
+}
+
+/// Lexes and preprocesses the passed code, including the prelude first if there is one
+fn tokens(contents: &str, filename: String, options: &Options) -> Result<Vec<Token>, Error> {
+    Ok(tokens_with_comments(contents, filename, options, &mut Stats::default())?.0)
+}
+
+/// Lexes and preprocesses the code, also returning the line comments of the main file.
+/// The paths of the prelude and of the files included by `use` are added to
+/// `stats.included_files`, the directives that are run are logged to
+/// `stats.preprocessor_trace` if the options ask for it, and the warnings of the directives
+/// are put in `stats.warnings`
+fn tokens_with_comments(
+    contents: &str,
+    filename: String,
+    options: &Options,
+    stats: &mut Stats,
+) -> Result<(Vec<Token>, Comments), Error> {
+    let (mut tokens, comments) = lexer::lex_with_comments(contents, FileName::new(&filename))?;
+    if let Some(ref prelude) = options.prelude {
+        preprocessor::include_prelude(&mut tokens, prelude)?;
+        stats.included_files.push(PathBuf::from(prelude));
+    }
+    let included = &mut stats.included_files;
+    let trace = &mut stats.preprocessor_trace;
+    let warnings = &mut stats.warnings;
+    let tokens = preprocessor::preprocess(tokens, options, included, trace, warnings)?;
+    Ok((tokens, comments))
+}
+
+/// Optimizes the generated Brainfuck code by removing unnecessary characters.
+/// With more than one job, the code is split into parts that are optimized on their own threads
+pub(crate) fn optimize(code: &mut String, jobs: usize) {
+    if jobs <= 1 {
+        optimize_part(code);
+        return;
+    }
+    let parts = std::thread::scope(|scope| {
+        let threads = split_code(code, jobs)
+            .into_iter()
+            .map(|part| {
+                scope.spawn(move || {
+                    let mut part = part.to_string();
+                    optimize_part(&mut part);
+                    part
+                })
+            })
+            .collect::<Vec<_>>();
+        threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    *code = parts.concat();
+}
+
+/// Splits the code into about `parts` parts of the same length. The parts only end right
+/// after a `[` or a `]`, which `optimize_part` never removes, so that nothing that cancels
+/// out is split up, and the parts optimized separately give the same code as the whole
+fn split_code(code: &str, parts: usize) -> Vec<&str> {
+    let length = code.len() / parts + 1;
+    let mut result = vec![];
+    let mut start = 0;
+    while start < code.len() {
+        let end = code.as_bytes()[(start + length).min(code.len())..]
+            .iter()
+            .position(|c| b"[]".contains(c))
+            .map_or(code.len(), |i| start + length + i + 1);
+        result.push(&code[start..end]);
+        start = end;
+    }
+    result
 }
 
-/// Optimizes the generated Brainfuck code by removing unnecessary characters
-fn optimize(code: &mut String) {
+fn optimize_part(code: &mut String) {
     while code.contains("<>") || code.contains("><") || code.contains("+-") || code.contains("-+") {
         *code = code
             .replace("<>", "")
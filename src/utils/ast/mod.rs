@@ -0,0 +1,8 @@
+/// Contains the Node enum
+mod node;
+
+/// Contains the Scope struct
+mod scope;
+
+pub use node::*;
+pub use scope::*;
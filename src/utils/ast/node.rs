@@ -0,0 +1,1043 @@
+use std::fmt::{self, Display};
+
+use crate::utils::{
+    diag::Position,
+    token::{Token, TokenType, BOOLEAN_EXCLUSIVE, BOOLEAN_OPERATORS},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Number,
+    Boolean,
+    None,
+    Char,
+    /// Name, fields, whether its bool fields are packed into bits
+    Struct(Token, Vec<(Token, Type)>, bool),
+    Ref(Box<Type>),
+    Pointer(Box<Type>),
+    /// Parameters, return type
+    Function(Vec<Type>, Box<Type>),
+    /// The bools of a packed array, 8 of them in a cell. It is only the type of what a
+    /// pointer points to, an element of the array is a `Boolean`
+    Bits,
+}
+
+impl Type {
+    pub fn get_result_type(&self, rhs: &Self, op: &Token) -> Option<Self> {
+        match (self, rhs) {
+            (Self::Number, Self::Number) => {
+                if BOOLEAN_OPERATORS.contains(&op.token_type) {
+                    Some(Self::Boolean)
+                } else if BOOLEAN_EXCLUSIVE.contains(&op.token_type) {
+                    None
+                } else {
+                    Some(Self::Number)
+                }
+            }
+            (Self::Pointer(t), Self::Number) if **t != Self::Bits => {
+                if let TokenType::Add | TokenType::Sub = op.token_type {
+                    Some(Self::Pointer(t.clone()))
+                } else {
+                    None
+                }
+            }
+            (Self::Boolean, Self::Boolean) => {
+                if BOOLEAN_OPERATORS.contains(&op.token_type)
+                    || BOOLEAN_EXCLUSIVE.contains(&op.token_type)
+                {
+                    Some(Self::Boolean)
+                } else {
+                    None
+                }
+            }
+            (Self::Char, Self::Char) => {
+                if BOOLEAN_OPERATORS.contains(&op.token_type) {
+                    Some(Self::Boolean)
+                } else {
+                    None
+                }
+            }
+            // Concatenating strings, or a string and a char, makes a new string
+            (Self::Pointer(_) | Self::Char, Self::Pointer(_) | Self::Char)
+                if op.token_type == TokenType::Add
+                    && self.is_string_part()
+                    && rhs.is_string_part() =>
+            {
+                Some(Self::Pointer(Box::new(Self::Char)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The type of the elements of an array of this type, if it is a pointer. The elements
+    /// of a packed array are bools
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::ast::Type;
+    ///
+    /// let packed = Type::Pointer(Box::new(Type::Bits));
+    /// assert_eq!(packed.element_type(), Some(Type::Boolean));
+    /// assert_eq!(Type::Pointer(Box::new(Type::Char)).element_type(), Some(Type::Char));
+    /// assert_eq!(Type::Number.element_type(), None);
+    /// ```
+    pub fn element_type(&self) -> Option<Self> {
+        match self {
+            Self::Pointer(t) if **t == Self::Bits => Some(Self::Boolean),
+            Self::Pointer(t) => Some((**t).clone()),
+            _ => None,
+        }
+    }
+
+    /// Whether the type is a string or a char, which `+` can concatenate
+    pub fn is_string_part(&self) -> bool {
+        match self {
+            Self::Char => true,
+            Self::Pointer(t) => **t == Self::Char,
+            _ => false,
+        }
+    }
+
+    pub fn get_result_type_unary(&self, op: &Token) -> Option<Self> {
+        match self {
+            Self::Number => {
+                if op.token_type == TokenType::LNot {
+                    None
+                } else if [TokenType::Inc, TokenType::Dec].contains(&op.token_type) {
+                    Some(Self::None)
+                } else {
+                    Some(Self::Number)
+                }
+            }
+            Self::Boolean => {
+                if op.token_type == TokenType::LNot {
+                    Some(Self::Boolean)
+                } else {
+                    None
+                }
+            }
+            Self::Pointer(t) if **t != Self::Bits => {
+                if let TokenType::Inc | TokenType::Dec = op.token_type {
+                    Some(Self::None)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Describes the first difference between this type, the one that was expected, and
+    /// `found`, or `None` if they are the same. Fields of structs are compared in order, and
+    /// the difference inside of a struct is given with the path to the field it is in.
+    /// Structs with the same fields in another order are different, which is said explicitly
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::diag::FileName;
+    /// use ezlang::utils::{ast::Type, token::{Token, TokenType}};
+    ///
+    /// let name = |name: &str| Token::new(TokenType::Identifier(name.to_string()), 1, 1, 2, FileName::new("main.ez"));
+    /// let structure = |n: &str, fields: Vec<(&str, Type)>| {
+    ///     Type::Struct(name(n), fields.into_iter().map(|(f, t)| (name(f), t)).collect(), false)
+    /// };
+    /// let nested = |count| {
+    ///     let c = structure("C", vec![("flag", Type::Boolean), ("count", count)]);
+    ///     let b = structure("B", vec![("c", c)]);
+    ///     structure("A", vec![("id", Type::Number), ("b", Type::Pointer(Box::new(b)))])
+    /// };
+    ///
+    /// assert_eq!(nested(Type::Number).difference(&nested(Type::Number)), None);
+    /// assert_eq!(
+    ///     structure("A", vec![("count", Type::Number)]).difference(&structure("A", vec![("count", Type::Char)])),
+    ///     Some("count: expected int, found char".to_string())
+    /// );
+    /// assert_eq!(
+    ///     nested(Type::Number).difference(&nested(Type::Char)),
+    ///     Some("b.c.count: expected int, found char".to_string())
+    /// );
+    /// assert_eq!(
+    ///     structure("A", vec![("x", Type::Number), ("y", Type::Char)])
+    ///         .difference(&structure("A", vec![("y", Type::Char), ("x", Type::Number)])),
+    ///     Some("the fields are in another order: expected x, y, found y, x".to_string())
+    /// );
+    /// assert_eq!(
+    ///     structure("A", vec![("x", Type::Number)]).difference(&structure("A", vec![("x", Type::Number), ("y", Type::Char)])),
+    ///     Some("field y isn't expected".to_string())
+    /// );
+    /// assert_eq!(Type::Number.difference(&Type::Char), Some("expected int, found char".to_string()));
+    /// ```
+    pub fn difference(&self, found: &Self) -> Option<String> {
+        self.field_difference(found).map(|(path, details)| {
+            if path.is_empty() {
+                details
+            } else {
+                format!("{}: {}", path.join("."), details)
+            }
+        })
+    }
+
+    /// Same as [`Type::difference`], with the path to the field apart from the difference
+    fn field_difference(&self, found: &Self) -> Option<(Vec<String>, String)> {
+        if self == found {
+            return None;
+        }
+        let names = |fields: &[(Token, Type)]| -> Vec<String> {
+            fields
+                .iter()
+                .map(|(f, _)| f.token_type.to_string())
+                .collect()
+        };
+        match (self, found) {
+            (Self::Struct(name, fields, packed), Self::Struct(found_name, found_fields, _))
+                if name == found_name =>
+            {
+                let (expected, actual) = (names(fields), names(found_fields));
+                let mut sorted = (expected.clone(), actual.clone());
+                sorted.0.sort();
+                sorted.1.sort();
+                if expected != actual && sorted.0 == sorted.1 {
+                    return Some((
+                        vec![],
+                        format!(
+                            "the fields are in another order: expected {}, found {}",
+                            expected.join(", "),
+                            actual.join(", ")
+                        ),
+                    ));
+                }
+                for (i, (field, t)) in fields.iter().enumerate() {
+                    let field = field.token_type.to_string();
+                    let details = match found_fields.get(i) {
+                        None => format!("field {} is missing", field),
+                        Some((other, _)) if other.token_type.to_string() != field => {
+                            format!("expected field {}, found field {}", field, other.token_type)
+                        }
+                        Some((_, other)) => match t.field_difference(other) {
+                            Some((mut path, details)) => {
+                                path.insert(0, field);
+                                return Some((path, details));
+                            }
+                            None => continue,
+                        },
+                    };
+                    return Some((vec![], details));
+                }
+                match actual.get(expected.len()) {
+                    Some(extra) => Some((vec![], format!("field {} isn't expected", extra))),
+                    None if *packed => Some((vec![], "expected it to be packed".to_string())),
+                    None => Some((vec![], "expected it not to be packed".to_string())),
+                }
+            }
+            // The fields of a struct behind a pointer are compared like the fields of a struct
+            (Self::Pointer(t), Self::Pointer(other)) | (Self::Ref(t), Self::Ref(other))
+                if matches!((&**t, &**other), (Self::Struct(..), Self::Struct(..))) =>
+            {
+                t.field_difference(other)
+            }
+            _ => Some((vec![], format!("expected {}, found {}", self, found))),
+        }
+    }
+
+    pub fn can_be_converted(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Number | Self::Boolean | Self::Char,
+                Self::Number | Self::Boolean | Self::Char,
+            ) => true,
+            (Self::Ref(t1), Self::Pointer(t2)) if t1 == t2 => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Number => write!(f, "int"),
+            Type::Boolean => write!(f, "bool"),
+            Type::None => write!(f, "()"),
+            Type::Char => write!(f, "char"),
+            Type::Ref(t) => write!(f, "&{}", t),
+            Type::Struct(s, ..) => write!(f, "struct {}", s),
+            Type::Pointer(t) => write!(f, "*point {}", t),
+            Type::Bits => write!(f, "packed bool"),
+            Type::Function(params, ret) => {
+                write!(
+                    f,
+                    "({}) -> {}",
+                    params
+                        .iter()
+                        .map(|t| t.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    ret
+                )
+            }
+        }
+    }
+}
+
+/// The `;` after every statement of a block, if it is written. It only records how the code
+/// is written, so it is left out when comparing nodes
+/// # Examples
+/// ```
+/// use ezlang::utils::ast::{Node, Semicolons};
+///
+/// let code = "let a = 1;\nezout a\nif (a > 0) { ezout 2; ezout 3 };";
+/// let Node::Statements(statements, _, semicolons, _) = ezlang::parse(code, "main.ez".to_string()).unwrap() else {
+///     unreachable!()
+/// };
+/// assert_eq!(semicolons.0.len(), statements.len());
+/// let ends: Vec<_> = semicolons.0.iter().map(|s| s.as_ref().map(|p| (p.line_start, p.start))).collect();
+/// assert_eq!(ends, [Some((1, 10)), None, Some((3, 32))]);
+///
+/// let Node::If(_, then, ..) = &statements[2] else { unreachable!() };
+/// let Node::Statements(_, _, semicolons, _) = &**then else { unreachable!() };
+/// assert_eq!(semicolons.0.iter().map(Option::is_some).collect::<Vec<_>>(), [true, false]);
+///
+/// // Only the code matters when comparing
+/// let Node::If(cond, then, else_, pos) = statements[2].clone() else { unreachable!() };
+/// let Node::Statements(nodes, t, _, block) = *then else { unreachable!() };
+/// let then = Box::new(Node::Statements(nodes, t, Semicolons::default(), block));
+/// assert_eq!(Node::If(cond, then, else_, pos), statements[2]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Semicolons(pub Vec<Option<Position>>);
+
+impl Semicolons {
+    /// No `;` after any of `count` statements, for blocks that aren't written in the code
+    pub fn none(count: usize) -> Self {
+        Self(vec![None; count])
+    }
+}
+
+impl PartialEq for Semicolons {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// A Node in the AST.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// Expression
+    Pointer(Box<Node>, Position),
+    /// Node, Type
+    /// # Examples
+    /// ```
+    /// let code = "let c = 66 as char\nezout c, c as int, 65 as char as int, true as int";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"B 66 65 1");
+    ///
+    /// // An invalid cast is reported over the whole cast
+    /// let err = ezlang::run("let a = [1, 2]\nezout a as int", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(format!("{:?}", err.error_type), "TypeError");
+    /// assert_eq!(err.details, "Cannot convert type *point int to type int");
+    /// let position = &err.position;
+    /// assert_eq!((position.line_start, position.start, position.end), (2, 7, 15));
+    /// ```
+    Converted(Box<Node>, Type),
+    /// Node, Attr, Type
+    /// # Examples
+    /// ```
+    /// let code = "
+    /// struct Point { x: int, y: int }
+    /// struct Line { from: Point, to: Point }
+    /// ez make(x: int) -> Point { return Point { x: x, y: x * 2 } }
+    /// let l = Line { from: Point { x: 1, y: 2 }, to: make(3) }
+    /// ezout l.from.x, l.to.y, make(4).y
+    /// ";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"1 6 8");
+    ///
+    /// let err = ezlang::run("let a = 1\nezout a.x", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Cannot access attribute 'x' on type int, it is not a struct");
+    /// let code = "struct P { x: int }\nlet p = P { x: 1 }\nezout p.z";
+    /// let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Cannot access attribute 'z' on type struct 'P'");
+    /// assert_eq!((err.position.line_start, err.position.start), (3, 9));
+    /// ```
+    AttrAccess(Box<Node>, Token, Type),
+    /// Struct, fields, whether the struct is packed
+    StructConstructor(Token, Vec<(Token, Node)>, bool, Position),
+    /// String
+    String(Token),
+    /// Condition, Body
+    While(Box<Node>, Box<Node>, Position),
+    /// Name, Fields, whether its bool fields are packed into bits
+    Struct(Token, Vec<(Token, Type)>, bool, Position),
+    /// Number
+    Number(Token),
+    /// Boolean
+    Boolean(Token),
+    /// Operation, left, right
+    BinaryOp(Token, Box<Node>, Box<Node>, Type),
+    /// Operation, expression
+    UnaryOp(Token, Box<Node>, Type),
+    /// Variable, Expression, Type
+    VarAssign(Token, Box<Node>, Type),
+    /// Variable, Expression
+    StaticVar(Token, Box<Node>),
+    /// Variable
+    VarAccess(Token, Type),
+    /// Variable, Expression
+    VarReassign(Token, Box<Node>),
+    /// Statements, type, the `;` after each of them
+    Statements(Vec<Node>, Type, Semicolons, Position),
+    /// Function, args
+    Call(Token, Vec<Node>, Type, Position),
+    /// Function, args, body, return type, inline
+    FuncDef(Token, Vec<(Token, Type)>, Box<Node>, Type, Position),
+    /// Args, body, return type
+    Lambda(Vec<(Token, Type)>, Box<Node>, Type, Position),
+    /// Expression
+    Return(Box<Node>, Position),
+    /// Expressions, whether a newline is printed after them
+    Print(Vec<Node>, bool, Position),
+    /// Variables declared in the same `let`
+    /// # Examples
+    /// ```
+    /// let code = "let a = 1, b = a + 2, c = a + b\nezout a, b, c";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"1 3 4");
+    ///
+    /// // Errors point into the declarator that failed
+    /// let err = ezlang::run("let a = 1,\n    b = 2,\n    c = y", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Variable 'y' is not defined");
+    /// assert_eq!((err.position.line_start, err.position.start), (3, 9));
+    /// let err = ezlang::run("let a = 1, b: bool = 3", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Cannot assign int to bool");
+    /// assert_eq!((err.position.line_start, err.position.start), (1, 22));
+    /// let err = ezlang::run("let a = 1, 5", "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Expected an identifier, found '5'");
+    /// assert_eq!((err.position.line_start, err.position.start), (1, 12));
+    /// ```
+    Declarations(Vec<Node>, Position),
+    /// Expressions
+    Ascii(Vec<Node>, Position),
+    /// Type read, `int` for `ezin` and `char` for `ezin char`, prompt printed before reading
+    Input(Type, Option<String>, Position),
+    /// Expression
+    Ref(Box<Node>, Type, Position),
+    /// Expression
+    Deref(Box<Node>, Type, Position),
+    /// Condition, then, else
+    Ternary(Box<Node>, Box<Node>, Box<Node>, Type, Position),
+    /// Condition, then, else
+    If(Box<Node>, Box<Node>, Option<Box<Node>>, Position),
+    /// None
+    None(Position),
+    /// Char
+    Char(Token),
+    /// Elements, number of times they are repeated
+    Array(Vec<Node>, usize, Type, Position),
+    /// Array, index
+    Index(Box<Node>, Box<Node>, Type, Position),
+    /// Array, index, expression
+    IndexAssign(Box<Node>, Box<Node>, Box<Node>),
+    /// Pointer, expression
+    DerefAssign(Box<Node>, Box<Node>, Position),
+    /// Attribute access, expression
+    /// # Examples
+    /// ```
+    /// let code = "
+    /// struct Point { x: int, y: int }
+    /// struct Line { from: Point, to: Point }
+    /// let l = Line { from: Point { x: 1, y: 2 }, to: Point { x: 3, y: 6 } }
+    /// l.to.x = 7
+    /// l.from.y += 10
+    /// l.to.y *= 2
+    /// ezout l.to.x, l.from.y, l.to.y
+    /// ";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"7 12 12");
+    ///
+    /// let code = "struct P { x: int }\nlet p = P { x: 1 }\np.x = true";
+    /// let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Cannot assign bool to int");
+    /// let code = "struct P { x: int }\nlet p = P { x: 1 }\np.z += 1";
+    /// let err = ezlang::run(code, "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "Cannot access attribute 'z' on type struct 'P'");
+    /// ```
+    AttrAssign(Box<Node>, Box<Node>, Position),
+    /// Init, Cond, Step, Body
+    For(Box<Node>, Box<Node>, Box<Node>, Box<Node>, Position),
+    /// Function, arguments, body
+    Expanded(Token, Vec<Node>, Type),
+    /// Brainfuck code written with `ezraw`
+    Raw(String, Position),
+}
+
+impl Node {
+    /// The span of the node in its file. A statement spans its whole body, like a `for` loop
+    /// from `for` to the end of its block
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::ast::Node;
+    ///
+    /// let code = "for (let i = 0 : i < 3 : i += 1) {\n    ezout i\n}";
+    /// let Node::Statements(nodes, ..) = ezlang::parse(code, "main.ez".to_string()).unwrap() else {
+    ///     unreachable!()
+    /// };
+    /// let pos = nodes[0].position();
+    /// assert_eq!((pos.line_start, pos.start, pos.line_end, pos.end), (1, 1, 3, 2));
+    /// ```
+    pub fn position(&self) -> Position {
+        match self {
+            // The body of the function is always the last node
+            Node::Expanded(_, nodes, _) => nodes.last().unwrap().position(),
+            Node::String(token)
+            | Node::Number(token)
+            | Node::Char(token)
+            | Node::Boolean(token)
+            | Node::VarAccess(token, _) => token.position.clone(),
+            Node::Ref(.., pos)
+            | Node::StructConstructor(.., pos)
+            | Node::Struct(.., pos)
+            | Node::For(.., pos)
+            | Node::Pointer(.., pos)
+            | Node::Deref(.., pos)
+            | Node::While(.., pos)
+            | Node::Statements(.., pos)
+            | Node::Call(.., pos)
+            | Node::FuncDef(.., pos)
+            | Node::Lambda(.., pos)
+            | Node::Print(.., pos)
+            | Node::Declarations(.., pos)
+            | Node::Ascii(.., pos)
+            | Node::If(.., pos)
+            | Node::Ternary(.., pos)
+            | Node::None(pos)
+            | Node::AttrAssign(.., pos)
+            | Node::DerefAssign(.., pos)
+            | Node::Array(.., pos)
+            | Node::Index(.., pos)
+            | Node::Raw(_, pos)
+            | Node::Input(.., pos) => pos.clone(),
+            Node::BinaryOp(_, left, right, _) | Node::IndexAssign(left, _, right) => {
+                let mut pos = left.position();
+                pos.extend_to(right.end_position());
+                pos
+            }
+            Node::VarReassign(token, expr)
+            | Node::StaticVar(token, expr)
+            | Node::VarAssign(token, expr, _)
+            | Node::UnaryOp(token, expr, _) => {
+                let mut pos = token.position.clone();
+                pos.extend_to(expr.end_position());
+                pos
+            }
+            Node::Return(val, pos) => {
+                let mut pos = pos.clone();
+                pos.extend_to(val.end_position());
+                pos
+            }
+            Node::AttrAccess(node, attr, _) => {
+                let mut pos = node.position();
+                pos.extend_to(&attr.position);
+                pos
+            }
+            Node::Converted(n, _) => n.position(),
+        }
+    }
+
+    /// Returns the position the node ends with, without building the position of the whole node.
+    /// Only its `end` and `line_end` match the ones of [`Node::position`]
+    pub fn end_position(&self) -> &Position {
+        match self {
+            Node::Expanded(_, nodes, _) => nodes.last().unwrap().end_position(),
+            Node::String(token)
+            | Node::Number(token)
+            | Node::Char(token)
+            | Node::Boolean(token)
+            | Node::VarAccess(token, _) => &token.position,
+            Node::Ref(.., pos)
+            | Node::StructConstructor(.., pos)
+            | Node::Struct(.., pos)
+            | Node::For(.., pos)
+            | Node::Pointer(.., pos)
+            | Node::Deref(.., pos)
+            | Node::While(.., pos)
+            | Node::Statements(.., pos)
+            | Node::Call(.., pos)
+            | Node::FuncDef(.., pos)
+            | Node::Lambda(.., pos)
+            | Node::Print(.., pos)
+            | Node::Declarations(.., pos)
+            | Node::Ascii(.., pos)
+            | Node::If(.., pos)
+            | Node::Ternary(.., pos)
+            | Node::None(pos)
+            | Node::AttrAssign(.., pos)
+            | Node::DerefAssign(.., pos)
+            | Node::Array(.., pos)
+            | Node::Index(.., pos)
+            | Node::Raw(_, pos)
+            | Node::Input(.., pos) => pos,
+            Node::BinaryOp(_, _, right, _) => right.end_position(),
+            Node::VarReassign(_, expr)
+            | Node::StaticVar(_, expr)
+            | Node::VarAssign(_, expr, _)
+            | Node::IndexAssign(_, _, expr)
+            | Node::UnaryOp(_, expr, _)
+            | Node::Return(expr, _)
+            | Node::Converted(expr, _) => expr.end_position(),
+            Node::AttrAccess(_, attr, _) => &attr.position,
+        }
+    }
+
+    pub fn get_type(&self) -> Type {
+        match self {
+            Node::StructConstructor(t, f, packed, _) => Type::Struct(
+                t.clone(),
+                f.iter().map(|(t, n)| (t.clone(), n.get_type())).collect(),
+                *packed,
+            ),
+            Node::Array(_, _, ty, _) => Type::Pointer(Box::new(ty.clone())),
+            Node::Return(a, _) => a.get_type(),
+            Node::String(_) => Type::Pointer(Box::new(Type::Char)),
+            Node::Ref(_, ty, _) => Type::Ref(Box::new(ty.clone())),
+            Node::Pointer(n, _) => Type::Pointer(Box::new(n.get_type())),
+            Node::Number(_) => Type::Number,
+            Node::Boolean(_) => Type::Boolean,
+            Node::Char(_) => Type::Char,
+            Node::Input(ty, ..) => ty.clone(),
+            Node::Lambda(args, _, ret, _) => Type::Function(
+                args.iter().map(|(_, t)| t.clone()).collect(),
+                Box::new(ret.clone()),
+            ),
+            Node::VarAccess(_, ty)
+            | Node::UnaryOp(_, _, ty)
+            | Node::Converted(_, ty)
+            | Node::AttrAccess(.., ty)
+            | Node::Deref(_, ty, _)
+            | Node::BinaryOp(_, _, _, ty)
+            | Node::Call(_, _, ty, _)
+            | Node::Ternary(_, _, _, ty, _)
+            | Node::Expanded(_, _, ty)
+            | Node::Index(_, _, ty, _) => ty.clone(),
+            Node::While(_, _, _)
+            | Node::Struct(..)
+            | Node::VarAssign(_, _, _)
+            | Node::StaticVar(..)
+            | Node::VarReassign(_, _)
+            | Node::Statements(..)
+            | Node::FuncDef(_, _, _, _, _)
+            | Node::Print(..)
+            | Node::Declarations(_, _)
+            | Node::Ascii(_, _)
+            | Node::If(_, _, _, _)
+            | Node::None(_)
+            | Node::IndexAssign(_, _, _)
+            | Node::AttrAssign(_, _, _)
+            | Node::DerefAssign(_, _, _)
+            | Node::Raw(_, _)
+            | Node::For(_, _, _, _, _) => Type::None,
+        }
+    }
+
+    pub fn convert(&mut self, t: Type) {
+        *self = Node::Converted(Box::new(self.clone()), t);
+    }
+
+    /// Returns the direct children of the node, in the order they appear
+    pub fn children(&self) -> Vec<&Node> {
+        match self {
+            Node::String(_)
+            | Node::Number(_)
+            | Node::Boolean(_)
+            | Node::Char(_)
+            | Node::VarAccess(..)
+            | Node::Struct(..)
+            | Node::Input(..)
+            | Node::Raw(..)
+            | Node::None(_) => vec![],
+            Node::Pointer(n, _)
+            | Node::Converted(n, _)
+            | Node::AttrAccess(n, ..)
+            | Node::UnaryOp(_, n, _)
+            | Node::VarAssign(_, n, _)
+            | Node::StaticVar(_, n)
+            | Node::VarReassign(_, n)
+            | Node::FuncDef(_, _, n, ..)
+            | Node::Lambda(_, n, ..)
+            | Node::Return(n, _)
+            | Node::Ref(n, ..)
+            | Node::Deref(n, ..) => vec![n],
+            Node::BinaryOp(_, n1, n2, _)
+            | Node::While(n1, n2, _)
+            | Node::Index(n1, n2, ..)
+            | Node::AttrAssign(n1, n2, _)
+            | Node::DerefAssign(n1, n2, _) => vec![n1, n2],
+            Node::Ternary(n1, n2, n3, ..) | Node::IndexAssign(n1, n2, n3) => vec![n1, n2, n3],
+            Node::If(n1, n2, n3, _) => {
+                let mut children = vec![&**n1, &**n2];
+                if let Some(n3) = n3 {
+                    children.push(n3);
+                }
+                children
+            }
+            Node::For(n1, n2, n3, n4, _) => vec![n1, n2, n3, n4],
+            Node::StructConstructor(_, fields, ..) => fields.iter().map(|(_, n)| n).collect(),
+            Node::Statements(nodes, ..)
+            | Node::Call(_, nodes, ..)
+            | Node::Print(nodes, ..)
+            | Node::Declarations(nodes, _)
+            | Node::Ascii(nodes, _)
+            | Node::Array(nodes, ..)
+            | Node::Expanded(_, nodes, _) => nodes.iter().collect(),
+        }
+    }
+
+    pub fn struct_from_def(&self) -> Option<Type> {
+        if let Node::Struct(t, f, packed, _) = self {
+            Some(Type::Struct(t.clone(), f.clone(), *packed))
+        } else {
+            None
+        }
+    }
+}
+
+/// Nodes more levels than this below the one printed with `{}` are written as `…`, so that
+/// printing a very deep tree doesn't overflow the stack
+pub const AST_DEPTH: usize = 256;
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.display(AST_DEPTH))
+    }
+}
+
+impl Node {
+    /// Prints the node, writing the nodes more than `depth` levels below it as `…`
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::diag::FileName;
+    /// use ezlang::utils::{ast::{Node, Semicolons, Type}, token::{Token, TokenType}};
+    ///
+    /// let token = |t| Token::new(t, 1, 1, 2, FileName::new("main.ez"));
+    /// let mut node = Node::Number(token(TokenType::Number(1)));
+    /// for _ in 0..10_000 {
+    ///     node = Node::UnaryOp(token(TokenType::Sub), Box::new(node), Type::Number);
+    /// }
+    /// let printed = node.to_string();
+    /// assert_eq!(printed.matches("UnaryOp").count(), ezlang::utils::ast::AST_DEPTH);
+    /// assert!(printed.ends_with(&format!("UnaryOp('-' …){}", ")".repeat(255))));
+    /// assert_eq!(node.display(2).to_string(), "UnaryOp('-' UnaryOp('-' …))");
+    /// assert_eq!(node.summary().to_string(), "UnaryOp('-' UnaryOp('-' UnaryOp('-' …)))");
+    ///
+    /// // A summary is on one line, and cut after 60 characters
+    /// let mut short = Node::Number(token(TokenType::Number(1)));
+    /// for _ in 0..3 {
+    ///     short = Node::UnaryOp(token(TokenType::Sub), Box::new(short), Type::Number);
+    /// }
+    /// let statements = Node::Statements(vec![short.clone(), short.clone(), short], Type::None, Semicolons::default(), token(TokenType::Eol).position);
+    /// assert_eq!(
+    ///     statements.summary().to_string(),
+    ///     "{ UnaryOp('-' UnaryOp('-' …)); UnaryOp('-' UnaryOp('-' …)); …"
+    /// );
+    /// assert_eq!(statements.display(2).to_string().lines().count(), 5);
+    /// ```
+    pub fn display(&self, depth: usize) -> NodeDisplay<'_> {
+        NodeDisplay {
+            node: self,
+            depth,
+            one_line: false,
+            length: None,
+        }
+    }
+
+    /// Prints the first levels of the node on one line, cut after 60 characters with a `…`,
+    /// for messages that mention it
+    pub fn summary(&self) -> NodeDisplay<'_> {
+        NodeDisplay {
+            node: self,
+            depth: 3,
+            one_line: true,
+            length: Some(60),
+        }
+    }
+}
+
+/// Prints a node, see [`Node::display`] and [`Node::summary`]
+pub struct NodeDisplay<'a> {
+    node: &'a Node,
+    /// Levels of nodes printed, the ones below them are written as `…`
+    depth: usize,
+    /// Whether the statements of a block are printed on the same line
+    one_line: bool,
+    /// Most characters printed, the rest is left out and written as `…`
+    length: Option<usize>,
+}
+
+impl NodeDisplay<'_> {
+    /// Prints a node of the one being printed
+    fn child<'a>(&self, node: &'a Node) -> NodeDisplay<'a> {
+        NodeDisplay {
+            node,
+            depth: self.depth.saturating_sub(1),
+            one_line: self.one_line,
+            length: None,
+        }
+    }
+
+    /// Prints nodes of the one being printed, with `separator` between them
+    fn children(&self, f: &mut fmt::Formatter, nodes: &[Node], separator: &str) -> fmt::Result {
+        for (i, node) in nodes.iter().enumerate() {
+            if i > 0 {
+                f.write_str(separator)?;
+            }
+            write!(f, "{}", self.child(node))?;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the first `left` characters written to it, failing once there are more
+struct Truncated {
+    text: String,
+    left: usize,
+}
+
+impl fmt::Write for Truncated {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let count = s.chars().count();
+        if count > self.left {
+            self.text.extend(s.chars().take(self.left));
+            self.left = 0;
+            return Err(fmt::Error);
+        }
+        self.text.push_str(s);
+        self.left -= count;
+        Ok(())
+    }
+}
+
+impl fmt::Display for NodeDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(length) = self.length {
+            let mut text = Truncated {
+                text: String::new(),
+                left: length,
+            };
+            let whole = NodeDisplay {
+                length: None,
+                ..*self
+            };
+            let cut = fmt::Write::write_fmt(&mut text, format_args!("{}", whole)).is_err();
+            f.write_str(&text.text)?;
+            return if cut { f.write_str("…") } else { Ok(()) };
+        }
+        if self.depth == 0 {
+            return f.write_str("…");
+        }
+        match self.node {
+            Node::Converted(expr, ty) => write!(f, "{} as {}", self.child(expr), ty),
+            Node::AttrAccess(node, attr, _) => write!(f, "{}.{}", self.child(node), attr),
+            Node::StructConstructor(name, fields, ..) => {
+                write!(f, "{} {{", name)?;
+                for (i, (field, val)) in fields.iter().enumerate() {
+                    write!(f, "{} {}: ", if i == 0 { "" } else { "," }, field)?;
+                    write!(f, "{}", self.child(val))?;
+                }
+                write!(f, "}}")
+            }
+            Node::String(token) => write!(f, "String({})", token),
+            Node::Struct(name, fields, packed, _) => {
+                let packed = if *packed { "packed " } else { "" };
+                write!(f, "struct {}{} {{", packed, name)?;
+                for (name, ty) in fields {
+                    write!(f, " {}: {},", name, ty)?;
+                }
+                write!(f, "}}")
+            }
+            Node::Number(token) => write!(f, "Number({})", token),
+            Node::Boolean(token) => write!(f, "Boolean({})", token),
+            Node::VarAccess(token, _) => write!(f, "Var({})", token),
+            Node::BinaryOp(token, left, right, _) => {
+                write!(
+                    f,
+                    "BinaryOp({} {} {})",
+                    self.child(left),
+                    token,
+                    self.child(right)
+                )
+            }
+            Node::UnaryOp(token, expr, _) => write!(f, "UnaryOp({} {})", token, self.child(expr)),
+            Node::VarReassign(token, expr) => {
+                write!(f, "Reassign({} = {})", token, self.child(expr))
+            }
+            Node::VarAssign(token, expr, t) => {
+                write!(f, "Assign({} : {} = {})", token, t, self.child(expr))
+            }
+            Node::Statements(statements, ..) => {
+                if self.one_line {
+                    write!(f, "{{ ")?;
+                    self.children(f, statements, "; ")?;
+                    write!(f, " }}")
+                } else {
+                    writeln!(f, "{{")?;
+                    self.children(f, statements, "\n")?;
+                    write!(f, "\n}}")
+                }
+            }
+            Node::Call(token, args, _, _) => {
+                write!(f, "Call({}(", token)?;
+                self.children(f, args, ", ")?;
+                write!(f, "))")
+            }
+            Node::FuncDef(token, args, body, ret, _) => {
+                write!(
+                    f,
+                    "FuncDef({}({}) -> {:?} {})",
+                    token,
+                    args.iter()
+                        .map(|n| format!("{} : {:?}", n.0, n.1))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    ret,
+                    self.child(body)
+                )
+            }
+            Node::Lambda(args, body, ret, _) => {
+                write!(
+                    f,
+                    "Lambda(({}) -> {:?} {})",
+                    args.iter()
+                        .map(|n| format!("{} : {:?}", n.0, n.1))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    ret,
+                    self.child(body)
+                )
+            }
+            Node::While(cond, body, _) => {
+                write!(f, "while ({}) {}", self.child(cond), self.child(body))
+            }
+            Node::Return(expr, _) => {
+                write!(f, "Return({})", self.child(expr))
+            }
+            Node::Print(expr, newline, _) => {
+                write!(f, "{}(", if *newline { "Println" } else { "Print" })?;
+                self.children(f, expr, ", ")?;
+                write!(f, ")")
+            }
+            Node::Declarations(nodes, _) => {
+                write!(f, "Declarations(")?;
+                self.children(f, nodes, ", ")?;
+                write!(f, ")")
+            }
+            Node::Ascii(expr, _) => {
+                write!(f, "Ascii(")?;
+                self.children(f, expr, ", ")?;
+                write!(f, ")")
+            }
+            Node::Input(ty, prompt, _) => {
+                write!(f, "input")?;
+                if *ty == Type::Char {
+                    write!(f, " char")?;
+                }
+                match prompt {
+                    Some(prompt) => write!(f, " {:?}", prompt),
+                    None => Ok(()),
+                }
+            }
+            Node::Raw(code, _) => write!(f, "Raw({:?})", code),
+            Node::Ref(expr, ..) => {
+                write!(f, "Ref({})", self.child(expr))
+            }
+            Node::Pointer(expr, ..) => {
+                write!(f, "Pointer({})", self.child(expr))
+            }
+            Node::Deref(expr, ..) => {
+                write!(f, "Deref({})", self.child(expr))
+            }
+            Node::Ternary(cond, then, else_, ..) => {
+                write!(
+                    f,
+                    "Ternary({} ? {} : {})",
+                    self.child(cond),
+                    self.child(then),
+                    self.child(else_)
+                )
+            }
+            Node::If(cond, then, Some(else_), ..) => {
+                write!(
+                    f,
+                    "If( if {} then {} else {})",
+                    self.child(cond),
+                    self.child(then),
+                    self.child(else_)
+                )
+            }
+            Node::If(cond, then, None, _) => {
+                write!(f, "If( if {} then {})", self.child(cond), self.child(then))
+            }
+            Node::None(_) => write!(f, "None"),
+            Node::Char(c) => write!(f, "Char({})", c),
+            Node::Array(arr, 1, ..) => {
+                write!(f, "Array(")?;
+                self.children(f, arr, ", ")?;
+                write!(f, ")")
+            }
+            Node::Array(arr, count, ..) => {
+                write!(f, "Array(")?;
+                self.children(f, arr, ", ")?;
+                write!(f, "; {})", count)
+            }
+            Node::Index(arr, idx, ..) => {
+                write!(f, "Index({}[{}])", self.child(arr), self.child(idx))
+            }
+            Node::IndexAssign(arr, idx, expr) => {
+                write!(
+                    f,
+                    "IndexAssign({}[{}] = {})",
+                    self.child(arr),
+                    self.child(idx),
+                    self.child(expr)
+                )
+            }
+            Node::AttrAssign(expr, expr2, _) => {
+                write!(
+                    f,
+                    "AttrAssign({} = {})",
+                    self.child(expr),
+                    self.child(expr2)
+                )
+            }
+            Node::DerefAssign(expr, expr2, _) => {
+                write!(
+                    f,
+                    "DerefAssign({} = {})",
+                    self.child(expr),
+                    self.child(expr2)
+                )
+            }
+            Node::For(init, cond, step, body, _) => {
+                write!(
+                    f,
+                    "For(({} ; {} ; {}) : {})",
+                    self.child(init),
+                    self.child(cond),
+                    self.child(step),
+                    self.child(body)
+                )
+            }
+            Node::Expanded(name, nodes, t) => {
+                write!(f, "Expanded({}: ", name)?;
+                self.children(f, nodes, ", ")?;
+                write!(f, " -> {:?})", t)
+            }
+            Node::StaticVar(var, expr) => {
+                write!(f, "StaticVar({} = {})", var, self.child(expr))
+            }
+        }
+    }
+}
@@ -0,0 +1,796 @@
+use crate::utils::{
+    ast::{Node, Type},
+    diag::{Error, ErrorType, Position},
+    token::{Token, TokenType},
+};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VarType {
+    /// A variable, its name, and whether it has been initialized yet
+    Variable(Type, Token, bool),
+    Function(Token, Vec<Type>),
+    Struct(Vec<(Token, Type)>, Token),
+}
+
+/// The name of a struct, its fields, and whether its bool fields are packed into bits
+pub type StructDefinition = (Token, Vec<(Token, Type)>, bool);
+
+/// Scope struct
+/// It is used to find undefined variables and functions
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub signatures: Vec<(Token, Vec<Type>, Type)>,
+    pub structs: Vec<StructDefinition>,
+    pub defined: Vec<VarType>,
+    pub args: Option<Vec<(Token, Type)>>,
+    /// The return type of the function the scope is the body of
+    pub ret: Option<Type>,
+    pub scopes: Vec<Scope>,
+    pub parent: Option<Box<Scope>>,
+    /// The part of the code the scope covers, `None` for the global scope
+    pub span: Option<Position>,
+    /// Every use of a symbol looked up from this scope, and where that symbol was declared
+    pub references: Vec<Reference>,
+    /// The lengths of the variables declared with an array literal, by their declaration
+    pub array_lengths: Vec<(Position, usize)>,
+}
+
+/// A use of a variable, function or struct, linked to its declaration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub usage: Position,
+    pub definition: Position,
+}
+
+/// What kind of symbol a [`SymbolInfo`] is
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    /// A function, with the types of its parameters and its return type
+    Function(Vec<Type>, Type),
+    Struct,
+}
+
+/// A variable, function or struct that can be used at some point in the code
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The type of the symbol, if it is a variable or a struct
+    pub type_: Option<Type>,
+    /// Where the symbol was declared
+    pub position: Position,
+}
+
+/// The result of checking a program without generating any code for it
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub ast: Node,
+    pub scope: Scope,
+    /// The references of every scope in the program
+    pub references: Vec<Reference>,
+}
+
+impl Analysis {
+    /// Returns the symbols visible at `pos`, see [`Scope::symbols_at`]
+    pub fn symbols_at(&self, pos: &Position) -> Vec<SymbolInfo> {
+        self.scope.symbols_at(pos)
+    }
+
+    /// Finds the symbol used or declared at `pos`.
+    /// Returns where it was declared, and every place it is used in
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::diag::Position;
+    /// use ezlang::utils::diag::FileName;
+    ///
+    /// let code = "let a = 1\n{\n    let a = 2\n    ezout a\n}\nezout a";
+    /// let analysis = ezlang::analyze(code, "main.ez".to_string()).unwrap();
+    /// let refs = |line, column| {
+    ///     let pos = Position::new(line, column, column, FileName::new("main.ez"));
+    ///     let (definition, usages) = analysis.references_at(&pos).unwrap();
+    ///     let usages: Vec<_> = usages.iter().map(|p| (p.line_start, p.start)).collect();
+    ///     ((definition.line_start, definition.start), usages)
+    /// };
+    ///
+    /// // The inner `a` shadows the outer one
+    /// assert_eq!(refs(4, 11), ((3, 9), vec![(4, 11)]));
+    /// assert_eq!(refs(1, 5), ((1, 5), vec![(6, 7)]));
+    /// ```
+    pub fn references_at(&self, pos: &Position) -> Option<(Position, Vec<Position>)> {
+        let definition = self
+            .references
+            .iter()
+            .find(|r| r.usage.contains(pos))
+            .map(|r| r.definition.clone())
+            .or_else(|| self.scope.declaration_at(pos))?;
+        let mut usages = self
+            .references
+            .iter()
+            .filter(|r| r.definition == definition)
+            .map(|r| r.usage.clone())
+            .collect::<Vec<_>>();
+        usages.sort_by_key(|p| (p.file.clone(), p.line_start, p.start));
+        Some((definition, usages))
+    }
+}
+
+impl Scope {
+    pub fn new(parent: Option<&Scope>) -> Self {
+        Self {
+            structs: vec![],
+            signatures: vec![],
+            defined: vec![],
+            scopes: vec![],
+            args: None,
+            ret: None,
+            span: None,
+            references: vec![],
+            array_lengths: vec![],
+            parent: parent.map(|p| {
+                // The finished child scopes of the parent are never looked at from here,
+                // copying them along would make every nested scope exponentially larger
+                Box::new(Self {
+                    signatures: p.signatures.clone(),
+                    structs: p.structs.clone(),
+                    defined: p.defined.clone(),
+                    args: p.args.clone(),
+                    ret: p.ret.clone(),
+                    scopes: vec![],
+                    parent: p.parent.clone(),
+                    span: p.span.clone(),
+                    references: vec![],
+                    array_lengths: p.array_lengths.clone(),
+                })
+            }),
+        }
+    }
+
+    /// Lists the variables, functions and structs that can be used at `pos`.
+    /// Symbols of inner scopes come first, and variables shadowed by an inner
+    /// or later declaration are left out
+    pub fn symbols_at(&self, pos: &Position) -> Vec<SymbolInfo> {
+        let mut symbols = self
+            .scopes
+            .iter()
+            .find(|scope| scope.span.as_ref().is_some_and(|span| span.contains(pos)))
+            .map_or_else(Vec::new, |scope| scope.symbols_at(pos));
+        let args = self.args.iter().flatten().map(|(token, t)| (token, t));
+        let variables = self.defined.iter().rev().filter_map(|var| match var {
+            VarType::Variable(t, token, _) if token.position.is_before(pos) => Some((token, t)),
+            _ => None,
+        });
+        for (token, t) in variables.chain(args) {
+            let name = token.token_type.to_string();
+            if !symbols
+                .iter()
+                .any(|s| s.kind == SymbolKind::Variable && s.name == name)
+            {
+                symbols.push(SymbolInfo {
+                    name,
+                    kind: SymbolKind::Variable,
+                    type_: Some(t.clone()),
+                    position: token.position.clone(),
+                });
+            }
+        }
+        for (token, params, ret) in &self.signatures {
+            symbols.push(SymbolInfo {
+                name: token.token_type.to_string(),
+                kind: SymbolKind::Function(params.clone(), ret.clone()),
+                type_: None,
+                position: token.position.clone(),
+            });
+        }
+        for (token, fields, packed) in &self.structs {
+            symbols.push(SymbolInfo {
+                name: token.token_type.to_string(),
+                kind: SymbolKind::Struct,
+                type_: Some(Type::Struct(token.clone(), fields.clone(), *packed)),
+                position: token.position.clone(),
+            });
+        }
+        symbols
+    }
+
+    /// Returns the references of this scope and all of the scopes inside of it
+    pub fn all_references(&self) -> Vec<Reference> {
+        let mut references = self.references.clone();
+        for scope in &self.scopes {
+            references.extend(scope.all_references());
+        }
+        references
+    }
+
+    /// Finds the declaration of a variable, function or struct that contains `pos`
+    pub fn declaration_at(&self, pos: &Position) -> Option<Position> {
+        let variables = self.defined.iter().filter_map(|var| match var {
+            VarType::Variable(_, token, _) => Some(token),
+            _ => None,
+        });
+        let args = self.args.iter().flatten().map(|(token, _)| token);
+        let functions = self.signatures.iter().map(|(token, ..)| token);
+        let structs = self.structs.iter().map(|(token, ..)| token);
+        variables
+            .chain(args)
+            .chain(functions)
+            .chain(structs)
+            .map(|token| &token.position)
+            .find(|position| position.contains(pos))
+            .cloned()
+            .or_else(|| self.scopes.iter().find_map(|s| s.declaration_at(pos)))
+    }
+
+    /// Returns where the variable `token` visible from this scope was declared
+    pub(crate) fn variable_definition(&self, token: &Token) -> Option<Position> {
+        let variable = self.defined.iter().rev().find_map(|var| match var {
+            VarType::Variable(_, n, _) if n == token => Some(n),
+            _ => None,
+        });
+        let arg = || {
+            self.args
+                .iter()
+                .flatten()
+                .find(|(n, _)| n == token)
+                .map(|(n, _)| n)
+        };
+        match variable.or_else(arg) {
+            Some(n) => Some(n.position.clone()),
+            None => self.parent.as_ref()?.variable_definition(token),
+        }
+    }
+
+    /// Returns the parameters and the return type of the function stored in the variable
+    /// `token`, if it holds one
+    pub fn function_variable(&self, token: &Token) -> Option<(Vec<Type>, Type)> {
+        let variable = self.defined.iter().rev().find_map(|var| match var {
+            VarType::Variable(t, n, _) if n == token => Some(t),
+            _ => None,
+        });
+        let arg = || {
+            self.args
+                .iter()
+                .flatten()
+                .find(|(n, _)| n == token)
+                .map(|(_, t)| t)
+        };
+        match variable.or_else(arg) {
+            Some(Type::Function(params, ret)) => Some((params.clone(), (**ret).clone())),
+            Some(_) => None,
+            None => self.parent.as_ref()?.function_variable(token),
+        }
+    }
+
+    /// Returns the type of the function called `token`, if there is exactly one function
+    /// with that name
+    pub fn function_type(&self, token: &Token) -> Option<Type> {
+        let mut functions = self.signatures.iter().filter(|(name, ..)| name == token);
+        match (functions.next(), functions.next()) {
+            (Some((_, params, ret)), None) => {
+                Some(Type::Function(params.clone(), Box::new(ret.clone())))
+            }
+            (Some(_), Some(_)) => None,
+            (None, _) => self.parent.as_ref()?.function_type(token),
+        }
+    }
+
+    /// Returns the length of the array the variable `token` was declared with,
+    /// if it was declared with an array literal
+    pub fn array_length(&self, token: &Token) -> Option<usize> {
+        let definition = self.variable_definition(token)?;
+        let mut scope = Some(self);
+        while let Some(s) = scope {
+            if let Some((_, length)) = s.array_lengths.iter().find(|(p, _)| *p == definition) {
+                return Some(*length);
+            }
+            scope = s.parent.as_deref();
+        }
+        None
+    }
+
+    /// Returns the numbers of parameters of the functions called `token`, sorted and without
+    /// duplicates. It is empty if there is no such function, or a variable hides it
+    pub fn function_arities(&self, token: &Token) -> Vec<usize> {
+        if self.variable_definition(token).is_some() {
+            return vec![];
+        }
+        let mut arities = vec![];
+        let mut scope = Some(self);
+        while let Some(s) = scope {
+            arities.extend(
+                s.signatures
+                    .iter()
+                    .filter(|(name, ..)| name == token)
+                    .map(|(_, params, _)| params.len()),
+            );
+            scope = s.parent.as_deref();
+        }
+        arities.sort_unstable();
+        arities.dedup();
+        arities
+    }
+
+    /// Checks whether `token` names a struct, and no variable hides it
+    pub fn names_struct(&self, token: &Token) -> bool {
+        self.variable_definition(token).is_none() && self.struct_definition(token).is_some()
+    }
+
+    fn function_definition(&self, token: &Token, args: &[Node]) -> Option<Position> {
+        match self.signatures.iter().find(|(name, params, _)| {
+            name == token
+                && params.len() == args.len()
+                && args.iter().zip(params).all(|(a, p)| a.get_type() == *p)
+        }) {
+            Some((name, ..)) => Some(name.position.clone()),
+            None => self.parent.as_ref()?.function_definition(token, args),
+        }
+    }
+
+    fn struct_definition(&self, token: &Token) -> Option<Position> {
+        match self.structs.iter().find(|(name, ..)| name == token) {
+            Some((name, ..)) => Some(name.position.clone()),
+            None => self.parent.as_ref()?.struct_definition(token),
+        }
+    }
+
+    /// Links the use of a symbol at `usage` to where it was declared
+    fn add_reference(&mut self, usage: &Token, definition: Option<Position>) {
+        if let Some(definition) = definition {
+            if !self.references.iter().any(|r| r.usage == usage.position) {
+                self.references.push(Reference {
+                    usage: usage.position.clone(),
+                    definition,
+                });
+            }
+        }
+    }
+
+    /// Checks whether this scope is a function body or is nested in one
+    pub fn in_function(&self) -> bool {
+        self.args.is_some() || self.parent.as_ref().is_some_and(|p| p.in_function())
+    }
+
+    /// The return type of the innermost function the scope is in
+    pub fn return_type(&self) -> Option<&Type> {
+        self.ret
+            .as_ref()
+            .or_else(|| self.parent.as_ref().and_then(|p| p.return_type()))
+    }
+
+    pub fn register_struct(&mut self, struct_: Node) -> Option<Error> {
+        let pos = struct_.position();
+        if let Node::Struct(token, fields, ..) = struct_ {
+            if self
+                .defined
+                .iter()
+                .any(|a| matches!(a, VarType::Struct(_, a) if *a == token))
+            {
+                return Some(Error::new(
+                    ErrorType::Redefinition,
+                    pos,
+                    format!("Struct {} already defined", token),
+                ));
+            } else {
+                self.defined.push(VarType::Struct(fields, token));
+            }
+        } else {
+            unreachable!();
+        }
+        None
+    }
+
+    /// Registers a function defined in this scope.
+    /// Functions can share a name, as long as they take a different number of arguments
+    pub fn register_function(&mut self, func: Node) -> Option<Error> {
+        if let Node::FuncDef(token, args, ..) = func {
+            if self
+                .defined
+                .iter()
+                .any(|a| matches!(a, VarType::Function(a, args1) if *a == token && args.len() == args1.len()))
+            {
+                return Some(Error::new(
+                    ErrorType::Redefinition,
+                    token.position.clone(),
+                    format!(
+                        "Function {} taking {} arguments is already defined",
+                        token,
+                        args.len()
+                    ),
+                ));
+            } else {
+                self.defined.push(VarType::Function(
+                    token,
+                    args.iter().map(|a| a.1.clone()).collect(),
+                ));
+            }
+        } else {
+            unreachable!();
+        }
+        None
+    }
+
+    pub fn register_signature(&mut self, func: (Token, Vec<Type>, Type)) {
+        self.signatures.push(func);
+    }
+
+    pub fn register_struct_premature(&mut self, struct_: StructDefinition) {
+        self.structs.push(struct_);
+    }
+
+    pub fn register_variable(&mut self, assign_node: Node) {
+        if let Node::VarAssign(token, e, t) = assign_node {
+            if let Node::Array(ref elements, count, ..) = *e {
+                self.array_lengths
+                    .push((token.position.clone(), elements.len() * count));
+            }
+            let initialized = !matches!(*e, Node::None(_));
+            self.defined.push(VarType::Variable(t, token, initialized));
+        } else if let Node::StaticVar(token, e) = assign_node {
+            let t = e.get_type();
+            self.defined.push(VarType::Variable(t, token, true));
+        } else {
+            unreachable!();
+        }
+    }
+
+    pub fn access_variable(&mut self, node: &Node) -> Result<Type, Error> {
+        match &node {
+            Node::VarAccess(token, _) | Node::VarReassign(token, ..) => {
+                self.add_reference(token, self.variable_definition(token));
+                if let Some(a) = self
+                    .defined
+                    .iter()
+                    .rev()
+                    .find(|a| matches!(a, VarType::Variable(_, n, _) if n == token))
+                {
+                    if let VarType::Variable(t, _, _) = a {
+                        Ok(t.clone())
+                    } else {
+                        unreachable!();
+                    }
+                } else {
+                    if let Some(args) = self.args.as_ref() {
+                        if let Some(arg) = args.iter().find(|t| t.0 == *token) {
+                            return Ok(arg.1.clone());
+                        }
+                    }
+                    if let Some(ref mut parent) = self.parent {
+                        return parent.access_variable(node);
+                    }
+                    Err(Error::new(
+                        ErrorType::UndefinedVariable,
+                        token.position.clone(),
+                        format!("Variable {} is not defined", token),
+                    ))
+                }
+            }
+            Node::IndexAssign(arr, ..) | Node::Index(arr, ..) => match self.access_variable(arr)? {
+                Type::Pointer(t) => Ok(*t),
+                t => Err(Error::new(
+                    ErrorType::TypeError,
+                    arr.position(),
+                    format!("Cannot index type {}, it is not an array", t),
+                )),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn access_variable_by_token(&mut self, token: &Token) -> Result<Type, Error> {
+        self.add_reference(token, self.variable_definition(token));
+        if let Some(a) = self
+            .defined
+            .iter()
+            .rev()
+            .find(|a| matches!(a, VarType::Variable(_, n, _) if n == token))
+        {
+            if let VarType::Variable(t, _, initialized) = a {
+                if !initialized {
+                    return Err(uninitialized(token));
+                }
+                Ok(t.clone())
+            } else {
+                unreachable!();
+            }
+        } else {
+            if let Some(args) = self.args.as_ref() {
+                if let Some(arg) = args.iter().find(|t| t.0 == *token) {
+                    return Ok(arg.1.clone());
+                }
+            }
+            if let Some(parent) = self.parent.as_mut() {
+                return parent.access_variable_by_token(token);
+            }
+            Err(Error::new(
+                ErrorType::UndefinedVariable,
+                token.position.clone(),
+                format!("Variable {} is not defined", token),
+            ))
+        }
+    }
+
+    /// Looks up a variable that is being assigned to, and marks it as initialized
+    pub fn assign_variable(&mut self, token: &Token) -> Result<Type, Error> {
+        self.add_reference(token, self.variable_definition(token));
+        if let Some(VarType::Variable(t, _, initialized)) = self
+            .defined
+            .iter_mut()
+            .rev()
+            .find(|a| matches!(a, VarType::Variable(_, n, _) if n == token))
+        {
+            *initialized = true;
+            return Ok(t.clone());
+        }
+        if let Some(args) = self.args.as_ref() {
+            if let Some(arg) = args.iter().find(|t| t.0 == *token) {
+                return Ok(arg.1.clone());
+            }
+        }
+        if let Some(parent) = self.parent.as_mut() {
+            return parent.assign_variable(token);
+        }
+        Err(Error::new(
+            ErrorType::UndefinedVariable,
+            token.position.clone(),
+            format!("Variable {} is not defined", token),
+        ))
+    }
+
+    /// Whether each variable of the scope, and of every scope around it, is initialized
+    pub fn initialized(&self) -> Vec<Vec<bool>> {
+        let mut scope = Some(self);
+        let mut initialized = vec![];
+        while let Some(current) = scope {
+            initialized.push(
+                current
+                    .defined
+                    .iter()
+                    .map(|var| matches!(var, VarType::Variable(_, _, true)))
+                    .collect(),
+            );
+            scope = current.parent.as_deref();
+        }
+        initialized
+    }
+
+    /// Sets which variables are initialized to what [`Scope::initialized`] returned. It is used
+    /// to forget the variables initialized by code that doesn't always run, like a loop
+    /// # Examples
+    /// ```
+    /// let check = |body: &str| {
+    ///     let code = format!("let c = ezin == 1\nlet x: int\n{}\nezout x", body);
+    ///     let result = ezlang::parse(&code, "main.ez".to_string());
+    ///     result.map(|_| ()).map_err(|e| format!("{:?}", e.error_type))
+    /// };
+    /// assert!(check("{ x = 5 }").is_ok());
+    /// assert!(check("if (c) { x = 5 } else x = 6").is_ok());
+    /// assert!(check("if (c) { if (!c) { x = 1 } else { x = 2 } } else { x = 3 }").is_ok());
+    ///
+    /// // The variable isn't initialized if the code assigning it might not run
+    /// assert_eq!(check("if (c) { x = 5 }"), Err("UseBeforeInit".to_string()));
+    /// assert_eq!(check("if (c) x = 5"), Err("UseBeforeInit".to_string()));
+    /// assert_eq!(check("if (c) { x = 5 } else { c = false }"), Err("UseBeforeInit".to_string()));
+    /// assert_eq!(check("while (c) { x = 5\nc = false }"), Err("UseBeforeInit".to_string()));
+    /// assert_eq!(check("for (let i = 0 : i < 3 : i += 1) { x = 5 }"), Err("UseBeforeInit".to_string()));
+    /// assert_eq!(check("for i in 0..3 { x = i }"), Err("UseBeforeInit".to_string()));
+    /// ```
+    pub fn set_initialized(&mut self, initialized: &[Vec<bool>]) {
+        let mut scope = Some(self);
+        for vars in initialized {
+            let Some(current) = scope else {
+                break;
+            };
+            for (var, &set) in current.defined.iter_mut().zip(vars) {
+                if let VarType::Variable(_, _, initialized) = var {
+                    *initialized = set;
+                }
+            }
+            scope = current.parent.as_deref_mut();
+        }
+    }
+
+    /// Child scopes only hold a copy of their parent, so variables initialized inside
+    /// of a block have to be copied back once the block ends
+    pub fn merge_initialized(&mut self, updated: &Scope) {
+        for (var, updated_var) in self.defined.iter_mut().zip(updated.defined.iter()) {
+            if let (VarType::Variable(_, _, initialized), VarType::Variable(_, _, true)) =
+                (var, updated_var)
+            {
+                *initialized = true;
+            }
+        }
+        if let (Some(parent), Some(updated)) = (self.parent.as_mut(), updated.parent.as_ref()) {
+            parent.merge_initialized(updated);
+        }
+    }
+
+    /// Finds the return type of the function called by `node`. Functions with the same name are
+    /// told apart by the number and the types of their arguments
+    /// # Examples
+    /// ```
+    /// let code = "
+    /// ez area(w: int) -> int { return w * w }
+    /// ez area(w: int, h: int) -> int { return w * h }
+    /// ezout area(3), area(2, 5)
+    /// ";
+    /// let code = ezlang::run(code, "main.ez".to_string()).unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&code, &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"9 10");
+    /// ```
+    pub fn access_function(&mut self, node: &Node) -> Result<Type, Error> {
+        match &node {
+            Node::Call(token1, args1, ..) => {
+                self.add_reference(token1, self.function_definition(token1, args1));
+                if let Some(a) = self.signatures.iter().find(|(name, args, _)| {
+                    name == token1
+                        && args.len() == args1.len()
+                        && args1
+                            .iter()
+                            .zip(args.iter())
+                            .all(|(a, p)| a.get_type() == *p)
+                }) {
+                    Ok(a.2.clone())
+                } else {
+                    if let Some(ref mut parent) = self.parent {
+                        return parent.access_function(node);
+                    }
+                    let mut arities = self
+                        .signatures
+                        .iter()
+                        .filter(|(name, ..)| name == token1)
+                        .map(|(_, args, _)| args.len())
+                        .collect::<Vec<_>>();
+                    arities.sort_unstable();
+                    arities.dedup();
+                    let details = if arities.is_empty() || arities.contains(&args1.len()) {
+                        format!("Function {} is not defined", token1)
+                    } else {
+                        format!(
+                            "Function {} is not defined for {} arguments, it takes {} arguments",
+                            token1,
+                            args1.len(),
+                            arities
+                                .iter()
+                                .map(|n| n.to_string())
+                                .collect::<Vec<_>>()
+                                .join(" or ")
+                        )
+                    };
+                    Err(Error::new(
+                        ErrorType::UndefinedFunction,
+                        token1.position.clone(),
+                        details,
+                    ))
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Checks that a struct constructor fills every field of its struct with a value of the
+    /// right type, and returns the type of the struct
+    /// # Examples
+    /// ```
+    /// let code = "struct Point { x: int, y: int }\nlet p = Point { y: 2, x: true }";
+    /// let err = ezlang::parse(code, "main.ez".to_string()).unwrap_err();
+    /// assert_eq!(err.details, "field `x` of `Point` expects `int`, found `bool`");
+    /// ```
+    pub fn access_struct(&mut self, node: &Node) -> Result<Type, Error> {
+        match &node {
+            Node::StructConstructor(token1, attrs1, ..) => {
+                self.add_reference(token1, self.struct_definition(token1));
+                if let Some((_, attrs, packed)) = self.structs.iter().find(|a| a.0 == *token1) {
+                    if attrs.len() != attrs1.len()
+                        || !attrs
+                            .iter()
+                            .all(|(t, _)| attrs1.iter().any(|(t1, _)| t1 == t))
+                    {
+                        return Err(Error::new(
+                            ErrorType::TypeError,
+                            token1.position.clone(),
+                            format!("All fields of struct {} are not filled", token1,),
+                        ));
+                    }
+                    // Checked in the order the fields were written in, so the first
+                    // wrong one gets reported
+                    for (field, value) in attrs1 {
+                        let (_, expected) = attrs.iter().find(|(t, _)| t == field).unwrap();
+                        if *expected != value.get_type() {
+                            return Err(Error::new(
+                                ErrorType::TypeError,
+                                value.position(),
+                                format!(
+                                    "field `{}` of `{}` expects `{}`, found `{}`",
+                                    field.token_type,
+                                    token1.token_type,
+                                    expected,
+                                    value.get_type()
+                                ),
+                            ));
+                        }
+                    }
+                    Ok(Type::Struct(token1.clone(), attrs.clone(), *packed))
+                } else {
+                    if let Some(ref mut parent) = self.parent {
+                        return parent.access_struct(node);
+                    }
+                    Err(Error::new(
+                        ErrorType::UndefinedStruct,
+                        token1.position.clone(),
+                        format!("Struct {} is not defined", token1),
+                    ))
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Looks up the struct named `token`, and returns its type
+    pub fn access_struct_by_token(&mut self, token: &Token) -> Result<Type, Error> {
+        self.add_reference(token, self.struct_definition(token));
+        if let Some((name, fields, packed)) = self.structs.iter().find(|a| a.0 == *token) {
+            Ok(Type::Struct(name.clone(), fields.clone(), *packed))
+        } else {
+            if let Some(ref mut parent) = self.parent {
+                return parent.access_struct_by_token(token);
+            }
+            Err(Error::new(
+                ErrorType::UndefinedStruct,
+                token.position.clone(),
+                format!("Struct {} is not defined", token),
+            ))
+        }
+    }
+
+    pub fn get_fields_by_token(&mut self, token: &Token) -> Option<&Vec<(Token, Type)>> {
+        match token.token_type {
+            TokenType::Identifier(ref t) => {
+                if let Some(VarType::Struct(x, _)) = self.defined.iter().find(|a| {
+                    if let VarType::Struct(
+                        _,
+                        Token {
+                            token_type: TokenType::Identifier(ref name),
+                            ..
+                        },
+                    ) = a
+                    {
+                        name == t
+                    } else {
+                        false
+                    }
+                }) {
+                    return Some(x);
+                }
+            }
+            _ => unreachable!(),
+        }
+        if let Some(ref mut parent) = self.parent {
+            return parent.get_fields_by_token(token);
+        }
+        None
+    }
+}
+
+fn uninitialized(token: &Token) -> Error {
+    Error::new(
+        ErrorType::UseBeforeInit,
+        token.position.clone(),
+        format!("Variable {} is used before being initialized", token),
+    )
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn delete_parent(scope: &mut Scope) {
+            scope.parent = None;
+            for child in &mut scope.scopes {
+                delete_parent(child);
+            }
+        }
+        let mut obj = self.clone();
+        delete_parent(&mut obj);
+        write!(f, "{:#?}", obj)
+    }
+}
@@ -0,0 +1,367 @@
+use std::error::Error as stdError;
+use std::{fmt, hash::Hash, ops::Deref, rc::Rc};
+
+/// An enum to specify the type of the error.
+#[derive(Debug, Clone)]
+pub enum ErrorType {
+    InvalidLiteral,
+    NumberTooLarge,
+    SyntaxError,
+    UndefinedFunction,
+    UndefinedStruct,
+    UndefinedVariable,
+    UseBeforeInit,
+    InvalidReturn,
+    TypeError,
+    IndexOutOfBounds,
+    FileNotFound,
+    Redefinition,
+    RecursionError,
+    PreprocessorError,
+    InvalidEncoding,
+    /// Expanding the function calls made the program bigger than allowed
+    ExpansionTooLarge,
+    /// A warning whose severity was set to `error`
+    Warning,
+    /// The intermediate code is invalid, like code changed by a hook of
+    /// [`crate::core::pipeline::Pipeline`]
+    InvalidCode,
+}
+
+/// An error that can occur during the compilation of the source code.
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub error_type: ErrorType,
+    pub position: Position,
+    pub details: String,
+    /// Other positions related to the error, like the start of an unclosed block
+    pub labels: Vec<(Position, String)>,
+    /// A change to the code that fixes the error. It is boxed, since it is rarely there
+    pub suggestion: Option<Box<Suggestion>>,
+}
+
+impl Error {
+    pub fn new(error_type: ErrorType, position: Position, details: String) -> Self {
+        Self {
+            error_type,
+            position,
+            details,
+            labels: vec![],
+            suggestion: None,
+        }
+    }
+
+    /// Adds a secondary position to the error, explained by `label`
+    pub fn with_label(mut self, position: Position, label: String) -> Self {
+        self.labels.push((position, label));
+        self
+    }
+
+    /// Adds a change to the code that fixes the error
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestion = Some(Box::new(suggestion));
+        self
+    }
+
+    /// Draws the line of `source` the error is on, with carets under the part of it the
+    /// error is about, followed by the lines of the labels in the same file and the suggestion.
+    /// An empty position, like the end of the file, gets a single caret
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::diag::FileName;
+    ///
+    /// let code = "let a = 5\nezout (a + 2\n";
+    /// let err = ezlang::core::lexer::lex(code, FileName::new("main.ez")).unwrap_err();
+    /// assert_eq!((err.position.line_start, err.position.start), (2, 13));
+    /// assert_eq!(
+    ///     err.snippet(code),
+    ///     concat!(
+    ///         "2 | ezout (a + 2\n",
+    ///         "  |             ^ Expected ')' before the end of the file\n",
+    ///         "2 | ezout (a + 2\n",
+    ///         "  |       - unclosed '(' opened here\n",
+    ///     )
+    /// );
+    ///
+    /// // Tabs before the error are kept, so the carets line up with the code
+    /// let code = "ez f() {\n\tezout 1 $ 2\n}";
+    /// let err = ezlang::core::lexer::lex(code, FileName::new("main.ez")).unwrap_err();
+    /// assert_eq!(err.snippet(code).lines().nth(1), Some("  | \t        ^ Unexpected '$'"));
+    /// ```
+    pub fn snippet(&self, source: &str) -> String {
+        let mut snippet = String::new();
+        let mut marked = vec![(&self.position, '^', &self.details)];
+        marked.extend(
+            self.labels
+                .iter()
+                .filter(|(p, _)| p.file == self.position.file && p.included_from.is_none())
+                .map(|(p, label)| (p, '-', label)),
+        );
+        let width = marked
+            .iter()
+            .map(|(p, ..)| p.line_start.to_string().len())
+            .max()
+            .unwrap();
+        for (position, marker, text) in marked {
+            let line = match source.lines().nth(position.line_start.wrapping_sub(1)) {
+                Some(line) => line,
+                None => continue,
+            };
+            let length = if position.line_end != position.line_start {
+                (line.chars().count() + 1)
+                    .saturating_sub(position.start)
+                    .max(1)
+            } else {
+                position.end.saturating_sub(position.start).max(1)
+            };
+            snippet += &format!("{:>width$} | {}\n", position.line_start, line);
+            snippet += &format!(
+                "{:>width$} | {}{} {}\n",
+                "",
+                padding(line, position.start),
+                marker.to_string().repeat(length),
+                text
+            );
+        }
+        if let Some(suggestion) = &self.suggestion {
+            snippet += &format!("{:>width$} = help: {}\n", "", suggestion.message);
+        }
+        snippet
+    }
+}
+
+/// The whitespace before column `start` of `line`. Tabs are kept, so that the markers line up
+/// with the line however wide the terminal shows a tab
+fn padding(line: &str, start: usize) -> String {
+    // Positions can be past the end of the line, like the end of the file
+    line.chars()
+        .chain(std::iter::repeat(' '))
+        .take(start.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} in {} at {}:{} to {}:{} :: {}",
+            self.error_type,
+            self.position.file,
+            self.position.line_start,
+            self.position.start,
+            self.position.line_end,
+            self.position.end,
+            self.details
+        )?;
+        let mut included_from = &self.position.included_from;
+        while let Some(position) = included_from {
+            if position.is_prelude() {
+                write!(f, "\n    included as the prelude of {}", position.file)?;
+            } else {
+                write!(
+                    f,
+                    "\n    included from {} at {}:{}",
+                    position.file, position.line_start, position.start
+                )?;
+            }
+            included_from = &position.included_from;
+        }
+        for (position, label) in &self.labels {
+            write!(
+                f,
+                "\n    {} at {}:{}",
+                label, position.line_start, position.start
+            )?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n    help: {}", suggestion.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl stdError for Error {}
+
+/// A change to the code that fixes an error, made of edits that tools can apply
+/// # Examples
+/// A number used as a condition gets a suggestion to compare it with 0, or to write a bool
+/// instead of a literal 0 or 1
+/// ```
+/// let check = |code: &str| {
+///     let err = ezlang::parse(code, "main.ez".to_string()).unwrap_err();
+///     let mut fixed = code.to_string();
+///     // The code is on one line, and the edits are applied from the last one so that the
+///     // columns of the others stay the same
+///     for (position, text) in err.suggestion.as_ref().unwrap().edits.iter().rev() {
+///         fixed.replace_range(position.start - 1..position.end - 1, text);
+///     }
+///     (err.snippet(code), fixed)
+/// };
+///
+/// let (snippet, fixed) = check("while (1) ezout 1");
+/// assert_eq!(
+///     snippet,
+///     concat!(
+///         "1 | while (1) ezout 1\n",
+///         "  |        ^ Condition in a while loop can only be a bool, and numbers aren't turned into bools\n",
+///         "  = help: use `true` instead\n",
+///     )
+/// );
+/// assert_eq!(fixed, "while (true) ezout 1");
+///
+/// let (snippet, fixed) = check("if (0) ezout 1");
+/// assert!(snippet.ends_with("  = help: use `false` instead\n"));
+/// assert_eq!(fixed, "if (false) ezout 1");
+///
+/// let (snippet, fixed) = check("let n = 3; if (n) ezout n");
+/// assert_eq!(
+///     snippet,
+///     concat!(
+///         "1 | let n = 3; if (n) ezout n\n",
+///         "  |                ^ Condition in an if statement can only be a bool, and numbers aren't turned into bools\n",
+///         "  = help: compare explicitly: `n != 0`\n",
+///     )
+/// );
+/// assert_eq!(fixed, "let n = 3; if (n != 0) ezout n");
+///
+/// let (snippet, fixed) = check("let n = 3; if (n % 2) ezout n");
+/// assert!(snippet.ends_with("  = help: compare it explicitly with `!= 0`\n"));
+/// assert_eq!(fixed, "let n = 3; if (n % 2 != 0) ezout n");
+///
+/// let (_, fixed) = check("let n = 3; while (n > 2 ? n : 0) n -= 1");
+/// assert_eq!(fixed, "let n = 3; while ((n > 2 ? n : 0) != 0) n -= 1");
+///
+/// let err = ezlang::parse("if ('a') ezout 1", "main.ez".to_string()).unwrap_err();
+/// assert!(err.suggestion.is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// What the change is, shown after `help:`
+    pub message: String,
+    /// The positions to replace, with what to replace them with.
+    /// An empty position is where its text is inserted
+    pub edits: Vec<(Position, String)>,
+}
+
+impl Suggestion {
+    pub fn new(message: String, edits: Vec<(Position, String)>) -> Self {
+        Self { message, edits }
+    }
+}
+
+/// The name of a source file. The lexer makes one for each file it reads and every token
+/// of the file shares it, so comparing names mostly comes down to comparing pointers
+/// # Examples
+/// ```
+/// use ezlang::utils::diag::{FileName, Position};
+/// use std::rc::Rc;
+///
+/// let file = FileName::new("main.ez");
+/// assert_eq!(file, FileName::new(&String::from("main.ez")));
+/// assert_eq!((&*file, file.to_string()), ("main.ez", String::from("main.ez")));
+///
+/// // The positions of an included file share the `use` directive they were included from
+/// let mut position = Position::new(2, 1, 5, FileName::new("lib.ez"));
+/// position.included_from = Some(Rc::new(Position::new(1, 1, 8, file)));
+/// let copy = position.clone();
+/// assert_eq!(copy, position);
+/// assert!(Rc::ptr_eq(copy.included_from.as_ref().unwrap(), position.included_from.as_ref().unwrap()));
+/// ```
+#[derive(Clone, PartialOrd, Ord)]
+pub struct FileName(Rc<str>);
+
+// Positions of the same file share the name, so the pointers are compared first
+impl PartialEq for FileName {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for FileName {}
+
+impl Hash for FileName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl FileName {
+    pub fn new(name: &str) -> FileName {
+        FileName(Rc::from(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for FileName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for FileName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for FileName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// A position in the source code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub line_start: usize,
+    pub line_end: usize,
+    pub start: usize,
+    pub end: usize,
+    pub file: FileName,
+    /// Position of the `use` that included the file, if it was included
+    pub included_from: Option<Rc<Position>>,
+}
+
+impl Position {
+    pub fn new(line: usize, start: usize, end: usize, file: FileName) -> Position {
+        Position {
+            line_start: line,
+            line_end: line,
+            start,
+            end,
+            file,
+            included_from: None,
+        }
+    }
+
+    /// Checks whether this is the position of the implicit `use` of a prelude, which is
+    /// the only position on line 0
+    pub fn is_prelude(&self) -> bool {
+        self.line_start == 0
+    }
+
+    /// Checks whether `other` starts inside this position
+    pub fn contains(&self, other: &Position) -> bool {
+        self.file == other.file
+            && (self.line_start, self.start) <= (other.line_start, other.start)
+            && (other.line_start, other.start) < (self.line_end, self.end)
+    }
+
+    /// Checks whether this position starts before `other`.
+    /// Positions in other files, like included ones, are always before
+    pub fn is_before(&self, other: &Position) -> bool {
+        self.file != other.file || (self.line_start, self.start) < (other.line_start, other.start)
+    }
+
+    /// Moves the end of this position to the end of `other`
+    pub fn extend_to(&mut self, other: &Position) {
+        self.end = other.end;
+        self.line_end = other.line_end;
+    }
+}
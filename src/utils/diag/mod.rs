@@ -0,0 +1,8 @@
+/// Contains the Error struct
+mod error;
+
+/// Contains the Warning struct and the settings of warnings
+mod warning;
+
+pub use error::*;
+pub use warning::*;
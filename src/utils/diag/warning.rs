@@ -0,0 +1,163 @@
+use std::fmt;
+
+use crate::utils::diag::Position;
+
+/// The kinds of warnings the compiler can give
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A variable that is declared but never used
+    Unused,
+    /// Code that can never run, like the body of `while (false)`
+    Unreachable,
+    /// A condition that is always true or always false
+    ConstantCondition,
+    /// Strings concatenated in a loop, which copies them every time the loop runs
+    LoopConcat,
+    /// A comparison of a number with a literal that has the same result for every number,
+    /// since a number is a cell from 0 to 255, like `x < 0`
+    ConstantComparison,
+    /// An `undeclare` of a flag that isn't declared
+    UnknownFlag,
+    /// The message of a `warning` directive
+    Directive,
+}
+
+impl WarningKind {
+    pub const ALL: [WarningKind; 7] = [
+        WarningKind::Unused,
+        WarningKind::Unreachable,
+        WarningKind::ConstantCondition,
+        WarningKind::LoopConcat,
+        WarningKind::ConstantComparison,
+        WarningKind::UnknownFlag,
+        WarningKind::Directive,
+    ];
+
+    /// The name of the kind, used by `--warn` and by `// ez: allow(..)` comments
+    pub fn name(&self) -> &'static str {
+        match self {
+            WarningKind::Unused => "unused",
+            WarningKind::Unreachable => "unreachable",
+            WarningKind::ConstantCondition => "constant-condition",
+            WarningKind::LoopConcat => "loop-concat",
+            WarningKind::ConstantComparison => "constant-comparison",
+            WarningKind::UnknownFlag => "unknown-flag",
+            WarningKind::Directive => "directive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// The severity of the kind when it isn't configured.
+    /// Constant conditions are off, since the code they make unreachable is already reported
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            WarningKind::Unused
+            | WarningKind::Unreachable
+            | WarningKind::LoopConcat
+            | WarningKind::ConstantComparison
+            | WarningKind::UnknownFlag
+            | WarningKind::Directive => Severity::Warn,
+            WarningKind::ConstantCondition => Severity::Off,
+        }
+    }
+}
+
+/// What is done with the warnings of a kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The warnings are ignored
+    Off,
+    /// The warnings are reported, but the program still compiles
+    Warn,
+    /// The warnings are reported as errors
+    Error,
+}
+
+impl Severity {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" | "allow" => Some(Severity::Off),
+            "warn" => Some(Severity::Warn),
+            "error" | "deny" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the severity of a kind of warning, written like `unused=off`
+/// # Examples
+/// ```
+/// use ezlang::utils::diag::{parse_warning_setting, Severity, WarningKind};
+///
+/// assert_eq!(
+///     parse_warning_setting("unreachable=error"),
+///     Ok((WarningKind::Unreachable, Severity::Error))
+/// );
+/// assert!(parse_warning_setting("unused").is_err());
+/// assert!(parse_warning_setting("unused=loud").is_err());
+/// ```
+pub fn parse_warning_setting(setting: &str) -> Result<(WarningKind, Severity), String> {
+    let (kind, severity) = setting
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid warning setting: {}, expected kind=level", setting))?;
+    let kind = WarningKind::from_name(kind.trim()).ok_or_else(|| {
+        format!(
+            "Unknown warning: {}, expected one of {}",
+            kind,
+            WarningKind::ALL.map(|kind| kind.name()).join(", ")
+        )
+    })?;
+    let severity = Severity::from_name(severity.trim()).ok_or_else(|| {
+        format!(
+            "Invalid warning level: {}, expected off, warn or error",
+            severity
+        )
+    })?;
+    Ok((kind, severity))
+}
+
+/// Something in the code that is probably a mistake, but doesn't stop it from compiling
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub position: Position,
+    pub details: String,
+    /// Other positions the warning is about, like the operands of a comparison
+    pub labels: Vec<(Position, String)>,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, position: Position, details: String) -> Self {
+        Self {
+            kind,
+            position,
+            details,
+            labels: vec![],
+        }
+    }
+
+    /// Adds a secondary position to the warning, explained by `label`
+    pub fn with_label(mut self, position: Position, label: String) -> Self {
+        self.labels.push((position, label));
+        self
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Warning({}) in {} at {}:{} to {}:{} :: {}",
+            self.kind.name(),
+            self.position.file,
+            self.position.line_start,
+            self.position.start,
+            self.position.line_end,
+            self.position.end,
+            self.details
+        )
+    }
+}
@@ -0,0 +1,1003 @@
+use crate::utils::{
+    ast::Type,
+    diag::{Error, ErrorType, Position},
+    ir::{ValNumber, CELL_BITS, NONE_SIZE, POINTER_SIZE},
+    token::{Token, TokenType, BOOLEAN_EXCLUSIVE, BOOLEAN_OPERATORS},
+};
+use std::{collections::HashSet, fmt};
+
+/// An enum to specify the type of the instruction.
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    If(Val, usize, bool),
+    DerefAssign(Val, Val),
+    DerefRef(Val),
+    DerefAssignRef(Val, Val),
+    While(Val),
+    EndWhile(Val),
+    Clear(usize, usize),
+    Return(Val),
+    Call(usize, Vec<Val>),
+    Else(usize),
+    EndIf(usize, bool),
+    TernaryIf(Val, Val, Val),
+    Copy(Val),
+    Ref(usize),
+    Deref(Val),
+    LXor(Val, Val),
+    Input,
+    Add(Val, Val),
+    Sub(Val, Val),
+    Mul(Val, Val),
+    Div(Val, Val),
+    Mod(Val, Val),
+    Neg(Val),
+    Print(Val),
+    Ascii(Val),
+    Eq(Val, Val),
+    Neq(Val, Val),
+    Lt(Val, Val),
+    Le(Val, Val),
+    LAnd(Val, Val),
+    LOr(Val, Val),
+    LNot(Val),
+    Inc(Val),
+    Dec(Val),
+    Pow(Val, Val),
+    Shl(Val, Val),
+    Shr(Val, Val),
+    BAnd(Val, Val),
+    BOr(Val, Val),
+    BXor(Val, Val),
+    BNot(Val),
+    /// Brainfuck code that is put in the output as it is
+    Raw(String),
+}
+
+impl Instruction {
+    /// Converts a `Token` to an `Operator`.
+    /// # Arguments
+    /// * `token` - The `Token` to convert.
+    /// * `unary` - Whether the operator is unary.
+    /// # Returns
+    /// The `Instruction` variant corresponding to the `Token`.
+    pub fn from_token_binary(t: &Token) -> fn(Val, Val) -> Self {
+        match t.token_type {
+            TokenType::Add => Self::Add,
+            TokenType::Sub => Self::Sub,
+            TokenType::Mul => Self::Mul,
+            TokenType::Div => Self::Div,
+            TokenType::Mod => Self::Mod,
+            TokenType::Eq => Self::Eq,
+            TokenType::Neq => Self::Neq,
+            TokenType::Lt => Self::Lt,
+            TokenType::Le => Self::Le,
+            TokenType::LAnd => Self::LAnd,
+            TokenType::LOr => Self::LOr,
+            TokenType::Pow => Self::Pow,
+            TokenType::Shl => Self::Shl,
+            TokenType::Shr => Self::Shr,
+            TokenType::BAnd => Self::BAnd,
+            TokenType::LXor => Self::LXor,
+            TokenType::BOr => Self::BOr,
+            TokenType::BXor => Self::BXor,
+            _ => unreachable!("{}", t),
+        }
+    }
+
+    /// Returns the name of the kind of the instruction, like `Add`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::If(..) => "If",
+            Self::DerefAssign(..) => "DerefAssign",
+            Self::DerefRef(..) => "DerefRef",
+            Self::DerefAssignRef(..) => "DerefAssignRef",
+            Self::While(..) => "While",
+            Self::EndWhile(..) => "EndWhile",
+            Self::Clear(..) => "Clear",
+            Self::Return(..) => "Return",
+            Self::Call(..) => "Call",
+            Self::Else(..) => "Else",
+            Self::EndIf(..) => "EndIf",
+            Self::TernaryIf(..) => "TernaryIf",
+            Self::Copy(..) => "Copy",
+            Self::Ref(..) => "Ref",
+            Self::Deref(..) => "Deref",
+            Self::LXor(..) => "LXor",
+            Self::Input => "Input",
+            Self::Raw(_) => "Raw",
+            Self::Add(..) => "Add",
+            Self::Sub(..) => "Sub",
+            Self::Mul(..) => "Mul",
+            Self::Div(..) => "Div",
+            Self::Mod(..) => "Mod",
+            Self::Neg(..) => "Neg",
+            Self::Print(..) => "Print",
+            Self::Ascii(..) => "Ascii",
+            Self::Eq(..) => "Eq",
+            Self::Neq(..) => "Neq",
+            Self::Lt(..) => "Lt",
+            Self::Le(..) => "Le",
+            Self::LAnd(..) => "LAnd",
+            Self::LOr(..) => "LOr",
+            Self::LNot(..) => "LNot",
+            Self::Inc(..) => "Inc",
+            Self::Dec(..) => "Dec",
+            Self::Pow(..) => "Pow",
+            Self::Shl(..) => "Shl",
+            Self::Shr(..) => "Shr",
+            Self::BAnd(..) => "BAnd",
+            Self::BOr(..) => "BOr",
+            Self::BXor(..) => "BXor",
+            Self::BNot(..) => "BNot",
+        }
+    }
+
+    /// Returns the values the instruction reads
+    pub fn operands(&self) -> Vec<&Val> {
+        match self {
+            Self::DerefAssign(a, b)
+            | Self::DerefAssignRef(a, b)
+            | Self::LXor(a, b)
+            | Self::Add(a, b)
+            | Self::Sub(a, b)
+            | Self::Mul(a, b)
+            | Self::Div(a, b)
+            | Self::Mod(a, b)
+            | Self::Eq(a, b)
+            | Self::Neq(a, b)
+            | Self::Lt(a, b)
+            | Self::Le(a, b)
+            | Self::LAnd(a, b)
+            | Self::LOr(a, b)
+            | Self::Pow(a, b)
+            | Self::Shl(a, b)
+            | Self::Shr(a, b)
+            | Self::BAnd(a, b)
+            | Self::BOr(a, b)
+            | Self::BXor(a, b) => vec![a, b],
+            Self::TernaryIf(a, b, c) => vec![a, b, c],
+            Self::If(a, ..)
+            | Self::DerefRef(a)
+            | Self::While(a)
+            | Self::EndWhile(a)
+            | Self::Return(a)
+            | Self::Copy(a)
+            | Self::Deref(a)
+            | Self::Neg(a)
+            | Self::Print(a)
+            | Self::Ascii(a)
+            | Self::LNot(a)
+            | Self::Inc(a)
+            | Self::Dec(a)
+            | Self::BNot(a) => vec![a],
+            Self::Call(_, args) => args.iter().collect(),
+            Self::Clear(..)
+            | Self::Else(_)
+            | Self::EndIf(..)
+            | Self::Ref(_)
+            | Self::Input
+            | Self::Raw(_) => {
+                vec![]
+            }
+        }
+    }
+
+    pub fn from_token_unary(t: &Token) -> fn(Val) -> Self {
+        match t.token_type {
+            TokenType::Sub => Self::Neg,
+            TokenType::LNot => Self::LNot,
+            TokenType::Inc => Self::Inc,
+            TokenType::Dec => Self::Dec,
+            TokenType::BNot => Self::BNot,
+            _ => unreachable!("{}", t),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DerefAssign(val, expr) | Self::DerefAssignRef(val, expr) => {
+                write!(f, "{} = {}", val, expr)
+            }
+            Self::Clear(from, to) => write!(f, "clear {} - {}", from, to - 1),
+            Self::While(cond) => write!(f, "WHILE {}", cond),
+            Self::EndWhile(cond) => write!(f, "END WHILE {}", cond),
+            Self::Return(v) => write!(f, "return {}", v),
+            Self::Call(a, b) => write!(f, "call {:?} : {:?}", a, b),
+            Self::LXor(left, right) => write!(f, "{:?} !&| {:?}", left, right),
+            Self::TernaryIf(a, b, c) => write!(f, "if {:?} then {:?} else {:?}", a, b, c),
+            Self::Copy(val) => write!(f, "{:?}", val),
+            Self::Input => write!(f, "?"),
+            Self::Raw(code) => write!(f, "raw {:?}", code),
+            Self::Add(left, right) => write!(f, "{:?} + {:?}", left, right),
+            Self::Sub(left, right) => write!(f, "{:?} - {:?}", left, right),
+            Self::Mul(left, right) => write!(f, "{:?} * {:?}", left, right),
+            Self::Div(left, right) => write!(f, "{:?} / {:?}", left, right),
+            Self::Mod(left, right) => write!(f, "{:?} % {:?}", left, right),
+            Self::Neg(val) => write!(f, "-{:?}", val),
+            Self::Print(val) => write!(f, "print {:?}", val),
+            Self::Ascii(val) => write!(f, "ascii {:?}", val),
+            Self::Pow(base, exp) => write!(f, "{:?} ** {:?}", base, exp),
+            Self::Shl(left, right) => write!(f, "{:?} << {:?}", left, right),
+            Self::Shr(left, right) => write!(f, "{:?} >> {:?}", left, right),
+            Self::BAnd(left, right) => write!(f, "{:?} & {:?}", left, right),
+            Self::BOr(left, right) => write!(f, "{:?} | {:?}", left, right),
+            Self::BXor(left, right) => write!(f, "{:?} ^ {:?}", left, right),
+            Self::BNot(val) => write!(f, "~{:?}", val),
+            Self::Eq(left, right) => write!(f, "{:?} == {:?}", left, right),
+            Self::Neq(left, right) => write!(f, "{:?} != {:?}", left, right),
+            Self::Lt(left, right) => write!(f, "{:?} < {:?}", left, right),
+            Self::Le(left, right) => write!(f, "{:?} <= {:?}", left, right),
+            Self::LAnd(left, right) => write!(f, "{:?} && {:?}", left, right),
+            Self::LOr(left, right) => write!(f, "{:?} || {:?}", left, right),
+            Self::LNot(val) => write!(f, "!{:?}", val),
+            Self::Inc(val) => write!(f, "++{:?}", val),
+            Self::Dec(val) => write!(f, "--{:?}", val),
+            Self::Ref(mem) => write!(f, "&[{}]", mem),
+            Self::Deref(val) | Self::DerefRef(val) => write!(f, "*{:?}", val),
+            Self::If(cond, _, _) => write!(f, "IF {:?}", cond),
+            Self::Else(_) => write!(f, "ELSE"),
+            Self::EndIf(_, _) => write!(f, "ENDIF"),
+        }
+    }
+}
+
+/// An enum to specify the type of the value.
+#[derive(Clone, PartialEq)]
+pub enum Val {
+    /// A int.
+    Num(ValNumber),
+    /// A boolean.
+    Bool(bool),
+    /// An index (variable).
+    Index(usize, ValType),
+    /// None
+    None,
+    /// A reference.
+    Ref(usize, ValType),
+    /// A pointer.
+    Pointer(usize, ValType),
+    /// A Char
+    Char(u8),
+}
+
+impl Val {
+    /// # Panics
+    /// Panics if the variant is not `Self::Num or Self::Bool` or `Self::Char`.
+    pub fn get_int(&self) -> ValNumber {
+        match self {
+            Val::Num(num) => *num,
+            Val::Bool(b) => *b as ValNumber,
+            Val::Char(c) => *c as ValNumber,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn r#type(&self) -> ValType {
+        match self {
+            Val::Char(_) => ValType::Char,
+            Val::Num(_) => ValType::Number,
+            Val::Bool(_) => ValType::Boolean,
+            Val::Index(_, t) => t.clone(),
+            Val::Pointer(_, t) => ValType::Pointer(Box::new(t.clone())),
+            Val::None => ValType::None,
+            Val::Ref(_, t) => ValType::Ref(Box::new(t.clone())),
+        }
+    }
+
+    pub fn to_ptr(&self) -> Option<usize> {
+        match self {
+            Val::Pointer(ptr, _) => Some(*ptr),
+            Val::Num(num) => Some(*num as usize),
+            Val::Index(num, ValType::Number) => Some(*num),
+            Val::Index(ptr, ValType::Pointer(..)) => Some(*ptr),
+            _ => None,
+        }
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.r#type().get_size()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValType {
+    None,
+    Number,
+    Char,
+    Boolean,
+    Ref(Box<ValType>),
+    Pointer(Box<ValType>),
+    /// Name, fields, size, whether its bool fields are packed into bits
+    Struct(Token, Vec<(Token, ValType)>, usize, bool),
+    /// A cell of a packed array, holding [`CELL_BITS`] of its bools
+    Bits,
+}
+
+impl ValType {
+    pub fn is_ptr(&self) -> bool {
+        matches!(self, ValType::Pointer(..))
+    }
+
+    pub fn get_result_type(&self, rhs: &ValType, op: &Token) -> Option<Self> {
+        match (self, rhs) {
+            (Self::Number, Self::Number) => {
+                if BOOLEAN_OPERATORS.contains(&op.token_type) {
+                    Some(Self::Boolean)
+                } else if BOOLEAN_EXCLUSIVE.contains(&op.token_type) {
+                    None
+                } else {
+                    Some(Self::Number)
+                }
+            }
+            (Self::Pointer(t), Self::Number) | (Self::Number, Self::Pointer(t)) => {
+                if let TokenType::Add | TokenType::Sub = op.token_type {
+                    Some(Self::Pointer(t.clone()))
+                } else {
+                    None
+                }
+            }
+            (Self::Boolean, Self::Boolean) => {
+                if BOOLEAN_OPERATORS.contains(&op.token_type)
+                    || BOOLEAN_EXCLUSIVE.contains(&op.token_type)
+                {
+                    Some(Self::Boolean)
+                } else {
+                    None
+                }
+            }
+            (Self::Char, Self::Char) => {
+                if BOOLEAN_OPERATORS.contains(&op.token_type) {
+                    Some(Self::Boolean)
+                } else {
+                    None
+                }
+            }
+            // Concatenating strings, or a string and a char, makes a new string
+            (Self::Pointer(_) | Self::Char, Self::Pointer(_) | Self::Char)
+                if op.token_type == TokenType::Add
+                    && self.is_string_part()
+                    && rhs.is_string_part() =>
+            {
+                Some(Self::Pointer(Box::new(Self::Char)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the type is a string or a char, which `+` can concatenate
+    pub fn is_string_part(&self) -> bool {
+        match self {
+            Self::Char => true,
+            Self::Pointer(t) => **t == Self::Char,
+            _ => false,
+        }
+    }
+
+    pub fn get_result_type_unary(&self, op: &Token) -> Option<Self> {
+        match self {
+            Self::Number => {
+                if op.token_type == TokenType::LNot {
+                    None
+                } else if [TokenType::Inc, TokenType::Dec].contains(&op.token_type) {
+                    Some(Self::None)
+                } else {
+                    Some(Self::Number)
+                }
+            }
+            Self::Boolean => {
+                if op.token_type == TokenType::LNot {
+                    Some(Self::Boolean)
+                } else {
+                    None
+                }
+            }
+            Self::Pointer(_) => {
+                if let TokenType::Inc | TokenType::Dec = op.token_type {
+                    Some(Self::None)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts a type from the parser into the type used by the code generator
+    /// # Errors
+    /// Returns an error if `t` has a `None` or a function nested inside of it, since those can't be
+    /// stored
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::diag::FileName;
+    /// use ezlang::utils::{ast::Type, diag::Position, ir::ValType, token::{Token, TokenType}};
+    ///
+    /// let file = FileName::new("main.ez");
+    /// let pos = Position::new(1, 1, 2, file.clone());
+    /// let convert = |t: Type| ValType::from_parse_type(&t, &pos);
+    /// let boxed = |t: Type| Box::new(t);
+    ///
+    /// assert_eq!(convert(Type::Number).unwrap(), ValType::Number);
+    /// assert_eq!(convert(Type::Boolean).unwrap(), ValType::Boolean);
+    /// assert_eq!(convert(Type::Char).unwrap(), ValType::Char);
+    /// // Nothing is stored for a function returning nothing
+    /// assert_eq!(convert(Type::None).unwrap(), ValType::None);
+    /// assert_eq!(convert(Type::Ref(boxed(Type::Char))).unwrap(), ValType::Ref(Box::new(ValType::Char)));
+    /// assert_eq!(
+    ///     convert(Type::Pointer(boxed(Type::Number))).unwrap(),
+    ///     ValType::Pointer(Box::new(ValType::Number))
+    /// );
+    ///
+    /// let token = |name: &str| Token::new(TokenType::Identifier(name.to_string()), 1, 1, 2, file.clone());
+    /// let fields = vec![(token("a"), Type::Boolean), (token("b"), Type::Boolean), (token("n"), Type::Number)];
+    /// let ValType::Struct(name, converted, size, false) =
+    ///     convert(Type::Struct(token("S"), fields.clone(), false)).unwrap()
+    /// else {
+    ///     panic!("expected a struct that isn't packed");
+    /// };
+    /// assert_eq!((name, size), (token("S"), 3));
+    /// assert_eq!(converted[2], (token("n"), ValType::Number));
+    /// let packed = convert(Type::Struct(token("S"), fields, true)).unwrap();
+    /// assert_eq!(packed.get_size(), 2);
+    ///
+    /// // Functions can't be stored, and neither can None inside of another type
+    /// let function = Type::Function(vec![Type::Number], boxed(Type::Number));
+    /// let err = convert(function.clone()).unwrap_err();
+    /// assert_eq!(err.details, "Values of type (int) -> int cannot be stored");
+    /// assert!(convert(Type::Pointer(boxed(function))).is_err());
+    /// let err = convert(Type::Ref(boxed(Type::None))).unwrap_err();
+    /// assert_eq!(err.details, "Values of type None cannot be stored");
+    /// let field = vec![(token("f"), Type::None)];
+    /// assert!(convert(Type::Struct(token("S"), field, false)).is_err());
+    /// ```
+    pub fn from_parse_type(t: &Type, position: &Position) -> Result<Self, Error> {
+        match t {
+            Type::None => Ok(Self::None),
+            t => Self::from_stored_type(t, position),
+        }
+    }
+
+    fn from_stored_type(t: &Type, position: &Position) -> Result<Self, Error> {
+        Ok(match t {
+            Type::Char => Self::Char,
+            Type::Number => Self::Number,
+            Type::Boolean => Self::Boolean,
+            Type::Bits => Self::Bits,
+            Type::Ref(t) => Self::Ref(Box::new(Self::from_stored_type(t, position)?)),
+            Type::Pointer(t) => Self::Pointer(Box::new(Self::from_stored_type(t, position)?)),
+            Type::None => {
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    position.clone(),
+                    "Values of type None cannot be stored".to_string(),
+                ))
+            }
+            Type::Function(..) => {
+                return Err(Error::new(
+                    ErrorType::TypeError,
+                    position.clone(),
+                    format!("Values of type {} cannot be stored", t),
+                ))
+            }
+            Type::Struct(token, fields, packed) => {
+                let fields = fields
+                    .iter()
+                    .map(|(t, ty)| Ok((t.clone(), Self::from_stored_type(ty, position)?)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let size = Self::struct_layout(&fields, *packed).1;
+                ValType::Struct(token.clone(), fields, size, *packed)
+            }
+        })
+    }
+
+    /// Computes where the fields of a struct are stored. Returns the offset of every field
+    /// from the start of the struct, along with the bit of that cell for the bools of a
+    /// packed struct, and the size of the struct.
+    /// In a packed struct, up to [`CELL_BITS`] consecutive bool fields share a cell, and any
+    /// other field ends the current group of bits
+    fn struct_layout(
+        fields: &[(Token, ValType)],
+        packed: bool,
+    ) -> (Vec<(usize, Option<usize>)>, usize) {
+        let mut layout = vec![];
+        let mut size = 0;
+        // The next free bit of the last cell, if it holds bools
+        let mut next_bit = None;
+        for (_, t) in fields {
+            if packed && *t == ValType::Boolean {
+                match next_bit {
+                    Some(bit) if bit < CELL_BITS => {
+                        layout.push((size - 1, Some(bit)));
+                        next_bit = Some(bit + 1);
+                    }
+                    _ => {
+                        layout.push((size, Some(0)));
+                        size += 1;
+                        next_bit = Some(1);
+                    }
+                }
+            } else {
+                layout.push((size, None));
+                size += t.get_size();
+                next_bit = None;
+            }
+        }
+        (layout, size)
+    }
+
+    /// Finds the field `name` of a struct type. Returns its offset from the start of the struct,
+    /// the bit of that cell it is stored in if it is a bool of a packed struct, and its type
+    pub fn field(&self, name: &Token) -> Option<(usize, Option<usize>, ValType)> {
+        match self {
+            Self::Struct(_, fields, _, packed) => {
+                let index = fields.iter().position(|(f, _)| f == name)?;
+                let (offset, bit) = Self::struct_layout(fields, *packed).0[index];
+                Some((offset, bit, fields[index].1.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the number of cells a value of this type takes up in memory
+    /// * `None` takes [`NONE_SIZE`] cells, nothing is stored for it
+    /// * Numbers take the size of [`ValNumber`], chars and bools a single cell
+    /// * Pointers take [`POINTER_SIZE`] cells, which is what `!` reads to move the pointer
+    /// * References take the size of the value they refer to
+    /// * Structs take the sum of the sizes of their fields, which is computed once, when the
+    ///   type is built, and kept in the type. Structs can't contain themselves, the parser
+    ///   reports those, so the size is always finite
+    /// * In packed structs, consecutive bool fields share a cell, see [`CELL_BITS`]
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::diag::FileName;
+    /// use ezlang::{core::{lexer, parser}, utils::ir::ValType};
+    ///
+    /// let code = "
+    /// struct Point { x: int, y: int }
+    /// struct Line { from: Point, to: Point, color: char }
+    /// struct Drawing { lines: point Line, count: int }
+    /// let lines = [Line { from: Point { x: 0, y: 0 }, to: Point { x: 1, y: 1 }, color: 'r' }]
+    /// let d = Drawing { lines: lines, count: 1 }
+    /// ";
+    /// let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+    /// let (.., structs) = parser::parse(tokens).unwrap();
+    /// let size = |name: &str| {
+    ///     let s = structs.iter().find(|s| s.struct_from_def().unwrap().to_string() == name);
+    ///     let s = s.unwrap();
+    ///     let t = ValType::from_parse_type(&s.struct_from_def().unwrap(), &s.position());
+    ///     t.unwrap().get_size()
+    /// };
+    /// assert_eq!(size("struct 'Point'"), 2);
+    /// assert_eq!(size("struct 'Line'"), 5);
+    /// // An array of structs is a pointer to its first element
+    /// assert_eq!(size("struct 'Drawing'"), 3);
+    ///
+    /// let code = "
+    /// struct packed Flags { a: bool, b: bool, c: bool, count: int, d: bool }
+    /// struct packed Many { a: bool, b: bool, c: bool, d: bool, e: bool, f: bool, g: bool, h: bool, i: bool }
+    /// ";
+    /// let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+    /// let (.., packed) = parser::parse(tokens).unwrap();
+    /// let size = |name: &str| {
+    ///     let s = packed.iter().find(|s| s.struct_from_def().unwrap().to_string() == name);
+    ///     let s = s.unwrap();
+    ///     let t = ValType::from_parse_type(&s.struct_from_def().unwrap(), &s.position());
+    ///     t.unwrap().get_size()
+    /// };
+    /// // a, b and c share a cell, and `count` ends their group
+    /// assert_eq!(size("struct 'Flags'"), 3);
+    /// // Only 8 bools fit in a cell
+    /// assert_eq!(size("struct 'Many'"), 2);
+    ///
+    /// let code = "struct Node { value: int, next: Node }";
+    /// let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+    /// let err = parser::parse(tokens).unwrap_err();
+    /// assert!(err.to_string().contains("use a pointer for field 'next'"));
+    ///
+    /// let code = "struct A { b: B }\nstruct B { value: int, a: A }";
+    /// let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+    /// assert!(parser::parse(tokens).is_err());
+    ///
+    /// let code = "struct Node { value: int, next: point Node }";
+    /// let tokens = lexer::lex(code, FileName::new("main.ez")).unwrap();
+    /// assert!(parser::parse(tokens).is_ok());
+    /// ```
+    pub fn get_size(&self) -> usize {
+        match self {
+            Self::None => NONE_SIZE,
+            Self::Number => std::mem::size_of::<ValNumber>(),
+            Self::Char => 1,
+            Self::Boolean => 1,
+            Self::Bits => 1,
+            Self::Pointer(..) => POINTER_SIZE,
+            Self::Ref(t) => t.get_size(),
+            Self::Struct(_, _, s, _) => *s,
+        }
+    }
+}
+
+impl fmt::Display for ValType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ref(t) => write!(f, "&{}", t),
+            Self::Char => write!(f, "char"),
+            Self::Pointer(t) => write!(f, "&{}", **t),
+            Self::None => write!(f, ";"),
+            Self::Number => write!(f, "integer"),
+            Self::Boolean => write!(f, "bool"),
+            Self::Struct(t, ..) => write!(f, "struct {}", t),
+            Self::Bits => write!(f, "packed bool"),
+        }
+    }
+}
+
+impl fmt::Display for Val {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Val::Char(c) => write!(f, "{}", *c as char),
+            Val::Pointer(mem, _) => write!(f, "*{}", mem),
+            Val::None => write!(f, ";"),
+            Val::Bool(b) => write!(f, "{}", b),
+            Val::Num(num) => write!(f, "{}", num),
+            Val::Index(index, _) => write!(f, "[{}]", index),
+            Val::Ref(ptr, _) => write!(f, "&{}", ptr),
+        }
+    }
+}
+
+impl fmt::Debug for Val {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Val::Char(c) => write!(f, "{:?}", *c as char),
+            Val::Pointer(mem, _) => write!(f, "*{}", mem),
+            Val::None => write!(f, "()"),
+            Val::Bool(b) => write!(f, "{}", b),
+            Val::Num(num) => write!(f, "{}", num),
+            Val::Index(index, _) => write!(f, "[{}]", index),
+            Val::Ref(ptr, _) => write!(f, "&{}", ptr),
+        }
+    }
+}
+
+/// Assignment Index, Result type, Free memory location
+pub type AssignType = (Option<(usize, usize)>, usize);
+
+/// The name the code outside of every function is given
+pub const MAIN_REGION: &str = "<main>";
+
+/// A range of instructions that were generated for a call of a function.
+/// The regions of the functions it calls are inside of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub function: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A vector of instructions, and the regions of the functions they were generated for.
+#[derive(Debug)]
+pub struct Instructions(pub Vec<(AssignType, Instruction)>, pub Vec<Region>);
+
+impl Instructions {
+    pub fn new() -> Self {
+        Self(Vec::new(), Vec::new())
+    }
+
+    /// Marks the instructions from `start` to the last one as the region of a call of `function`
+    pub fn add_region(&mut self, function: String, start: usize) {
+        self.1.push(Region {
+            function,
+            start,
+            end: self.0.len(),
+        });
+    }
+
+    /// Copies the regions of `from` to the instructions a pass made from it, where `starts` has
+    /// the index of the first instruction made from every instruction of `from`
+    pub fn keep_regions(&mut self, from: &Instructions, starts: &[usize]) {
+        let index = |i: usize| starts.get(i).copied().unwrap_or(self.0.len());
+        self.1 = from
+            .1
+            .iter()
+            .map(|region| Region {
+                function: region.function.clone(),
+                start: index(region.start),
+                end: index(region.end),
+            })
+            .collect();
+    }
+
+    /// Returns the function the instruction at `index` was generated for, the innermost one
+    /// when calls are nested, or [`MAIN_REGION`] if it is outside of every function
+    pub fn function_at(&self, index: usize) -> &str {
+        self.1
+            .iter()
+            .filter(|region| (region.start..region.end).contains(&index))
+            .min_by_key(|region| region.end - region.start)
+            .map_or(MAIN_REGION, |region| &region.function)
+    }
+
+    /// Returns the instructions generated for the calls of `function`, including the functions
+    /// it calls, or `None` if no instruction was generated for it
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::{ir::Instruction, Options, Stats};
+    ///
+    /// let code = "
+    /// ez double(n: int) -> int { return n * 2 }
+    /// ez greet() { ezout 'h', 'i' }
+    /// greet()
+    /// ezout double(4)
+    /// ";
+    /// let options = Options::default();
+    /// let code = ezlang::compile_ir(code, "main.ez".to_string(), &options, &mut Stats::default()).unwrap();
+    /// let double = code.function("double").unwrap();
+    /// assert!(double.0.iter().any(|(_, i)| matches!(i, Instruction::Mul(..))));
+    /// assert!(!double.0.iter().any(|(_, i)| matches!(i, Instruction::Ascii(..) | Instruction::Print(..))));
+    /// let greet = code.function("greet").unwrap();
+    /// assert!(greet.0.iter().any(|(_, i)| matches!(i, Instruction::Ascii(..))));
+    /// assert!(!greet.0.iter().any(|(_, i)| matches!(i, Instruction::Mul(..))));
+    /// assert!(code.function("nope").is_none());
+    ///
+    /// // A call in the arguments of another call isn't repeated
+    /// let nested = "ez double(n: int) -> int { return n * 2 }\nezout double(double(3))";
+    /// let nested = ezlang::compile_ir(nested, "main.ez".to_string(), &options, &mut Stats::default()).unwrap();
+    /// let double = nested.function("double").unwrap();
+    /// assert_eq!(double.0.iter().filter(|(_, i)| matches!(i, Instruction::Mul(..))).count(), 2);
+    ///
+    /// // The printing is done by the code outside of the functions
+    /// let counts = code.counts_per_function();
+    /// assert_eq!(counts.iter().map(|(f, _)| f.as_str()).collect::<Vec<_>>(), ["<main>", "greet", "double"]);
+    /// assert_eq!(counts.iter().map(|(_, n)| n).sum::<usize>(), code.0.len());
+    /// ```
+    pub fn function(&self, function: &str) -> Option<Instructions> {
+        let mut regions: Vec<_> = self
+            .1
+            .iter()
+            .filter(|region| region.function == function)
+            .map(|region| (region.start, region.end))
+            .collect();
+        regions.sort();
+        let mut code = Instructions::new();
+        let mut end = 0;
+        // A call in the arguments of another call of the function is inside of its region
+        for (start, region_end) in regions {
+            if region_end > end {
+                code.0
+                    .extend_from_slice(&self.0[start.max(end)..region_end]);
+                end = region_end;
+            }
+        }
+        (!code.0.is_empty()).then_some(code)
+    }
+
+    /// Counts the instructions generated for every function, without the ones of the
+    /// functions it calls. The code outside of every function comes first, and the functions
+    /// follow in the order they first appear in
+    pub fn counts_per_function(&self) -> Vec<(String, usize)> {
+        let mut counts = vec![(MAIN_REGION.to_string(), 0)];
+        for i in 0..self.0.len() {
+            let function = self.function_at(i);
+            match counts.iter_mut().find(|(name, _)| name == function) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((function.to_string(), 1)),
+            }
+        }
+        counts
+    }
+
+    pub fn push(&mut self, instruction: Instruction, assign: AssignType) {
+        self.0.push((assign, instruction));
+    }
+
+    /// Checks that the instructions are consistent, to catch mistakes of the code generator.
+    /// * Every cell used or assigned to is below the highest free memory index seen so far
+    /// * Nothing is assigned to the cells an open `If` keeps its flags in
+    /// * `If`, `Else` and `EndIf`, and `While` and `EndWhile`, are properly matched and nested
+    /// * The conditions of `If` and `While` are booleans
+    /// * A cell read as a bool holds 0 or 1, which means the last instruction that assigned to
+    ///   it was a comparison, a logical operator, a copy of a bool, 0 or 1, or a bitwise and
+    ///   with one of them. Values read from a pointer or returned by a function are trusted
+    /// # Errors
+    /// Returns a description of every problem found
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::ir::{Instruction, Instructions, Val, ValType};
+    ///
+    /// let mut code = Instructions::new();
+    /// code.push(Instruction::Copy(Val::Bool(true)), (Some((0, 1)), 3));
+    /// code.push(Instruction::If(Val::Index(0, ValType::Boolean), 1, false), (None, 3));
+    /// code.push(Instruction::Print(Val::Index(0, ValType::Boolean)), (None, 3));
+    /// code.push(Instruction::EndIf(1, false), (None, 3));
+    /// assert!(code.validate().is_ok());
+    ///
+    /// // A cell that was never allocated, an assignment to the flag of the `If`,
+    /// // a condition that isn't a bool, and a `While` that is never closed
+    /// let mut code = Instructions::new();
+    /// code.push(Instruction::If(Val::Index(0, ValType::Boolean), 1, false), (None, 3));
+    /// code.push(Instruction::Print(Val::Index(7, ValType::Number)), (None, 3));
+    /// code.push(Instruction::Copy(Val::Num(1)), (Some((1, 1)), 3));
+    /// code.push(Instruction::EndIf(1, false), (None, 3));
+    /// code.push(Instruction::While(Val::Num(1)), (None, 3));
+    /// assert_eq!(code.validate().unwrap_err().len(), 4);
+    ///
+    /// // A char read as a bool, without being compared to 0 first
+    /// let mut code = Instructions::new();
+    /// code.push(Instruction::Input, (Some((0, 1)), 2));
+    /// code.push(Instruction::LNot(Val::Index(0, ValType::Boolean)), (Some((1, 1)), 2));
+    /// assert_eq!(code.validate().unwrap_err().len(), 1);
+    /// code.0.insert(1, ((Some((0, 1)), 2), Instruction::Neq(Val::Index(0, ValType::Char), Val::Num(0))));
+    /// assert!(code.validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        enum Block {
+            If(usize, bool),
+            Else(usize),
+            While(Val),
+        }
+        let mut problems = vec![];
+        let mut blocks = vec![];
+        let mut peak = 0;
+        // The cells whose value may be something other than 0 or 1
+        let mut not_bools = HashSet::new();
+        for (i, ((assign, free_idx), instruction)) in self.0.iter().enumerate() {
+            let mut problem = |problem: String| {
+                problems.push(format!("instruction {} `{}`: {}", i, instruction, problem))
+            };
+            peak = peak.max(*free_idx);
+            for val in instruction.operands() {
+                let end = match val {
+                    Val::Index(mem, t) | Val::Ref(mem, t) => mem + t.get_size(),
+                    Val::Pointer(mem, _) => mem + 1,
+                    _ => continue,
+                };
+                if end > peak {
+                    problem(format!(
+                        "{:?} uses cells up to {}, but only {} are allocated",
+                        val, end, peak
+                    ));
+                }
+            }
+            for val in instruction.operands() {
+                if let Val::Index(mem, ValType::Boolean) = val {
+                    if not_bools.contains(mem) {
+                        problem(format!(
+                            "reads [{}] as a bool, but it may hold something other than 0 or 1",
+                            mem
+                        ));
+                    }
+                }
+            }
+            if let Instruction::Ref(mem) = instruction {
+                if *mem >= peak {
+                    problem(format!(
+                        "refers to [{}], but only {} cells are allocated",
+                        mem, peak
+                    ));
+                }
+            }
+            if let Some((mem, size)) = *assign {
+                if mem + size > peak {
+                    problem(format!(
+                        "assigns to cells up to {}, but only {} are allocated",
+                        mem + size,
+                        peak
+                    ));
+                }
+                let flags = blocks.iter().find_map(|block| match *block {
+                    Block::If(flag, else_)
+                        if flag < mem + size && mem < flag + 1 + else_ as usize =>
+                    {
+                        Some(flag)
+                    }
+                    Block::Else(flag) if flag < mem + size && mem < flag + 2 => Some(flag),
+                    _ => None,
+                });
+                if let Some(flag) = flags {
+                    problem(format!(
+                        "assigns to [{}], which holds the flags of an open if",
+                        flag
+                    ));
+                }
+            }
+            if let Some((mem, size)) = *assign {
+                // Whether the value copied to the `offset`th cell is 0 or 1
+                let is_bool = |val: &Val, offset: usize| match *val {
+                    Val::Bool(_) => true,
+                    Val::Num(n) => n == 0 || n == 1,
+                    Val::Char(c) => c <= 1,
+                    Val::Index(from, _) => !not_bools.contains(&(from + offset)),
+                    _ => false,
+                };
+                let bools: Vec<bool> = (0..size)
+                    .map(|offset| match instruction {
+                        Instruction::Eq(..)
+                        | Instruction::Neq(..)
+                        | Instruction::Lt(..)
+                        | Instruction::Le(..)
+                        | Instruction::LNot(..)
+                        | Instruction::LAnd(..)
+                        | Instruction::LOr(..)
+                        | Instruction::LXor(..)
+                        | Instruction::Deref(..)
+                        | Instruction::Call(..) => true,
+                        Instruction::Copy(val) => is_bool(val, offset),
+                        Instruction::TernaryIf(_, a, b) => is_bool(a, offset) && is_bool(b, offset),
+                        // Reading a bit of a packed struct
+                        Instruction::BAnd(a, b) => is_bool(a, offset) || is_bool(b, offset),
+                        _ => false,
+                    })
+                    .collect();
+                for (offset, is_bool) in bools.into_iter().enumerate() {
+                    if is_bool {
+                        not_bools.remove(&(mem + offset));
+                    } else {
+                        not_bools.insert(mem + offset);
+                    }
+                }
+            }
+            if let Instruction::Clear(from, to) = instruction {
+                not_bools.retain(|mem| !(from..to).contains(&mem));
+            }
+            match instruction {
+                Instruction::If(cond, _, _) | Instruction::While(cond)
+                    if cond.r#type() != ValType::Boolean =>
+                {
+                    problem(format!("the condition {:?} isn't a bool", cond))
+                }
+                _ => {}
+            }
+            match instruction {
+                Instruction::If(_, mem, else_) => blocks.push(Block::If(*mem, *else_)),
+                Instruction::While(cond) => blocks.push(Block::While(cond.clone())),
+                Instruction::Else(mem) => match blocks.pop() {
+                    Some(Block::If(flag, true)) if flag == *mem => blocks.push(Block::Else(flag)),
+                    _ => problem("doesn't match an open if with an else".to_string()),
+                },
+                Instruction::EndIf(mem, else_) => match blocks.pop() {
+                    Some(Block::If(flag, false)) if !else_ && flag == *mem => {}
+                    Some(Block::Else(flag)) if *else_ && flag == *mem => {}
+                    _ => problem("doesn't match an open if".to_string()),
+                },
+                Instruction::EndWhile(cond) => match blocks.pop() {
+                    Some(Block::While(c)) if c == *cond => {}
+                    _ => problem("doesn't match an open while".to_string()),
+                },
+                _ => {}
+            }
+        }
+        for block in blocks {
+            problems.push(match block {
+                Block::If(mem, _) | Block::Else(mem) => {
+                    format!("the if using [{}] is never closed", mem)
+                }
+                Block::While(cond) => format!("the while on {:?} is never closed", cond),
+            });
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+impl Default for Instructions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for Instructions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (assign, instruction) in &self.0 {
+            match assign.0 {
+                Some(assign) => writeln!(f, "[{}] = {}", assign.0, instruction),
+                None => writeln!(f, "{}", instruction),
+            }?;
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
-use super::Val;
+use crate::utils::{diag::Position, ir::Val};
 
 #[derive(Debug, Clone)]
 pub struct Memory {
@@ -78,3 +78,43 @@ impl Default for Variables {
         Variables::new()
     }
 }
+
+/// Cells used by a variable or by a temporary value, from the instruction that first assigns
+/// to them to the last one that uses them
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellUse {
+    /// The first of the cells
+    pub start: usize,
+    pub size: usize,
+    /// What the cells hold, like `variable x` or `` `+` expression ``
+    pub owner: String,
+    /// Where the variable or expression is in the code
+    pub position: Position,
+    /// The indices of the first and the last instruction using the cells
+    pub live: (usize, usize),
+    /// Whether some of the cells are also used by something else, before or after
+    pub reused: bool,
+}
+
+impl fmt::Display for CellUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}..{}] {} cell{} :: {} at {}:{}:{}, live in instructions {} to {}",
+            self.start,
+            self.start + self.size,
+            self.size,
+            if self.size == 1 { "" } else { "s" },
+            self.owner,
+            self.position.file,
+            self.position.line_start,
+            self.position.start,
+            self.live.0,
+            self.live.1
+        )?;
+        if self.reused {
+            write!(f, ", reused")?;
+        }
+        Ok(())
+    }
+}
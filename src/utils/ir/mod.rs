@@ -0,0 +1,22 @@
+/// Contains the Instruction and Instructions structs
+mod instructions;
+
+/// Models the memory in brainfuck
+mod memory_model;
+
+/// Writes the instructions as text and reads them back
+mod text;
+
+pub use instructions::*;
+pub use memory_model::CellUse;
+pub(crate) use memory_model::{Memory, Variables};
+pub use text::IR_TEXT_VERSION;
+
+/// The value of a number in the intermediate code
+pub type ValNumber = i8;
+/// None takes no cells
+pub const NONE_SIZE: usize = 0;
+/// Pointers take 2 cells, the low byte first, since `!` jumps to `tape[p] + 256 * tape[p + 1]`
+pub const POINTER_SIZE: usize = 2;
+/// The number of bits in a cell, which is how many bool fields of a packed struct share one
+pub const CELL_BITS: usize = 8;
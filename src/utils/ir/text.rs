@@ -0,0 +1,459 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::utils::{
+    diag::FileName,
+    ir::{AssignType, Instruction, Instructions, Region, Val, ValNumber, ValType},
+    token::{Token, TokenType},
+};
+
+/// The version of the text form of the intermediate code. It is written on the first line, and
+/// text written with another version is rejected, since the instructions may have changed
+pub const IR_TEXT_VERSION: u32 = 1;
+
+impl Instructions {
+    /// Writes the instructions in a text form that [`Instructions::from_text`] reads back into
+    /// the same instructions, so that they can be stored and run later without compiling the
+    /// program again. The first line is `ezir` and the version of the form, then come the
+    /// regions of the functions and an instruction on every line, like
+    /// `32768+1 32769 Add i32768:int n3`: the cells its result is put in, or `-` if it has
+    /// none, the first free cell, the kind of the instruction and its operands
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::{ir::Instructions, Options, Stats};
+    ///
+    /// let code = "let a = ezin\nezout a + 3";
+    /// let ir = ezlang::compile_ir(code, "main.ez".to_string(), &Options::default(), &mut Stats::default()).unwrap();
+    /// let text = ir.to_text();
+    /// assert!(text.starts_with("ezir 1\n"));
+    /// assert!(text.lines().any(|line| line.ends_with(" Input")));
+    ///
+    /// let read = Instructions::from_text(&text).unwrap();
+    /// assert_eq!(read.to_text(), text);
+    /// assert_eq!(ezlang::to_brainfuck(&read), ezlang::to_brainfuck(&ir));
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut text = format!("ezir {}\n", IR_TEXT_VERSION);
+        for region in &self.1 {
+            text += &format!(
+                "region {} {} {}\n",
+                region.function, region.start, region.end
+            );
+        }
+        for ((assign, free), instruction) in &self.0 {
+            match assign {
+                Some((start, size)) => text += &format!("{}+{} {} ", start, size, free),
+                None => text += &format!("- {} ", free),
+            }
+            text += instruction.name();
+            for operand in instruction_text(instruction) {
+                text.push(' ');
+                text += &operand;
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Reads instructions written by [`Instructions::to_text`]
+    /// # Errors
+    /// Returns an error with the line it is on if the text isn't intermediate code, or if it
+    /// was written with another version of the form
+    /// # Examples
+    /// ```
+    /// use ezlang::utils::ir::Instructions;
+    ///
+    /// let code = Instructions::from_text("ezir 1\n32768+1 32769 Copy n5\n- 32769 Print i32768:int\n").unwrap();
+    /// let mut output = vec![];
+    /// ezlang::core::interpreter::interpret(&ezlang::to_brainfuck(&code), &mut std::io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, b"5");
+    ///
+    /// // Text written by another version is rejected, instead of running something else
+    /// let err = Instructions::from_text("ezir 0\n- 32768 Input\n").unwrap_err();
+    /// assert_eq!(err, "The intermediate code was written with version 0 of the format, but this ezlang reads version 1, compile the program again");
+    /// assert!(Instructions::from_text("ezout 5").is_err());
+    /// let err = Instructions::from_text("ezir 1\n- 32768 Print n5 n6\n").unwrap_err();
+    /// assert_eq!(err, "line 2: Expected the end of the line after the operands of Print, found n6");
+    /// ```
+    /// Every program in `examples/` compiles to intermediate code that is read back into the
+    /// same code, and prints the same output when run from it
+    /// ```
+    /// use ezlang::{core::interpreter, utils::{ir::Instructions, Options, Stats}};
+    ///
+    /// let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/examples");
+    /// let options = Options {
+    ///     include_paths: vec![examples.to_string()],
+    ///     ..Options::default()
+    /// };
+    /// let run = |bf: &str, input: &[u8]| {
+    ///     let mut output = vec![];
+    ///     interpreter::interpret(bf, &mut &input[..], &mut output).unwrap();
+    ///     output
+    /// };
+    /// for file in std::fs::read_dir(examples).unwrap() {
+    ///     let path = file.unwrap().path();
+    ///     if path.extension().map_or(true, |ext| ext != "ez") {
+    ///         continue;
+    ///     }
+    ///     let code = std::fs::read_to_string(&path).unwrap();
+    ///     let name = path.display().to_string();
+    ///     let ir = ezlang::compile_ir(&code, name, &options, &mut Stats::default()).unwrap();
+    ///     let text = ir.to_text();
+    ///     let read = Instructions::from_text(&text).unwrap();
+    ///     assert_eq!(read.to_text(), text, "{}", path.display());
+    ///     assert_eq!(read.1, ir.1, "{}", path.display());
+    ///
+    ///     let input = std::fs::read(path.with_extension("in")).unwrap_or_default();
+    ///     let compiled = ezlang::to_brainfuck_with_options(&ir, &options);
+    ///     let cached = ezlang::to_brainfuck_with_options(&read, &options);
+    ///     assert_eq!(cached, compiled, "{}", path.display());
+    ///     assert_eq!(run(&cached, &input), run(&compiled, &input), "{}", path.display());
+    /// }
+    /// ```
+    pub fn from_text(text: &str) -> Result<Instructions, String> {
+        let mut lines = text.lines().enumerate();
+        let version = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix("ezir "))
+            .ok_or_else(|| {
+                format!(
+                    "Expected `ezir {}` on the first line, this isn't intermediate code written by --emit=ir-text",
+                    IR_TEXT_VERSION
+                )
+            })?;
+        match version.trim().parse::<u32>() {
+            Ok(IR_TEXT_VERSION) => (),
+            Ok(version) => {
+                return Err(format!(
+                    "The intermediate code was written with version {} of the format, but this ezlang reads version {}, compile the program again",
+                    version, IR_TEXT_VERSION
+                ))
+            }
+            Err(_) => return Err(format!("line 1: Invalid version: {}", version)),
+        }
+
+        let mut code = Instructions::new();
+        for (i, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+            let mut words = Words(line);
+            let read = if words.0.starts_with("region ") {
+                words.next("region")?;
+                region(&mut words).map(|region| code.1.push(region))
+            } else {
+                instruction(&mut words).map(|(assign, instruction)| code.push(instruction, assign))
+            };
+            read.map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        Ok(code)
+    }
+}
+
+/// The words of a line of the text form, separated by spaces
+struct Words<'a>(&'a str);
+
+impl<'a> Words<'a> {
+    /// Takes the next word, `what` is what it should be, for the error if there is none
+    fn next(&mut self, what: &str) -> Result<&'a str, String> {
+        if self.0.is_empty() {
+            return Err(format!("Expected {}, found the end of the line", what));
+        }
+        let (word, rest) = self.0.split_once(' ').unwrap_or((self.0, ""));
+        self.0 = rest;
+        Ok(word)
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, what: &str) -> Result<T, String> {
+        let word = self.next(what)?;
+        word.parse()
+            .map_err(|_| format!("Expected {}, found {}", what, word))
+    }
+
+    fn bool(&mut self) -> Result<bool, String> {
+        match self.next("true or false")? {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            word => Err(format!("Expected true or false, found {}", word)),
+        }
+    }
+
+    fn val(&mut self) -> Result<Val, String> {
+        val(self.next("a value")?)
+    }
+}
+
+fn region(words: &mut Words) -> Result<Region, String> {
+    Ok(Region {
+        function: words.next("the name of a function")?.to_string(),
+        start: words.number("the start of the region")?,
+        end: words.number("the end of the region")?,
+    })
+}
+
+fn instruction(words: &mut Words) -> Result<(AssignType, Instruction), String> {
+    let assign = match words.next("the cells of the result")? {
+        "-" => None,
+        cells => {
+            let (start, size) = cells
+                .split_once('+')
+                .and_then(|(start, size)| Some((start.parse().ok()?, size.parse().ok()?)))
+                .ok_or_else(|| format!("Expected the cells of the result, found {}", cells))?;
+            Some((start, size))
+        }
+    };
+    let free = words.number("the first free cell")?;
+    let name = words.next("an instruction")?;
+    let instruction = match name {
+        "If" => Instruction::If(words.val()?, words.number("a cell")?, words.bool()?),
+        "DerefAssign" => Instruction::DerefAssign(words.val()?, words.val()?),
+        "DerefRef" => Instruction::DerefRef(words.val()?),
+        "DerefAssignRef" => Instruction::DerefAssignRef(words.val()?, words.val()?),
+        "While" => Instruction::While(words.val()?),
+        "EndWhile" => Instruction::EndWhile(words.val()?),
+        "Clear" => Instruction::Clear(words.number("a cell")?, words.number("a cell")?),
+        "Return" => Instruction::Return(words.val()?),
+        "Call" => {
+            let function = words.number("a function")?;
+            let mut args = vec![];
+            while !words.0.is_empty() {
+                args.push(words.val()?);
+            }
+            Instruction::Call(function, args)
+        }
+        "Else" => Instruction::Else(words.number("a cell")?),
+        "EndIf" => Instruction::EndIf(words.number("a cell")?, words.bool()?),
+        "TernaryIf" => Instruction::TernaryIf(words.val()?, words.val()?, words.val()?),
+        "Copy" => Instruction::Copy(words.val()?),
+        "Ref" => Instruction::Ref(words.number("a cell")?),
+        "Deref" => Instruction::Deref(words.val()?),
+        "LXor" => Instruction::LXor(words.val()?, words.val()?),
+        "Input" => Instruction::Input,
+        "Raw" => Instruction::Raw(unescape(std::mem::take(&mut words.0))?),
+        "Add" => Instruction::Add(words.val()?, words.val()?),
+        "Sub" => Instruction::Sub(words.val()?, words.val()?),
+        "Mul" => Instruction::Mul(words.val()?, words.val()?),
+        "Div" => Instruction::Div(words.val()?, words.val()?),
+        "Mod" => Instruction::Mod(words.val()?, words.val()?),
+        "Neg" => Instruction::Neg(words.val()?),
+        "Print" => Instruction::Print(words.val()?),
+        "Ascii" => Instruction::Ascii(words.val()?),
+        "Eq" => Instruction::Eq(words.val()?, words.val()?),
+        "Neq" => Instruction::Neq(words.val()?, words.val()?),
+        "Lt" => Instruction::Lt(words.val()?, words.val()?),
+        "Le" => Instruction::Le(words.val()?, words.val()?),
+        "LAnd" => Instruction::LAnd(words.val()?, words.val()?),
+        "LOr" => Instruction::LOr(words.val()?, words.val()?),
+        "LNot" => Instruction::LNot(words.val()?),
+        "Inc" => Instruction::Inc(words.val()?),
+        "Dec" => Instruction::Dec(words.val()?),
+        "Pow" => Instruction::Pow(words.val()?, words.val()?),
+        "Shl" => Instruction::Shl(words.val()?, words.val()?),
+        "Shr" => Instruction::Shr(words.val()?, words.val()?),
+        "BAnd" => Instruction::BAnd(words.val()?, words.val()?),
+        "BOr" => Instruction::BOr(words.val()?, words.val()?),
+        "BXor" => Instruction::BXor(words.val()?, words.val()?),
+        "BNot" => Instruction::BNot(words.val()?),
+        _ => return Err(format!("Unknown instruction: {}", name)),
+    };
+    if !words.0.is_empty() {
+        return Err(format!(
+            "Expected the end of the line after the operands of {}, found {}",
+            name, words.0
+        ));
+    }
+    Ok(((assign, free), instruction))
+}
+
+/// The operands of an instruction, written the way [`instruction`] reads them
+fn instruction_text(instruction: &Instruction) -> Vec<String> {
+    match instruction {
+        Instruction::If(val, flags, else_) => {
+            vec![val_text(val), flags.to_string(), else_.to_string()]
+        }
+        Instruction::Clear(from, to) => vec![from.to_string(), to.to_string()],
+        Instruction::Call(function, args) => {
+            let mut operands = vec![function.to_string()];
+            operands.extend(args.iter().map(val_text));
+            operands
+        }
+        Instruction::Else(flags) | Instruction::Ref(flags) => vec![flags.to_string()],
+        Instruction::EndIf(flags, else_) => vec![flags.to_string(), else_.to_string()],
+        Instruction::Raw(code) => vec![escape(code)],
+        _ => instruction.operands().into_iter().map(val_text).collect(),
+    }
+}
+
+/// Writes a value as a single word, like `n5`, `true`, `c97` or `i32768:int`
+fn val_text(val: &Val) -> String {
+    match val {
+        Val::Num(n) => format!("n{}", n),
+        Val::Bool(b) => b.to_string(),
+        Val::Char(c) => format!("c{}", c),
+        Val::None => "none".to_string(),
+        Val::Index(idx, t) => format!("i{}:{}", idx, type_text(t)),
+        Val::Ref(idx, t) => format!("r{}:{}", idx, type_text(t)),
+        Val::Pointer(idx, t) => format!("p{}:{}", idx, type_text(t)),
+    }
+}
+
+fn val(word: &str) -> Result<Val, String> {
+    let invalid = || format!("Invalid value: {}", word);
+    let cell = |rest: &str| -> Result<(usize, ValType), String> {
+        let (idx, t) = rest.split_once(':').ok_or_else(invalid)?;
+        let mut chars = t.chars().peekable();
+        let t = val_type(&mut chars)?;
+        if chars.next().is_some() {
+            return Err(invalid());
+        }
+        Ok((idx.parse().map_err(|_| invalid())?, t))
+    };
+    Ok(match word {
+        "true" => Val::Bool(true),
+        "false" => Val::Bool(false),
+        "none" => Val::None,
+        _ => match word.split_at(word.len().min(1)) {
+            ("n", n) => Val::Num(n.parse::<ValNumber>().map_err(|_| invalid())?),
+            ("c", c) => Val::Char(c.parse().map_err(|_| invalid())?),
+            ("i", rest) => cell(rest).map(|(idx, t)| Val::Index(idx, t))?,
+            ("r", rest) => cell(rest).map(|(idx, t)| Val::Ref(idx, t))?,
+            ("p", rest) => cell(rest).map(|(idx, t)| Val::Pointer(idx, t))?,
+            _ => return Err(invalid()),
+        },
+    })
+}
+
+/// Writes a type without spaces, like `*int` or `struct(Point,2,false,x:int,y:int)`
+fn type_text(t: &ValType) -> String {
+    match t {
+        ValType::None => "none".to_string(),
+        ValType::Number => "int".to_string(),
+        ValType::Char => "char".to_string(),
+        ValType::Boolean => "bool".to_string(),
+        ValType::Bits => "bits".to_string(),
+        ValType::Ref(t) => format!("&{}", type_text(t)),
+        ValType::Pointer(t) => format!("*{}", type_text(t)),
+        ValType::Struct(name, fields, size, packed) => {
+            let mut text = format!("struct({},{},{}", name_text(name), size, packed);
+            for (field, t) in fields {
+                text += &format!(",{}:{}", name_text(field), type_text(t));
+            }
+            text + ")"
+        }
+    }
+}
+
+fn name_text(token: &Token) -> String {
+    match token.token_type {
+        TokenType::Identifier(ref name) => name.clone(),
+        ref t => t.to_string(),
+    }
+}
+
+fn val_type(chars: &mut Peekable<Chars>) -> Result<ValType, String> {
+    match chars.peek() {
+        Some('&') => {
+            chars.next();
+            return Ok(ValType::Ref(Box::new(val_type(chars)?)));
+        }
+        Some('*') => {
+            chars.next();
+            return Ok(ValType::Pointer(Box::new(val_type(chars)?)));
+        }
+        _ => (),
+    }
+    let word = name(chars);
+    Ok(match word.as_str() {
+        "none" => ValType::None,
+        "int" => ValType::Number,
+        "char" => ValType::Char,
+        "bool" => ValType::Boolean,
+        "bits" => ValType::Bits,
+        "struct" => {
+            expect(chars, '(')?;
+            let struct_name = identifier(name(chars));
+            expect(chars, ',')?;
+            let size = name(chars);
+            let size = size
+                .parse()
+                .map_err(|_| format!("Invalid size of struct: {}", size))?;
+            expect(chars, ',')?;
+            let packed = match name(chars).as_str() {
+                "true" => true,
+                "false" => false,
+                packed => return Err(format!("Expected true or false, found {}", packed)),
+            };
+            let mut fields = vec![];
+            while chars.next_if_eq(&',').is_some() {
+                let field = identifier(name(chars));
+                expect(chars, ':')?;
+                fields.push((field, val_type(chars)?));
+            }
+            expect(chars, ')')?;
+            ValType::Struct(struct_name, fields, size, packed)
+        }
+        _ => return Err(format!("Unknown type: {}", word)),
+    })
+}
+
+/// Takes the letters, digits and `_` at the start of `chars`
+fn name(chars: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+        name.push(c);
+    }
+    name
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(format!("Expected '{}' in a type, found '{}'", expected, c)),
+        None => Err(format!("Expected '{}' in a type", expected)),
+    }
+}
+
+/// The names of structs and fields have no position, since they aren't in the code
+fn identifier(name: String) -> Token {
+    Token::new(TokenType::Identifier(name), 0, 0, 0, FileName::new(""))
+}
+
+/// Escapes the `\`, spaces and line breaks of raw brainfuck code, so that it is one word
+fn escape(code: &str) -> String {
+    let mut escaped = String::new();
+    for c in code.chars() {
+        match c {
+            '\\' => escaped += "\\\\",
+            ' ' => escaped += "\\s",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(code: &str) -> Result<String, String> {
+    let mut unescaped = String::new();
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next() {
+            Some('\\') => '\\',
+            Some('s') => ' ',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            c => {
+                return Err(format!(
+                    "Invalid escape in raw code: \\{}",
+                    c.unwrap_or(' ')
+                ))
+            }
+        });
+    }
+    Ok(unescaped)
+}
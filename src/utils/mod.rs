@@ -1,31 +1,23 @@
-//! Includes all the utils functions and structs
+//! The types shared by the stages of the compiler, grouped by the stage they belong to.
+//! The ones most programs need are also in [`crate::prelude`]
 
-/// Contains the Error struct
-mod error;
+/// The syntax tree the parser makes, and the scopes of its symbols
+pub mod ast;
 
-/// Contains the Instruction and Instructions structs
-mod instructions;
+/// Errors, warnings and the positions they point at
+pub mod diag;
 
-/// Contains the Node enum
-mod node;
+/// The intermediate code, and the memory it runs on
+pub mod ir;
 
-/// Contains the Scope struct
-mod scope;
+/// The tokens the lexer makes
+pub mod token;
 
-/// Contains the Token struct
-mod token;
+/// Contains the Options struct
+mod options;
 
-/// Models the memory in brainfuck
-mod memory_model;
+/// Contains the Stats struct
+mod stats;
 
-pub use error::*;
-pub use instructions::*;
-pub use memory_model::*;
-pub use node::*;
-pub use scope::*;
-pub use token::*;
-
-pub type LexNumber = u16;
-pub type ValNumber = i8;
-pub const NONE_SIZE: usize = 0;
-pub const POINTER_SIZE: usize = 2;
+pub use options::Options;
+pub use stats::Stats;
@@ -0,0 +1,71 @@
+use crate::utils::{
+    ast::AST_DEPTH,
+    diag::{Severity, WarningKind},
+};
+
+/// Options that change how a program is compiled
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// File included before the main file, as if it started with a `use` of it
+    pub prelude: Option<String>,
+    /// Directories searched for files included by `use`, after the current directory
+    pub include_paths: Vec<String>,
+    /// Flags declared before the program is preprocessed, like with `!declare`
+    pub defines: Vec<String>,
+    /// 0 leaves the generated brainfuck as is, anything else removes redundant instructions
+    pub opt_level: u8,
+    /// Number of threads the generated brainfuck is optimized on
+    pub jobs: usize,
+    /// Severities of kinds of warnings, the last one given for a kind is used
+    pub warnings: Vec<(WarningKind, Severity)>,
+    /// Global variables whose value is known while compiling are used as constants, without
+    /// any memory or instructions. Turning it off generates them like the other variables
+    pub fold_globals: bool,
+    /// Rewrite multiplication, division, modulo and powers into loops of simpler instructions,
+    /// see [`crate::core::lowering::lower_arithmetic`]
+    pub lower_arith: bool,
+    /// Printed between the values of an `ezout`, a space by default
+    pub ezout_separator: String,
+    /// The most nodes the bodies of expanded function calls can add to the program, so that
+    /// programs whose expansion blows up fail instead of taking forever to compile
+    pub max_inline_size: usize,
+    /// Most tokens the `replace` directives can add in total, so that replacements that keep
+    /// growing stop with an error
+    pub max_replaced_tokens: usize,
+    /// Log every preprocessor directive that is run, see [`crate::core::preprocessor::preprocess`]
+    pub trace_preprocessor: bool,
+    /// Levels of nodes shown of the AST printed with `--emit=ast`, the deeper ones are written
+    /// as `…`
+    pub ast_depth: usize,
+}
+
+impl Options {
+    /// Returns what is done with the warnings of `kind`
+    pub fn severity(&self, kind: WarningKind) -> Severity {
+        self.warnings
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == kind)
+            .map_or_else(|| kind.default_severity(), |(_, severity)| *severity)
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            prelude: None,
+            include_paths: vec![],
+            defines: vec![],
+            opt_level: 1,
+            jobs: 1,
+            warnings: vec![],
+            fold_globals: true,
+            lower_arith: false,
+            ezout_separator: String::from(" "),
+            max_inline_size: 1_000_000,
+            max_replaced_tokens: 100_000,
+            trace_preprocessor: false,
+            ast_depth: AST_DEPTH,
+        }
+    }
+}
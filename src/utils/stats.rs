@@ -0,0 +1,32 @@
+use std::{fmt, path::PathBuf};
+
+use crate::utils::{
+    diag::{Position, Warning},
+    ir::CellUse,
+};
+
+/// Statistics collected while compiling a program
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Number of global variables whose value was known at compile time
+    pub folded_globals: usize,
+    /// Warnings found in the program, apart from the ones that are turned off
+    pub warnings: Vec<Warning>,
+    /// The prelude and the files included by `use`, with the paths they were read from, in the
+    /// order they were included
+    pub included_files: Vec<PathBuf>,
+    /// The cells every variable and temporary value uses, the biggest first
+    pub memory: Vec<CellUse>,
+    /// The code every instruction of the intermediate code was generated from, by its index.
+    /// It is empty when the arithmetic is lowered, since lowering changes the instructions
+    pub source_map: Vec<Option<Position>>,
+    /// The preprocessor directives that were run, if [`super::Options::trace_preprocessor`]
+    /// is set. Kept when the compilation fails
+    pub preprocessor_trace: Vec<String>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Const-folded globals: {}", self.folded_globals)
+    }
+}
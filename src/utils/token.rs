@@ -1,23 +1,35 @@
-use super::{LexNumber, Position};
-use std::{cmp, fmt, rc::Rc};
+use super::diag::{FileName, Position};
+use std::{cmp, fmt};
+
+/// A number literal, which is negative if it is written with a `-` in front of it
+pub type LexNumber = i32;
 
 /// List of all the keywords identified by the lexer
-pub const KEYWORDS: [&str; 19] = [
-    "ez", "return", "ezout", "ezin", "ezascii", "true", "false", "if", "else", "bool", "int",
-    "char", "while", "for", "struct", "let", "static", "as", "point",
+pub(crate) const KEYWORDS: [&str; 22] = [
+    "ez", "return", "ezout", "ezoutln", "ezin", "ezascii", "true", "false", "if", "else", "bool",
+    "int", "char", "while", "for", "struct", "let", "static", "as", "point", "sizeof", "ezraw",
 ];
 
-pub const PREPROCESSOR_STATEMENTS: [&str; 7] = [
+/// The smallest and the largest int literals. An int is a cell, so it goes up to 255, and
+/// `-256` is the same cell as `0`
+pub(crate) const INT_MIN: LexNumber = -256;
+pub(crate) const INT_MAX: LexNumber = 255;
+
+pub(crate) const PREPROCESSOR_STATEMENTS: [&str; 11] = [
     "use",
     "replace",
     "declare",
+    "undeclare",
     "ifdeclared",
+    "ifnotdeclared",
+    "elifdeclared",
     "else",
     "endif",
     "error",
+    "warning",
 ];
 
-pub const BOOLEAN_OPERATORS: [TokenType; 6] = [
+pub(crate) const BOOLEAN_OPERATORS: [TokenType; 6] = [
     TokenType::Neq,
     TokenType::Gt,
     TokenType::Le,
@@ -26,14 +38,14 @@ pub const BOOLEAN_OPERATORS: [TokenType; 6] = [
     TokenType::Eq,
 ];
 
-pub const BOOLEAN_EXCLUSIVE: [TokenType; 4] = [
+pub(crate) const BOOLEAN_EXCLUSIVE: [TokenType; 4] = [
     TokenType::LAnd,
     TokenType::LOr,
     TokenType::LNot,
     TokenType::LXor,
 ];
 
-pub const ASSIGNMENT_OPERATORS: [TokenType; 15] = [
+pub(crate) const ASSIGNMENT_OPERATORS: [TokenType; 15] = [
     TokenType::Assign,
     TokenType::SubAssign,
     TokenType::AddAssign,
@@ -103,6 +115,8 @@ pub enum TokenType {
     Assign,
     Comma,
     Dot,
+    DotDot,
+    DotDotEq,
     Path,
     Eol,
     Eof,
@@ -186,6 +200,8 @@ impl fmt::Display for TokenType {
                 TokenType::PreprocessorStatement(ref stmt) => format!("!{stmt}"),
                 TokenType::String(ref s) => format!("\"{s}\""),
                 TokenType::Dot => ".".to_string(),
+                TokenType::DotDot => "..".to_string(),
+                TokenType::DotDotEq => "..=".to_string(),
                 TokenType::Path => "::".to_string(),
             }
         )
@@ -230,7 +246,7 @@ impl Token {
         line: usize,
         start: usize,
         end: usize,
-        filename: Rc<String>,
+        filename: FileName,
     ) -> Self {
         Self {
             token_type,